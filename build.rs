@@ -0,0 +1,21 @@
+//! Dumps the code themes embedded in the binary one by one, so startup
+//! deserializes a single theme instead of syntect's whole bundled set.
+
+use std::env;
+use std::path::PathBuf;
+use syntect::dumps::dump_to_uncompressed_file;
+use syntect::highlighting::ThemeSet;
+
+/// Keep in sync with `EMBEDDED_THEMES` in src/highlight.rs.
+const EMBEDDED_THEMES: &[&str] = &["base16-ocean.dark", "base16-ocean.light"];
+
+fn main() {
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").expect("OUT_DIR is set by cargo"));
+    let themes = ThemeSet::load_defaults();
+    for name in EMBEDDED_THEMES {
+        let path = out_dir.join(format!("{name}.themedump"));
+        dump_to_uncompressed_file(&themes.themes[*name], &path)
+            .unwrap_or_else(|e| panic!("failed to dump theme {name}: {e}"));
+    }
+    println!("cargo::rerun-if-changed=build.rs");
+}
@@ -0,0 +1,151 @@
+//! GitHub-style admonitions: blockquotes whose first line is a marker such
+//! as `> [!WARNING]`, rendered with a severity-colored bar and title.
+
+use crate::render::RenderOptions;
+use std::io::{self, Write};
+use termimad::MadSkin;
+use termimad::crossterm::style::Color;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Note,
+    Tip,
+    Important,
+    Warning,
+    Caution,
+}
+
+impl Kind {
+    /// Parses the `[!NOTE]` marker line of a blockquote.
+    fn from_marker(line: &str) -> Option<Kind> {
+        let marker = line.strip_prefix('>')?.trim();
+        let name = marker.strip_prefix("[!")?.strip_suffix(']')?;
+        match name.to_ascii_uppercase().as_str() {
+            "NOTE" => Some(Kind::Note),
+            "TIP" => Some(Kind::Tip),
+            "IMPORTANT" => Some(Kind::Important),
+            "WARNING" => Some(Kind::Warning),
+            "CAUTION" => Some(Kind::Caution),
+            _ => None,
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            Kind::Note => "Note",
+            Kind::Tip => "Tip",
+            Kind::Important => "Important",
+            Kind::Warning => "Warning",
+            Kind::Caution => "Caution",
+        }
+    }
+
+    fn icon(self, ascii: bool) -> &'static str {
+        match (self, ascii) {
+            (Kind::Note, false) => "ℹ",
+            (Kind::Tip, false) => "✔",
+            (Kind::Important, false) => "❗",
+            (Kind::Warning, false) => "⚠",
+            (Kind::Caution, false) => "✖",
+            (Kind::Note, true) => "(i)",
+            (Kind::Tip, true) => "(+)",
+            (Kind::Important | Kind::Warning, true) => "(!)",
+            (Kind::Caution, true) => "(x)",
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            Kind::Note => Color::Blue,
+            Kind::Tip => Color::Green,
+            Kind::Important => Color::Magenta,
+            Kind::Warning => Color::Yellow,
+            Kind::Caution => Color::Red,
+        }
+    }
+}
+
+pub enum Block {
+    Prose(String),
+    /// `body` keeps its `> ` quote prefixes so termimad still wraps it.
+    Admonition {
+        kind: Kind,
+        body: String,
+    },
+}
+
+/// Splits prose into plain runs and admonition blockquotes.
+pub fn split(text: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut prose = String::new();
+    let mut lines = text.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        let Some(kind) = Kind::from_marker(line) else {
+            prose.push_str(line);
+            prose.push('\n');
+            continue;
+        };
+        if !prose.is_empty() {
+            blocks.push(Block::Prose(std::mem::take(&mut prose)));
+        }
+        let mut body = String::new();
+        while let Some(quoted) = lines.next_if(|next| next.starts_with('>')) {
+            body.push_str(quoted);
+            body.push('\n');
+        }
+        blocks.push(Block::Admonition { kind, body });
+    }
+
+    if !prose.is_empty() {
+        blocks.push(Block::Prose(prose));
+    }
+    blocks
+}
+
+pub fn print(
+    out: &mut impl Write,
+    skin: &MadSkin,
+    kind: Kind,
+    body: &str,
+    options: &RenderOptions,
+) -> io::Result<()> {
+    let mut skin = skin.clone();
+    if options.color {
+        skin.quote_mark.set_fg(kind.color());
+        skin.bold.set_fg(kind.color());
+    }
+    let title = format!("> **{} {}**\n", kind.icon(options.ascii), kind.title());
+    // Rendered apart so a CommonMark backend doesn't join title and body
+    let backend = options
+        .renderer
+        .backend(!options.no_reflow, options.list_style);
+    for text in [&title, body] {
+        write!(out, "{}", backend.render(&skin, text, options.width()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_detects_marker() {
+        let blocks = split("Intro\n> [!warning]\n> Careful\n> here\nAfter\n");
+        assert_eq!(blocks.len(), 3);
+        assert!(matches!(&blocks[0], Block::Prose(p) if p == "Intro\n"));
+        assert!(
+            matches!(&blocks[1], Block::Admonition { kind: Kind::Warning, body }
+            if body == "> Careful\n> here\n")
+        );
+        assert!(matches!(&blocks[2], Block::Prose(p) if p == "After\n"));
+    }
+
+    #[test]
+    fn test_plain_blockquote_is_prose() {
+        let blocks = split("> [!UNKNOWN]\n> just a quote\n");
+        assert_eq!(blocks.len(), 1);
+        assert!(matches!(&blocks[0], Block::Prose(_)));
+    }
+}
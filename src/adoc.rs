@@ -0,0 +1,135 @@
+//! Minimal AsciiDoc support, converted to markdown-flavoured segments.
+//! Admonition paragraphs become `> [!NOTE]` blockquotes so they share the
+//! markdown callout rendering.
+
+use crate::segment::{Segment, SegmentBuilder};
+
+const ADMONITIONS: &[&str] = &["NOTE", "TIP", "IMPORTANT", "WARNING", "CAUTION"];
+
+pub fn split_segments(content: &str) -> Vec<Segment<'static>> {
+    let mut builder = SegmentBuilder::default();
+    let mut lines = content.lines().peekable();
+    // Language from a `[source,lang]` line, applied to the next listing block
+    let mut pending_lang: Option<String> = None;
+
+    while let Some(line) = lines.next() {
+        if let Some(lang) = source_lang(line) {
+            pending_lang = Some(lang);
+            continue;
+        }
+
+        if let Some(delimiter) = block_delimiter(line) {
+            let mut code = Vec::new();
+            let mut closed = false;
+            for inner in lines.by_ref() {
+                if inner.trim_end() == delimiter {
+                    closed = true;
+                    break;
+                }
+                code.push(inner);
+            }
+            if closed {
+                builder.push(Segment::Code {
+                    lang: pending_lang.take().unwrap_or_default(),
+                    code: code.join("\n").into(),
+                });
+            } else {
+                // Unclosed block — keep it as plain text
+                builder.line(line);
+                code.into_iter().for_each(|raw| builder.line(raw));
+            }
+            continue;
+        }
+        pending_lang = None;
+
+        if let Some((kind, first)) = admonition(line) {
+            builder.line(&format!("> [!{kind}]"));
+            builder.line(&format!("> {first}"));
+            while let Some(more) = lines.next_if(|next| !next.trim().is_empty()) {
+                builder.line(&format!("> {more}"));
+            }
+            continue;
+        }
+
+        builder.line(&convert_line(line));
+    }
+
+    builder.finish()
+}
+
+/// Language of a `[source,bash]` block attribute line.
+fn source_lang(line: &str) -> Option<String> {
+    let attrs = line.trim().strip_prefix('[')?.strip_suffix(']')?;
+    let mut parts = attrs.split(',').map(str::trim);
+    (parts.next()? == "source").then(|| parts.next().unwrap_or_default().to_string())
+}
+
+/// Listing (`----`) and literal (`....`) block delimiters.
+fn block_delimiter(line: &str) -> Option<&str> {
+    let line = line.trim_end();
+    let is_delimiter =
+        line.len() >= 4 && (line.chars().all(|c| c == '-') || line.chars().all(|c| c == '.'));
+    is_delimiter.then_some(line)
+}
+
+/// Splits a `NOTE: text` paragraph opener into its kind and text.
+fn admonition(line: &str) -> Option<(&str, &str)> {
+    let (kind, text) = line.split_once(": ")?;
+    ADMONITIONS.contains(&kind).then_some((kind, text.trim()))
+}
+
+fn convert_line(line: &str) -> String {
+    let equals = line.chars().take_while(|&c| c == '=').count();
+    if equals > 0 && line[equals..].starts_with(' ') {
+        return format!("{} {}", "#".repeat(equals), line[equals..].trim());
+    }
+    for marker in ['*', '-'] {
+        let depth = line.chars().take_while(|&c| c == marker).count();
+        if depth > 0 && line[depth..].starts_with(' ') && (marker == '*' || depth == 1) {
+            return format!("{}* {}", "  ".repeat(depth - 1), line[depth..].trim());
+        }
+    }
+    line.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_headings_and_lists() {
+        let segments = split_segments("= Runbook\n:toc:\n== Steps\n* one\n** nested\n- two\n");
+        assert_eq!(segments.len(), 1);
+        assert!(matches!(&segments[0], Segment::Text(t)
+            if t == "# Runbook\n:toc:\n## Steps\n* one\n  * nested\n* two\n"));
+    }
+
+    #[test]
+    fn test_source_listing() {
+        let segments = split_segments("[source,bash]\n----\nsystemctl restart app\n----\n");
+        assert_eq!(segments.len(), 1);
+        assert!(matches!(&segments[0], Segment::Code { lang, code }
+            if lang == "bash" && code == "systemctl restart app"));
+    }
+
+    #[test]
+    fn test_admonition_paragraph() {
+        let segments = split_segments("WARNING: Drains traffic\nfor a minute.\n\nDone\n");
+        assert!(matches!(&segments[0], Segment::Text(t)
+            if t == "> [!WARNING]\n> Drains traffic\n> for a minute.\n\nDone\n"));
+    }
+
+    #[test]
+    fn test_fixture() {
+        let segments = split_segments(include_str!("../tests/fixtures/runbook.adoc"));
+        let langs: Vec<&str> = segments
+            .iter()
+            .filter_map(|s| match s {
+                Segment::Code { lang, .. } => Some(lang.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(langs, ["bash", ""]);
+        assert!(matches!(&segments[0], Segment::Text(t) if t.starts_with("# Deploy Runbook\n")));
+    }
+}
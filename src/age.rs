@@ -0,0 +1,125 @@
+//! Encrypted sheets: `git.md.age` is an age-encrypted `git.md`, found by
+//! its name like any sheet and decrypted in memory by the `age` command,
+//! with the `age_identities` keys or, without any, a passphrase `age` asks
+//! for on the terminal. Plain text goes through pipes, never to disk.
+
+use crate::CheetError;
+use anyhow::{Context, Result, bail};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+/// The extension after the sheet's own, as in `git.md.age`.
+pub const EXTENSION: &str = "age";
+
+/// Whether `path` is an encrypted sheet.
+pub fn is_encrypted(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == EXTENSION)
+}
+
+/// `path` without its `.age`, e.g. to tell the sheet's format.
+pub fn plain_path(path: &Path) -> PathBuf {
+    match is_encrypted(path) {
+        true => path.with_extension(""),
+        false => path.to_path_buf(),
+    }
+}
+
+/// The plain text of the encrypted sheet at `path`, decrypted with the
+/// `identities` files or else a passphrase.
+pub fn decrypt(path: &Path, identities: &[PathBuf]) -> Result<String> {
+    let mut command = Command::new("age");
+    command.arg("--decrypt");
+    for identity in identities {
+        command.arg("--identity").arg(identity);
+    }
+    command.arg(path);
+    let output = run(&mut command, None)?;
+    if !output.status.success() {
+        bail!(CheetError::External(format!(
+            "failed to decrypt {}: {}",
+            path.display(),
+            reason(&output)
+        )));
+    }
+    String::from_utf8(output.stdout).map_err(|_| {
+        CheetError::Parse(format!("{} is not UTF-8 once decrypted", path.display())).into()
+    })
+}
+
+/// Encrypts `plain` into `path` for `recipients`, or with a passphrase
+/// `age` asks for when there are none.
+pub fn encrypt(plain: &[u8], path: &Path, recipients: &[String]) -> Result<()> {
+    let mut command = Command::new("age");
+    command.arg("--encrypt");
+    match recipients.is_empty() {
+        true => {
+            command.arg("--passphrase");
+        }
+        false => {
+            for recipient in recipients {
+                command.arg("--recipient").arg(recipient);
+            }
+        }
+    }
+    command.arg("--output").arg(path);
+    let output = run(&mut command, Some(plain))?;
+    if !output.status.success() {
+        bail!(CheetError::External(format!(
+            "failed to encrypt {}: {}",
+            path.display(),
+            reason(&output)
+        )));
+    }
+    Ok(())
+}
+
+/// Overwrites the file at `path` with zeros before removing it, so the
+/// plain text of a sheet doesn't linger in its blocks.
+pub fn shred(path: &Path) -> Result<()> {
+    let len = fs::metadata(path)?.len();
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    file.write_all(&vec![0; len as usize])?;
+    file.sync_all()?;
+    drop(file);
+    fs::remove_file(path).with_context(|| format!("failed to remove {}", path.display()))
+}
+
+/// Runs `command` with `input` on its stdin, or the terminal's when `None`
+/// so `age` can prompt there.
+fn run(command: &mut Command, input: Option<&[u8]>) -> Result<Output> {
+    if input.is_some() {
+        command.stdin(Stdio::piped());
+    }
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context(CheetError::External(
+            "failed to run `age`; is it installed? See https://age-encryption.org".into(),
+        ))?;
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        // `age` may refuse, say a recipient, before reading any of it; its
+        // exit status tells why, not the broken pipe
+        match stdin.write_all(input) {
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {}
+            written => written?,
+        }
+    }
+    Ok(child.wait_with_output()?)
+}
+
+/// What `age` said went wrong, without its `age: error:` prefix.
+fn reason(output: &Output) -> String {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let line = stderr
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .unwrap_or("age failed");
+    let line = line.trim();
+    line.strip_prefix("age: error: ")
+        .or_else(|| line.strip_prefix("age: "))
+        .unwrap_or(line)
+        .to_string()
+}
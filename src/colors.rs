@@ -0,0 +1,156 @@
+//! Color strings for the `[colors]` config table: names like `cyan` or
+//! `dark-grey`, ANSI indexes 0-255 and `#rrggbb` hex.
+
+use serde::Deserialize;
+use termimad::MadSkin;
+use termimad::crossterm::style::Color;
+
+const NAMES: &[(&str, Color)] = &[
+    ("black", Color::Black),
+    ("dark_grey", Color::DarkGrey),
+    ("red", Color::Red),
+    ("dark_red", Color::DarkRed),
+    ("green", Color::Green),
+    ("dark_green", Color::DarkGreen),
+    ("yellow", Color::Yellow),
+    ("dark_yellow", Color::DarkYellow),
+    ("blue", Color::Blue),
+    ("dark_blue", Color::DarkBlue),
+    ("magenta", Color::Magenta),
+    ("dark_magenta", Color::DarkMagenta),
+    ("cyan", Color::Cyan),
+    ("dark_cyan", Color::DarkCyan),
+    ("white", Color::White),
+    ("grey", Color::Grey),
+];
+
+pub fn parse(text: &str) -> Result<Color, String> {
+    let text = text.trim();
+    if let Some(hex) = text.strip_prefix('#') {
+        let channel = |i: usize| {
+            hex.get(i..i + 2)
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+        };
+        return match (hex.len(), channel(0), channel(2), channel(4)) {
+            (6, Some(r), Some(g), Some(b)) => Ok(Color::Rgb { r, g, b }),
+            _ => Err(format!("invalid hex color '{text}', expected #rrggbb")),
+        };
+    }
+    if let Ok(index) = text.parse::<i64>() {
+        return index_color(index);
+    }
+    let name = text
+        .to_ascii_lowercase()
+        .replace(['-', ' '], "_")
+        .replace("gray", "grey");
+    NAMES
+        .iter()
+        .find(|(known, _)| *known == name)
+        .map(|(_, color)| *color)
+        .ok_or_else(|| {
+            format!(
+                "unknown color '{text}'; use a name like \"cyan\", an ANSI index 0-255 or #rrggbb"
+            )
+        })
+}
+
+fn index_color(index: i64) -> Result<Color, String> {
+    u8::try_from(index)
+        .map(Color::AnsiValue)
+        .map_err(|_| format!("ANSI color index {index} is out of range 0-255"))
+}
+
+/// A color given as a string or a bare ANSI index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "toml::Value")]
+pub struct ColorValue(pub Color);
+
+impl TryFrom<toml::Value> for ColorValue {
+    type Error = String;
+
+    fn try_from(value: toml::Value) -> Result<Self, String> {
+        match value {
+            toml::Value::String(text) => parse(&text).map(ColorValue),
+            toml::Value::Integer(index) => index_color(index).map(ColorValue),
+            other => Err(format!("expected a color, found {}", other.type_str())),
+        }
+    }
+}
+
+/// Overrides for individual skin elements, applied over the theme's skin.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Colors {
+    pub headers: Option<ColorValue>,
+    pub bold: Option<ColorValue>,
+    pub italic: Option<ColorValue>,
+    pub inline_code_fg: Option<ColorValue>,
+    pub inline_code_bg: Option<ColorValue>,
+    pub code_block_bg: Option<ColorValue>,
+    pub table: Option<ColorValue>,
+}
+
+impl Colors {
+    pub fn apply(&self, skin: &mut MadSkin) {
+        if let Some(ColorValue(color)) = self.headers {
+            skin.set_headers_fg(color);
+        }
+        if let Some(ColorValue(color)) = self.bold {
+            skin.bold.set_fg(color);
+        }
+        if let Some(ColorValue(color)) = self.italic {
+            skin.italic.set_fg(color);
+        }
+        if let Some(ColorValue(color)) = self.inline_code_fg {
+            skin.inline_code.set_fg(color);
+        }
+        if let Some(ColorValue(color)) = self.inline_code_bg {
+            skin.inline_code.set_bg(color);
+        }
+        if let Some(ColorValue(color)) = self.code_block_bg {
+            skin.code_block.set_bg(color);
+        }
+        if let Some(ColorValue(color)) = self.table {
+            skin.table.set_fg(color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_forms() {
+        assert_eq!(parse("Cyan"), Ok(Color::Cyan));
+        assert_eq!(parse("dark-gray"), Ok(Color::DarkGrey));
+        assert_eq!(parse("236"), Ok(Color::AnsiValue(236)));
+        assert_eq!(
+            parse("#88c0D0"),
+            Ok(Color::Rgb {
+                r: 0x88,
+                g: 0xc0,
+                b: 0xd0
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse("teal").unwrap_err().contains("unknown color 'teal'"));
+        assert!(parse("256").unwrap_err().contains("out of range"));
+        assert!(parse("#fff").unwrap_err().contains("#rrggbb"));
+        assert!(parse("#gg0000").is_err());
+    }
+
+    #[test]
+    fn test_colors_table() {
+        let colors: Colors = toml::from_str("headers = \"cyan\"\ninline_code_bg = 236\n").unwrap();
+        assert_eq!(colors.headers, Some(ColorValue(Color::Cyan)));
+        assert_eq!(
+            colors.inline_code_bg,
+            Some(ColorValue(Color::AnsiValue(236)))
+        );
+        assert!(toml::from_str::<Colors>("heading = \"cyan\"\n").is_err());
+    }
+}
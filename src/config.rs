@@ -0,0 +1,874 @@
+//! Optional `cheetsheet.toml` in the config directory. Its values seed the
+//! defaults; `CHEETSHEET_*` environment variables override them and
+//! command-line flags override both.
+
+use crate::colors::Colors;
+use crate::error::CheetError;
+use crate::explain;
+use crate::fuzzy::Matcher;
+use crate::locale;
+use crate::prose::{ListStyle, Renderer};
+use crate::term;
+use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const FILE_NAME: &str = "cheetsheet.toml";
+
+/// The file `init` writes: every key, commented out at its default.
+pub const STARTER: &str = r##"# cheetsheet settings. Uncomment a line to change it; flags and
+# CHEETSHEET_<KEY> environment variables override these values.
+
+# theme = "dark"               # or "light"
+# code_theme = "base16-ocean.dark"
+# width = 100                  # wrap at most this wide
+# margin = 0                   # blank columns on each side of the sheet
+# pager = "less -R"            # for man fallbacks; default $PAGER
+# editor = "nvim"              # for `edit`; default $VISUAL, then $EDITOR
+# age_recipients = []          # for `encrypt`, e.g. ["age1..."]; a passphrase when empty
+# age_identities = []          # key files for .age sheets, e.g. ["/home/me/.age/key.txt"]
+# color = "auto"               # or "always", "never"
+# quiet = false                # no warnings, notices or blank lines around sheets
+# accessible = false           # plain text worded for screen readers
+# header = false               # sheet name, path and age above it
+# footer = false               # word and code block counts below it
+# show_meta = false            # front matter in a panel above the sheet
+# meta_keys = ["title", "description", "tags", "updated"]  # the panel's keys, in order
+# default_lang = "bash"        # for fences without a language
+# search_paths = []            # more directories to look sheets up in
+# lang = "zh-TW"               # prefer git.zh-TW.md, then git.zh.md; default the locale's
+# fallbacks = []               # e.g. ["man"] when no sheet matches
+# default_command = "list"     # or "picker", "recent", "view:<name>"
+# history = true               # keep a log of the sheets viewed, for `history` and `-`
+# history_size = 100           # views the log keeps
+# template = "template.md"     # for `new`, relative to this directory
+# author = "Your Name"         # {{author}} in templates; default git's user.name
+# extension = "md"             # or "markdown", for sheets cheetsheet creates
+# normalize_names = false      # create `my-tool.md` for "My Tool"
+# code_spacing_before = 1      # blank lines around code blocks, 0 to 2
+# code_spacing_after = 1
+# tab_width = 4                # columns between tab stops in code, 1 to 16
+# renderer = "termimad"       # or "cmark" for a CommonMark parser
+# reflow = true                # false keeps each line of prose as written
+# list_style = "decimal"       # or "alpha", "roman" for ordered lists
+# highlight_theme_bg = "on"    # "off" keeps code on the terminal's background
+# wide_tables = "wrap"         # or "clip", "records" for tables wider than the terminal
+# colors = { headers = "cyan", inline_code_bg = 236, table = "#88c0d0" }
+# block_renderers = { dot = "graph-easy --from=dot" }  # code on stdin, output shown
+# lang_aliases = { pwsh = "bash", tpl = "html" }       # fence tag = syntax to use
+"##;
+
+/// Declares `Config` along with `KEYS`, its field names, so every key is
+/// known to the unknown-key check and gets a `CHEETSHEET_<KEY>` variable.
+macro_rules! config {
+    ($($(#[$meta:meta])* $key:ident: $ty:ty,)*) => {
+        #[derive(Debug, Default, Deserialize)]
+        #[serde(default)]
+        pub struct Config {
+            $($(#[$meta])* pub $key: $ty,)*
+        }
+
+        const KEYS: &[&str] = &[$(stringify!($key)),*];
+    };
+}
+
+config! {
+    theme: Option<Theme>,
+    /// syntect theme for code blocks.
+    code_theme: Option<String>,
+    /// Maximum render width; the terminal width still applies when smaller.
+    width: Option<usize>,
+    /// Blank columns on each side of the sheet.
+    margin: Option<usize>,
+    /// Pager for man fallbacks, instead of `$PAGER`.
+    pager: Option<String>,
+    /// Editor for `edit`, instead of `$VISUAL`/`$EDITOR`.
+    editor: Option<String>,
+    /// `age` recipients `encrypt` encrypts sheets for; a passphrase when empty.
+    age_recipients: Vec<String>,
+    /// `age` identity files encrypted sheets are decrypted with.
+    age_identities: Vec<PathBuf>,
+    color: Option<ColorMode>,
+    /// Leave out warnings, notices and blank lines around the sheet.
+    quiet: bool,
+    /// Plain text worded for screen readers, with no color.
+    accessible: bool,
+    /// A line with the sheet's name, path and age above it.
+    header: bool,
+    /// A line counting the sheet's words and code blocks below it.
+    footer: bool,
+    /// Show the front matter in a panel above the sheet.
+    show_meta: bool,
+    /// Front matter keys the panel lists, in order; all of them when empty.
+    meta_keys: Vec<String>,
+    /// Language for code blocks whose fence names none.
+    default_lang: Option<String>,
+    /// More directories to look sheets up in, after the config directory.
+    search_paths: Vec<PathBuf>,
+    /// Language whose sheet variants to prefer, instead of the locale's.
+    lang: Option<Lang>,
+    fallbacks: Vec<Fallback>,
+    /// What a bare `cheetsheet` does on a terminal.
+    default_command: Option<DefaultCommand>,
+    /// Log the sheets viewed by name; on unless set to false.
+    history: Option<bool>,
+    /// How many views the log keeps.
+    history_size: Option<usize>,
+    /// Template for `new`, relative to the config directory.
+    template: Option<PathBuf>,
+    /// `{{author}}` in templates, instead of git's `user.name`.
+    author: Option<String>,
+    /// Extension for markdown sheets that cheetsheet creates.
+    extension: Option<Extension>,
+    /// Create sheets under lowercase, dash-separated names.
+    normalize_names: bool,
+    /// Blank lines before each code block.
+    code_spacing_before: Option<Spacing>,
+    /// Blank lines after each code block.
+    code_spacing_after: Option<Spacing>,
+    /// Columns between tab stops when expanding tabs in code.
+    tab_width: Option<TabWidth>,
+    /// Backend laying out prose.
+    renderer: Option<Renderer>,
+    /// Join and wrap paragraphs; false keeps each line as written.
+    reflow: Option<bool>,
+    /// Numbering of ordered lists.
+    list_style: Option<ListStyle>,
+    /// Whether code blocks get a background from the themes.
+    highlight_theme_bg: Option<HighlightBg>,
+    /// What becomes of tables wider than the terminal.
+    wide_tables: Option<WideTables>,
+    /// Colors of individual skin elements, over the theme's.
+    colors: Colors,
+    /// Shell commands drawing code blocks of a fence language.
+    block_renderers: BTreeMap<String, String>,
+    /// Fence tags highlighted as another language, over the built-in ones.
+    lang_aliases: BTreeMap<String, String>,
+}
+
+/// Prefix of the environment variables overriding config keys.
+const ENV_PREFIX: &str = "CHEETSHEET_";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    /// Color when stdout is a terminal that isn't `TERM=dumb` and `NO_COLOR` is unset
+    #[default]
+    Auto,
+    /// Color even when piped, e.g. into `less -R`, and despite `NO_COLOR`
+    Always,
+    /// No escape codes at all
+    Never,
+}
+
+/// Backgrounds behind code: the syntect theme's when on, none at all when off.
+/// Left unset, code blocks keep the skin's background only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum HighlightBg {
+    On,
+    Off,
+}
+
+/// What becomes of a markdown table wider than the terminal.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum WideTables {
+    /// Wrap the text inside every cell to fit
+    #[default]
+    Wrap,
+    /// Keep the columns that fit and name the ones left out
+    Clip,
+    /// One `header: value` line per cell, a blank line between rows
+    Records,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Fallback {
+    /// The system man page, via `man`
+    Man,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Extension {
+    #[default]
+    Md,
+    Markdown,
+}
+
+impl Extension {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Extension::Md => "md",
+            Extension::Markdown => "markdown",
+        }
+    }
+}
+
+/// A number of blank lines around code blocks, from 0 to 2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "i64")]
+pub struct Spacing(pub usize);
+
+impl Default for Spacing {
+    fn default() -> Self {
+        Spacing(1)
+    }
+}
+
+impl TryFrom<i64> for Spacing {
+    type Error = String;
+
+    fn try_from(value: i64) -> Result<Self, String> {
+        match value {
+            0..=2 => Ok(Spacing(value as usize)),
+            _ => Err(format!("spacing must be 0, 1 or 2 lines, not {value}")),
+        }
+    }
+}
+
+impl std::str::FromStr for Spacing {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, String> {
+        let value: i64 = text
+            .parse()
+            .map_err(|_| format!("'{text}' is not a number"))?;
+        Spacing::try_from(value)
+    }
+}
+
+/// Columns between tab stops in code blocks, from 1 to 16.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "i64")]
+pub struct TabWidth(pub usize);
+
+impl Default for TabWidth {
+    fn default() -> Self {
+        TabWidth(4)
+    }
+}
+
+impl TryFrom<i64> for TabWidth {
+    type Error = String;
+
+    fn try_from(value: i64) -> Result<Self, String> {
+        match value {
+            1..=16 => Ok(TabWidth(value as usize)),
+            _ => Err(format!("tab width must be 1 to 16 columns, not {value}")),
+        }
+    }
+}
+
+impl std::str::FromStr for TabWidth {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, String> {
+        let value: i64 = text
+            .parse()
+            .map_err(|_| format!("'{text}' is not a number"))?;
+        TabWidth::try_from(value)
+    }
+}
+
+/// A language code, e.g. `zh-TW` or `zh`, as variant files are named.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Lang(pub String);
+
+impl TryFrom<String> for Lang {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, String> {
+        value.parse()
+    }
+}
+
+impl std::str::FromStr for Lang {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, String> {
+        locale::parse(text)
+            .map(Lang)
+            .ok_or_else(|| format!("'{text}' is not a language code, e.g. zh or zh-TW"))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum DefaultCommand {
+    List,
+    Picker,
+    Recent,
+    View(String),
+}
+
+impl TryFrom<String> for DefaultCommand {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, String> {
+        match value.as_str() {
+            "list" => Ok(DefaultCommand::List),
+            "picker" => Ok(DefaultCommand::Picker),
+            "recent" => Ok(DefaultCommand::Recent),
+            _ => match value.strip_prefix("view:") {
+                Some(name) if !name.is_empty() => Ok(DefaultCommand::View(name.to_string())),
+                _ => Err(format!(
+                    "unknown default_command '{value}', expected one of: list, picker, recent, view:<name>"
+                )),
+            },
+        }
+    }
+}
+
+impl ColorMode {
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorMode::Auto => term::auto_color(),
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+        }
+    }
+}
+
+impl Config {
+    /// Reads `path` and applies environment overrides. A missing file means
+    /// defaults, unless `required`.
+    pub fn load(path: &Path, required: bool) -> Result<Config> {
+        let mut table = read_file(path, required)?;
+        table.extend(env_table()?);
+        table
+            .try_into()
+            .with_context(|| CheetError::Config(format!("invalid config {}", path.display())))
+    }
+}
+
+/// Where the effective value of a key comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Default,
+    File,
+    Env,
+    Flag,
+}
+
+impl Source {
+    /// `env CHEETSHEET_WIDTH`, `file /path/cheetsheet.toml`, ...
+    pub fn describe(self, key: &str, path: &Path) -> String {
+        match self {
+            Source::Default => "default".to_string(),
+            Source::File => format!("file {}", path.display()),
+            Source::Env => format!("env {}", env_name(key)),
+            Source::Flag => format!("flag --{}", key.replace('_', "-")),
+        }
+    }
+}
+
+/// The effective value of every key, with its source; `None` when unset.
+/// `flags` holds the keys given on the command line.
+pub fn effective(
+    path: &Path,
+    required: bool,
+    flags: &toml::Table,
+) -> Result<Vec<(&'static str, Option<toml::Value>, Source)>> {
+    let file = read_file(path, required)?;
+    let env = env_table()?;
+    let layers = [
+        (flags, Source::Flag),
+        (&env, Source::Env),
+        (&file, Source::File),
+    ];
+    Ok(KEYS
+        .iter()
+        .map(|key| {
+            layers
+                .iter()
+                .find_map(|(table, source)| Some((*key, Some(table.get(*key)?.clone()), *source)))
+                .unwrap_or((key, None, Source::Default))
+        })
+        .collect())
+}
+
+/// Sets `key` in the file at `path`, creating it if needed. Comments and
+/// the formatting of other entries are kept; the file is replaced
+/// atomically.
+pub fn set(path: &Path, key: &str, raw: &str) -> Result<()> {
+    let value = parse_value(key, raw)?;
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+    };
+    let mut document: toml_edit::DocumentMut = text
+        .parse()
+        .with_context(|| CheetError::Config(format!("invalid config {}", path.display())))?;
+    let value: toml_edit::Value = value.to_string().parse()?;
+    match document.get_mut(key).and_then(|item| item.as_value_mut()) {
+        // Keep the entry's surrounding whitespace and trailing comment
+        Some(existing) => {
+            let decor = existing.decor().clone();
+            *existing = value;
+            *existing.decor_mut() = decor;
+        }
+        None => document[key] = toml_edit::value(value),
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    let temp = path.with_extension("toml.tmp");
+    fs::write(&temp, document.to_string())
+        .with_context(|| format!("failed to write {}", temp.display()))?;
+    fs::rename(&temp, path).with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Checks `key` is a config key.
+pub fn check_key(key: &str) -> Result<()> {
+    if !KEYS.contains(&key) {
+        bail!(CheetError::Config(format!(
+            "unknown config key '{key}'; valid keys: {}",
+            KEYS.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+/// Accepted range for `width`.
+const WIDTH_RANGE: std::ops::RangeInclusive<usize> = 20..=1000;
+
+/// A problem in a config file, at a 1-based line and column.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Problem {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+    /// Unknown keys only warn on load, but fail `config check`.
+    pub unknown_key: bool,
+}
+
+impl Problem {
+    fn at(text: &str, offset: usize, message: String) -> Self {
+        let before = &text[..offset.min(text.len())];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        Problem {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            message,
+            unknown_key: false,
+        }
+    }
+
+    /// `path:line:column: message`
+    pub fn describe(&self, path: &Path) -> String {
+        format!(
+            "{}:{}:{}: {}",
+            path.display(),
+            self.line,
+            self.column,
+            self.message
+        )
+    }
+}
+
+/// Validates config `text` key by key, collecting every problem instead of
+/// stopping at the first. `code_themes`, when given, are the names
+/// `code_theme` may take.
+pub fn check(text: &str, code_themes: Option<&[String]>) -> Vec<Problem> {
+    let document = match toml_edit::Document::parse(text) {
+        Ok(document) => document,
+        Err(e) => {
+            let offset = e.span().map_or(0, |span| span.start);
+            return vec![Problem::at(text, offset, e.message().trim().to_string())];
+        }
+    };
+    let Ok(values) = toml::from_str::<toml::Table>(text) else {
+        return Vec::new();
+    };
+    let keys: Vec<String> = KEYS.iter().map(|key| key.to_string()).collect();
+    let mut problems = Vec::new();
+    for (key, item) in document.iter() {
+        let key_offset = document
+            .key(key)
+            .and_then(|k| k.span())
+            .map_or(0, |s| s.start);
+        let value_offset = item.span().map_or(key_offset, |span| span.start);
+        if !KEYS.contains(&key) {
+            let mut message = format!("unknown config key '{key}'");
+            if let Some(close) = Matcher::default().suggest(key, &keys).first() {
+                message.push_str(&format!(", did you mean '{close}'?"));
+            }
+            let mut problem = Problem::at(text, key_offset, message);
+            problem.unknown_key = true;
+            problems.push(problem);
+            continue;
+        }
+        let value = &values[key];
+        // Check each entry of a table on its own, to point at the bad one
+        let entries: Vec<(String, toml::Value, usize)> = match (value, item.as_table_like()) {
+            (toml::Value::Table(table), Some(table_like)) => table
+                .iter()
+                .map(|(sub, value)| {
+                    let offset = table_like
+                        .get(sub)
+                        .and_then(|item| item.span())
+                        .or_else(|| table_like.key(sub).and_then(|k| k.span()))
+                        .map_or(value_offset, |span| span.start);
+                    let single = toml::Table::from_iter([(sub.clone(), value.clone())]);
+                    (format!("{key}.{sub}"), toml::Value::Table(single), offset)
+                })
+                .collect(),
+            _ => vec![(key.to_string(), value.clone(), value_offset)],
+        };
+        for (label, value, offset) in entries {
+            if let Some(message) = value_problem(key, &label, value, code_themes) {
+                problems.push(Problem::at(text, offset, message));
+            }
+        }
+    }
+    problems
+}
+
+/// What is wrong with `value` for `key`, which is `label` in messages.
+fn value_problem(
+    key: &str,
+    label: &str,
+    value: toml::Value,
+    code_themes: Option<&[String]>,
+) -> Option<String> {
+    let single = toml::Table::from_iter([(key.to_string(), value.clone())]);
+    if let Err(e) = single.try_into::<Config>() {
+        let message = e.message().trim();
+        // The key is already located by line and column
+        let message = message.split("\nin `").next().unwrap_or(message);
+        return Some(format!("invalid {label}: {message}"));
+    }
+    match (key, &value, code_themes) {
+        ("width", toml::Value::Integer(width), _)
+            if !WIDTH_RANGE.contains(&usize::try_from(*width).unwrap_or(0)) =>
+        {
+            Some(format!(
+                "width {width} is out of range {}-{}",
+                WIDTH_RANGE.start(),
+                WIDTH_RANGE.end()
+            ))
+        }
+        ("code_theme", toml::Value::String(theme), Some(themes)) if !themes.contains(theme) => {
+            let mut message = format!("unknown code theme \"{theme}\"");
+            match Matcher::default().suggest(theme, themes).first() {
+                Some(close) => message.push_str(&format!(", did you mean \"{close}\"?")),
+                None => message.push_str(&format!("; available: {}", themes.join(", "))),
+            }
+            Some(message)
+        }
+        _ => None,
+    }
+}
+
+/// Reads the config file, warning about unknown keys and failing on any
+/// other problem.
+fn read_file(path: &Path, required: bool) -> Result<toml::Table> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => {
+            log::info!("reading config {}", path.display());
+            text
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound && !required => {
+            log::info!("no config at {}, using defaults", path.display());
+            return Ok(toml::Table::new());
+        }
+        Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+    };
+    let (warnings, errors): (Vec<Problem>, Vec<Problem>) =
+        check(&text, None).into_iter().partition(|p| p.unknown_key);
+    for warning in warnings {
+        explain::warn(warning.describe(path));
+    }
+    if !errors.is_empty() {
+        let errors: Vec<String> = errors.iter().map(|e| e.describe(path)).collect();
+        bail!(CheetError::Config(format!(
+            "invalid config\n{}",
+            errors.join("\n")
+        )));
+    }
+    parse_table(&text)
+        .with_context(|| CheetError::Config(format!("invalid config {}", path.display())))
+}
+
+fn parse_table(text: &str) -> Result<toml::Table> {
+    Ok(toml::from_str(text)?)
+}
+
+/// `CHEETSHEET_CODE_THEME` for `code_theme`.
+fn env_name(key: &str) -> String {
+    format!("{ENV_PREFIX}{}", key.to_ascii_uppercase())
+}
+
+/// The keys set through `CHEETSHEET_*` variables.
+fn env_table() -> Result<toml::Table> {
+    let mut table = toml::Table::new();
+    apply_env(&mut table, |name| std::env::var(name).ok())?;
+    Ok(table)
+}
+
+/// Overrides keys of `table` with the environment variables `var` returns.
+fn apply_env(table: &mut toml::Table, var: impl Fn(&str) -> Option<String>) -> Result<()> {
+    for key in KEYS {
+        let name = env_name(key);
+        if let Some(raw) = var(&name) {
+            log::info!("{name} sets {key}");
+            let value = parse_value(key, &raw).with_context(|| name.clone())?;
+            table.insert(key.to_string(), value);
+        }
+    }
+    Ok(())
+}
+
+/// Reads a value given as text for `key`: as TOML when it parses as such
+/// (`80`, `["a", "b"]`), as a plain string otherwise, and for lists also as
+/// `a,b`. The first reading that fits the key's type wins.
+fn parse_value(key: &str, raw: &str) -> Result<toml::Value> {
+    check_key(key)?;
+    let fits = |value: &toml::Value| {
+        toml::Table::from_iter([(key.to_string(), value.clone())]).try_into::<Config>()
+    };
+    let string = toml::Value::String(raw.to_string());
+    let candidates = [
+        toml::from_str::<toml::Table>(&format!("value = {raw}"))
+            .ok()
+            .and_then(|mut t| t.remove("value")),
+        Some(string.clone()),
+        Some(toml::Value::Array(
+            raw.split(',')
+                .map(|item| toml::Value::String(item.trim().to_string()))
+                .collect(),
+        )),
+    ];
+    if let Some(value) = candidates.into_iter().flatten().find(|v| fits(v).is_ok()) {
+        return Ok(value);
+    }
+    let reason = fits(&string).err().map(|e| e.message().to_string());
+    bail!(CheetError::Config(format!(
+        "invalid value '{raw}' for '{key}': {}",
+        reason.unwrap_or_default().trim()
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<Config> {
+        Ok(parse_table(text)?.try_into()?)
+    }
+
+    #[test]
+    fn test_parse() {
+        let config = parse(
+            "theme = \"light\"\nwidth = 80\nfallbacks = [\"man\"]\nsearch_paths = [\"/srv/sheets\"]\n",
+        )
+        .unwrap();
+        assert_eq!(config.theme, Some(Theme::Light));
+        assert_eq!(config.width, Some(80));
+        assert_eq!(config.fallbacks, [Fallback::Man]);
+        assert_eq!(config.search_paths, [PathBuf::from("/srv/sheets")]);
+        assert_eq!(config.pager, None);
+    }
+
+    #[test]
+    fn test_unknown_key_is_not_an_error() {
+        let config = parse("colour = \"never\"\n").unwrap();
+        assert_eq!(config.color, None);
+    }
+
+    #[test]
+    fn test_bad_value_is_an_error() {
+        assert!(parse("width = \"wide\"\n").is_err());
+    }
+
+    #[test]
+    fn test_starter_lists_every_key() {
+        let uncommented: String = STARTER
+            .lines()
+            .filter_map(|line| line.strip_prefix("# "))
+            .filter(|line| line.contains(" = "))
+            .map(|line| format!("{line}\n"))
+            .collect();
+        let table = parse_table(&uncommented).unwrap();
+        for key in KEYS {
+            assert!(table.contains_key(*key), "{key} missing from STARTER");
+        }
+        assert!(toml::Value::Table(table).try_into::<Config>().is_ok());
+    }
+
+    #[test]
+    fn test_check_locates_problems() {
+        let text =
+            "width = 5\ncolour = \"never\"\n\n[colors]\nbold = \"red\"\nheaders = \"teal\"\n";
+        let problems = check(text, None);
+        assert_eq!(problems.len(), 3);
+        assert_eq!((problems[0].line, problems[0].column), (1, 9));
+        assert!(problems[0].message.contains("out of range"));
+        assert!(problems[1].unknown_key);
+        assert!(problems[1].message.contains("did you mean 'color'?"));
+        assert_eq!((problems[2].line, problems[2].column), (6, 11));
+        assert!(
+            problems[2]
+                .message
+                .starts_with("invalid colors.headers: unknown color")
+        );
+    }
+
+    #[test]
+    fn test_check_code_theme() {
+        let themes = [
+            "base16-ocean.dark".to_string(),
+            "Solarized (dark)".to_string(),
+        ];
+        let problems = check("code_theme = \"base16-ocean.drk\"\n", Some(&themes));
+        assert_eq!(
+            problems[0].message,
+            "unknown code theme \"base16-ocean.drk\", did you mean \"base16-ocean.dark\"?"
+        );
+        assert!(check("code_theme = \"Solarized (dark)\"\n", Some(&themes)).is_empty());
+        assert!(check("width = \n", None)[0].line == 1);
+    }
+
+    #[test]
+    fn test_code_spacing_range() {
+        let config = parse("code_spacing_before = 0\ncode_spacing_after = 2\n").unwrap();
+        assert_eq!(config.code_spacing_before, Some(Spacing(0)));
+        assert_eq!(config.code_spacing_after, Some(Spacing(2)));
+        assert!(parse("code_spacing_after = 3\n").is_err());
+        assert!("-1".parse::<Spacing>().is_err());
+    }
+
+    #[test]
+    fn test_tab_width_range() {
+        assert_eq!(
+            parse("tab_width = 8\n").unwrap().tab_width,
+            Some(TabWidth(8))
+        );
+        assert!(parse("tab_width = 0\n").is_err());
+        assert_eq!("2".parse::<TabWidth>(), Ok(TabWidth(2)));
+        assert!("17".parse::<TabWidth>().is_err());
+    }
+
+    fn with_env(text: &str, vars: &[(&str, &str)]) -> Result<Config> {
+        let mut table = parse_table(text)?;
+        apply_env(&mut table, |name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.to_string())
+        })?;
+        Ok(table.try_into()?)
+    }
+
+    #[test]
+    fn test_env_overrides_file() {
+        let file = "width = 80\npager = \"more\"\ntheme = \"dark\"\n";
+        let config = with_env(
+            file,
+            &[("CHEETSHEET_WIDTH", "100"), ("CHEETSHEET_THEME", "light")],
+        )
+        .unwrap();
+        assert_eq!(config.width, Some(100));
+        assert_eq!(config.theme, Some(Theme::Light));
+        assert_eq!(config.pager.as_deref(), Some("more"));
+    }
+
+    #[test]
+    fn test_env_value_readings() {
+        let config = with_env(
+            "",
+            &[
+                ("CHEETSHEET_PAGER", "less -R"),
+                ("CHEETSHEET_CODE_THEME", "100"),
+                ("CHEETSHEET_SEARCH_PATHS", "/a, /b"),
+                ("CHEETSHEET_FALLBACKS", "man"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(config.pager.as_deref(), Some("less -R"));
+        assert_eq!(config.code_theme.as_deref(), Some("100"));
+        assert_eq!(config.search_paths, [Path::new("/a"), Path::new("/b")]);
+        assert_eq!(config.fallbacks, [Fallback::Man]);
+    }
+
+    #[test]
+    fn test_env_type_error_names_variable() {
+        let err = with_env("", &[("CHEETSHEET_WIDTH", "wide")]).unwrap_err();
+        assert!(format!("{err:#}").starts_with("CHEETSHEET_WIDTH: invalid value 'wide'"));
+    }
+
+    #[test]
+    fn test_set_keeps_comments() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join(FILE_NAME);
+        fs::write(
+            &path,
+            "# my settings\nwidth = 80 # narrow\npager = \"more\"\n",
+        )
+        .unwrap();
+
+        set(&path, "width", "100").unwrap();
+        set(&path, "theme", "light").unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# my settings\nwidth = 100 # narrow\npager = \"more\"\ntheme = \"light\"\n"
+        );
+    }
+
+    #[test]
+    fn test_set_rejects_bad_input() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join(FILE_NAME);
+        let err = set(&path, "colour", "never").unwrap_err().to_string();
+        assert!(err.contains("valid keys: theme, code_theme"));
+        let err = set(&path, "theme", "gruvbox-dark").unwrap_err().to_string();
+        assert!(err.contains("`dark`"), "{err}");
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_default_command() {
+        let config = parse("default_command = \"view:git\"\n").unwrap();
+        assert_eq!(
+            config.default_command,
+            Some(DefaultCommand::View("git".into()))
+        );
+        let err = parse("default_command = \"tui\"\n").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("list, picker, recent, view:<name>")
+        );
+    }
+
+    #[test]
+    fn test_lang() {
+        let config = parse("lang = \"zh_TW\"\n").unwrap();
+        assert_eq!(config.lang, Some(Lang("zh-TW".into())));
+        let err = parse("lang = \"chinese\"\n").unwrap_err();
+        assert!(err.to_string().contains("not a language code"));
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let path = Path::new("/nonexistent/cheetsheet.toml");
+        assert!(Config::load(path, false).is_ok());
+        assert!(Config::load(path, true).is_err());
+    }
+}
@@ -0,0 +1,91 @@
+//! GitHub-style collapsible `<details>` blocks. The terminal can't fold
+//! them, so the `<summary>` becomes a heading and the body is either kept
+//! as-is or left out entirely.
+
+use std::borrow::Cow;
+
+/// Rewrites the `<details>` blocks of a markdown sheet. Tags inside fenced
+/// code are left alone. Sheets without any are borrowed unchanged.
+pub fn convert(content: &str, collapse: bool) -> Cow<'_, str> {
+    if !content.contains("<details") {
+        return Cow::Borrowed(content);
+    }
+    let mut out = String::with_capacity(content.len());
+    let mut in_fence = false;
+    let mut depth = 0;
+    // Depth of the outermost collapsed block, if any
+    let mut hidden_from: Option<usize> = None;
+    let mut awaiting_summary = false;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if !in_fence && trimmed.starts_with("<details") {
+            depth += 1;
+            let rest = trimmed[trimmed.find('>').map_or(trimmed.len(), |i| i + 1)..].trim();
+            if hidden_from.is_none() {
+                match summary(rest) {
+                    Some(summary) => out.push_str(&format!("#### {summary}\n")),
+                    None => awaiting_summary = true,
+                }
+                if collapse {
+                    hidden_from = Some(depth);
+                }
+            }
+            continue;
+        }
+        if awaiting_summary && !trimmed.is_empty() {
+            awaiting_summary = false;
+            if let Some(summary) = summary(trimmed) {
+                out.push_str(&format!("#### {summary}\n"));
+                continue;
+            }
+        }
+        if !in_fence && trimmed == "</details>" {
+            if hidden_from == Some(depth) {
+                hidden_from = None;
+            }
+            depth = depth.saturating_sub(1);
+            continue;
+        }
+        if trimmed.starts_with("```") {
+            in_fence = !in_fence;
+        }
+        if hidden_from.is_none() {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// The text of a `<summary>…</summary>` line.
+fn summary(line: &str) -> Option<&str> {
+    let inner = line.strip_prefix("<summary>")?;
+    Some(inner.strip_suffix("</summary>").unwrap_or(inner).trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHEET: &str = "Intro\n<details>\n<summary>Verbose flags</summary>\n\n```bash\n<details>\n```\n</details>\nAfter\n";
+
+    #[test]
+    fn test_summary_becomes_heading() {
+        assert_eq!(
+            convert(SHEET, false),
+            "Intro\n#### Verbose flags\n\n```bash\n<details>\n```\nAfter\n"
+        );
+    }
+
+    #[test]
+    fn test_collapse_hides_body() {
+        assert_eq!(convert(SHEET, true), "Intro\n#### Verbose flags\nAfter\n");
+    }
+
+    #[test]
+    fn test_summary_on_details_line() {
+        let sheet = "<details open><summary>More</summary>\nbody\n</details>\n";
+        assert_eq!(convert(sheet, false), "#### More\nbody\n");
+    }
+}
@@ -0,0 +1,85 @@
+//! The kinds of failure scripts can tell apart by exit code. Errors still
+//! travel as `anyhow::Error`; a `CheetError` anywhere in the chain, as the
+//! error itself or as context, decides the kind.
+
+use std::io;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CheetError {
+    /// No sheet, part, heading or template by that name.
+    #[error("{0}")]
+    NotFound(String),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// A config file, value or profile name that isn't valid.
+    #[error("{0}")]
+    Config(String),
+    /// A sheet or template that can't be understood.
+    #[error("{0}")]
+    Parse(String),
+    /// The pager, editor, opener or git failing.
+    #[error("{0}")]
+    External(String),
+}
+
+impl CheetError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CheetError::NotFound(_) => 3,
+            CheetError::Io(_) => 4,
+            CheetError::Config(_) => 5,
+            CheetError::Parse(_) => 6,
+            CheetError::External(_) => 7,
+        }
+    }
+
+    /// The name used for it by `--json-errors`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CheetError::NotFound(_) => "not_found",
+            CheetError::Io(_) => "io",
+            CheetError::Config(_) => "config",
+            CheetError::Parse(_) => "parse",
+            CheetError::External(_) => "external",
+        }
+    }
+}
+
+/// The exit code and kind of `e`: its `CheetError`, else `io` when an I/O
+/// error caused it, else 1 and `other`.
+pub fn classify(e: &anyhow::Error) -> (i32, &'static str) {
+    if let Some(error) = e.downcast_ref::<CheetError>() {
+        return (error.exit_code(), error.kind());
+    }
+    if e.chain().any(|cause| cause.is::<io::Error>()) {
+        return (4, "io");
+    }
+    (1, "other")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_classify() {
+        let not_found = anyhow::Error::new(CheetError::NotFound("no sheet".into()));
+        assert_eq!(
+            classify(&not_found.context("while listing")),
+            (3, "not_found")
+        );
+
+        let config: anyhow::Result<()> = Err(anyhow::anyhow!("bad toml"))
+            .context(CheetError::Config("invalid config".into()))
+            .context("outer");
+        let config = config.unwrap_err();
+        assert_eq!(classify(&config), (5, "config"));
+        assert_eq!(format!("{config:#}"), "outer: invalid config: bad toml");
+
+        let io: anyhow::Result<()> =
+            Err(io::Error::from(io::ErrorKind::PermissionDenied)).context("failed to read");
+        assert_eq!(classify(&io.unwrap_err()), (4, "io"));
+        assert_eq!(classify(&anyhow::anyhow!("odd")), (1, "other"));
+    }
+}
@@ -0,0 +1,99 @@
+//! `--examples-only`: a sheet's code blocks without the prose explaining
+//! them, each group under the heading it sat beneath for context.
+
+use crate::range;
+use crate::segment::Segment;
+
+/// The code blocks of `segments`, each preceded by the last heading seen
+/// when that differs from the previous block's. With `captions`, the line
+/// right above a block is kept too.
+pub fn select<'a>(
+    segments: impl IntoIterator<Item = Segment<'a>>,
+    captions: bool,
+) -> impl Iterator<Item = Segment<'a>> {
+    // The last heading, until a block has been shown under it
+    let mut heading: Option<String> = None;
+    let mut caption: Option<String> = None;
+    segments
+        .into_iter()
+        .flat_map(move |segment| {
+            let code = match segment {
+                Segment::Text(text) => {
+                    if let Some(line) = text
+                        .lines()
+                        .rev()
+                        .find(|line| range::heading(line).is_some())
+                    {
+                        heading = Some(line.trim().to_string());
+                    }
+                    caption = text
+                        .lines()
+                        .rev()
+                        .find(|line| !line.trim_start_matches(['>', ' ']).trim().is_empty())
+                        .filter(|line| range::heading(line).is_none())
+                        .map(str::to_string);
+                    return [None, None];
+                }
+                Segment::Marker(_) => return [None, None],
+                code => code,
+            };
+            let caption = caption.take().filter(|_| captions);
+            let context = match (heading.take(), caption) {
+                (Some(heading), Some(caption)) => format!("{heading}\n\n{caption}\n"),
+                (Some(heading), None) => format!("{heading}\n"),
+                (None, Some(caption)) => format!("{caption}\n"),
+                (None, None) => return [Some(code), None],
+            };
+            [Some(Segment::Text(context.into())), Some(code)]
+        })
+        .flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::segment::split_segments;
+
+    const SHEET: &str = "# Git\n\nIntro prose.\n\n## Branches\n\nList them:\n\n```bash\ngit branch\n```\n\nOr all of them:\n```bash\ngit branch -a\n```\n\n## Tags\n\n```bash\ngit tag\n```\n";
+
+    fn texts(segments: impl Iterator<Item = Segment<'static>>) -> Vec<String> {
+        segments
+            .map(|segment| match segment {
+                Segment::Text(text) => text.into_owned(),
+                Segment::Code { code, .. } => format!("code: {code}"),
+                _ => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_select_examples() {
+        let segments = split_segments(SHEET).into_iter().map(Segment::into_owned);
+        assert_eq!(
+            texts(select(segments, false)),
+            [
+                "## Branches\n",
+                "code: git branch",
+                "code: git branch -a",
+                "## Tags\n",
+                "code: git tag",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_select_examples_with_captions() {
+        let segments = split_segments(SHEET).into_iter().map(Segment::into_owned);
+        assert_eq!(
+            texts(select(segments, true)),
+            [
+                "## Branches\n\nList them:\n",
+                "code: git branch",
+                "Or all of them:\n",
+                "code: git branch -a",
+                "## Tags\n",
+                "code: git tag",
+            ]
+        );
+    }
+}
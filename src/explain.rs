@@ -0,0 +1,88 @@
+//! `--explain`: a JSON trace of how a sheet was found and rendered, printed
+//! to stderr for bug reports and scripts. Warnings go through `warn` so the
+//! trace can list them.
+
+use serde::Serialize;
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+/// Every warning printed so far.
+static WARNINGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Set by `-q`: warnings are kept for the trace but not printed.
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// Prints `warning: {message}` to stderr and keeps it for the trace.
+pub fn warn(message: impl Display) {
+    let message = message.to_string();
+    if !QUIET.load(Ordering::Relaxed) {
+        eprintln!("warning: {message}");
+    }
+    WARNINGS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push(message);
+}
+
+pub fn warnings() -> Vec<String> {
+    WARNINGS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Trace {
+    /// The sheet name looked up; `None` for a file or stdin.
+    pub query: Option<String>,
+    /// The sheet file rendered; `None` for stdin.
+    pub path: Option<PathBuf>,
+    /// The local overlay appended to it, if any.
+    pub overlay: Option<PathBuf>,
+    pub format: String,
+    pub profile: String,
+    /// The config file settings were read from; `None` when there is none.
+    pub config: Option<PathBuf>,
+    pub theme: String,
+    /// The syntect theme used; `None` when no code was highlighted.
+    pub code_theme: Option<String>,
+    pub renderer: String,
+    pub color: bool,
+    /// Segments rendered, after `--after`/`--before`.
+    pub segments: usize,
+    /// Languages code blocks were highlighted as.
+    pub languages: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+impl Trace {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("a trace always serializes")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_json() {
+        let trace = Trace {
+            path: Some(PathBuf::from("/sheets/git.md")),
+            segments: 2,
+            languages: vec!["bash".to_string()],
+            ..Trace::default()
+        };
+        let json: serde_json::Value = serde_json::from_str(&trace.to_json()).unwrap();
+        assert_eq!(json["path"], "/sheets/git.md");
+        assert_eq!(json["query"], serde_json::Value::Null);
+        assert_eq!(json["languages"][0], "bash");
+        assert_eq!(json["segments"], 2);
+    }
+}
@@ -0,0 +1,172 @@
+//! The `---` delimited block at the top of a markdown sheet, as written by
+//! `new --wizard` and `new --tags`.
+
+/// Splits `content` into its front matter, without delimiters, and body.
+/// Lines may end in `\r\n`, and the closing `---` may end the file.
+pub fn split(content: &str) -> (Option<&str>, &str) {
+    let Some(rest) = content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))
+    else {
+        return (None, content);
+    };
+    let mut end = 0;
+    for line in rest.split_inclusive('\n') {
+        // The closing line can't be the first
+        if end > 0 && is_delimiter(line) {
+            let front_matter = rest[..end].strip_suffix('\n').unwrap_or_default();
+            let front_matter = front_matter.strip_suffix('\r').unwrap_or(front_matter);
+            let body = rest[end + line.len()..].trim_start_matches(['\r', '\n']);
+            return (Some(front_matter), body);
+        }
+        end += line.len();
+    }
+    (None, content)
+}
+
+/// Whether `line`, with its line ending if any, is a `---` delimiter.
+pub(crate) fn is_delimiter(line: &str) -> bool {
+    matches!(line, "---\n" | "---\r\n" | "---")
+}
+
+/// The value of a one-line `key: value` entry, unquoted.
+pub fn value<'a>(content: &'a str, key: &str) -> Option<&'a str> {
+    split(content)
+        .0?
+        .lines()
+        .find_map(|line| line.strip_prefix(key)?.strip_prefix(':'))
+        .map(|value| value.trim().trim_matches(['"', '\'']))
+        .filter(|value| !value.is_empty())
+}
+
+/// Its top-level `key: value` entries in order, unquoted, with lists such as
+/// `tags` joined by commas. Entries without a value are left out.
+pub fn entries(content: &str) -> Vec<(String, String)> {
+    let Some(front_matter) = split(content).0 else {
+        return Vec::new();
+    };
+    let mut entries = Vec::new();
+    let mut lines = front_matter.lines().peekable();
+    while let Some(line) = lines.next() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        if key.starts_with([' ', '\t', '-']) {
+            continue;
+        }
+        let value = value.trim();
+        let items: Vec<&str> = if value.is_empty() {
+            std::iter::from_fn(|| {
+                lines
+                    .next_if(|line| line.trim_start().starts_with("- "))?
+                    .trim_start()
+                    .strip_prefix("- ")
+            })
+            .collect()
+        } else {
+            match value
+                .strip_prefix('[')
+                .and_then(|list| list.strip_suffix(']'))
+            {
+                Some(list) => list.split(',').collect(),
+                None => vec![value],
+            }
+        };
+        let items: Vec<&str> = items
+            .into_iter()
+            .map(|item| item.trim().trim_matches(['"', '\'']))
+            .filter(|item| !item.is_empty())
+            .collect();
+        if !items.is_empty() {
+            entries.push((key.trim().to_string(), items.join(", ")));
+        }
+    }
+    entries
+}
+
+/// The sheet's tags, from `tags: [a, b]`, `tags: a, b` or a YAML list.
+pub fn tags(content: &str) -> Vec<String> {
+    let Some(front_matter) = split(content).0 else {
+        return Vec::new();
+    };
+    let mut lines = front_matter.lines();
+    let Some(value) = lines.find_map(|line| line.strip_prefix("tags:")) else {
+        return Vec::new();
+    };
+    let value = value.trim();
+    let items: Vec<&str> = if value.is_empty() {
+        lines
+            .map_while(|line| line.trim_start().strip_prefix("- "))
+            .collect()
+    } else {
+        let value = value.strip_prefix('[').unwrap_or(value);
+        let value = value.strip_suffix(']').unwrap_or(value);
+        value.split(',').collect()
+    };
+    items
+        .into_iter()
+        .map(|tag| tag.trim().trim_matches(['"', '\'']).to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tags() {
+        assert_eq!(
+            tags("---\ntags: [git, \"vcs\"]\n---\n# git\n"),
+            ["git", "vcs"]
+        );
+        assert_eq!(tags("---\ntitle: x\ntags: net\n---\n"), ["net"]);
+        assert_eq!(
+            tags("---\ntags:\n  - a\n  - b\nother: 1\n---\n"),
+            ["a", "b"]
+        );
+        assert!(tags("# no front matter\ntags: [x]\n").is_empty());
+    }
+
+    #[test]
+    fn test_entries() {
+        let content = "---\ntitle: \"Git: Basics\"\ntags:\n  - git\n  - vcs\n\
+                       aliases: [g, 'gt']\nempty:\nupdated: 2024-05-01\n---\n# git\n";
+        assert_eq!(
+            entries(content),
+            [
+                ("title", "Git: Basics"),
+                ("tags", "git, vcs"),
+                ("aliases", "g, gt"),
+                ("updated", "2024-05-01"),
+            ]
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+        );
+        assert!(entries("# no front matter\n").is_empty());
+    }
+
+    #[test]
+    fn test_split() {
+        assert_eq!(
+            split("---\ntitle: x\n---\n\n# Git\n"),
+            (Some("title: x"), "# Git\n")
+        );
+        assert_eq!(
+            split("---\r\ntitle: x\r\ntags: a\r\n---\r\n\r\n# Git\r\n"),
+            (Some("title: x\r\ntags: a"), "# Git\r\n")
+        );
+        assert_eq!(split("---\ntitle: x\n---"), (Some("title: x"), ""));
+        assert_eq!(split("---\n---\n"), (None, "---\n---\n"));
+        assert_eq!(split("---\nunclosed\n"), (None, "---\nunclosed\n"));
+        assert_eq!(tags("---\r\ntags: [git]\r\n---\r\n# git\r\n"), ["git"]);
+    }
+
+    #[test]
+    fn test_value() {
+        let content = "---\ntitle: \"Git Basics\"\ntitles: no\ndraft: true\n---\n";
+        assert_eq!(value(content, "title"), Some("Git Basics"));
+        assert_eq!(value(content, "draft"), Some("true"));
+        assert_eq!(value(content, "tags"), None);
+        assert_eq!(value("title: x\n", "title"), None);
+    }
+}
@@ -0,0 +1,111 @@
+//! "Did you mean" suggestions for lookup misses.
+
+use clap::ValueEnum;
+
+/// Most suggestions listed in a lookup error.
+const MAX_SUGGESTIONS: usize = 3;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Algorithm {
+    /// Edit distance only
+    #[default]
+    Levenshtein,
+    /// Edit distance, plus names containing the query's letters in order (`kctl` → `kubectl`)
+    Subsequence,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Matcher {
+    pub max_distance: usize,
+    pub algorithm: Algorithm,
+}
+
+impl Default for Matcher {
+    fn default() -> Self {
+        Matcher {
+            max_distance: 2,
+            algorithm: Algorithm::default(),
+        }
+    }
+}
+
+impl Matcher {
+    /// Up to three candidates close to `query`, closest first. A candidate
+    /// in a subfolder also matches on its basename.
+    pub fn suggest<'a>(&self, query: &str, candidates: &'a [String]) -> Vec<&'a str> {
+        let mut ranked: Vec<(usize, &str)> = candidates
+            .iter()
+            .filter_map(|candidate| {
+                let base = candidate.rsplit('/').next().unwrap_or(candidate);
+                let distance = levenshtein(query, candidate).min(levenshtein(query, base));
+                let matches = distance <= self.max_distance
+                    || (self.algorithm == Algorithm::Subsequence
+                        && (is_subsequence(query, candidate) || is_subsequence(query, base)));
+                matches.then_some((distance, candidate.as_str()))
+            })
+            .collect();
+        ranked.sort();
+        ranked
+            .into_iter()
+            .take(MAX_SUGGESTIONS)
+            .map(|(_, name)| name)
+            .collect()
+    }
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+fn is_subsequence(query: &str, candidate: &str) -> bool {
+    let mut rest = candidate.chars();
+    !query.is_empty() && query.chars().all(|c| rest.any(|other| other == c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("gti", "git"), 2);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_suggest_ranks_and_limits() {
+        let candidates = names(&["gh", "git", "gist", "go", "tmux"]);
+        let matcher = Matcher::default();
+        assert_eq!(matcher.suggest("gi", &candidates), ["gh", "git", "go"]);
+        assert_eq!(matcher.suggest("tmx", &candidates), ["tmux"]);
+        assert!(matcher.suggest("docker", &candidates).is_empty());
+    }
+
+    #[test]
+    fn test_subsequence_algorithm() {
+        let candidates = names(&["cloud/kubectl", "kafka"]);
+        let levenshtein = Matcher::default();
+        assert!(levenshtein.suggest("kctl", &candidates).is_empty());
+        let subsequence = Matcher {
+            algorithm: Algorithm::Subsequence,
+            ..Matcher::default()
+        };
+        assert_eq!(subsequence.suggest("kctl", &candidates), ["cloud/kubectl"]);
+    }
+}
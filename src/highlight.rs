@@ -0,0 +1,222 @@
+//! Loading of syntect's syntax and theme sets, optionally through an
+//! uncompressed on-disk dump that is much faster to deserialize than the
+//! compressed defaults bundled with syntect. The common code themes are
+//! embedded one by one (see build.rs) so they load without the full set.
+
+use crate::explain;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use syntect::dumps::{
+    dump_to_uncompressed_file, from_uncompressed_data, from_uncompressed_dump_file,
+};
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::parsing::SyntaxSet;
+
+const SYNTAXES_FILE: &str = "syntaxes.bin";
+const THEMES_FILE: &str = "themes.bin";
+
+/// Themes dumped by build.rs, loadable without deserializing the others.
+const EMBEDDED_THEMES: &[(&str, &[u8])] = &[
+    (
+        "base16-ocean.dark",
+        include_bytes!(concat!(env!("OUT_DIR"), "/base16-ocean.dark.themedump")),
+    ),
+    (
+        "base16-ocean.light",
+        include_bytes!(concat!(env!("OUT_DIR"), "/base16-ocean.light.themedump")),
+    ),
+];
+
+/// Fence tags syntect doesn't know, and the syntax token to highlight them as.
+pub const LANG_ALIASES: &[(&str, &str)] = &[
+    ("shell", "bash"),
+    ("console", "bash"),
+    ("shell-session", "bash"),
+    ("terminal", "bash"),
+    ("env", "bash"),
+    ("dotenv", "bash"),
+    ("jsonc", "json"),
+    ("json5", "json"),
+    ("golang", "go"),
+    ("csharp", "cs"),
+    ("text", "txt"),
+    ("plaintext", "txt"),
+];
+
+/// The token to look `lang`'s syntax up by: the user's `lang_aliases`
+/// first, then `LANG_ALIASES`, ignoring case.
+pub fn syntax_token<'a>(lang: &'a str, aliases: &'a BTreeMap<String, String>) -> &'a str {
+    aliases
+        .iter()
+        .map(|(alias, token)| (alias.as_str(), token.as_str()))
+        .chain(LANG_ALIASES.iter().copied())
+        .find(|(alias, _)| alias.eq_ignore_ascii_case(lang))
+        .map_or(lang, |(_, token)| token)
+}
+
+/// How the syntax/theme dump cache is used.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SyntaxCache {
+    /// Load syntect's bundled defaults every time.
+    #[default]
+    Off,
+    /// Load from the cache, creating it on first use.
+    Use,
+    /// Regenerate the cache before using it.
+    Rebuild,
+}
+
+pub struct Highlighter {
+    pub syntaxes: SyntaxSet,
+}
+
+impl Highlighter {
+    pub fn load(cache: SyntaxCache) -> Self {
+        match (cache, cache_dir()) {
+            (SyntaxCache::Off, _) | (_, None) => Self::load_defaults(),
+            (cache, Some(dir)) => Self::load_cached(&dir, cache == SyntaxCache::Rebuild),
+        }
+    }
+
+    fn load_defaults() -> Self {
+        Highlighter {
+            syntaxes: SyntaxSet::load_defaults_newlines(),
+        }
+    }
+
+    /// Loads the dumps in `dir`, (re)creating them from the defaults when
+    /// they are missing, unreadable or `rebuild` is set.
+    fn load_cached(dir: &Path, rebuild: bool) -> Self {
+        if !rebuild {
+            let syntaxes = from_uncompressed_dump_file(dir.join(SYNTAXES_FILE));
+            if let (Ok(syntaxes), true) = (syntaxes, dir.join(THEMES_FILE).exists()) {
+                return Highlighter { syntaxes };
+            }
+        }
+        let highlighter = Self::load_defaults();
+        if let Err(e) = highlighter.write_cache(dir) {
+            explain::warn(format!(
+                "could not write syntax cache to {}: {e}",
+                dir.display()
+            ));
+        }
+        highlighter
+    }
+
+    fn write_cache(&self, dir: &Path) -> anyhow::Result<()> {
+        fs::create_dir_all(dir)?;
+        remove_stale_caches(dir);
+        dump_to_uncompressed_file(&self.syntaxes, dir.join(SYNTAXES_FILE))?;
+        dump_to_uncompressed_file(&ThemeSet::load_defaults(), dir.join(THEMES_FILE))?;
+        Ok(())
+    }
+}
+
+/// The code theme called `name`, deserializing the whole theme set only when
+/// it isn't one of the embedded themes. `None` for an unknown theme.
+pub fn load_theme(name: &str, cache: SyntaxCache) -> Option<Theme> {
+    if let Some((_, dump)) = EMBEDDED_THEMES
+        .iter()
+        .find(|(embedded, _)| *embedded == name)
+    {
+        return from_uncompressed_data(dump).ok();
+    }
+    let cached = match (cache, cache_dir()) {
+        (SyntaxCache::Off, _) | (_, None) => None,
+        (_, Some(dir)) => from_uncompressed_dump_file(dir.join(THEMES_FILE)).ok(),
+    };
+    let mut themes: ThemeSet = cached.unwrap_or_else(ThemeSet::load_defaults);
+    themes.themes.remove(name)
+}
+
+/// Cache location, keyed by crate version so upgrades never read a dump
+/// produced by a different syntect.
+fn cache_dir() -> Option<PathBuf> {
+    let version = env!("CARGO_PKG_VERSION");
+    Some(
+        dirs::cache_dir()?
+            .join("cheetsheet")
+            .join(format!("syntect-{version}")),
+    )
+}
+
+/// Removes dumps left behind by other versions next to `dir`.
+fn remove_stale_caches(dir: &Path) {
+    let Some(parent) = dir.parent() else { return };
+    let Ok(entries) = fs::read_dir(parent) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let is_dump = entry.file_name().to_string_lossy().starts_with("syntect-");
+        if is_dump && path != dir {
+            let _ = fs::remove_dir_all(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_syntax_token() {
+        let mut aliases = BTreeMap::new();
+        assert_eq!(syntax_token("Console", &aliases), "bash");
+        assert_eq!(syntax_token("python", &aliases), "python");
+        aliases.insert("text".to_string(), "markdown".to_string());
+        aliases.insert("pwsh".to_string(), "bash".to_string());
+        assert_eq!(syntax_token("pwsh", &aliases), "bash");
+        assert_eq!(syntax_token("text", &aliases), "markdown");
+
+        // Every built-in alias points at a syntax syntect has
+        let syntaxes = SyntaxSet::load_defaults_newlines();
+        for (alias, token) in LANG_ALIASES {
+            assert!(syntaxes.find_syntax_by_token(alias).is_none(), "{alias}");
+            assert!(syntaxes.find_syntax_by_token(token).is_some(), "{token}");
+        }
+    }
+
+    #[test]
+    fn test_load_cached_round_trip() {
+        let tmp = TempDir::new().unwrap();
+        let dir = tmp.path().join("syntect-0.0.0");
+        fs::create_dir_all(tmp.path().join("syntect-old")).unwrap();
+
+        let first = Highlighter::load_cached(&dir, false);
+        assert!(dir.join(SYNTAXES_FILE).exists());
+        assert!(dir.join(THEMES_FILE).exists());
+        assert!(!tmp.path().join("syntect-old").exists());
+
+        let second = Highlighter::load_cached(&dir, false);
+        assert_eq!(
+            first.syntaxes.syntaxes().len(),
+            second.syntaxes.syntaxes().len()
+        );
+        let themes: ThemeSet = from_uncompressed_dump_file(dir.join(THEMES_FILE)).unwrap();
+        assert!(themes.themes.len() > EMBEDDED_THEMES.len());
+    }
+
+    #[test]
+    fn test_embedded_themes_match_bundled() {
+        let bundled = ThemeSet::load_defaults();
+        for (name, _) in EMBEDDED_THEMES {
+            assert_eq!(
+                load_theme(name, SyntaxCache::Off).as_ref(),
+                Some(&bundled.themes[*name])
+            );
+        }
+        assert!(load_theme("InspiredGitHub", SyntaxCache::Off).is_some());
+        assert!(load_theme("no-such-theme", SyntaxCache::Off).is_none());
+    }
+
+    #[test]
+    fn test_load_cached_recovers_from_corrupt_dump() {
+        let tmp = TempDir::new().unwrap();
+        fs::write(tmp.path().join(SYNTAXES_FILE), b"garbage").unwrap();
+        let highlighter = Highlighter::load_cached(tmp.path(), false);
+        assert!(highlighter.syntaxes.find_syntax_by_token("bash").is_some());
+    }
+}
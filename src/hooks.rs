@@ -0,0 +1,130 @@
+//! `[block_renderers]`: external commands drawing the code blocks of a
+//! fence language, e.g. mermaid or graphviz, instead of highlighting them.
+//! The block is piped to the command's stdin and its stdout is printed as
+//! is, or without its escape codes when colors are off. A command that
+//! fails or hangs just means the block is highlighted.
+
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long a block renderer may run before it is killed.
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Runs `command` through the shell with `code` on its stdin, returning
+/// what it printed, or why there is nothing to show.
+pub fn run(command: &str, code: &str, timeout: Duration) -> Result<String, String> {
+    let mut child = shell(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("can't start: {e}"))?;
+    // Written and read on threads so a command that never reads its input
+    // or fills the pipe can't block the viewer
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let code = code.to_string();
+    thread::spawn(move || stdin.write_all(code.as_bytes()));
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let reader = thread::spawn(move || {
+        let mut output = Vec::new();
+        stdout.read_to_end(&mut output).map(|_| output)
+    });
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait().map_err(|e| e.to_string())? {
+            Some(status) => break status,
+            None if Instant::now() < deadline => thread::sleep(Duration::from_millis(10)),
+            None => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("timed out after {}s", timeout.as_secs_f32()));
+            }
+        }
+    };
+    if !status.success() {
+        return Err(format!("exited with {status}"));
+    }
+    let output = reader
+        .join()
+        .map_err(|_| "failed to read its output".to_string())?
+        .map_err(|e| e.to_string())?;
+    match output.is_empty() {
+        true => Err("printed nothing".to_string()),
+        false => Ok(String::from_utf8_lossy(&output).into_owned()),
+    }
+}
+
+/// `text` without CSI (colors, cursor moves) and OSC (titles, links)
+/// escape sequences, for output that must stay uncolored.
+pub fn strip_escapes(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            // Parameters, then one final byte from `@` to `~`
+            Some('[') => while chars.next().is_some_and(|c| !('@'..='~').contains(&c)) {},
+            // Up to BEL or ESC `\`
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run() {
+        assert_eq!(run("tr a-z A-Z", "graph\n", TIMEOUT).unwrap(), "GRAPH\n");
+        assert_eq!(
+            run("exit 3", "", TIMEOUT).unwrap_err(),
+            "exited with exit status: 3"
+        );
+        assert_eq!(
+            run("cat >/dev/null", "x", TIMEOUT).unwrap_err(),
+            "printed nothing"
+        );
+
+        let start = Instant::now();
+        let err = run("sleep 5", "", Duration::from_millis(100)).unwrap_err();
+        assert!(err.starts_with("timed out"));
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_strip_escapes() {
+        assert_eq!(
+            strip_escapes("\x1b[1;38;2;1;2;3mbox\x1b[0m \x1b]8;;https://x\x1b\\link\x1b]8;;\x07\n"),
+            "box link\n"
+        );
+        assert_eq!(strip_escapes("plain ─┐\n"), "plain ─┐\n");
+    }
+}
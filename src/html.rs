@@ -0,0 +1,191 @@
+//! `--output-format html`: a sheet as one standalone page, prose turned into
+//! tags and code left to a highlighter passed in, so no stylesheet or script
+//! has to travel with the file.
+
+use crate::range;
+use crate::segment::Segment;
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+use std::io::{self, Write};
+
+/// Writes `segments` as an HTML document titled after the first heading.
+/// `code` turns a fence tag and its code into a highlighted block.
+pub fn write<'a>(
+    out: &mut impl Write,
+    segments: impl IntoIterator<Item = Segment<'a>>,
+    mut code: impl FnMut(&str, &str) -> String,
+) -> io::Result<()> {
+    let mut segments = segments.into_iter().peekable();
+    let title = match segments.peek() {
+        Some(Segment::Text(text)) => text.lines().find_map(range::heading),
+        _ => None,
+    };
+    writeln!(
+        out,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">"
+    )?;
+    writeln!(
+        out,
+        "<title>{}</title>",
+        escape(title.unwrap_or("cheetsheet"))
+    )?;
+    writeln!(out, "</head>\n<body>")?;
+    for segment in segments {
+        match segment {
+            Segment::Text(text) => write!(out, "{}", prose(&text))?,
+            Segment::Code { lang, code: text } => write!(out, "{}", code(&lang, &text))?,
+            Segment::QuotedCode { lang, code: text } => {
+                write!(out, "<blockquote>\n{}</blockquote>\n", code(&lang, &text))?
+            }
+            Segment::Marker(note) => writeln!(out, "<p><em>{}</em></p>", escape(&note))?,
+        }
+    }
+    writeln!(out, "</body>\n</html>")
+}
+
+/// `text` safe inside an element or a quoted attribute.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Markdown prose as HTML. Raw HTML in it is passed through as written.
+fn prose(text: &str) -> String {
+    let options =
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let mut html = String::new();
+    // Cells of a table's header are `th`, the rest `td`
+    let mut in_head = false;
+    // Text inside an image goes into its `alt`
+    let mut in_image = false;
+    for event in Parser::new_ext(text, options) {
+        match event {
+            Event::Start(tag) => match tag {
+                Tag::Paragraph => html.push_str("<p>"),
+                Tag::Heading { level, .. } => html.push_str(&format!("<{level}>")),
+                Tag::BlockQuote(_) => html.push_str("<blockquote>\n"),
+                Tag::CodeBlock(CodeBlockKind::Fenced(info)) if !info.is_empty() => {
+                    let lang = info.split_whitespace().next().unwrap_or_default();
+                    html.push_str(&format!("<pre><code class=\"language-{}\">", escape(lang)));
+                }
+                Tag::CodeBlock(_) => html.push_str("<pre><code>"),
+                Tag::List(Some(1)) => html.push_str("<ol>\n"),
+                Tag::List(Some(start)) => html.push_str(&format!("<ol start=\"{start}\">\n")),
+                Tag::List(None) => html.push_str("<ul>\n"),
+                Tag::Item => html.push_str("<li>"),
+                Tag::Table(_) => html.push_str("<table>\n"),
+                Tag::TableHead => {
+                    in_head = true;
+                    html.push_str("<thead><tr>");
+                }
+                Tag::TableRow => html.push_str("<tr>"),
+                Tag::TableCell => html.push_str(if in_head { "<th>" } else { "<td>" }),
+                Tag::Emphasis => html.push_str("<em>"),
+                Tag::Strong => html.push_str("<strong>"),
+                Tag::Strikethrough => html.push_str("<del>"),
+                Tag::Link {
+                    dest_url, title, ..
+                } => {
+                    html.push_str(&format!("<a href=\"{}\"", escape(&dest_url)));
+                    if !title.is_empty() {
+                        html.push_str(&format!(" title=\"{}\"", escape(&title)));
+                    }
+                    html.push('>');
+                }
+                Tag::Image { dest_url, .. } => {
+                    in_image = true;
+                    html.push_str(&format!("<img src=\"{}\" alt=\"", escape(&dest_url)));
+                }
+                _ => {}
+            },
+            Event::End(tag) => match tag {
+                TagEnd::Paragraph => html.push_str("</p>\n"),
+                TagEnd::Heading(level) => html.push_str(&format!("</{level}>\n")),
+                TagEnd::BlockQuote(_) => html.push_str("</blockquote>\n"),
+                TagEnd::CodeBlock => html.push_str("</code></pre>\n"),
+                TagEnd::List(true) => html.push_str("</ol>\n"),
+                TagEnd::List(false) => html.push_str("</ul>\n"),
+                TagEnd::Item => html.push_str("</li>\n"),
+                TagEnd::Table => html.push_str("</tbody>\n</table>\n"),
+                TagEnd::TableHead => {
+                    in_head = false;
+                    html.push_str("</tr></thead>\n<tbody>\n");
+                }
+                TagEnd::TableRow => html.push_str("</tr>\n"),
+                TagEnd::TableCell => html.push_str(if in_head { "</th>" } else { "</td>" }),
+                TagEnd::Emphasis => html.push_str("</em>"),
+                TagEnd::Strong => html.push_str("</strong>"),
+                TagEnd::Strikethrough => html.push_str("</del>"),
+                TagEnd::Link => html.push_str("</a>"),
+                TagEnd::Image => {
+                    in_image = false;
+                    html.push_str("\">");
+                }
+                _ => {}
+            },
+            Event::Text(text) => html.push_str(&escape(&text)),
+            Event::Code(code) if in_image => html.push_str(&escape(&code)),
+            Event::Code(code) => html.push_str(&format!("<code>{}</code>", escape(&code))),
+            Event::Html(raw) | Event::InlineHtml(raw) => html.push_str(&raw),
+            Event::InlineMath(math) | Event::DisplayMath(math) => html.push_str(&escape(&math)),
+            Event::FootnoteReference(name) => {
+                html.push_str(&format!("<sup>[{}]</sup>", escape(&name)))
+            }
+            Event::TaskListMarker(done) => html.push_str(match done {
+                true => "<input type=\"checkbox\" disabled checked> ",
+                false => "<input type=\"checkbox\" disabled> ",
+            }),
+            Event::SoftBreak => html.push('\n'),
+            Event::HardBreak => html.push_str("<br>\n"),
+            Event::Rule => html.push_str("<hr>\n"),
+        }
+    }
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::segment::split_segments;
+
+    fn html(content: &str) -> String {
+        let mut out = Vec::new();
+        write(&mut out, split_segments(content), |lang, code| {
+            format!("<pre lang=\"{lang}\">{}</pre>\n", escape(code))
+        })
+        .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_html_document() {
+        let out = html(
+            "# Git & friends\n\nUse **`git status`**, see [docs](https://git-scm.com).\n\n\
+             ```bash\ngit log <ref>\n```\n\n| A | B |\n|---|---|\n| 1 | 2 |\n",
+        );
+        assert!(out.starts_with("<!DOCTYPE html>"));
+        assert!(out.contains("<title>Git &amp; friends</title>"));
+        assert!(out.contains("<h1>Git &amp; friends</h1>"));
+        assert!(out.contains(
+            "<p>Use <strong><code>git status</code></strong>, see \
+             <a href=\"https://git-scm.com\">docs</a>.</p>"
+        ));
+        assert!(out.contains("<pre lang=\"bash\">git log &lt;ref&gt;</pre>"));
+        assert!(out.contains("<thead><tr><th>A</th><th>B</th></tr></thead>"));
+        assert!(out.contains("<tr><td>1</td><td>2</td></tr>"));
+        assert!(out.ends_with("</body>\n</html>\n"));
+    }
+
+    #[test]
+    fn test_html_title_without_heading() {
+        assert!(html("Just prose\n").contains("<title>cheetsheet</title>"));
+    }
+}
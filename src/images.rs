@@ -0,0 +1,175 @@
+//! Markdown images. termimad shows `![alt](src)` as raw text, so images are
+//! replaced by their alt text or, on terminals with an inline image
+//! protocol, drawn in place.
+
+use crate::term;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use std::io::IsTerminal;
+use std::path::Path;
+
+pub enum Chunk {
+    Text(String),
+    /// An image alone on its line.
+    Image {
+        alt: String,
+        src: String,
+    },
+}
+
+/// Splits prose into text and standalone images. Images inside a line of
+/// text are replaced by their alt text.
+pub fn split(text: &str) -> Vec<Chunk> {
+    let mut chunks = Vec::new();
+    let mut prose = String::new();
+    for line in text.lines() {
+        match image(line.trim()) {
+            Some((alt, src, "")) => {
+                if !prose.is_empty() {
+                    chunks.push(Chunk::Text(std::mem::take(&mut prose)));
+                }
+                chunks.push(Chunk::Image {
+                    alt: alt.to_string(),
+                    src: src.to_string(),
+                });
+            }
+            _ => {
+                prose.push_str(&replace_inline(line));
+                prose.push('\n');
+            }
+        }
+    }
+    if !prose.is_empty() {
+        chunks.push(Chunk::Text(prose));
+    }
+    chunks
+}
+
+/// The markdown shown in place of an image.
+pub fn placeholder(alt: &str) -> String {
+    let alt = if alt.is_empty() { "image" } else { alt };
+    format!("**[{alt}]**")
+}
+
+/// Splits `![alt](src)` at the start of `line` into alt, src and the rest.
+fn image(line: &str) -> Option<(&str, &str, &str)> {
+    let rest = line.strip_prefix("![")?;
+    let (alt, rest) = rest.split_once("](")?;
+    let (src, rest) = rest.split_once(')')?;
+    // Drop an optional title: ![alt](src "title")
+    let src = src.split_once(' ').map_or(src, |(src, _)| src);
+    Some((alt, src, rest))
+}
+
+fn replace_inline(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(start) = rest.find("![") {
+        out.push_str(&rest[..start]);
+        match image(&rest[start..]) {
+            Some((alt, _, after)) => {
+                out.push_str(&placeholder(alt));
+                rest = after;
+            }
+            None => {
+                out.push_str("![");
+                rest = &rest[start + 2..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Kitty,
+    Iterm,
+}
+
+impl Protocol {
+    /// The inline image protocol of the terminal on stdout, if any.
+    pub fn detect() -> Option<Protocol> {
+        if !std::io::stdout().is_terminal() || term::is_dumb() {
+            return None;
+        }
+        let var = |name| std::env::var(name).unwrap_or_default();
+        if var("TERM").contains("kitty") || std::env::var_os("KITTY_WINDOW_ID").is_some() {
+            Some(Protocol::Kitty)
+        } else if matches!(var("TERM_PROGRAM").as_str(), "iTerm.app" | "WezTerm") {
+            Some(Protocol::Iterm)
+        } else {
+            None
+        }
+    }
+
+    /// The escape sequence drawing the image file at `path`, or `None` if
+    /// it can't be read or the protocol can't show its format.
+    pub fn escape(self, path: &Path) -> Option<String> {
+        let is_png = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("png"));
+        if self == Protocol::Kitty && !is_png {
+            return None;
+        }
+        let data = std::fs::read(path).ok()?;
+        Some(self.encode(&data))
+    }
+
+    fn encode(self, data: &[u8]) -> String {
+        let encoded = STANDARD.encode(data);
+        match self {
+            Protocol::Iterm => format!(
+                "\x1b]1337;File=inline=1;size={}:{encoded}\x07\n",
+                data.len()
+            ),
+            Protocol::Kitty => {
+                // Kitty takes the payload in chunks of at most 4096 bytes
+                let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(4096).collect();
+                let mut out = String::new();
+                for (index, chunk) in chunks.iter().enumerate() {
+                    let more = u8::from(index + 1 < chunks.len());
+                    let keys = match index {
+                        0 => format!("a=T,f=100,m={more}"),
+                        _ => format!("m={more}"),
+                    };
+                    let chunk = std::str::from_utf8(chunk).unwrap_or_default();
+                    out.push_str(&format!("\x1b_G{keys};{chunk}\x1b\\"));
+                }
+                out.push('\n');
+                out
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split() {
+        let chunks =
+            split("Intro ![logo](l.png) here\n![Flow chart](img/flow.png \"Flow\")\nAfter\n");
+        assert_eq!(chunks.len(), 3);
+        assert!(matches!(&chunks[0], Chunk::Text(t) if t == "Intro **[logo]** here\n"));
+        assert!(matches!(&chunks[1], Chunk::Image { alt, src }
+            if alt == "Flow chart" && src == "img/flow.png"));
+        assert!(matches!(&chunks[2], Chunk::Text(t) if t == "After\n"));
+    }
+
+    #[test]
+    fn test_kitty_chunks() {
+        let out = Protocol::Kitty.encode(&[0; 4000]);
+        assert!(out.starts_with("\x1b_Ga=T,f=100,m=1;AAAA"));
+        assert!(out.contains("\x1b\\\x1b_Gm=0;"));
+    }
+
+    #[test]
+    fn test_iterm_escape() {
+        assert_eq!(
+            Protocol::Iterm.encode(b"png"),
+            "\x1b]1337;File=inline=1;size=3:cG5n\x07\n"
+        );
+    }
+}
@@ -0,0 +1,96 @@
+//! `cheetsheet init`: a config directory with starter settings and an
+//! example sheet. Existing files are reported and never overwritten.
+
+use anyhow::{Context, Result, bail};
+use cheet_sheet::{CheetError, config};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+
+pub const EXAMPLE_NAME: &str = "example.md";
+
+const EXAMPLE: &str = r#"# Example
+
+A sheet is a markdown file in the config directory, named after the command
+it is for. View this one with `cheetsheet example`.
+
+## Tables
+
+| Key      | Action       |
+|----------|--------------|
+| `Ctrl-a` | Line start   |
+| `Ctrl-e` | Line end     |
+
+## Code
+
+Fenced blocks are highlighted by their language:
+
+```bash
+# Find large files
+du -ah . | sort -rh | head -n 10
+```
+"#;
+
+pub fn run(config_dir: &Path, config_path: &Path, git: bool) -> Result<()> {
+    if config_dir.is_dir() {
+        println!("exists   {}", config_dir.display());
+    } else {
+        fs::create_dir_all(config_dir)
+            .with_context(|| format!("failed to create {}", config_dir.display()))?;
+        println!("created  {}", config_dir.display());
+    }
+    write_new(config_path, config::STARTER)?;
+    write_new(&config_dir.join(EXAMPLE_NAME), EXAMPLE)?;
+    if git {
+        git_init(config_dir)?;
+    }
+
+    println!();
+    println!("Next steps:");
+    println!("  cheetsheet example         view the example sheet");
+    println!("  cheetsheet new <command>   start a sheet, or add .md files to");
+    println!("                             {}", config_dir.display());
+    println!("  cheetsheet config list     see every setting and where it comes from");
+    Ok(())
+}
+
+/// Writes `content` to `path` unless something is already there.
+fn write_new(path: &Path, content: &str) -> Result<()> {
+    let file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path);
+    match file {
+        Ok(mut file) => {
+            file.write_all(content.as_bytes())
+                .with_context(|| format!("failed to write {}", path.display()))?;
+            println!("created  {}", path.display());
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            println!("exists   {}", path.display());
+        }
+        Err(e) => return Err(e).with_context(|| format!("failed to create {}", path.display())),
+    }
+    Ok(())
+}
+
+fn git_init(dir: &Path) -> Result<()> {
+    if dir.join(".git").exists() {
+        println!("exists   {}", dir.join(".git").display());
+        return Ok(());
+    }
+    let status = Command::new("git")
+        .args(["init", "--quiet"])
+        .current_dir(dir)
+        .status()
+        .context(CheetError::External("failed to run git".to_string()))?;
+    if !status.success() {
+        bail!(CheetError::External(format!(
+            "git init failed in {}",
+            dir.display()
+        )));
+    }
+    println!("created  {}", dir.join(".git").display());
+    Ok(())
+}
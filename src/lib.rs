@@ -0,0 +1,63 @@
+//! Looking up, parsing and rendering cheatsheets, as used by the
+//! `cheetsheet` binary. The API is young: any 0.x release may change it.
+//!
+//! ```no_run
+//! use cheet_sheet::{RenderOptions, Renderer, SheetLocator};
+//! use std::path::Path;
+//!
+//! let locator = SheetLocator {
+//!     config_dir: Path::new("/home/me/.config/cheetsheet"),
+//!     search_paths: &[],
+//!     recursive: true,
+//!     langs: &[],
+//!     matcher: Default::default(),
+//!     read: Default::default(),
+//! };
+//! let sheet = locator.find("tmux")?;
+//! let options = RenderOptions {
+//!     width: Some(80),
+//!     ..RenderOptions::default()
+//! };
+//! Renderer::new().render_to(&mut std::io::stdout(), &sheet, &options)?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+mod admonition;
+pub mod adoc;
+pub mod age;
+pub mod colors;
+pub mod config;
+mod details;
+pub mod error;
+mod examples;
+pub mod explain;
+pub mod front_matter;
+pub mod fuzzy;
+pub mod highlight;
+mod hooks;
+pub mod html;
+mod images;
+pub mod links;
+pub mod locale;
+mod margin;
+mod math;
+pub mod org;
+pub mod plain;
+pub mod profile;
+pub mod prose;
+pub mod range;
+pub mod render;
+pub mod rst;
+pub mod segment;
+pub mod sheet;
+mod tables;
+pub mod term;
+pub mod tldr;
+pub mod variables;
+mod walk;
+
+pub use config::Config;
+pub use error::CheetError;
+pub use render::{RenderOptions, Renderer};
+pub use segment::{Segment, SegmentReader, Segments, split_segments};
+pub use sheet::{Format, ReadOptions, Sheet, SheetLocator};
@@ -0,0 +1,118 @@
+//! Relative references in sheets: links to other sheets, as in
+//! `[see also](./docker.md)`, and images. The renderer finds images and
+//! `doctor` finds dead references through `resolve`, so the two agree on
+//! where a reference points.
+
+use crate::front_matter;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Link,
+    Image,
+}
+
+/// A `[text](target)` or `![alt](target)` in a sheet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    pub kind: Kind,
+    pub target: String,
+    /// 1-based, front matter included.
+    pub line: usize,
+}
+
+/// The references in markdown `content`, outside code blocks and spans.
+pub fn references(content: &str) -> Vec<Reference> {
+    let body = front_matter::split(content).1;
+    let skipped = content[..content.len() - body.len()].lines().count();
+    let mut found = Vec::new();
+    let mut in_code = false;
+    for (index, line) in body.lines().enumerate() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+        }
+        if in_code {
+            continue;
+        }
+        // Every other piece between backticks is a code span
+        for text in line.split('`').step_by(2) {
+            let mut rest = text;
+            while let Some(start) = rest.find("](") {
+                let open = rest[..start].rfind('[');
+                let Some(end) = rest[start + 2..].find(')') else {
+                    break;
+                };
+                let target = rest[start + 2..start + 2 + end].trim();
+                // Drop an optional title: [text](target "title")
+                let target = target.split_once(' ').map_or(target, |(target, _)| target);
+                if let Some(open) = open.filter(|_| !target.is_empty()) {
+                    let kind = match rest[..open].ends_with('!') {
+                        true => Kind::Image,
+                        false => Kind::Link,
+                    };
+                    found.push(Reference {
+                        kind,
+                        target: target.to_string(),
+                        line: skipped + index + 1,
+                    });
+                }
+                rest = &rest[start + 2 + end + 1..];
+            }
+        }
+    }
+    found
+}
+
+/// The file `target` refers to from a sheet in `base_dir`, or `None` when
+/// it isn't a local file: a URL, an email address or an anchor in the same
+/// sheet. `#fragment`s are dropped and `%20` stands for a space.
+pub fn resolve(base_dir: &Path, target: &str) -> Option<PathBuf> {
+    let is_url = target
+        .split_once(':')
+        .is_some_and(|(scheme, _)| scheme.len() > 1 && !scheme.contains(['/', '.']));
+    if is_url || target.starts_with(['#', '<']) || target.starts_with("//") {
+        return None;
+    }
+    let path = target.split(['#', '?']).next().unwrap_or_default();
+    Some(base_dir.join(path.replace("%20", " ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_references() {
+        let sheet = "---\ntitle: Net\n---\nSee [docker](./docker.md) and ![map](img/map.png \"Map\").\n\n\
+                     ```bash\necho [not](a-link.md)\n```\n\n`[code](span.md)` [web](https://example.com)\n";
+        let found = references(sheet);
+        let found: Vec<_> = found
+            .iter()
+            .map(|found| (found.kind, found.target.as_str(), found.line))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (Kind::Link, "./docker.md", 4),
+                (Kind::Image, "img/map.png", 4),
+                (Kind::Link, "https://example.com", 10),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve() {
+        let dir = Path::new("/sheets");
+        assert_eq!(
+            resolve(dir, "./docker.md#run"),
+            Some(PathBuf::from("/sheets/./docker.md"))
+        );
+        assert_eq!(
+            resolve(dir, "my%20notes.md"),
+            Some(PathBuf::from("/sheets/my notes.md"))
+        );
+        assert_eq!(resolve(dir, "https://example.com/a.md"), None);
+        assert_eq!(resolve(dir, "mailto:me@example.com"), None);
+        assert_eq!(resolve(dir, "#install"), None);
+    }
+}
@@ -0,0 +1,114 @@
+//! Sheets in other languages: `git.zh.md` or `git.zh-TW.md` beside `git.md`
+//! are its variants, preferred over it when the language asked for with
+//! `--lang`, the `lang` key or the locale in `LC_ALL`, `LC_MESSAGES` or
+//! `LANG` matches, from the exact locale down to its language.
+
+/// The language code of the environment's locale, e.g. `zh-TW` for
+/// `LANG=zh_TW.UTF-8`. `var` looks variables up.
+pub fn from_env(var: impl Fn(&str) -> Option<String>) -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .filter_map(var)
+        .find(|value| !value.is_empty())
+        .and_then(|value| parse(&value))
+}
+
+/// A locale or language code in the form variant files are named by:
+/// `zh_TW.UTF-8@latin` becomes `zh-TW`. None for `C`, `POSIX` and anything
+/// that isn't a language.
+pub fn parse(locale: &str) -> Option<String> {
+    let code = locale.split(['.', '@']).next().unwrap_or_default();
+    let mut parts = code.split(['_', '-']);
+    let lang = parts.next()?.to_ascii_lowercase();
+    let region = parts.next().map(str::to_ascii_uppercase);
+    let code = match region {
+        Some(region) if parts.next().is_none() => format!("{lang}-{region}"),
+        Some(_) => return None,
+        None => lang,
+    };
+    is_code(&code).then_some(code)
+}
+
+/// The codes to try for `code`, most specific first: `zh-TW`, then `zh`.
+pub fn chain(code: &str) -> Vec<String> {
+    let mut chain = vec![code.to_string()];
+    if let Some((lang, _)) = code.split_once('-') {
+        chain.push(lang.to_string());
+    }
+    chain
+}
+
+/// Splits a sheet name like `git.zh-TW` into `git` and its language, when
+/// it ends in one.
+pub fn variant(name: &str) -> Option<(&str, &str)> {
+    let (base, code) = name.rsplit_once('.')?;
+    (!base.is_empty() && !code.contains('/') && is_code(code)).then_some((base, code))
+}
+
+/// Whether `code` is a language, two or three lowercase letters, with an
+/// optional region of two capitals or three digits.
+fn is_code(code: &str) -> bool {
+    let (lang, region) = match code.split_once('-') {
+        Some((lang, region)) => (lang, Some(region)),
+        None => (code, None),
+    };
+    (2..=3).contains(&lang.len())
+        && lang.bytes().all(|b| b.is_ascii_lowercase())
+        && region.is_none_or(|region| {
+            (region.len() == 2 && region.bytes().all(|b| b.is_ascii_uppercase()))
+                || (region.len() == 3 && region.bytes().all(|b| b.is_ascii_digit()))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("zh_TW.UTF-8").as_deref(), Some("zh-TW"));
+        assert_eq!(parse("de_DE@euro").as_deref(), Some("de-DE"));
+        assert_eq!(parse("pt-br").as_deref(), Some("pt-BR"));
+        assert_eq!(parse("es_419").as_deref(), Some("es-419"));
+        assert_eq!(parse("FR").as_deref(), Some("fr"));
+        assert_eq!(parse("C.UTF-8"), None);
+        assert_eq!(parse("POSIX"), None);
+        assert_eq!(parse(""), None);
+    }
+
+    #[test]
+    fn test_from_env() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+        assert_eq!(
+            from_env(env(&[("LANG", "en_US.UTF-8"), ("LC_ALL", "zh_TW.UTF-8")])).as_deref(),
+            Some("zh-TW")
+        );
+        assert_eq!(
+            from_env(env(&[("LC_ALL", ""), ("LANG", "ja_JP.UTF-8")])).as_deref(),
+            Some("ja-JP")
+        );
+        assert_eq!(from_env(env(&[("LANG", "C")])), None);
+        assert_eq!(from_env(env(&[])), None);
+    }
+
+    #[test]
+    fn test_chain() {
+        assert_eq!(chain("zh-TW"), ["zh-TW", "zh"]);
+        assert_eq!(chain("zh"), ["zh"]);
+    }
+
+    #[test]
+    fn test_variant() {
+        assert_eq!(variant("git.zh"), Some(("git", "zh")));
+        assert_eq!(variant("cloud/aws.pt-BR"), Some(("cloud/aws", "pt-BR")));
+        assert_eq!(variant("git"), None);
+        assert_eq!(variant("docker.local"), None);
+        assert_eq!(variant("v1.2"), None);
+    }
+}
@@ -0,0 +1,66 @@
+//! `-v`/`CHEETSHEET_LOG`: prints the library's `log` records about lookup,
+//! config loading and rendering to stderr, so stdout stays the sheet. With
+//! neither set the max level is `Off` and every `log!` is a single check.
+
+use cheet_sheet::explain;
+use log::{LevelFilter, Log, Metadata, Record};
+
+pub const ENV: &str = "CHEETSHEET_LOG";
+
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        // Dependencies such as `ignore` log too; only our records are wanted
+        metadata.level() <= log::max_level() && metadata.target().starts_with("cheet")
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            let level = record.level().as_str().to_ascii_lowercase();
+            eprintln!("{level}: {}", record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// The level for `-v` given `verbose` times: the lookup at `-v`, segments
+/// and syntaxes at `-vv`. Without `-v`, `env` names a level like `debug`.
+pub fn level(verbose: u8, env: Option<&str>) -> LevelFilter {
+    match verbose {
+        0 => env.map_or(LevelFilter::Off, |name| {
+            name.parse().unwrap_or_else(|_| {
+                explain::warn(format!(
+                    "invalid {ENV} '{name}'; use off, error, warn, info, debug or trace"
+                ));
+                LevelFilter::Off
+            })
+        }),
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+
+pub fn init(level: LevelFilter) {
+    if log::set_logger(&StderrLogger).is_ok() {
+        log::set_max_level(level);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level() {
+        assert_eq!(level(0, None), LevelFilter::Off);
+        assert_eq!(level(0, Some("DEBUG")), LevelFilter::Debug);
+        assert_eq!(level(0, Some("loud")), LevelFilter::Off);
+        // `-v` wins over the environment
+        assert_eq!(level(1, Some("trace")), LevelFilter::Info);
+        assert_eq!(level(2, None), LevelFilter::Debug);
+        assert_eq!(level(5, None), LevelFilter::Trace);
+    }
+}
@@ -1,160 +1,2466 @@
-use anyhow::Result;
-use clap::Parser;
+mod init;
+mod logger;
+mod man;
+mod migrate;
+mod recent;
+mod shell_hook;
+mod template;
+mod tips;
+mod watch;
+mod wizard;
+
+use anyhow::{Context, Result};
+use cheet_sheet::config::{
+    self, ColorMode, Config, DefaultCommand, Fallback, HighlightBg, Lang, Spacing, TabWidth, Theme,
+    WideTables,
+};
+use cheet_sheet::fuzzy::{Algorithm, Matcher};
+use cheet_sheet::highlight::SyntaxCache;
+use cheet_sheet::range::{self, HeadingRange};
+use cheet_sheet::render::Output;
+use cheet_sheet::sheet::{
+    Format, OVERLAY_SUFFIX, ReadOptions, SHEET_EXTENSIONS, TEMPLATES_DIR, collect_sheets, decode,
+    find_overlay, is_remote_managed, normalize_name, sheet_name, sheet_with_extension,
+};
+use cheet_sheet::{
+    CheetError, RenderOptions, Renderer, Segment, Sheet, SheetLocator, age, error, explain,
+    front_matter, links, locale, profile, prose, render, term, tldr, variables,
+};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use encoding_rs::{Encoding, UTF_8};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::IndexedRandom;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::{OsStr, OsString};
 use std::fs;
+use std::io::{self, IsTerminal, Read, Write};
 use std::path::{Path, PathBuf};
-use syntect::easy::HighlightLines;
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use syntect::highlighting::ThemeSet;
-use syntect::parsing::SyntaxSet;
-use syntect::util::{LinesWithEndings, as_24_bit_terminal_escaped};
-use termimad::crossterm::style::{Attribute, Color::Yellow};
-use termimad::{MadSkin, ansi, gray};
+use termimad::crossterm::style::Stylize;
 
 #[derive(Debug, Parser)]
-#[command(author, version, about = "Terminal cheatsheet viewer")]
+#[command(
+    author,
+    version,
+    about = "Terminal cheatsheet viewer",
+    subcommand_negates_reqs = true
+)]
 struct Args {
-    /// Command name to look up (e.g., tmux, git, docker)
+    #[command(subcommand)]
+    action: Option<Action>,
+
+    /// Command name to look up (e.g., tmux, git, docker), `-` for stdin,
+    /// or a path to a sheet file (containing `/` or ending in `.md`).
+    /// `git#stash` shows only the stash section, `git#` the headings;
+    /// write `\#` for a `#` in the name
     #[arg(value_name = "COMMAND")]
-    command: String,
+    command: Option<String>,
+
+    /// More sheets to render after the first, each under its name
+    #[arg(value_name = "MORE")]
+    more: Vec<String>,
+
+    /// Between several sheets: rule (a line), heading (their names), formfeed
+    /// (a page break for pagers) or any TEXT (default: a line and their names)
+    #[arg(long, value_name = "STYLE")]
+    separator: Option<Separator>,
+
+    /// Only consider sheets at the top level of the config directory
+    #[arg(long, global = true)]
+    flat: bool,
+
+    /// Largest edit distance for "did you mean" suggestions
+    #[arg(long, value_name = "N", default_value_t = 2, global = true)]
+    max_distance: usize,
 
-    /// Custom config directory (default: ~/.config/cheetsheet)
-    #[arg(short, long, value_name = "DIR")]
+    /// How to find "did you mean" suggestions
+    #[arg(
+        long = "match",
+        value_name = "ALGORITHM",
+        value_enum,
+        default_value_t,
+        global = true
+    )]
+    match_algorithm: Algorithm,
+
+    /// Render this file instead of looking up a sheet
+    #[arg(short, long, value_name = "PATH", conflicts_with = "command")]
+    file: Option<PathBuf>,
+
+    /// Custom config directory (or $CHEETSHEET_CONFIG_DIR; default: ~/.config/cheetsheet)
+    #[arg(short, long, value_name = "DIR", global = true)]
     config_dir: Option<String>,
+
+    /// Read settings from FILE instead of cheetsheet.toml in the config directory
+    #[arg(long, value_name = "FILE", global = true)]
+    config: Option<PathBuf>,
+
+    /// Use the sheets, settings and state of profile NAME (or $CHEETSHEET_PROFILE)
+    #[arg(long, value_name = "NAME", global = true)]
+    profile: Option<String>,
+
+    /// Prefer sheets' variants in language CODE, e.g. zh-TW (default: the locale's)
+    #[arg(long, value_name = "CODE", global = true)]
+    lang: Option<Lang>,
+
+    /// Sheet format (default: inferred from the file extension)
+    #[arg(long, value_enum)]
+    format: Option<Format>,
+
+    /// Read sheets written in encoding NAME, e.g. latin1, gbk or shift_jis (default: utf-8)
+    #[arg(long, value_name = "NAME", value_parser = parse_encoding, global = true)]
+    encoding: Option<&'static Encoding>,
+
+    /// Only render what follows this heading
+    #[arg(long, value_name = "HEADING")]
+    after: Option<String>,
+
+    /// Only render what precedes this heading
+    #[arg(long, value_name = "HEADING")]
+    before: Option<String>,
+
+    /// Lay out short list items across N columns
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    columns: usize,
+
+    /// Load syntax definitions from a fast on-disk cache, creating it if needed
+    #[arg(long)]
+    syntax_cache: bool,
+
+    /// Regenerate the syntax cache (implies --syntax-cache)
+    #[arg(long)]
+    rebuild_cache: bool,
+
+    /// Highlight every code block as LANG, ignoring fence tags
+    #[arg(long, value_name = "LANG")]
+    context_lang: Option<String>,
+
+    /// Only use ASCII characters for tables, rules, bullets, icons and bars
+    #[arg(long, global = true)]
+    ascii: bool,
+
+    /// Color scheme for prose
+    #[arg(long, value_enum, global = true)]
+    theme: Option<Theme>,
+
+    /// syntect theme for code blocks (default: base16-ocean.dark)
+    #[arg(long, value_name = "NAME", global = true)]
+    code_theme: Option<String>,
+
+    /// Wrap output at N columns at most
+    #[arg(long, value_name = "N", global = true)]
+    width: Option<usize>,
+
+    /// Leave N blank columns on each side of the sheet
+    #[arg(long, value_name = "N", global = true)]
+    margin: Option<usize>,
+
+    /// When to use colors
+    #[arg(long, value_enum, global = true)]
+    color: Option<ColorMode>,
+
+    /// Leave stdin alone unless COMMAND is `-`, without checking for ignored input
+    #[arg(long)]
+    no_args_stdin: bool,
+
+    /// Draw a frame around code blocks with the language in its top border
+    #[arg(long)]
+    code_frame: bool,
+
+    /// Keep running and re-render the sheet whenever its file changes
+    #[arg(long)]
+    watch: bool,
+
+    /// Render only part N of a sheet split into numbered files like git/01-basics.md
+    #[arg(long, value_name = "N")]
+    part: Option<usize>,
+
+    /// Print how the sheet name resolved, e.g. `aws → cloud/aws → /path`, to stderr before rendering
+    #[arg(long)]
+    follow_aliases: bool,
+
+    /// Print how the sheet name resolved to stdout instead of rendering the sheet
+    #[arg(long)]
+    print_path_only: bool,
+
+    /// After rendering, print a JSON trace of the lookup and rendering to stderr
+    #[arg(long)]
+    explain: bool,
+
+    /// Print errors as a JSON object on stderr, with their kind and exit code
+    #[arg(long, global = true)]
+    json_errors: bool,
+
+    /// Print only the sheet: no warnings, notices or blank lines around it
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// Log the lookup to stderr; -vv adds segments and syntaxes (or $CHEETSHEET_LOG=debug)
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Backend laying out prose; code blocks are highlighted the same either way
+    #[arg(long, value_enum, global = true)]
+    renderer: Option<prose::Renderer>,
+
+    /// Number ordered lists 1. 2. 3., a. b. c. or i. ii. iii.
+    #[arg(long, value_enum, global = true)]
+    list_style: Option<prose::ListStyle>,
+
+    /// Blank lines before each code block, 0 to 2 (default: 1)
+    #[arg(long, value_name = "N", global = true)]
+    code_spacing_before: Option<Spacing>,
+
+    /// Blank lines after each code block, 0 to 2 (default: 1)
+    #[arg(long, value_name = "N", global = true)]
+    code_spacing_after: Option<Spacing>,
+
+    /// Columns between tab stops when expanding tabs in code, 1 to 16 (default: 4)
+    #[arg(long, value_name = "N", global = true)]
+    tab_width: Option<TabWidth>,
+
+    /// Paint the code theme's background behind code blocks, or no code background at all
+    #[arg(long, value_enum, value_name = "WHEN", global = true)]
+    highlight_theme_bg: Option<HighlightBg>,
+
+    /// Tables wider than the terminal: wrap their cells, clip to the columns
+    /// that fit, or show each row as records
+    #[arg(long, value_enum, value_name = "MODE", global = true)]
+    wide_tables: Option<WideTables>,
+
+    /// Show local images inline on terminals that support it (kitty, iTerm2)
+    #[arg(long)]
+    images: bool,
+
+    /// Show `$...$` and `$$...$$` math in prose as inline code, without the dollars
+    #[arg(long)]
+    math: bool,
+
+    /// Render only the code blocks, each under its nearest heading
+    #[arg(long)]
+    examples_only: bool,
+
+    /// With --examples-only, keep the line right above each code block
+    #[arg(long, requires = "examples_only")]
+    with_captions: bool,
+
+    /// Print copy-friendly plain text: no colors, underlined headings, `|`
+    /// tables and code fenced again as written
+    #[arg(long)]
+    plain: bool,
+
+    /// Write the rendering to FILE instead of stdout, replacing it at once
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// What to render: ansi, plain or html (default: from the --output
+    /// extension, .html or .ansi, else plain; ansi on stdout)
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    output_format: Option<Output>,
+
+    /// Let --output replace a file that already exists
+    #[arg(long, requires = "output")]
+    force: bool,
+
+    /// Output for screen readers: headings and code blocks announced, tables
+    /// read row by row, no color, box drawing, rules or emoji
+    #[arg(long)]
+    accessible: bool,
+
+    /// Print a line above the sheet with its name, path and when it changed
+    #[arg(long)]
+    header: bool,
+
+    /// Print a line below the sheet counting its words and code blocks
+    #[arg(long)]
+    footer: bool,
+
+    /// Show the sheet's front matter (title, tags, ...) in a panel above it
+    #[arg(long)]
+    show_meta: bool,
+
+    /// Highlight every code block, ignoring the block_renderers config
+    #[arg(long)]
+    no_hooks: bool,
+
+    /// Hide the body of `<details>` blocks, showing only their summary
+    #[arg(long)]
+    collapse_details: bool,
+
+    /// Keep each line of prose as written instead of joining and wrapping paragraphs
+    #[arg(long)]
+    no_reflow: bool,
+
+    /// Warn about headings that repeat an earlier one's title
+    #[arg(long)]
+    warn_duplicate_headings: bool,
+
+    /// Leave `{{env:VAR}}`, `{{hostname}}`, `{{user}}` and `{{date:...}}` as written
+    #[arg(long)]
+    no_expand: bool,
+
+    /// Don't append local overlays to remote-managed sheets
+    #[arg(long)]
+    no_overlay: bool,
+
+    /// What to show when no sheet matches COMMAND (repeatable)
+    #[arg(long, value_enum, value_name = "SOURCE")]
+    fallback: Vec<Fallback>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Action {
+    /// Open a sheet with the system's default application
+    Open {
+        /// Command name whose sheet to open
+        #[arg(value_name = "COMMAND")]
+        command: String,
+
+        /// Open the folder containing the sheet instead
+        #[arg(long)]
+        reveal: bool,
+    },
+    /// Edit a sheet in $VISUAL or $EDITOR
+    Edit {
+        /// Command name whose sheet to edit
+        #[arg(value_name = "COMMAND")]
+        command: String,
+
+        /// Edit a remote-managed sheet itself rather than its local overlay
+        #[arg(long)]
+        base: bool,
+    },
+    /// Encrypt a sheet in place with age, e.g. git.md to git.md.age
+    Encrypt {
+        /// Command name whose sheet to encrypt
+        #[arg(value_name = "COMMAND")]
+        command: String,
+    },
+    /// Create a new sheet in the config directory
+    New {
+        /// Command name the sheet is for
+        #[arg(value_name = "COMMAND", required_unless_present = "list_templates")]
+        command: Option<String>,
+
+        /// Ask for a title, description and sections
+        #[arg(long, conflicts_with = "template")]
+        wizard: bool,
+
+        /// Start from templates/NAME.md in the config directory
+        #[arg(long, value_name = "NAME")]
+        template: Option<String>,
+
+        /// Tags to record in the sheet's front matter
+        #[arg(long, value_name = "TAGS", value_delimiter = ',')]
+        tags: Vec<String>,
+
+        /// List the available templates
+        #[arg(long, conflicts_with_all = ["wizard", "template"])]
+        list_templates: bool,
+    },
+    /// Create the config directory with starter settings and an example sheet
+    Init {
+        /// Also make the config directory a git repository
+        #[arg(long)]
+        git: bool,
+    },
+    /// Move a config directory misspelled `cheatsheet` to `cheetsheet`
+    Migrate {
+        /// Leave a symlink at the old location
+        #[arg(long)]
+        symlink: bool,
+    },
+    /// List available sheets, grouped by folder
+    List {
+        /// Print an array of sheets with their metadata as JSON instead
+        #[arg(long)]
+        json: bool,
+
+        /// Show each sheet's sections, last change and description too
+        #[arg(short, long, conflicts_with = "json")]
+        long: bool,
+    },
+    /// List the tags used in sheets' front matter, most used first
+    Tags,
+    /// Show the sheets viewed most, with their view counts
+    Top {
+        /// Count only the views of the last N days
+        #[arg(long, value_name = "N")]
+        days: Option<u64>,
+
+        /// Show at most N sheets
+        #[arg(long, value_name = "N", default_value_t = 10)]
+        limit: usize,
+
+        /// Print an array of sheets with their view counts as JSON instead
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show the sheets viewed by name, numbered, newest last
+    History {
+        /// Forget every view instead
+        #[arg(long)]
+        clear: bool,
+    },
+    /// Show a sheet's uncommitted changes when its folder is a git checkout
+    Diff {
+        /// Command name whose sheet to compare
+        #[arg(value_name = "COMMAND")]
+        command: String,
+
+        /// Compare with this revision instead, e.g. HEAD~3
+        #[arg(long, value_name = "REV")]
+        rev: Option<String>,
+    },
+    /// Show a random sheet, e.g. as a tip in a login message
+    Random {
+        /// Seed for a reproducible pick
+        #[arg(long, value_name = "N")]
+        seed: Option<u64>,
+
+        /// Show a single random code block and the sheet it comes from
+        #[arg(long)]
+        block: bool,
+    },
+    /// Print one short code block and its caption, fast enough for a
+    /// shell's startup file; prints nothing when there are none
+    Tip {
+        /// Only pick from this sheet
+        #[arg(long, value_name = "NAME")]
+        sheet: Option<String>,
+
+        /// Seed for a reproducible pick
+        #[arg(long, value_name = "N")]
+        seed: Option<u64>,
+
+        /// Rebuild the tips cache from the sheets first
+        #[arg(long)]
+        refresh: bool,
+    },
+    /// Check sheet file names against the configured conventions, and
+    /// sheets for repeated headings and dead links
+    Doctor {
+        /// Rename offending files to the conventional name
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Read or change settings in cheetsheet.toml
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Work with profiles, separate collections of sheets and settings
+    Profile {
+        #[command(subcommand)]
+        action: ProfileAction,
+    },
+    /// Convert a page between tldr and cheetsheet markdown
+    Convert {
+        /// Format of the input file
+        #[arg(long, value_enum)]
+        from: ConvertFormat,
+
+        /// Format to convert to
+        #[arg(long, value_enum)]
+        to: ConvertFormat,
+
+        /// File to convert
+        #[arg(value_name = "INPUT")]
+        input: PathBuf,
+
+        /// Write the result here instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Print a shell function offering a missing command's sheet, for eval in a shell's rc file
+    ShellHook {
+        #[arg(value_enum)]
+        shell: shell_hook::Shell,
+
+        /// Show the sheet without asking first
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigAction {
+    /// Print a setting's effective value and where it comes from
+    Get { key: String },
+    /// Write a setting to the config file
+    Set { key: String, value: String },
+    /// Print every setting with its source
+    List,
+    /// Validate the config file, exiting non-zero on problems
+    Check,
+}
+
+#[derive(Debug, Subcommand)]
+enum ProfileAction {
+    /// List the profiles found in the config directory
+    List,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ConvertFormat {
+    Tldr,
+    Md,
+}
+
+/// What goes between sheets rendered in one run, for `--separator`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Separator {
+    /// A full-width rule, without the sheets' names.
+    Rule,
+    /// Each sheet's name line, without a rule.
+    Heading,
+    /// A form feed, which pagers and printers take as a new page.
+    Formfeed,
+    Text(String),
+}
+
+impl std::str::FromStr for Separator {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, String> {
+        Ok(match text {
+            "rule" => Separator::Rule,
+            "heading" => Separator::Heading,
+            "formfeed" => Separator::Formfeed,
+            _ => Separator::Text(text.to_string()),
+        })
+    }
+}
+
+/// The command line, with global flags allowed before a subcommand, as in
+/// `cheetsheet --config-dir DIR list`. Once a sheet name is given, later
+/// words are sheets even if one is named like a subcommand: `cheetsheet git
+/// list` renders `git` and `list`.
+fn parse_args() -> Args {
+    let args = Args::parse();
+    if args.command.is_none() || args.action.is_none() {
+        return args;
+    }
+    let matches = Args::command()
+        .args_conflicts_with_subcommands(true)
+        .get_matches();
+    Args::from_arg_matches(&matches).unwrap_or_else(|err| err.exit())
 }
 
 fn main() {
-    if let Err(e) = run(Args::parse()) {
-        eprintln!("Error: {e}");
-        std::process::exit(1);
+    // Once the reader of a pipe (`head`, a quit `less`) has gone away,
+    // writes fail with BrokenPipe: like cat and grep, stop quietly with 0.
+    // Output goes through `Write` so the error reaches here; the hook
+    // covers any `print!` left, which panics instead.
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if !is_broken_pipe_panic(info.payload()) {
+            default_hook(info);
+        }
+    }));
+    let args = parse_args();
+    let json_errors = args.json_errors;
+    match std::panic::catch_unwind(|| run(args)) {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) if is_broken_pipe(&e) => {}
+        Ok(Err(e)) => {
+            let (code, _) = error::classify(&e);
+            report(&e, json_errors);
+            std::process::exit(code);
+        }
+        Err(payload) if is_broken_pipe_panic(&*payload) => {}
+        Err(payload) => std::panic::resume_unwind(payload),
     }
 }
 
-fn run(args: Args) -> Result<()> {
-    let config_dir = resolve_config_dir(args.config_dir.as_deref());
-    let sheet_path = find_sheet(&config_dir, &args.command)?;
-    let content = fs::read_to_string(&sheet_path)?;
-    render_markdown(&content);
-    Ok(())
+/// Prints `e` on stderr, as a JSON object with `json_errors`.
+fn report(e: &anyhow::Error, json_errors: bool) {
+    let (code, kind) = error::classify(e);
+    if json_errors {
+        let error = serde_json::json!({
+            "kind": kind,
+            "code": code,
+            "message": format!("{e:#}"),
+        });
+        eprintln!("{error}");
+    } else {
+        eprintln!("Error: {e:#}");
+    }
 }
 
-fn resolve_config_dir(custom: Option<&str>) -> PathBuf {
-    if let Some(dir) = custom {
-        return PathBuf::from(dir);
+fn is_broken_pipe(e: &anyhow::Error) -> bool {
+    e.chain()
+        .filter_map(|cause| cause.downcast_ref::<io::Error>())
+        .any(|e| e.kind() == io::ErrorKind::BrokenPipe)
+}
+
+/// Whether a panic comes from `print!` failing on a closed stdout.
+fn is_broken_pipe_panic(payload: &(dyn std::any::Any + Send)) -> bool {
+    let message = match payload.downcast_ref::<String>() {
+        Some(message) => message.as_str(),
+        None => payload.downcast_ref::<&str>().copied().unwrap_or_default(),
+    };
+    let broken_pipe = io::Error::from(io::ErrorKind::BrokenPipe).to_string();
+    message.starts_with("failed printing to stdout")
+        && (message.contains(&broken_pipe) || message.contains("os error 32"))
+}
+
+fn run(mut args: Args) -> Result<()> {
+    let env_level = std::env::var(logger::ENV).ok();
+    logger::init(logger::level(args.verbose, env_level.as_deref()));
+    explain::set_quiet(args.quiet);
+    let base_dir = resolve_config_dir(args.config_dir.as_deref());
+    let (profile, profile_source) = active_profile(&args)?;
+    let migrating = matches!(args.action, Some(Action::Migrate { .. }));
+    if let Some(typo) = migrate::typo_dir(&base_dir).filter(|_| !base_dir.exists() && !migrating) {
+        explain::warn(format!(
+            "sheets are read from {}, not {}; run `cheetsheet migrate` to move them",
+            base_dir.display(),
+            typo.display()
+        ));
+    }
+    let config_dir = profile::sheet_dir(&base_dir, &profile);
+    log::info!("profile '{profile}', sheets in {}", config_dir.display());
+    let config_path = args
+        .config
+        .clone()
+        .unwrap_or_else(|| profile::config_file(&base_dir, &profile));
+    if let Some(Action::Config {
+        action: ConfigAction::Check,
+    }) = args.action
+    {
+        // Before loading, which would stop at the first problem
+        return check_config(&config_path);
     }
-    if let Ok(xdg) = std::env::var("XDG_CONFIG_HOME") {
-        return PathBuf::from(xdg).join("cheetsheet");
+    let config = Config::load(&config_path, args.config.is_some())?;
+    let quiet = args.quiet || config.quiet;
+    explain::set_quiet(quiet);
+    // crossterm, under termimad's skins too, drops colors on its own when
+    // NO_COLOR is set, which `--color always` overrides
+    let color_mode = args.color.or(config.color).unwrap_or_default();
+    // Before any escape is written; on Windows a console that can't read
+    // them is dumb below and gets plain ASCII instead
+    term::enable_ansi();
+    let color = colored(&args, color_mode);
+    termimad::crossterm::style::force_color_output(color);
+    // `--color always` says the terminal can do more than its TERM claims
+    if color_mode != ColorMode::Always && args.output.is_none() && term::is_dumb() {
+        log::info!("dumb terminal; no colors or box drawing");
+        args.ascii = true;
+    }
+    let lang = match args.lang.clone().or(config.lang.clone()) {
+        Some(Lang(lang)) => Some(lang),
+        None => locale::from_env(|var| std::env::var(var).ok()),
+    };
+    let langs = lang.as_deref().map(locale::chain).unwrap_or_default();
+    let lookup = SheetLocator {
+        config_dir: &config_dir,
+        search_paths: &config.search_paths,
+        recursive: !args.flat,
+        langs: &langs,
+        matcher: Matcher {
+            max_distance: args.max_distance,
+            algorithm: args.match_algorithm,
+        },
+        read: ReadOptions {
+            encoding: args.encoding.filter(|&encoding| encoding != UTF_8),
+            age_identities: &config.age_identities,
+        },
+    };
+    match args.action {
+        Some(Action::Open { command, reveal }) => open_sheet(&lookup, &command, reveal),
+        Some(Action::Edit { command, base }) => edit_sheet(&lookup, &command, base, &config),
+        Some(Action::Encrypt { command }) => {
+            encrypt_sheet(&lookup, &command, &config.age_recipients)
+        }
+        Some(Action::New {
+            list_templates: true,
+            ..
+        }) => list_templates(&config_dir, &config),
+        Some(Action::New {
+            command,
+            wizard,
+            template,
+            tags,
+            ..
+        }) => {
+            let command = command.unwrap_or_default();
+            let content = match wizard {
+                true => {
+                    let stdin = io::stdin();
+                    let interactive = stdin.is_terminal();
+                    let answers =
+                        wizard::ask(&command, &mut stdin.lock(), &mut io::stderr(), interactive)?;
+                    wizard::skeleton(&command, &answers)
+                }
+                false => from_template(&config_dir, &config, &command, template.as_deref(), &tags)?,
+            };
+            let content = template::add_tags(content, &tags);
+            // `new git --lang zh` writes the `git.zh.md` variant
+            let file = match &args.lang {
+                Some(Lang(lang)) => format!("{command}.{lang}"),
+                None => command,
+            };
+            new_sheet(&config_dir, &config, &file, &content)
+        }
+        Some(Action::Init { git }) => init::run(&config_dir, &config_path, git),
+        Some(Action::Migrate { symlink }) => migrate::run(&base_dir, symlink),
+        Some(Action::List { json: true, .. }) => list_json(&config_dir, args.flat, &lookup.read),
+        Some(Action::List { long: true, .. }) => {
+            list_long(&config_dir, args.flat, color, &lookup.read)
+        }
+        Some(Action::List { .. }) => list_sheets(&config_dir, args.flat),
+        Some(Action::Tags) => list_tags(&config_dir, args.flat, &lookup.read),
+        Some(Action::History { clear }) => show_history(&config, &profile, clear),
+        Some(Action::Top { days, limit, json }) => {
+            show_top(&config, &profile, days, limit, json, args.ascii)
+        }
+        Some(Action::Diff {
+            ref command,
+            ref rev,
+        }) => diff_sheet(
+            &lookup,
+            command,
+            rev.as_deref(),
+            &render_options(&args, &config),
+        ),
+        Some(Action::Random { seed, block }) => {
+            random_sheet(&lookup, seed, block, &render_options(&args, &config))
+        }
+        Some(Action::Tip {
+            ref sheet,
+            seed,
+            refresh,
+        }) => {
+            let tips = tips::load(
+                &config_dir,
+                &profile,
+                lookup.recursive,
+                &lookup.read,
+                refresh,
+            );
+            let tips: Vec<_> = tips
+                .iter()
+                .filter(|tip| sheet.as_ref().is_none_or(|name| tip.sheet == *name))
+                .collect();
+            let mut rng = StdRng::seed_from_u64(seed.unwrap_or_else(rand::random));
+            if let Some(tip) = tips.choose(&mut rng) {
+                let width = render::terminal_width(None);
+                print!("{}", tips::format(tip, width, args.ascii));
+            }
+            Ok(())
+        }
+        Some(Action::Doctor { fix }) => doctor(&lookup, &config, fix),
+        Some(Action::Config { ref action }) => {
+            if let ConfigAction::List = action {
+                let source = profile_source.describe("profile", &config_path);
+                println!("profile = \"{profile}\"  # {source}");
+            }
+            run_config(action, &config_path, args.config.is_some(), &args)
+        }
+        Some(Action::Profile {
+            action: ProfileAction::List,
+        }) => {
+            for name in profile::list(&base_dir) {
+                let marker = if name == profile { "*" } else { " " };
+                println!("{marker} {name}");
+            }
+            Ok(())
+        }
+        Some(Action::ShellHook { shell, yes }) => {
+            print!("{}", shell_hook::script(shell, !yes));
+            Ok(())
+        }
+        Some(Action::Convert {
+            from,
+            to,
+            input,
+            output,
+        }) => convert(from, to, &input, output.as_deref()),
+        None if !args.more.is_empty() => view_sheets(&args, &config, &lookup, &profile),
+        None => {
+            if args.command.is_none() && args.file.is_none() {
+                let default = config
+                    .default_command
+                    .as_ref()
+                    .filter(|_| io::stdout().is_terminal());
+                let name = match default {
+                    None => Args::command()
+                        .error(
+                            clap::error::ErrorKind::MissingRequiredArgument,
+                            "the following required arguments were not provided:\n  <COMMAND>",
+                        )
+                        .exit(),
+                    Some(DefaultCommand::List) => return list_sheets(&config_dir, args.flat),
+                    Some(DefaultCommand::Picker) => pick_sheet(&config_dir, args.flat)?,
+                    Some(DefaultCommand::Recent) => last_sheet(&profile)?,
+                    Some(DefaultCommand::View(name)) => name.clone(),
+                };
+                args.command = Some(name);
+            }
+            // Like `cd -`, unless something is piped in to be read
+            if args.command.as_deref() == Some(STDIN_ARG) && io::stdin().is_terminal() {
+                args.command = Some(last_sheet(&profile)?);
+            }
+            let mut section = None;
+            if let Some(command) = args.command.take() {
+                let (name, part) = split_section(&command);
+                if part.is_some() && (args.after.is_some() || args.before.is_some()) {
+                    Args::command()
+                        .error(
+                            clap::error::ErrorKind::ArgumentConflict,
+                            format!("'{command}' names a section; drop --after and --before"),
+                        )
+                        .exit()
+                }
+                args.command = Some(name);
+                section = part;
+            }
+            // `git#` lists the headings instead of a section
+            let toc = section.as_deref() == Some("");
+            let source = sheet_source(&args);
+            if !matches!(source, SheetSource::Stdin) && !args.no_args_stdin && stdin_has_data() {
+                explain::warn("ignoring input piped to stdin; use `cheetsheet -` to render it");
+            }
+            let query = match &source {
+                SheetSource::Named(command) => Some(command.clone()),
+                _ => None,
+            };
+            let sheet_path = match source {
+                SheetSource::Stdin => None,
+                SheetSource::File(path) => Some(path),
+                SheetSource::Named(command) => match lookup.find_path(&command) {
+                    Ok(path) => {
+                        remember(&config, &profile, &command, &path);
+                        Some(path)
+                    }
+                    // A fallback page has no path to print
+                    Err(e) if args.print_path_only => return Err(e),
+                    Err(e) => {
+                        let fallbacks = match args.fallback.is_empty() {
+                            true => &config.fallbacks,
+                            false => &args.fallback,
+                        };
+                        let width = args.width.or(config.width);
+                        match fallback_page(fallbacks, &command, width, color, quiet) {
+                            Some(page) => {
+                                let title = (args.header || config.header)
+                                    .then(|| format!("{command} (man page)"));
+                                return show_paged(
+                                    &page,
+                                    config.pager.as_deref(),
+                                    title.as_deref(),
+                                );
+                            }
+                            None => return Err(e),
+                        }
+                    }
+                },
+            };
+            if args.watch && sheet_path.is_none() {
+                anyhow::bail!("--watch needs a sheet file; stdin can't be watched");
+            }
+            let with_overlay = !args.no_overlay && args.file.is_none();
+            if args.follow_aliases || args.print_path_only {
+                let Some(path) = &sheet_path else {
+                    anyhow::bail!("stdin has no path to print; name a sheet or file");
+                };
+                let chain = match &query {
+                    Some(query) => lookup.resolution(query, path, with_overlay),
+                    None => vec![path.display().to_string()],
+                };
+                let chain = chain.join(if args.ascii { " -> " } else { " → " });
+                if args.print_path_only {
+                    println!("{chain}");
+                    return Ok(());
+                }
+                eprintln!("{chain}");
+            }
+            let load = |path: &Path| -> Result<Sheet> {
+                let mut sheet = match args.part {
+                    Some(part) if path.is_dir() => {
+                        Sheet::read_parts(path, Some(part), &lookup.read)?
+                    }
+                    Some(_) => anyhow::bail!(
+                        "--part needs a sheet split into numbered files, like git/01-basics.md"
+                    ),
+                    None => lookup.load(path, with_overlay)?,
+                };
+                sheet.format = args.format.unwrap_or(sheet.format);
+                if args.warn_duplicate_headings {
+                    warn_duplicate_headings(&sheet);
+                }
+                if toc {
+                    sheet.content = range::toc(sheet.segments());
+                    sheet.format = Format::Markdown;
+                }
+                Ok(sheet)
+            };
+            // The parts of a split sheet sit in its directory
+            let base_dir = match sheet_path.as_deref() {
+                Some(dir) if dir.is_dir() => Some(dir),
+                path => path.and_then(Path::parent),
+            };
+            let header = (args.header || config.header) && decorated(&args, &config);
+            let options = RenderOptions {
+                base_dir: base_dir.map(Path::to_path_buf),
+                header: sheet_path.as_deref().filter(|_| header).map(|path| {
+                    match path.starts_with(&config_dir) {
+                        true => sheet_name(&config_dir, path),
+                        false => sheet_name(path.parent().unwrap_or(path), path),
+                    }
+                }),
+                range: HeadingRange {
+                    section: section.filter(|_| !toc),
+                    ..render_options(&args, &config).range
+                },
+                ..render_options(&args, &config)
+            };
+            // A large file is shown as it is read unless a range, the meta panel
+            // or the header and footer need all of it
+            let stream = sheet_path.as_deref().filter(|path| {
+                let format = args.format.unwrap_or(Format::from_path(path));
+                args.part.is_none()
+                    && !toc
+                    && !args.warn_duplicate_headings
+                    && options.range.is_empty()
+                    && !options.show_meta
+                    && options.header.is_none()
+                    && !options.footer
+                    && lookup.streams(path, format, with_overlay)
+            });
+            let renderer = Renderer::new();
+            let mut out = io::BufWriter::new(io::stdout().lock());
+            // Rendered whole before `--output` is touched
+            let mut document = Vec::new();
+            let mut target: &mut dyn Write = match args.output {
+                Some(_) => &mut document,
+                None => &mut out,
+            };
+            let format = match stream {
+                Some(path) => {
+                    log::info!("rendering {} while reading it", path.display());
+                    let file = fs::File::open(path)
+                        .with_context(|| format!("failed to read {}", path.display()))?;
+                    renderer.render_reader(&mut target, io::BufReader::new(file), &options)?;
+                    Format::Markdown
+                }
+                None => {
+                    let sheet = match &sheet_path {
+                        None if args.part.is_some() => anyhow::bail!(
+                            "--part needs a sheet split into numbered files, not stdin"
+                        ),
+                        None if toc => anyhow::bail!("stdin has no headings to list; name a sheet"),
+                        None => Sheet::new(
+                            read_stdin(lookup.read.encoding)?,
+                            args.format.unwrap_or(Format::Markdown),
+                        ),
+                        Some(path) => load(path)?,
+                    };
+                    renderer.render_to(&mut target, &sheet, &options)?;
+                    sheet.format
+                }
+            };
+            match &args.output {
+                Some(path) => {
+                    check_replace(path, &document, args.force)?;
+                    write_output(path, &document)?
+                }
+                None => out.flush()?,
+            }
+            if args.explain {
+                let stats = renderer.stats();
+                let trace = explain::Trace {
+                    query,
+                    overlay: sheet_path
+                        .as_deref()
+                        .filter(|_| with_overlay)
+                        .and_then(|path| find_overlay(&config_dir, path)),
+                    path: sheet_path.clone(),
+                    format: value_name(format),
+                    profile: profile.clone(),
+                    config: config_path.is_file().then(|| config_path.clone()),
+                    theme: value_name(options.theme),
+                    code_theme: stats.code_theme,
+                    renderer: value_name(options.renderer),
+                    color: options.color,
+                    segments: stats.segments,
+                    languages: stats.languages,
+                    warnings: explain::warnings(),
+                };
+                eprintln!("{}", trace.to_json());
+            }
+            match sheet_path {
+                Some(path) if args.watch => {
+                    let mut screen = watch::Screen::default();
+                    watch::run(&path, |update| {
+                        let mut frame = Vec::new();
+                        match update {
+                            watch::Update::Changed => {
+                                let sheet = load(&path)?;
+                                renderer.render_to(&mut frame, &sheet, &options)?;
+                                // The file written above is this run's to rewrite
+                                if let Some(output) = &args.output {
+                                    return write_output(output, &frame);
+                                }
+                                let stamp = watch::stamp(SystemTime::now(), options.color);
+                                writeln!(frame, "{}{stamp}", " ".repeat(options.margin))?;
+                            }
+                            // Keep the last good file until the sheet is back
+                            watch::Update::Missing if args.output.is_some() => return Ok(()),
+                            watch::Update::Missing => writeln!(
+                                frame,
+                                "{} is gone; waiting for it to come back (Ctrl-C to quit)",
+                                path.display()
+                            )?,
+                        }
+                        let size = termimad::crossterm::terminal::size().ok();
+                        screen.repaint(&mut out, &String::from_utf8_lossy(&frame), size)?;
+                        out.flush()?;
+                        Ok(())
+                    })
+                }
+                _ => Ok(()),
+            }
+        }
     }
-    dirs::home_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join(".config")
-        .join("cheetsheet")
 }
 
-fn find_sheet(config_dir: &Path, command: &str) -> Result<PathBuf> {
-    let path = config_dir.join(format!("{command}.md"));
-    if path.exists() {
-        Ok(path)
+/// Rendering settings from the flags, falling back to the config.
+fn render_options(args: &Args, config: &Config) -> RenderOptions {
+    let syntax_cache = if args.rebuild_cache {
+        SyntaxCache::Rebuild
+    } else if args.syntax_cache {
+        SyntaxCache::Use
     } else {
-        anyhow::bail!(
-            "No cheatsheet found for '{command}'.\nExpected: {}\nTip: create a markdown file at that path to get started.",
-            path.display()
-        )
+        SyntaxCache::Off
+    };
+    RenderOptions {
+        columns: args.columns,
+        syntax_cache,
+        context_lang: args.context_lang.clone(),
+        ascii: args.ascii,
+        theme: args.theme.or(config.theme).unwrap_or_default(),
+        code_theme: args.code_theme.clone().or(config.code_theme.clone()),
+        width: args.width.or(config.width),
+        margin: args.margin.or(config.margin).unwrap_or_default(),
+        color: colored(args, args.color.or(config.color).unwrap_or_default()),
+        default_lang: config.default_lang.clone(),
+        collapse_details: args.collapse_details,
+        code_frame: args.code_frame,
+        renderer: args.renderer.or(config.renderer).unwrap_or_default(),
+        no_reflow: args.no_reflow || config.reflow == Some(false),
+        list_style: args.list_style.or(config.list_style).unwrap_or_default(),
+        expand: !args.no_expand,
+        code_spacing_before: args
+            .code_spacing_before
+            .or(config.code_spacing_before)
+            .unwrap_or_default(),
+        code_spacing_after: args
+            .code_spacing_after
+            .or(config.code_spacing_after)
+            .unwrap_or_default(),
+        tab_width: args.tab_width.or(config.tab_width).unwrap_or_default(),
+        highlight_theme_bg: args.highlight_theme_bg.or(config.highlight_theme_bg),
+        wide_tables: args.wide_tables.or(config.wide_tables).unwrap_or_default(),
+        range: HeadingRange {
+            after: args.after.clone(),
+            before: args.before.clone(),
+            section: None,
+        },
+        images: args.images,
+        math: args.math,
+        output: output_format(args),
+        quiet: args.quiet || config.quiet,
+        examples_only: args.examples_only,
+        captions: args.with_captions,
+        accessible: args.accessible || config.accessible,
+        header: None,
+        footer: (args.footer || config.footer) && decorated(args, config),
+        show_meta: args.show_meta || config.show_meta,
+        meta_keys: config.meta_keys.clone(),
+        block_renderers: match args.no_hooks {
+            true => BTreeMap::new(),
+            false => config.block_renderers.clone(),
+        },
+        lang_aliases: config.lang_aliases.clone(),
+        colors: config.colors.clone(),
+        base_dir: None,
     }
 }
 
-fn make_skin() -> MadSkin {
-    let mut skin = MadSkin::default();
-    skin.set_headers_fg(ansi(178)); // 橙黃色標題
-    skin.bold.set_fg(Yellow);
-    skin.italic.set_fg(ansi(147)); // 淡紫色
-    skin.inline_code.set_fgbg(ansi(222), ansi(236)); // 暖黃 on 深灰
-    skin.code_block.set_fgbg(gray(17), gray(3));
-    skin.table.set_fg(ansi(117)); // 淡藍色表格
-    skin.headers[0].add_attr(Attribute::Bold);
-    skin.headers[1].add_attr(Attribute::Bold);
-    skin
+/// What the sheet renders as: `--output-format`, else `--plain`, else what
+/// the `--output` file's extension asks for.
+fn output_format(args: &Args) -> Output {
+    match (args.output_format, &args.output) {
+        (Some(format), _) => format,
+        _ if args.plain => Output::Plain,
+        (None, Some(path)) => Output::from_path(path),
+        (None, None) => Output::Ansi,
+    }
 }
 
-enum Segment {
-    Text(String),
-    Code { lang: String, code: String },
+/// Whether the header and footer lines go out: only around a sheet styled
+/// for the terminal, so not in plain or accessible text, in uncolored
+/// output piped elsewhere, or with --quiet.
+fn decorated(args: &Args, config: &Config) -> bool {
+    let color = colored(args, args.color.or(config.color).unwrap_or_default());
+    output_format(args) == Output::Ansi
+        && !(args.accessible || config.accessible)
+        && !(args.quiet || config.quiet)
+        && (color || io::stdout().is_terminal())
+}
+
+/// Whether to write color escapes. An `--output` file gets them for `auto`
+/// when it is to hold ANSI, whatever stdout is.
+fn colored(args: &Args, mode: ColorMode) -> bool {
+    match mode {
+        ColorMode::Auto if args.output.is_some() => output_format(args) == Output::Ansi,
+        mode => mode.enabled(),
+    }
+}
+
+/// Refuses to replace the file at `path` without `--force`, unless it
+/// already holds `content`, as when the same rendering is written again.
+fn check_replace(path: &Path, content: &[u8], force: bool) -> Result<()> {
+    let unchanged = || fs::read(path).is_ok_and(|existing| existing == content);
+    anyhow::ensure!(
+        force || !path.exists() || unchanged(),
+        "{} exists; pass --force to replace it",
+        path.display()
+    );
+    Ok(())
 }
 
-fn split_segments(content: &str) -> Vec<Segment> {
-    let mut segments = Vec::new();
-    let mut rest = content;
+/// Replaces `path` with `content` through a temporary file beside it, so a
+/// failed render or write never leaves half a sheet there.
+fn write_output(path: &Path, content: &[u8]) -> Result<()> {
+    let name = path
+        .file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy();
+    let temp = path.with_file_name(format!(".{name}.tmp"));
+    fs::write(&temp, content).with_context(|| format!("failed to write {}", temp.display()))?;
+    fs::rename(&temp, path).with_context(|| {
+        let _ = fs::remove_file(&temp);
+        format!("failed to write {}", path.display())
+    })
+}
 
-    while let Some(fence_start) = rest.find("```") {
-        // Text before the fence
-        let before = &rest[..fence_start];
-        if !before.is_empty() {
-            segments.push(Segment::Text(before.to_string()));
+/// Renders a random sheet, or with `block` a random code block from any
+/// sheet under its sheet's name.
+fn random_sheet(
+    lookup: &SheetLocator,
+    seed: Option<u64>,
+    block: bool,
+    options: &RenderOptions,
+) -> Result<()> {
+    let sheets = lookup.sheets();
+    let place = match lookup.search_paths.is_empty() {
+        true => lookup.config_dir.display().to_string(),
+        false => format!("{} or the search_paths", lookup.config_dir.display()),
+    };
+    let mut rng = StdRng::seed_from_u64(seed.unwrap_or_else(rand::random));
+    if !block {
+        let Some((_, path)) = sheets.choose(&mut rng) else {
+            anyhow::bail!(CheetError::NotFound(format!("no sheets in {place}")));
+        };
+        let sheet = Sheet::read(path, &lookup.read)?;
+        let mut out = io::BufWriter::new(io::stdout().lock());
+        Renderer::new().render_to(&mut out, &sheet, options)?;
+        return Ok(out.flush()?);
+    }
+    let mut blocks = Vec::new();
+    for (name, path) in &sheets {
+        let sheet = Sheet::read(path, &lookup.read)?;
+        let expand = options.expand
+            && !front_matter::split(&sheet.content)
+                .0
+                .is_some_and(variables::switched_off);
+        for segment in sheet.segments() {
+            let segment = if expand {
+                variables::fill(segment)
+            } else {
+                segment
+            };
+            if let Segment::Code { lang, code } = segment {
+                blocks.push((name.clone(), lang, code.into_owned()));
+            }
         }
+    }
+    let Some((name, lang, code)) = blocks.choose(&mut rng).cloned() else {
+        anyhow::bail!(CheetError::NotFound(format!(
+            "no code blocks in the sheets in {place}"
+        )));
+    };
+    let heading = Segment::Text(format!("**{name}**\n").into());
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    let code = Segment::Code {
+        lang,
+        code: code.into(),
+    };
+    Renderer::new().render_segments(&mut out, vec![heading, code], options)?;
+    Ok(out.flush()?)
+}
+
+/// Renders COMMAND and each of MORE back to back, every sheet under a rule
+/// and its name or the `--separator` asked for, and pages them as one. A
+/// sheet that can't be found or read is left out and reported before the
+/// rest are shown.
+fn view_sheets(args: &Args, config: &Config, lookup: &SheetLocator, profile: &str) -> Result<()> {
+    let single = [
+        ("--after", args.after.is_some()),
+        ("--before", args.before.is_some()),
+        ("--part", args.part.is_some()),
+        ("--watch", args.watch),
+        ("--follow-aliases", args.follow_aliases),
+        ("--print-path-only", args.print_path_only),
+        ("--explain", args.explain),
+        ("--output-format html", output_format(args) == Output::Html),
+    ];
+    if let Some((flag, _)) = single.iter().find(|(_, set)| *set) {
+        Args::command()
+            .error(
+                clap::error::ErrorKind::ArgumentConflict,
+                format!("{flag} works on a single sheet; name only one"),
+            )
+            .exit()
+    }
+    let names: Vec<&String> = args.command.iter().chain(&args.more).collect();
+    for name in &names {
+        let problem = if split_section(name).1.is_some() {
+            format!("'{name}' names a section, which works on a single sheet; name only one")
+        } else if matches!(command_source(name), SheetSource::Stdin) {
+            "stdin can only be read as the only sheet".to_string()
+        } else {
+            continue;
+        };
+        Args::command()
+            .error(clap::error::ErrorKind::ArgumentConflict, problem)
+            .exit()
+    }
+    let options = render_options(args, config);
+    let renderer = Renderer::new();
+    let with_overlay = !args.no_overlay;
+    let mut document = Vec::new();
+    let mut failures = Vec::new();
+    for name in names {
+        let path = match command_source(name) {
+            SheetSource::Stdin => unreachable!("stdin was refused above"),
+            SheetSource::File(path) => Ok(path),
+            SheetSource::Named(command) => lookup
+                .find_path(&command)
+                .inspect(|path| remember(config, profile, &command, path)),
+        };
+        let sheet = path.and_then(|path| {
+            let mut sheet = lookup.load(&path, with_overlay)?;
+            sheet.format = args.format.unwrap_or(sheet.format);
+            if args.warn_duplicate_headings {
+                warn_duplicate_headings(&sheet);
+            }
+            Ok(sheet)
+        });
+        let sheet = match sheet {
+            Ok(sheet) => sheet,
+            Err(e) => {
+                failures.push(e);
+                continue;
+            }
+        };
+        if !document.is_empty() {
+            let rule = || {
+                let rule = if options.ascii { "-" } else { "─" }.repeat(options.width());
+                match options.color {
+                    true => rule.dark_grey().to_string(),
+                    false => rule,
+                }
+            };
+            let margin = " ".repeat(options.margin);
+            match &args.separator {
+                None | Some(Separator::Rule) => writeln!(document, "\n{margin}{}\n", rule())?,
+                Some(Separator::Heading) => writeln!(document)?,
+                Some(Separator::Formfeed) => writeln!(document, "\n\x0c")?,
+                Some(Separator::Text(text)) => writeln!(document, "\n{margin}{text}\n")?,
+            }
+        }
+        let options = RenderOptions {
+            base_dir: sheet
+                .path
+                .as_deref()
+                .and_then(Path::parent)
+                .map(Path::to_path_buf),
+            header: Some(name.clone()).filter(|_| args.separator != Some(Separator::Rule)),
+            ..render_options(args, config)
+        };
+        renderer.render_to(&mut document, &sheet, &options)?;
+    }
+    // Failures are printed before the pager takes the screen, where they'd
+    // go unseen; the last one's exit code is the run's
+    let code = failures.last().map(|e| error::classify(e).0);
+    for e in &failures {
+        report(e, args.json_errors);
+    }
+    match &args.output {
+        Some(path) => {
+            check_replace(path, &document, args.force)?;
+            write_output(path, &document)?
+        }
+        None => show_paged(
+            &String::from_utf8_lossy(&document),
+            config.pager.as_deref(),
+            None,
+        )?,
+    }
+    if let Some(code) = code {
+        std::process::exit(code);
+    }
+    Ok(())
+}
+
+/// Adds a view of `name` at `path` to the history, unless `history = false`.
+fn remember(config: &Config, profile: &str, name: &str, path: &Path) {
+    if config.history.unwrap_or(true) {
+        let size = config.history_size.unwrap_or(recent::DEFAULT_SIZE);
+        recent::record(profile, name, path, size);
+    }
+}
+
+/// The name of the sheet viewed last, for `-` and `default_command = "recent"`.
+fn last_sheet(profile: &str) -> Result<String> {
+    recent::last(profile).context(CheetError::NotFound(
+        "no recently viewed sheet yet; view one by name first".to_string(),
+    ))
+}
 
-        let after_fence = &rest[fence_start + 3..];
+/// `history`: the sheets viewed by name, numbered, oldest first.
+fn show_history(config: &Config, profile: &str, clear: bool) -> Result<()> {
+    if clear {
+        return recent::clear(profile).context("failed to clear the history");
+    }
+    let size = config.history_size.unwrap_or(recent::DEFAULT_SIZE);
+    let entries = recent::entries(profile, size);
+    let digits = entries.len().to_string().len();
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    for (n, entry) in entries.iter().enumerate() {
+        let time = UNIX_EPOCH + Duration::from_secs(entry.time);
+        writeln!(
+            out,
+            "{:>digits$}  {}  {}  {}",
+            n + 1,
+            template::rfc3339(time),
+            entry.name,
+            entry.path.display()
+        )?;
+    }
+    Ok(out.flush()?)
+}
+
+/// `top`: the sheets viewed most, with a bar for their share of views.
+fn show_top(
+    config: &Config,
+    profile: &str,
+    days: Option<u64>,
+    limit: usize,
+    json: bool,
+    ascii: bool,
+) -> Result<()> {
+    let size = config.history_size.unwrap_or(recent::DEFAULT_SIZE);
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let since = days.map_or(0, |days| now.saturating_sub(days * 86_400));
+    let mut top = recent::top(&recent::entries(profile, size), since);
+    top.truncate(limit);
+    if json {
+        let json = serde_json::to_string_pretty(&top)?;
+        return Ok(writeln!(io::stdout(), "{json}")?);
+    }
+    let most = top.first().map_or(0, |views| views.views);
+    let name_width = top.iter().map(|views| views.name.chars().count()).max();
+    let name_width = name_width.unwrap_or(0);
+    let count_width = most.to_string().len();
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    for views in &top {
+        let bar = recent::bar(views.views, most, 20, ascii);
+        let line = format!(
+            "{:<name_width$}  {:>count_width$}  {bar}",
+            views.name, views.views
+        );
+        writeln!(out, "{}", line.trim_end())?;
+    }
+    Ok(out.flush()?)
+}
 
-        // Find the end of the opening fence line to extract language
-        let lang_end = after_fence.find('\n').unwrap_or(after_fence.len());
-        let lang = after_fence[..lang_end].trim().to_string();
-        let code_start = &after_fence[lang_end..].trim_start_matches('\n');
+/// The COMMAND that reads the sheet from stdin instead of the config dir.
+const STDIN_ARG: &str = "-";
 
-        // Find the closing fence
-        if let Some(close) = code_start.find("\n```") {
-            let code = &code_start[..close];
-            segments.push(Segment::Code {
-                lang,
-                code: code.to_string(),
+/// Where the sheet to view comes from.
+enum SheetSource {
+    Stdin,
+    /// An explicit file, bypassing `find_sheet`.
+    File(PathBuf),
+    /// A sheet name resolved with `find_sheet`.
+    Named(String),
+}
+
+fn sheet_source(args: &Args) -> SheetSource {
+    if let Some(path) = &args.file {
+        return SheetSource::File(path.clone());
+    }
+    command_source(args.command.as_deref().unwrap_or_default())
+}
+
+/// Splits `git#stash` into the sheet name and the section after the first
+/// `#` not written as `\#`, unescaping the name. `git#` gives an empty
+/// section.
+fn split_section(command: &str) -> (String, Option<String>) {
+    let mut name = String::new();
+    let mut chars = command.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' if chars.peek().is_some_and(|&(_, next)| next == '#') => {}
+            '#' if !command[..i].ends_with('\\') => {
+                return (name, Some(command[i + 1..].trim().to_string()));
+            }
+            c => name.push(c),
+        }
+    }
+    (name, None)
+}
+
+/// Where a COMMAND argument points.
+fn command_source(command: &str) -> SheetSource {
+    match command {
+        STDIN_ARG => SheetSource::Stdin,
+        command if looks_like_path(command) => SheetSource::File(PathBuf::from(command)),
+        // `cloud/aws` is a namespaced sheet unless such a file exists
+        command if command.contains('/') && Path::new(command).is_file() => {
+            SheetSource::File(PathBuf::from(command))
+        }
+        command => SheetSource::Named(command.to_string()),
+    }
+}
+
+/// Whether a COMMAND should be read as a file path rather than a sheet name.
+fn looks_like_path(command: &str) -> bool {
+    let has_extension = SHEET_EXTENSIONS
+        .iter()
+        .any(|ext| command.ends_with(&format!(".{ext}")));
+    has_extension || command.starts_with(['.', '/', '~']) || command.contains('\\')
+}
+
+/// Whether stdin is redirected and has data waiting: the unread part of a
+/// file, or what a pipe holds right now. Nothing is read, so the next
+/// command of a script still gets all of its input.
+#[cfg(unix)]
+fn stdin_has_data() -> bool {
+    let stdin = io::stdin();
+    !stdin.is_terminal() && rustix::io::ioctl_fionread(&stdin).is_ok_and(|pending| pending > 0)
+}
+
+#[cfg(not(unix))]
+fn stdin_has_data() -> bool {
+    false
+}
+
+fn read_stdin(encoding: Option<&'static Encoding>) -> Result<String> {
+    let mut stdin = io::stdin();
+    if stdin.is_terminal() {
+        anyhow::bail!(
+            "'-' reads a sheet from stdin, but stdin is a terminal.\nTip: pipe markdown in, e.g. `cat notes.md | cheetsheet -`."
+        );
+    }
+    let mut bytes = Vec::new();
+    stdin
+        .read_to_end(&mut bytes)
+        .context("failed to read stdin")?;
+    decode(bytes, encoding).context("failed to read stdin")
+}
+
+/// `--encoding`'s value: any label the WHATWG Encoding Standard knows.
+fn parse_encoding(label: &str) -> Result<&'static Encoding, String> {
+    Encoding::for_label(label.trim().as_bytes())
+        .ok_or_else(|| format!("unknown encoding '{label}', e.g. utf-8, latin1, gbk or shift_jis"))
+}
+
+/// The first available fallback page for a sheet name that didn't resolve.
+fn fallback_page(
+    fallbacks: &[Fallback],
+    command: &str,
+    width: Option<usize>,
+    color: bool,
+    quiet: bool,
+) -> Option<String> {
+    let width = render::terminal_width(width);
+    fallbacks.iter().find_map(|fallback| match fallback {
+        Fallback::Man if quiet => man::render(command, width),
+        Fallback::Man => man::render(command, width).map(|page| {
+            let banner = format!("No cheatsheet for '{command}'; showing its man page.");
+            let banner = match color {
+                true => banner.dim().to_string(),
+                false => banner,
+            };
+            format!("{banner}\n\n{page}")
+        }),
+    })
+}
+
+/// Shows `text` through `pager`, `$PAGER` or `less -R` when stdout is a
+/// terminal, or prints it directly otherwise or if the pager can't start.
+fn show_paged(text: &str, pager: Option<&str>, title: Option<&str>) -> Result<()> {
+    if !io::stdout().is_terminal() {
+        return Ok(io::stdout().write_all(text.as_bytes())?);
+    }
+    let pager = pager
+        .map(String::from)
+        .or_else(|| std::env::var("PAGER").ok())
+        .unwrap_or_else(|| "less -R".to_string());
+    // Anything already written must not end up behind the pager
+    io::stdout().flush()?;
+    let mut words = pager.split_whitespace();
+    let program = words.next().unwrap_or("less");
+    let mut command = Command::new(program);
+    command.args(words);
+    // less shows the `--header` title in place of its own prompt
+    if let Some(title) = title.filter(|_| Path::new(program).file_stem() == Some("less".as_ref())) {
+        command.arg(format!("--prompt=s{}", less_prompt(title)));
+    }
+    let Ok(mut child) = command.stdin(Stdio::piped()).spawn() else {
+        return Ok(io::stdout().write_all(text.as_bytes())?);
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        // The user quitting the pager early closes the pipe; that's fine
+        let _ = stdin.write_all(text.as_bytes());
+    }
+    child
+        .wait()
+        .context(CheetError::External("pager failed".to_string()))?;
+    Ok(())
+}
+
+/// `text` with the characters less gives a meaning in prompts escaped.
+fn less_prompt(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '?' | ':' | '.' | '%' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Opens a sheet in the `editor` key's editor, `$VISUAL` or `$EDITOR`.
+/// Remote-managed sheets open their local overlay instead (created on save)
+/// unless `base` is set.
+fn edit_sheet(lookup: &SheetLocator, command: &str, base: bool, config: &Config) -> Result<()> {
+    let config_dir = lookup.config_dir;
+    let sheet_path = lookup.find_path(command)?;
+    let base = base || age::is_encrypted(&sheet_path);
+    let target = if base || !is_remote_managed(config_dir, &sheet_path) {
+        sheet_path
+    } else {
+        find_overlay(config_dir, &sheet_path).unwrap_or_else(|| {
+            let stem = sheet_path.file_stem().unwrap_or_default().to_string_lossy();
+            config_dir.join(format!("{stem}{OVERLAY_SUFFIX}.md"))
+        })
+    };
+    let editor = config
+        .editor
+        .clone()
+        .or_else(|| std::env::var("VISUAL").ok())
+        .or_else(|| std::env::var("EDITOR").ok())
+        .unwrap_or_else(|| "vi".to_string());
+    match age::is_encrypted(&target) {
+        true => edit_encrypted(
+            &target,
+            lookup.read.age_identities,
+            &config.age_recipients,
+            |path| run_editor(&editor, path),
+        ),
+        false => run_editor(&editor, &target),
+    }
+}
+
+/// Decrypts the sheet at `path` with `identities` into a file only the user
+/// can read, lets `edit` change it, encrypts any changes back for
+/// `recipients` and shreds the file. When the changes can't be saved the
+/// file is kept, and named in the error, so they aren't lost.
+fn edit_encrypted(
+    path: &Path,
+    identities: &[PathBuf],
+    recipients: &[String],
+    edit: impl FnOnce(&Path) -> Result<()>,
+) -> Result<()> {
+    let plain = age::decrypt(path, identities)?;
+    let name = age::plain_path(path);
+    let name = name.file_name().unwrap_or_default().to_string_lossy();
+    let temp = std::env::temp_dir().join(format!("cheetsheet-{}-{name}", std::process::id()));
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+        .open(&temp)
+        .and_then(|mut file| file.write_all(plain.as_bytes()))
+        .with_context(|| format!("failed to write {}", temp.display()))?;
+    let edited = match edit(&temp).and_then(|()| Ok(fs::read(&temp)?)) {
+        Ok(edited) => edited,
+        Err(err) => {
+            // The editor's failure is the one to report, naming any file left
+            return Err(match age::shred(&temp) {
+                Ok(()) => err,
+                Err(_) => err.context(format!("the decrypted sheet is left in {}", temp.display())),
             });
-            rest = &code_start[close + 4..]; // skip "\n```"
+        }
+    };
+    if edited != plain.as_bytes() {
+        // Encrypted beside the sheet first, so a failure leaves it whole
+        let mut staged = path.as_os_str().to_owned();
+        staged.push(".tmp");
+        let staged = PathBuf::from(staged);
+        let saved = age::encrypt(&edited, &staged, recipients).and_then(|()| {
+            fs::rename(&staged, path)
+                .with_context(|| format!("failed to replace {}", path.display()))
+        });
+        if let Err(err) = saved {
+            let _ = fs::remove_file(&staged);
+            return Err(err.context(format!(
+                "your changes are kept unencrypted in {}",
+                temp.display()
+            )));
+        }
+    }
+    age::shred(&temp)
+}
+
+/// Encrypts a sheet with age where it is, `git.md` becoming `git.md.age`,
+/// and shreds the plain file.
+fn encrypt_sheet(lookup: &SheetLocator, command: &str, recipients: &[String]) -> Result<()> {
+    let path = lookup.find_path(command)?;
+    anyhow::ensure!(
+        !age::is_encrypted(&path),
+        "{} is already encrypted",
+        path.display()
+    );
+    anyhow::ensure!(
+        path.is_file(),
+        "{} is split into parts; only a single sheet file can be encrypted",
+        path.display()
+    );
+    let mut encrypted = path.clone().into_os_string();
+    encrypted.push(format!(".{}", age::EXTENSION));
+    let encrypted = PathBuf::from(encrypted);
+    anyhow::ensure!(!encrypted.exists(), "{} exists", encrypted.display());
+    let plain = fs::read(&path).with_context(|| format!("failed to read {}", path.display()))?;
+    age::encrypt(&plain, &encrypted, recipients)?;
+    age::shred(&path)?;
+    println!("Encrypted {}", encrypted.display());
+    Ok(())
+}
+
+/// Runs `editor`, a command with optional arguments, on `path`.
+fn run_editor(editor: &str, path: &Path) -> Result<()> {
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+    let status = Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()
+        .with_context(|| CheetError::External(format!("failed to launch editor '{program}'")))?;
+    if !status.success() {
+        anyhow::bail!(CheetError::External(format!(
+            "editor '{program}' exited with {status}"
+        )));
+    }
+    Ok(())
+}
+
+/// Shows `git diff` for a sheet, against `rev` or else the index, colored
+/// like a `diff` code block.
+fn diff_sheet(
+    lookup: &SheetLocator,
+    command: &str,
+    rev: Option<&str>,
+    options: &RenderOptions,
+) -> Result<()> {
+    let path = lookup.find_path(command)?;
+    // A sheet in parts is its whole folder
+    let (dir, target) = match path.is_dir() {
+        true => (path.as_path(), Path::new(".")),
+        false => (
+            path.parent().unwrap_or(lookup.config_dir),
+            Path::new(path.file_name().unwrap_or_default()),
+        ),
+    };
+    let git = |args: &[&OsStr]| {
+        Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .output()
+            .context(CheetError::External("failed to run git".to_string()))
+    };
+    let checkout = git(&["rev-parse".as_ref(), "--is-inside-work-tree".as_ref()])?;
+    if !checkout.status.success() {
+        anyhow::bail!(CheetError::NotFound(format!(
+            "{} is not in a git checkout, so there is nothing to compare {command} with",
+            dir.display()
+        )));
+    }
+    let tracked = git(&[
+        "ls-files".as_ref(),
+        "--error-unmatch".as_ref(),
+        "--".as_ref(),
+        target.as_os_str(),
+    ])?;
+    if !tracked.status.success() {
+        anyhow::bail!(CheetError::NotFound(format!(
+            "{command} is untracked in {}, so there is nothing to compare it with",
+            dir.display()
+        )));
+    }
+    // An external diff tool or a revision like `--output=...` must not
+    // take over the comparison
+    let mut args: Vec<&OsStr> = vec![
+        "diff".as_ref(),
+        "--no-color".as_ref(),
+        "--no-ext-diff".as_ref(),
+        "--end-of-options".as_ref(),
+    ];
+    args.extend(rev.map(OsStr::new));
+    args.extend(["--".as_ref(), target.as_os_str()]);
+    let output = git(&args)?;
+    if !output.status.success() {
+        anyhow::bail!(CheetError::External(format!(
+            "git diff failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    let diff = String::from_utf8_lossy(&output.stdout);
+    if diff.is_empty() {
+        eprintln!("No changes to {command}");
+        return Ok(());
+    }
+    let code = Segment::Code {
+        lang: "diff".to_string(),
+        code: diff,
+    };
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    Renderer::new().render_segments(&mut out, [code], options)?;
+    Ok(out.flush()?)
+}
+
+fn open_sheet(lookup: &SheetLocator, command: &str, reveal: bool) -> Result<()> {
+    let sheet_path = lookup.find_path(command)?;
+    let target = if reveal {
+        sheet_path
+            .parent()
+            .unwrap_or(lookup.config_dir)
+            .to_path_buf()
+    } else {
+        sheet_path
+    };
+    let (program, opener_args) = opener_command(&target);
+    let status = Command::new(program)
+        .args(&opener_args)
+        .status()
+        .with_context(|| CheetError::External(format!("failed to launch '{program}'")))?;
+    if !status.success() {
+        anyhow::bail!(CheetError::External(format!(
+            "'{program}' exited with {status}"
+        )));
+    }
+    Ok(())
+}
+
+fn run_config(action: &ConfigAction, path: &Path, required: bool, args: &Args) -> Result<()> {
+    let show = |value: &Option<toml::Value>| match value {
+        Some(toml::Value::String(text)) => text.clone(),
+        Some(value) => value.to_string(),
+        None => "(default)".to_string(),
+    };
+    match action {
+        ConfigAction::Set { key, value } => config::set(path, key, value),
+        ConfigAction::Check => check_config(path),
+        ConfigAction::Get { key } => {
+            config::check_key(key)?;
+            let settings = config::effective(path, required, &flag_settings(args))?;
+            if let Some((_, value, source)) = settings.iter().find(|(k, ..)| k == key) {
+                println!("{} ({})", show(value), source.describe(key, path));
+            }
+            Ok(())
+        }
+        ConfigAction::List => {
+            for (key, value, source) in config::effective(path, required, &flag_settings(args))? {
+                let value = match value {
+                    Some(value) => value.to_string(),
+                    None => show(&None),
+                };
+                println!("{key} = {value}  # {}", source.describe(key, path));
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Reports every problem in the config file at `path`, for `config check`.
+fn check_config(path: &Path) -> Result<()> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            println!("{}: not found, using defaults", path.display());
+            return Ok(());
+        }
+        Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+    };
+    let mut themes: Vec<String> = ThemeSet::load_defaults().themes.into_keys().collect();
+    themes.sort();
+    let problems = config::check(&text, Some(&themes));
+    for problem in &problems {
+        println!("{}", problem.describe(path));
+    }
+    match problems.len() {
+        0 => {
+            println!("{}: ok", path.display());
+            Ok(())
+        }
+        1 => anyhow::bail!(CheetError::Config(format!(
+            "1 problem in {}",
+            path.display()
+        ))),
+        n => anyhow::bail!(CheetError::Config(format!(
+            "{n} problems in {}",
+            path.display()
+        ))),
+    }
+}
+
+/// How `value` is spelled on the command line and in the config.
+fn value_name(value: impl ValueEnum) -> String {
+    let name = value.to_possible_value().map(|v| v.get_name().to_string());
+    name.unwrap_or_default()
+}
+
+/// The config keys set by flags on this command line.
+fn flag_settings(args: &Args) -> toml::Table {
+    fn name(value: impl ValueEnum) -> toml::Value {
+        toml::Value::String(value_name(value))
+    }
+    let mut flags = toml::Table::new();
+    if let Some(theme) = args.theme {
+        flags.insert("theme".into(), name(theme));
+    }
+    if let Some(code_theme) = &args.code_theme {
+        flags.insert("code_theme".into(), code_theme.clone().into());
+    }
+    if let Some(width) = args.width {
+        flags.insert("width".into(), (width as i64).into());
+    }
+    if let Some(margin) = args.margin {
+        flags.insert("margin".into(), (margin as i64).into());
+    }
+    if let Some(color) = args.color {
+        flags.insert("color".into(), name(color));
+    }
+    if let Some(renderer) = args.renderer {
+        flags.insert("renderer".into(), name(renderer));
+    }
+    if let Some(style) = args.list_style {
+        flags.insert("list_style".into(), name(style));
+    }
+    if let Some(Lang(lang)) = &args.lang {
+        flags.insert("lang".into(), lang.clone().into());
+    }
+    if let Some(Spacing(lines)) = args.code_spacing_before {
+        flags.insert("code_spacing_before".into(), (lines as i64).into());
+    }
+    if let Some(Spacing(lines)) = args.code_spacing_after {
+        flags.insert("code_spacing_after".into(), (lines as i64).into());
+    }
+    if let Some(TabWidth(columns)) = args.tab_width {
+        flags.insert("tab_width".into(), (columns as i64).into());
+    }
+    if let Some(bg) = args.highlight_theme_bg {
+        flags.insert("highlight_theme_bg".into(), name(bg));
+    }
+    if !args.fallback.is_empty() {
+        let fallbacks = args.fallback.iter().map(|f| name(*f)).collect();
+        flags.insert("fallbacks".into(), toml::Value::Array(fallbacks));
+    }
+    flags
+}
+
+fn new_sheet(config_dir: &Path, config: &Config, command: &str, content: &str) -> Result<()> {
+    let name = match config.normalize_names {
+        true => normalize_name(command),
+        false => command.to_string(),
+    };
+    let extension = config.extension.unwrap_or_default();
+    let path = config_dir.join(format!("{name}.{}", extension.as_str()));
+    if let Some(existing) = sheet_with_extension(&config_dir.join(&name)) {
+        anyhow::bail!("{} already exists", existing.display());
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    fs::write(&path, content).with_context(|| format!("failed to write {}", path.display()))?;
+    println!("Created {}", path.display());
+    Ok(())
+}
+
+/// Reports sheets whose file names break the `extension` and
+/// `normalize_names` conventions, renaming them when `fix` is set, and
+/// markdown sheets' repeated headings and dead references. Dead links and
+/// sheets that can't be read are errors, which make it fail.
+fn doctor(lookup: &SheetLocator, config: &Config, fix: bool) -> Result<()> {
+    let (config_dir, flat) = (lookup.config_dir, !lookup.recursive);
+    let extension = config.extension.unwrap_or_default().as_str();
+    let mut problems = 0;
+    for path in collect_sheets(config_dir, !flat) {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let ext = path.extension().unwrap_or_default().to_string_lossy();
+        let expected_stem = match config.normalize_names {
+            true => normalize_name(&stem),
+            false => stem.to_string(),
+        };
+        let expected_ext = match Format::from_path(&path) {
+            Format::Markdown => extension,
+            _ => &ext,
+        };
+        let expected = path.with_file_name(format!("{expected_stem}.{expected_ext}"));
+        if expected == path {
+            continue;
+        }
+        problems += 1;
+        let (from, to) = (
+            sheet_file(config_dir, &path),
+            sheet_file(config_dir, &expected),
+        );
+        if !fix {
+            println!("{from}: should be named {to}");
+        } else if expected.exists() && !same_file(&path, &expected) {
+            println!("{from}: not renamed, {to} already exists");
         } else {
-            // Unclosed fence — treat remainder as text
-            segments.push(Segment::Text(rest[fence_start..].to_string()));
-            rest = "";
-            break;
+            rename_sheet(&path, &expected)
+                .with_context(|| format!("failed to rename {from} to {to}"))?;
+            println!("{from}: renamed to {to}");
+        }
+    }
+    if problems > 0 && !fix {
+        println!("Run `cheetsheet doctor --fix` to rename them.");
+    }
+    let (mut warnings, mut errors) = (0, 0);
+    for path in collect_sheets(config_dir, !flat) {
+        if age::is_encrypted(&path) || Format::from_path(&path) != Format::Markdown {
+            continue;
+        }
+        let sheet = match Sheet::read(&path, &lookup.read) {
+            Ok(sheet) => sheet,
+            Err(err) => {
+                errors += 1;
+                println!(
+                    "{}: error: can't be read: {}",
+                    sheet_file(config_dir, &path),
+                    err.root_cause()
+                );
+                continue;
+            }
+        };
+        let name = sheet_name(config_dir, &path);
+        for duplicate in range::duplicate_headings(&sheet.content) {
+            warnings += 1;
+            println!(
+                "{}:{}: heading '{}' repeats line {}; `{name}#{}` shows it",
+                sheet_file(config_dir, &path),
+                duplicate.line,
+                duplicate.title,
+                duplicate.first,
+                duplicate.anchor
+            );
+        }
+        let base_dir = path.parent().unwrap_or(config_dir);
+        for reference in links::references(&sheet.content) {
+            let Some(target) = links::resolve(base_dir, &reference.target) else {
+                continue;
+            };
+            if target.exists() {
+                continue;
+            }
+            let location = format!("{}:{}", sheet_file(config_dir, &path), reference.line);
+            match reference.kind {
+                links::Kind::Image => {
+                    warnings += 1;
+                    println!(
+                        "{location}: warning: image {} doesn't exist",
+                        reference.target
+                    );
+                }
+                links::Kind::Link => {
+                    errors += 1;
+                    println!(
+                        "{location}: error: link to {} doesn't exist{}",
+                        reference.target,
+                        closest_file(&lookup.matcher, &reference.target, &target, &path)
+                            .map(|target| format!("; did you mean {target}?"))
+                            .unwrap_or_default()
+                    );
+                }
+            }
+        }
+    }
+    if problems + warnings + errors == 0 {
+        println!("No problems found.");
+    }
+    match errors {
+        0 => Ok(()),
+        1 => anyhow::bail!("1 error in the sheets"),
+        n => anyhow::bail!("{n} errors in the sheets"),
+    }
+}
+
+/// Whether `a` and `b` name one file, as `Git.md` and `git.md` do on a
+/// case-insensitive file system.
+fn same_file(a: &Path, b: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        match (fs::metadata(a), fs::metadata(b)) {
+            (Ok(a), Ok(b)) => (a.dev(), a.ino()) == (b.dev(), b.ino()),
+            _ => false,
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        fs::canonicalize(a).ok() == fs::canonicalize(b).ok()
+    }
+}
+
+/// Renames the sheet at `from` to `to`, which may already name the same
+/// file: on a case-insensitive file system, where only the case changes
+/// and the rename goes through a temporary name, or as another hard link,
+/// which makes `from` redundant.
+fn rename_sheet(from: &Path, to: &Path) -> io::Result<()> {
+    if !to.exists() {
+        return fs::rename(from, to);
+    }
+    let dir = to.parent().unwrap_or(Path::new("."));
+    let listed = fs::read_dir(dir)?
+        .flatten()
+        .any(|entry| Some(entry.file_name().as_os_str()) == to.file_name());
+    if listed {
+        return fs::remove_file(from);
+    }
+    let mut temporary = from.as_os_str().to_owned();
+    temporary.push(".rename");
+    let temporary = PathBuf::from(temporary);
+    fs::rename(from, &temporary)?;
+    fs::rename(&temporary, to).inspect_err(|_| {
+        let _ = fs::rename(&temporary, from);
+    })
+}
+
+/// `target`, a link from the sheet at `source` to the missing file `path`,
+/// pointed at the closest name in that file's folder instead. The sheet
+/// itself is never suggested.
+fn closest_file(matcher: &Matcher, target: &str, path: &Path, source: &Path) -> Option<String> {
+    let wanted = path.file_name()?.to_string_lossy();
+    let source = fs::canonicalize(source).ok();
+    let names: Vec<String> = fs::read_dir(path.parent()?)
+        .ok()?
+        .flatten()
+        .filter(|entry| fs::canonicalize(entry.path()).ok() != source)
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect();
+    let closest = *matcher.suggest(&wanted, &names).first()?;
+    let folder = target.len() - target.rsplit('/').next().unwrap_or(target).len();
+    Some(format!("{}{closest}", &target[..folder]))
+}
+
+/// `--warn-duplicate-headings`: a warning for each heading of a markdown
+/// `sheet` that `sheet#title` can't reach, with the anchor that can.
+fn warn_duplicate_headings(sheet: &Sheet) {
+    if sheet.format != Format::Markdown {
+        return;
+    }
+    let file = sheet
+        .path
+        .as_deref()
+        .map_or_else(|| "stdin".to_string(), |path| path.display().to_string());
+    for duplicate in range::duplicate_headings(&sheet.content) {
+        explain::warn(format!(
+            "{file}:{}: heading '{}' repeats line {}; #{} reaches it",
+            duplicate.line, duplicate.title, duplicate.first, duplicate.anchor
+        ));
+    }
+}
+
+/// `path` relative to the config directory, extension included.
+fn sheet_file(config_dir: &Path, path: &Path) -> String {
+    path.strip_prefix(config_dir)
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
+
+/// A new sheet's content from `templates/NAME.md`, the `template` config
+/// key or the built-in template, in that order.
+fn from_template(
+    config_dir: &Path,
+    config: &Config,
+    command: &str,
+    name: Option<&str>,
+    tags: &[String],
+) -> Result<String> {
+    let path = match name {
+        Some(name) => {
+            let path = config_dir.join(TEMPLATES_DIR).join(format!("{name}.md"));
+            if !path.is_file() {
+                anyhow::bail!(CheetError::NotFound(format!(
+                    "no template '{name}'; expected {}\nTip: `cheetsheet new --list-templates` shows the available ones.",
+                    path.display()
+                )));
+            }
+            Some(path)
+        }
+        None => config.template.as_ref().map(|path| config_dir.join(path)),
+    };
+    let (text, origin) = match &path {
+        Some(path) => (
+            fs::read_to_string(path)
+                .with_context(|| format!("failed to read {}", path.display()))?,
+            path.display().to_string(),
+        ),
+        None => (template::BUILTIN.to_string(), "(built-in)".to_string()),
+    };
+    let vars = template::Vars {
+        name: command.to_string(),
+        date: template::today(),
+        author: config
+            .author
+            .clone()
+            .or_else(template::git_author)
+            .unwrap_or_default(),
+        tags: tags.to_vec(),
+    };
+    template::expand(&text, &vars, &origin)
+}
+
+fn list_templates(config_dir: &Path, config: &Config) -> Result<()> {
+    let default = match &config.template {
+        Some(path) => format!("{} (config)", config_dir.join(path).display()),
+        None => "built-in".to_string(),
+    };
+    let mut out = io::stdout().lock();
+    writeln!(out, "default: {default}")?;
+    let dir = config_dir.join(TEMPLATES_DIR);
+    for path in collect_sheets(&dir, false) {
+        if path.extension().is_some_and(|ext| ext == "md") {
+            writeln!(out, "{}", sheet_name(&dir, &path))?;
+        }
+    }
+    Ok(())
+}
+
+fn convert(
+    from: ConvertFormat,
+    to: ConvertFormat,
+    input: &Path,
+    output: Option<&Path>,
+) -> Result<()> {
+    let content =
+        fs::read_to_string(input).with_context(|| format!("failed to read {}", input.display()))?;
+    let converted = match (from, to) {
+        (ConvertFormat::Tldr, ConvertFormat::Md) => tldr::to_markdown(&content),
+        (ConvertFormat::Md, ConvertFormat::Tldr) => tldr::from_markdown(&content),
+        _ => anyhow::bail!("--from and --to must differ"),
+    }
+    .with_context(|| CheetError::Parse(format!("cannot convert {}", input.display())))?;
+    match output {
+        Some(path) => fs::write(path, converted)
+            .with_context(|| format!("failed to write {}", path.display()))?,
+        None => io::stdout().write_all(converted.as_bytes())?,
+    }
+    Ok(())
+}
+
+/// The platform's "open with default application" command for `target`.
+fn opener_command(target: &Path) -> (&'static str, Vec<OsString>) {
+    if cfg!(target_os = "macos") {
+        ("open", vec![target.into()])
+    } else if cfg!(windows) {
+        // `start` is a cmd builtin; its first quoted argument is the window title.
+        let args = ["/C", "start", ""].map(OsString::from);
+        ("cmd", args.into_iter().chain([target.into()]).collect())
+    } else {
+        ("xdg-open", vec![target.into()])
+    }
+}
+
+/// The profile chosen by `--profile` or `CHEETSHEET_PROFILE`, else the
+/// default one.
+fn active_profile(args: &Args) -> Result<(String, config::Source)> {
+    let (name, source) = match (&args.profile, std::env::var(profile::ENV)) {
+        (Some(name), _) => (name.clone(), config::Source::Flag),
+        (None, Ok(name)) if !name.is_empty() => (name, config::Source::Env),
+        _ => (profile::DEFAULT.to_string(), config::Source::Default),
+    };
+    profile::check_name(&name)?;
+    Ok((name, source))
+}
+
+/// Overrides the config directory, below `--config-dir`.
+const CONFIG_DIR_ENV: &str = "CHEETSHEET_CONFIG_DIR";
+
+fn resolve_config_dir(custom: Option<&str>) -> PathBuf {
+    config_dir_from(
+        custom,
+        |name| std::env::var(name).ok(),
+        dirs::config_dir(),
+        dirs::home_dir(),
+    )
+}
+
+/// The config directory: `custom`, else `CHEETSHEET_CONFIG_DIR`, else
+/// `cheetsheet` in `$XDG_CONFIG_HOME`, the `platform` config directory or
+/// `home`'s `.config`, in that order. Empty variables count as unset.
+fn config_dir_from(
+    custom: Option<&str>,
+    var: impl Fn(&str) -> Option<String>,
+    platform: Option<PathBuf>,
+    home: Option<PathBuf>,
+) -> PathBuf {
+    if let Some(dir) = custom {
+        return PathBuf::from(dir);
+    }
+    let var = |name| var(name).filter(|value| !value.is_empty());
+    if let Some(dir) = var(CONFIG_DIR_ENV) {
+        return PathBuf::from(dir);
+    }
+    let parent = var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or(platform)
+        .unwrap_or_else(|| home.unwrap_or_else(|| PathBuf::from(".")).join(".config"));
+    parent.join("cheetsheet")
+}
+
+fn list_tags(config_dir: &Path, flat: bool, read: &ReadOptions) -> Result<()> {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for path in collect_sheets(config_dir, !flat) {
+        let sheet = Sheet::read(&path, read)?;
+        // A tag listed twice in one sheet still counts that sheet once
+        let tags: BTreeSet<String> = front_matter::tags(&sheet.content).into_iter().collect();
+        for tag in tags {
+            *counts.entry(tag).or_default() += 1;
         }
     }
+    let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+    counts.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    for (tag, count) in counts {
+        writeln!(out, "{count:>4}  {tag}")?;
+    }
+    Ok(out.flush()?)
+}
 
-    if !rest.is_empty() {
-        segments.push(Segment::Text(rest.to_string()));
+/// Lists the sheets numbered on stderr and reads a choice, by number or
+/// name, from stdin.
+fn pick_sheet(config_dir: &Path, flat: bool) -> Result<String> {
+    let names: Vec<String> = collect_sheets(config_dir, !flat)
+        .iter()
+        .map(|path| sheet_name(config_dir, path))
+        .collect();
+    if names.is_empty() {
+        anyhow::bail!(CheetError::NotFound(format!(
+            "no sheets in {}",
+            config_dir.display()
+        )));
+    }
+    for (number, name) in names.iter().enumerate() {
+        eprintln!("{:>3}  {name}", number + 1);
+    }
+    eprint!("Sheet: ");
+    let mut choice = String::new();
+    io::stdin()
+        .read_line(&mut choice)
+        .context("failed to read choice")?;
+    let choice = choice.trim();
+    match choice.parse::<usize>() {
+        Ok(number) => names
+            .get(number.wrapping_sub(1))
+            .cloned()
+            .with_context(|| CheetError::NotFound(format!("no sheet number {number}"))),
+        Err(_) if choice.is_empty() => anyhow::bail!("no sheet picked"),
+        Err(_) => Ok(choice.to_string()),
     }
+}
 
-    segments
+/// `list`: sheet names as a tree, a sheet's language variants noted after
+/// it as `git [zh, zh-TW]` rather than listed apart.
+fn list_sheets(config_dir: &Path, flat: bool) -> Result<()> {
+    let sheets = collect_sheets(config_dir, !flat);
+    let all: BTreeSet<String> = sheets
+        .iter()
+        .map(|path| sheet_name(config_dir, path))
+        .collect();
+    let mut langs: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for name in &all {
+        if let Some((base, lang)) = locale::variant(name).filter(|(base, _)| all.contains(*base)) {
+            langs.entry(base).or_default().push(lang);
+        }
+    }
+    let variants: BTreeSet<&str> = all
+        .iter()
+        .filter(|name| locale::variant(name).is_some_and(|(base, _)| langs.contains_key(base)))
+        .map(String::as_str)
+        .collect();
+    let names: BTreeSet<String> = sheets
+        .iter()
+        .filter_map(|path| {
+            let name = sheet_name(config_dir, path);
+            if variants.contains(name.as_str()) {
+                return None;
+            }
+            let mut line = name.clone();
+            if find_overlay(config_dir, path).is_some() {
+                line.push_str(" (+local)");
+            }
+            if let Some(langs) = langs.get(name.as_str()) {
+                line.push_str(&format!(" [{}]", langs.join(", ")));
+            }
+            Some(line)
+        })
+        .collect();
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    for line in list_lines(&names) {
+        writeln!(out, "{line}")?;
+    }
+    Ok(out.flush()?)
 }
 
-fn highlight_code(lang: &str, code: &str) {
-    let ss = SyntaxSet::load_defaults_newlines();
-    let ts = ThemeSet::load_defaults();
-    let theme = &ts.themes["base16-ocean.dark"];
+/// `list --long`: a line per sheet with its name, how many sections it has,
+/// the day it last changed and its `description` or title, in columns.
+fn list_long(config_dir: &Path, flat: bool, color: bool, read: &ReadOptions) -> Result<()> {
+    let mut rows = Vec::new();
+    for path in collect_sheets(config_dir, !flat) {
+        let sheet = Sheet::read(&path, read)?;
+        let sections = match sheet.sections() {
+            1 => "1 section".to_string(),
+            n => format!("{n} sections"),
+        };
+        let modified = fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .map_or_else(
+                |_| "-".to_string(),
+                |time| template::rfc3339(time)[..10].to_string(),
+            );
+        let description = front_matter::value(&sheet.content, "description")
+            .map(String::from)
+            .or_else(|| sheet.title())
+            .unwrap_or_default();
+        rows.push((
+            sheet_name(config_dir, &path),
+            sections,
+            modified,
+            description,
+        ));
+    }
+    rows.sort();
+    let width = |column: fn(&(String, String, String, String)) -> &String| {
+        rows.iter()
+            .map(|row| column(row).chars().count())
+            .max()
+            .unwrap_or(0)
+    };
+    let name_width = width(|row| &row.0);
+    let sections_width = width(|row| &row.1);
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    for (name, sections, modified, description) in &rows {
+        let name = format!("{name:<name_width$}");
+        let name = match color {
+            true => name.bold().to_string(),
+            false => name,
+        };
+        let line = format!("{name}  {sections:>sections_width$}  {modified}  {description}");
+        writeln!(out, "{}", line.trim_end())?;
+    }
+    Ok(out.flush()?)
+}
 
-    let syntax = ss
-        .find_syntax_by_token(lang)
-        .unwrap_or_else(|| ss.find_syntax_plain_text());
+/// A sheet in `list --json`. Scripts rely on these field names, which the
+/// README documents.
+#[derive(Debug, Serialize)]
+struct ListEntry {
+    name: String,
+    path: PathBuf,
+    /// `remote` under `remotes/`, `user` otherwise.
+    source: &'static str,
+    title: Option<String>,
+    tags: Vec<String>,
+    /// In bytes.
+    size: u64,
+    /// RFC 3339, in UTC.
+    modified: Option<String>,
+    /// `draft: true` in the front matter.
+    draft: bool,
+}
 
-    let mut hl = HighlightLines::new(syntax, theme);
-    println!(); // blank line before code block
-    for line in LinesWithEndings::from(code) {
-        let ranges = hl.highlight_line(line, &ss).unwrap_or_default();
-        let escaped = as_24_bit_terminal_escaped(&ranges, false);
-        print!("  {escaped}");
+fn list_json(config_dir: &Path, flat: bool, read: &ReadOptions) -> Result<()> {
+    let mut entries = Vec::new();
+    for path in collect_sheets(config_dir, !flat) {
+        let sheet = Sheet::read(&path, read)?;
+        let metadata =
+            fs::metadata(&path).with_context(|| format!("failed to read {}", path.display()))?;
+        entries.push(ListEntry {
+            name: sheet_name(config_dir, &path),
+            path: std::path::absolute(&path)?,
+            source: match is_remote_managed(config_dir, &path) {
+                true => "remote",
+                false => "user",
+            },
+            title: sheet.title(),
+            tags: front_matter::tags(&sheet.content),
+            size: metadata.len(),
+            modified: metadata.modified().ok().map(template::rfc3339),
+            draft: front_matter::value(&sheet.content, "draft") == Some("true"),
+        });
     }
-    print!("\x1b[0m");
-    println!(); // blank line after code block
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    let json = serde_json::to_string_pretty(&entries)?;
+    Ok(writeln!(io::stdout(), "{json}")?)
 }
 
-fn render_markdown(content: &str) {
-    let skin = make_skin();
-    for segment in split_segments(content) {
-        match segment {
-            Segment::Text(text) => skin.print_text(&text),
-            Segment::Code { lang, code } => highlight_code(&lang, &code),
+/// Lays out sheet names as a tree, printing each folder once as `name/`
+/// with its sheets indented below it.
+fn list_lines(names: &BTreeSet<String>) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut open_folders: Vec<&str> = Vec::new();
+    for name in names {
+        let mut parts: Vec<&str> = name.split('/').collect();
+        let stem = parts.pop().unwrap_or_default();
+        let shared = open_folders
+            .iter()
+            .zip(&parts)
+            .take_while(|(open, part)| open == part)
+            .count();
+        open_folders.truncate(shared);
+        for folder in &parts[shared..] {
+            lines.push(format!("{}{folder}/", "  ".repeat(open_folders.len())));
+            open_folders.push(folder);
         }
+        lines.push(format!("{}{stem}", "  ".repeat(open_folders.len())));
     }
+    lines
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs;
-    use tempfile::TempDir;
 
     #[test]
     fn test_resolve_config_dir_custom() {
@@ -163,40 +2469,88 @@ mod tests {
     }
 
     #[test]
-    fn test_find_sheet_missing() {
-        let tmp = TempDir::new().unwrap();
-        let result = find_sheet(tmp.path(), "nonexistent");
-        assert!(result.is_err());
-        let msg = result.unwrap_err().to_string();
-        assert!(msg.contains("No cheatsheet found for 'nonexistent'"));
+    fn test_config_dir_precedence() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+        let all = env(&[
+            ("CHEETSHEET_CONFIG_DIR", "/env"),
+            ("XDG_CONFIG_HOME", "/xdg"),
+        ]);
+        let platform = || Some(PathBuf::from("/platform"));
+        let home = || Some(PathBuf::from("/home/ada"));
+        let resolve = |custom, var, platform, home| config_dir_from(custom, var, platform, home);
+
+        assert_eq!(
+            resolve(Some("/flag"), all, platform(), home()),
+            Path::new("/flag")
+        );
+        assert_eq!(resolve(None, all, platform(), home()), Path::new("/env"));
+        let xdg = env(&[("CHEETSHEET_CONFIG_DIR", ""), ("XDG_CONFIG_HOME", "/xdg")]);
+        assert_eq!(
+            resolve(None, xdg, platform(), home()),
+            Path::new("/xdg/cheetsheet")
+        );
+        assert_eq!(
+            resolve(None, env(&[]), platform(), home()),
+            Path::new("/platform/cheetsheet")
+        );
+        assert_eq!(
+            resolve(None, env(&[]), None, home()),
+            Path::new("/home/ada/.config/cheetsheet")
+        );
+    }
+
+    #[test]
+    fn test_opener_command_passes_target() {
+        let (program, args) = opener_command(Path::new("/tmp/tmux.md"));
+        assert!(!program.is_empty());
+        assert_eq!(args.last().unwrap(), "/tmp/tmux.md");
+    }
+
+    #[test]
+    fn test_list_lines_groups_by_folder() {
+        let names: BTreeSet<String> = ["cloud/aws", "cloud/gcp", "cloud/k8s/helm", "docker", "git"]
+            .map(String::from)
+            .into();
+        assert_eq!(
+            list_lines(&names),
+            [
+                "cloud/", "  aws", "  gcp", "  k8s/", "    helm", "docker", "git"
+            ]
+        );
     }
 
     #[test]
-    fn test_find_sheet_found() {
-        let tmp = TempDir::new().unwrap();
-        let sheet = tmp.path().join("tmux.md");
-        fs::write(&sheet, "# tmux\n").unwrap();
-        let result = find_sheet(tmp.path(), "tmux");
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), sheet);
+    fn test_looks_like_path() {
+        assert!(looks_like_path("./README.md"));
+        assert!(looks_like_path("README.md"));
+        assert!(looks_like_path("./docs/guide"));
+        assert!(!looks_like_path("cloud/aws"));
+        assert!(!looks_like_path("tmux"));
+        assert!(!looks_like_path("node.js"));
     }
 
     #[test]
-    fn test_split_segments_no_code() {
-        let content = "# Title\n\nSome text\n";
-        let segments = split_segments(content);
-        assert_eq!(segments.len(), 1);
-        assert!(matches!(&segments[0], Segment::Text(t) if t == content));
+    fn test_split_section() {
+        let split = |command| split_section(command);
+        assert_eq!(split("git"), ("git".into(), None));
+        assert_eq!(split("git#stash"), ("git".into(), Some("stash".into())));
+        assert_eq!(
+            split("kubectl#port forward"),
+            ("kubectl".into(), Some("port forward".into()))
+        );
+        assert_eq!(split("docker#"), ("docker".into(), Some(String::new())));
+        assert_eq!(split("c\\#/x#y"), ("c#/x".into(), Some("y".into())));
     }
 
     #[test]
-    fn test_split_segments_with_code() {
-        let content = "# Title\n\n```bash\necho hello\n```\n\nAfter\n";
-        let segments = split_segments(content);
-        assert_eq!(segments.len(), 3);
-        assert!(matches!(&segments[0], Segment::Text(_)));
-        assert!(matches!(&segments[1], Segment::Code { lang, code }
-            if lang == "bash" && code == "echo hello"));
-        assert!(matches!(&segments[2], Segment::Text(_)));
+    fn test_less_prompt() {
+        assert_eq!(less_prompt("git (man page)"), "git (man page)");
+        assert_eq!(less_prompt("node.js: 50%?"), "node\\.js\\: 50\\%\\?");
     }
 }
@@ -0,0 +1,55 @@
+//! Fallback to the system man page when no cheatsheet exists.
+
+use std::process::{Command, Stdio};
+
+/// Formats the man page for `command` as plain text `width` columns wide,
+/// or `None` when `man` or the page is unavailable.
+pub fn render(command: &str, width: usize) -> Option<String> {
+    let exists = Command::new("man")
+        .args(["-w", "--", command])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .ok()?
+        .success();
+    if !exists {
+        return None;
+    }
+    let output = Command::new("man")
+        .args(["--", command])
+        .env("MANPAGER", "cat")
+        .env("MANWIDTH", width.to_string())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(strip_overstrike(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Removes nroff's backspace emphasis: `N\bN` (bold) and `_\bx` (underline)
+/// both collapse to the character after the backspace.
+pub fn strip_overstrike(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c == '\u{8}' {
+            out.pop();
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_overstrike() {
+        assert_eq!(strip_overstrike("N\u{8}NA\u{8}AM\u{8}ME\u{8}E"), "NAME");
+        assert_eq!(strip_overstrike("_\u{8}f_\u{8}i_\u{8}l_\u{8}e"), "file");
+        assert_eq!(strip_overstrike("plain text"), "plain text");
+    }
+}
@@ -0,0 +1,60 @@
+//! `--margin`: a writer putting blank columns before every line of a
+//! rendered sheet, so prose, tables and code blocks all move in by the same
+//! amount whoever drew them. `RenderOptions::width` leaves as many columns
+//! free on the right.
+
+use std::io::{self, Write};
+
+/// Writes to `inner` with `columns` spaces before each line that isn't
+/// empty, so blank lines stay free of trailing whitespace.
+pub struct Margin<W> {
+    inner: W,
+    indent: String,
+    /// Whether the next byte starts a line.
+    line_start: bool,
+}
+
+impl<W: Write> Margin<W> {
+    pub fn new(inner: W, columns: usize) -> Self {
+        Margin {
+            inner,
+            indent: " ".repeat(columns),
+            line_start: true,
+        }
+    }
+}
+
+impl<W: Write> Write for Margin<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for line in buf.split_inclusive(|&byte| byte == b'\n') {
+            if self.line_start && !matches!(line, b"\n" | b"\r\n") {
+                self.inner.write_all(self.indent.as_bytes())?;
+            }
+            self.inner.write_all(line)?;
+            self.line_start = line.ends_with(b"\n");
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_margin_indents_lines() {
+        let mut out = Vec::new();
+        let mut margin = Margin::new(&mut out, 2);
+        write!(margin, "# Git\n\n  \x1b[1mls").unwrap();
+        writeln!(margin, " -l\x1b[0m").unwrap();
+        write!(margin, "done").unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "  # Git\n\n    \x1b[1mls -l\x1b[0m\n  done"
+        );
+    }
+}
@@ -0,0 +1,142 @@
+//! `--math`: TeX in prose. Nothing is typeset; `$...$` and `$$...$$` lose
+//! their delimiters and become inline code, so formulas stand apart from
+//! the words around them. Code blocks never reach here and code spans are
+//! left alone, so shell `$VAR`s keep their dollars.
+
+use std::borrow::Cow;
+
+/// `text` with its math spans turned into inline code.
+pub fn convert(text: &str) -> Cow<'_, str> {
+    if !text.contains('$') {
+        return Cow::Borrowed(text);
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut lines = text.split_inclusive('\n');
+    while let Some(line) = lines.next() {
+        let Some(opening) = display_opening(line) else {
+            convert_inline(line, &mut out);
+            continue;
+        };
+        // A `$$` display block over several lines, shown one line of code
+        // per line of math
+        let mut block = vec![line];
+        let mut body = vec![opening];
+        let closed = loop {
+            let Some(line) = lines.next() else {
+                break false;
+            };
+            block.push(line);
+            match line.trim().strip_suffix("$$") {
+                Some(last) => {
+                    body.push(last);
+                    break true;
+                }
+                None => body.push(line),
+            }
+        };
+        if !closed {
+            block.iter().for_each(|line| convert_inline(line, &mut out));
+            continue;
+        }
+        for math in body.iter().map(|line| line.trim()) {
+            if !math.is_empty() {
+                out.push_str(&format!("`{math}`\n"));
+            }
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// The math after `$$` on a line opening a display block, one that isn't
+/// closed on the same line.
+fn display_opening(line: &str) -> Option<&str> {
+    let rest = line.trim().strip_prefix("$$")?;
+    (!rest.contains("$$")).then_some(rest)
+}
+
+fn convert_inline(line: &str, out: &mut String) {
+    let mut rest = line;
+    while let Some(i) = rest.find(['$', '`', '\\']) {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let len = match rest.as_bytes()[0] {
+            // `\$` is a dollar sign, not a delimiter
+            b'\\' => 1 + rest[1..].chars().next().map_or(0, char::len_utf8),
+            b'`' => code_span_len(rest),
+            _ => match math_span(rest) {
+                Some((len, math)) => {
+                    out.push_str(&format!("`{}`", math.trim()));
+                    rest = &rest[len..];
+                    continue;
+                }
+                None => 1,
+            },
+        };
+        out.push_str(&rest[..len]);
+        rest = &rest[len..];
+    }
+    out.push_str(rest);
+}
+
+/// The length of the code span `text` starts with, or of its opening
+/// backticks when they are never closed.
+fn code_span_len(text: &str) -> usize {
+    let ticks = text.len() - text.trim_start_matches('`').len();
+    match text[ticks..].find(&text[..ticks]) {
+        Some(end) => 2 * ticks + end,
+        None => ticks,
+    }
+}
+
+/// The length and the math of the `$...$` or `$$...$$` span `text` starts
+/// with. Like pandoc, `$` must hug its math and can't be followed by a
+/// digit, so prices like `$5 or $10` stay prose.
+fn math_span(text: &str) -> Option<(usize, &str)> {
+    let delimiter = if text.starts_with("$$") { "$$" } else { "$" };
+    let body = &text[delimiter.len()..];
+    let end = body.find(delimiter)?;
+    let math = &body[..end];
+    let after = &body[end + delimiter.len()..];
+    let loose = delimiter == "$"
+        && (math.starts_with(char::is_whitespace)
+            || math.ends_with(char::is_whitespace)
+            || after.starts_with(|c: char| c.is_ascii_digit()));
+    if loose || math.trim().is_empty() || math.contains('`') {
+        return None;
+    }
+    Some((2 * delimiter.len() + end, math))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inline_math() {
+        assert_eq!(
+            convert("Euler: $e^{i\\pi} + 1 = 0$, and $$\\sum x$$.\n"),
+            "Euler: `e^{i\\pi} + 1 = 0`, and `\\sum x`.\n"
+        );
+        // Prices, shell variables in code spans and escaped dollars stay
+        for text in [
+            "costs $5 or $10\n",
+            "run `echo $HOME` then `echo $PATH`\n",
+            "a \\$literal\\$ dollar\n",
+            "$ x $ is loose\n",
+            "no text\n",
+        ] {
+            assert_eq!(convert(text), text);
+        }
+    }
+
+    #[test]
+    fn test_display_math() {
+        assert_eq!(
+            convert("Area:\n$$\n\\pi r^2\n$$\nthen\n"),
+            "Area:\n`\\pi r^2`\nthen\n"
+        );
+        assert_eq!(convert("  $$ a + b\n  = c $$\n"), "`a + b`\n`= c`\n");
+        // Unclosed blocks are left as they are
+        assert_eq!(convert("$$\nx\n"), "$$\nx\n");
+    }
+}
@@ -0,0 +1,94 @@
+//! `cheetsheet migrate`: moving a config directory created under the
+//! common misspelling `cheatsheet` to `cheetsheet`, the name it is read
+//! from. Once moved there is nothing left to do, so running it again is
+//! harmless.
+
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[cfg(unix)]
+use std::os::unix::fs::symlink as symlink_dir;
+#[cfg(windows)]
+use std::os::windows::fs::symlink_dir;
+
+const NAME: &str = "cheetsheet";
+const TYPO: &str = "cheatsheet";
+
+/// The misspelled sibling of `config_dir`, when it is a real directory
+/// rather than a link left by an earlier `migrate --symlink`.
+pub fn typo_dir(config_dir: &Path) -> Option<PathBuf> {
+    if config_dir.file_name()? != NAME {
+        return None;
+    }
+    let typo = config_dir.with_file_name(TYPO);
+    (typo.is_dir() && !typo.is_symlink()).then_some(typo)
+}
+
+/// Moves the misspelled directory to `config_dir`, leaving a symlink to it
+/// behind when `symlink` is set.
+pub fn run(config_dir: &Path, symlink: bool) -> Result<()> {
+    let Some(typo) = typo_dir(config_dir) else {
+        println!("nothing to migrate into {}", config_dir.display());
+        return Ok(());
+    };
+    if config_dir.exists() {
+        bail!(
+            "both {} and {} exist\nTip: move the sheets to keep into {}, then remove {}.",
+            typo.display(),
+            config_dir.display(),
+            config_dir.display(),
+            typo.display()
+        );
+    }
+    fs::rename(&typo, config_dir).with_context(|| {
+        format!(
+            "failed to move {} to {}",
+            typo.display(),
+            config_dir.display()
+        )
+    })?;
+    println!("moved    {} -> {}", typo.display(), config_dir.display());
+    if symlink {
+        symlink_dir(config_dir, &typo)
+            .with_context(|| format!("failed to link {}", typo.display()))?;
+        println!("linked   {} -> {}", typo.display(), config_dir.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_migrate_moves_once() {
+        let tmp = TempDir::new().unwrap();
+        let (typo, config_dir) = (tmp.path().join(TYPO), tmp.path().join(NAME));
+        fs::create_dir_all(&typo).unwrap();
+        fs::write(typo.join("git.md"), "# git\n").unwrap();
+        assert_eq!(typo_dir(&config_dir), Some(typo.clone()));
+
+        run(&config_dir, true).unwrap();
+        assert!(config_dir.join("git.md").is_file());
+        assert!(typo.is_symlink());
+        assert_eq!(typo_dir(&config_dir), None);
+        // Nothing to do the second time
+        run(&config_dir, true).unwrap();
+        assert!(config_dir.join("git.md").is_file());
+    }
+
+    #[test]
+    fn test_migrate_keeps_both_when_both_exist() {
+        let tmp = TempDir::new().unwrap();
+        let (typo, config_dir) = (tmp.path().join(TYPO), tmp.path().join(NAME));
+        fs::create_dir_all(&typo).unwrap();
+        fs::create_dir_all(&config_dir).unwrap();
+        let err = run(&config_dir, false).unwrap_err().to_string();
+        assert!(err.starts_with("both "));
+        assert!(typo.is_dir() && config_dir.is_dir());
+        // Only a directory named `cheetsheet` has a misspelled sibling
+        assert_eq!(typo_dir(&tmp.path().join("sheets")), None);
+    }
+}
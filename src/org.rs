@@ -0,0 +1,162 @@
+//! Minimal Org-mode support: converts an Org document into the same
+//! segments the markdown path produces, so rendering stays shared.
+
+use crate::segment::{Segment, SegmentBuilder};
+
+pub fn split_segments(content: &str) -> Vec<Segment<'static>> {
+    let mut builder = SegmentBuilder::default();
+    let mut lines = content.lines();
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim_start();
+        if let Some(lang) = block_start(trimmed) {
+            let mut code = Vec::new();
+            let mut closed = false;
+            let mut raw = vec![line];
+            for inner in lines.by_ref() {
+                raw.push(inner);
+                if is_block_end(inner.trim_start()) {
+                    closed = true;
+                    break;
+                }
+                code.push(inner);
+            }
+            if closed {
+                builder.push(Segment::Code {
+                    lang,
+                    code: code.join("\n").into(),
+                });
+            } else {
+                // Unclosed block — keep it as plain text
+                raw.into_iter().for_each(|raw_line| builder.line(raw_line));
+            }
+            continue;
+        }
+        builder.line(&convert_line(line));
+    }
+
+    builder.finish()
+}
+
+/// Language of a `#+BEGIN_SRC lang` / `#+BEGIN_EXAMPLE` line.
+fn block_start(line: &str) -> Option<String> {
+    let upper = line.to_ascii_uppercase();
+    if upper.starts_with("#+BEGIN_SRC") {
+        let lang = line["#+BEGIN_SRC".len()..].split_whitespace().next();
+        Some(lang.unwrap_or_default().to_string())
+    } else if upper.starts_with("#+BEGIN_EXAMPLE") {
+        Some(String::new())
+    } else {
+        None
+    }
+}
+
+fn is_block_end(line: &str) -> bool {
+    let upper = line.to_ascii_uppercase();
+    upper.starts_with("#+END_SRC") || upper.starts_with("#+END_EXAMPLE")
+}
+
+fn convert_line(line: &str) -> String {
+    let stars = line.chars().take_while(|&c| c == '*').count();
+    if stars > 0 && line[stars..].starts_with(' ') {
+        return format!(
+            "{} {}",
+            "#".repeat(stars),
+            convert_inline(line[stars..].trim())
+        );
+    }
+    if let Some(title) = line
+        .strip_prefix("#+TITLE:")
+        .or(line.strip_prefix("#+title:"))
+    {
+        return format!("# {}", convert_inline(title.trim()));
+    }
+
+    let indent = &line[..line.len() - line.trim_start().len()];
+    let body = line.trim_start();
+    if let Some(item) = body.strip_prefix("- ").or(body.strip_prefix("+ ")) {
+        return format!("{indent}- {}", convert_inline(item));
+    }
+    if let Some((number, item)) = ordered_item(body) {
+        return format!("{indent}{number}. {}", convert_inline(item));
+    }
+    convert_inline(line)
+}
+
+/// Splits `1. item` / `1) item` into its number and item text.
+fn ordered_item(body: &str) -> Option<(&str, &str)> {
+    let digits = body.chars().take_while(char::is_ascii_digit).count();
+    if digits == 0 {
+        return None;
+    }
+    let rest = &body[digits..];
+    let item = rest.strip_prefix(". ").or(rest.strip_prefix(") "))?;
+    Some((&body[..digits], item))
+}
+
+/// Turns `=verbatim=` and `~code~` into markdown inline code.
+fn convert_inline(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let opens = (c == '=' || c == '~')
+            && (i == 0 || !chars[i - 1].is_alphanumeric())
+            && chars.get(i + 1).is_some_and(|n| !n.is_whitespace());
+        if opens {
+            let close = (i + 2..chars.len()).find(|&j| {
+                chars[j] == c
+                    && !chars[j - 1].is_whitespace()
+                    && chars.get(j + 1).is_none_or(|n| !n.is_alphanumeric())
+            });
+            if let Some(j) = close {
+                out.push('`');
+                out.extend(&chars[i + 1..j]);
+                out.push('`');
+                i = j + 1;
+                continue;
+            }
+        }
+        out.push(c);
+        i += 1;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_headlines_and_lists() {
+        let segments = split_segments("* Tmux\n** Sessions\n- new =tmux new=\n+ list\n1) attach\n");
+        assert_eq!(segments.len(), 1);
+        assert!(matches!(&segments[0], Segment::Text(t)
+            if t == "# Tmux\n## Sessions\n- new `tmux new`\n- list\n1. attach\n"));
+    }
+
+    #[test]
+    fn test_src_block() {
+        let content = "Intro\n#+BEGIN_SRC bash\necho hi\n#+END_SRC\nAfter ~x~\n";
+        let segments = split_segments(content);
+        assert_eq!(segments.len(), 3);
+        assert!(matches!(&segments[1], Segment::Code { lang, code }
+            if lang == "bash" && code == "echo hi"));
+        assert!(matches!(&segments[2], Segment::Text(t) if t == "After `x`\n"));
+    }
+
+    #[test]
+    fn test_unclosed_block_is_text() {
+        let segments = split_segments("#+begin_src python\nprint(1)\n");
+        assert_eq!(segments.len(), 1);
+        assert!(matches!(&segments[0], Segment::Text(t) if t.contains("#+begin_src")));
+    }
+
+    #[test]
+    fn test_inline_markers_need_boundaries() {
+        assert_eq!(convert_inline("a=b and x = y"), "a=b and x = y");
+        assert_eq!(convert_inline("run ~ls -l~."), "run `ls -l`.");
+    }
+}
@@ -0,0 +1,511 @@
+//! `--plain`: sheets as copy-friendly text with no escapes, for pasting into
+//! tickets and chats. Headings are underlined, tables drawn with `|`, and
+//! code is fenced again byte for byte, so the structure survives the paste.
+//! Nothing is wrapped, which keeps the output stable for snapshots.
+//!
+//! `--accessible` writes the same structure in words for screen readers:
+//! headings and code blocks announced, tables read row by row, and no
+//! quote marks, rules, backticks or emoji.
+
+use crate::segment::Segment;
+use crate::tables;
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+use std::io::{self, Write};
+use unicode_width::UnicodeWidthStr;
+
+/// Writes `segments` as plain text, one blank line between blocks; with
+/// `accessible`, worded for a screen reader.
+pub fn write<'a>(
+    out: &mut impl Write,
+    segments: impl IntoIterator<Item = Segment<'a>>,
+    accessible: bool,
+) -> io::Result<()> {
+    let mut started = false;
+    for segment in segments {
+        let block = match segment {
+            Segment::Text(text) => prose(&text, accessible),
+            Segment::Code { lang, code } | Segment::QuotedCode { lang, code } if accessible => {
+                announce(&lang, &code)
+            }
+            Segment::Code { lang, code } => fence(&lang, &code, ""),
+            Segment::QuotedCode { lang, code } => fence(&lang, &code, "> "),
+            Segment::Marker(note) if accessible => format!("{}\n", strip_emoji(&note)),
+            Segment::Marker(note) => format!("{note}\n"),
+        };
+        if block.trim().is_empty() {
+            continue;
+        }
+        if started {
+            writeln!(out)?;
+        }
+        write!(out, "{block}")?;
+        started = true;
+    }
+    Ok(())
+}
+
+/// A code block fenced the way `Segments` reads it back, each line behind
+/// `prefix`.
+fn fence(lang: &str, code: &str, prefix: &str) -> String {
+    let mut block = format!("{prefix}```{lang}\n");
+    if !code.is_empty() {
+        for line in code.split('\n') {
+            match line.is_empty() {
+                true => block.push_str(prefix.trim_end()),
+                false => block.push_str(&format!("{prefix}{line}")),
+            }
+            block.push('\n');
+        }
+    }
+    block.push_str(&format!("{prefix}```\n"));
+    block
+}
+
+/// A code block between lines saying where it begins and ends.
+fn announce(lang: &str, code: &str) -> String {
+    let begin = match lang.is_empty() {
+        true => "Begin code".to_string(),
+        false => format!("Begin code ({lang})"),
+    };
+    match code.is_empty() {
+        true => format!("{begin}\nEnd code\n"),
+        false => format!("{begin}\n{code}\nEnd code\n"),
+    }
+}
+
+/// `text` without emoji, or the space one leaves behind at a word's start.
+fn strip_emoji(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut dropped = false;
+    for c in text.chars() {
+        if is_emoji(c) {
+            dropped = true;
+            continue;
+        }
+        if dropped && c == ' ' && (out.is_empty() || out.ends_with(' ')) {
+            continue;
+        }
+        if dropped && c.is_ascii_punctuation() && out.ends_with(' ') {
+            out.pop();
+        }
+        dropped = false;
+        out.push(c);
+    }
+    out
+}
+
+/// Pictographs, symbols and the joiners and selectors that compose them.
+fn is_emoji(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1F000..=0x1FAFF | 0x2600..=0x27BF | 0x2B00..=0x2BFF | 0xFE0F | 0x200D | 0x20E3
+    )
+}
+
+/// Markdown prose as plain text, without blank lines around it.
+fn prose(text: &str, accessible: bool) -> String {
+    let options =
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let mut writer = Writer {
+        accessible,
+        ..Writer::default()
+    };
+    for event in Parser::new_ext(text, options) {
+        writer.event(event);
+    }
+    writer.flush_line();
+    let body = writer.out.trim_matches('\n');
+    match body.is_empty() {
+        true => String::new(),
+        false => format!("{body}\n"),
+    }
+}
+
+/// Lays out pulldown-cmark events line by line.
+#[derive(Default)]
+struct Writer {
+    out: String,
+    /// The line being written, without its prefix.
+    line: String,
+    /// Prefixes of continuation lines: quote marks and list indentation.
+    indents: Vec<String>,
+    /// Bullet or number replacing the innermost indent on an item's first line.
+    bullet: Option<String>,
+    /// Next number of each open list; `None` for bullet lists.
+    lists: Vec<Option<u64>>,
+    /// Destinations of the open links and images.
+    links: Vec<String>,
+    /// Rows of the table being read, its header first.
+    table: Option<Vec<Vec<String>>>,
+    /// Language and text of an indented or `~~~` block inside the prose.
+    code: Option<(String, String)>,
+    /// Worded for a screen reader rather than laid out for the eye.
+    accessible: bool,
+}
+
+impl Writer {
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) => match &mut self.code {
+                Some((_, code)) => code.push_str(&text),
+                None if self.accessible => self.line.push_str(&strip_emoji(&text)),
+                None => self.line.push_str(&text),
+            },
+            Event::Code(code) if self.accessible => self.line.push_str(&code),
+            Event::Code(code) => self.line.push_str(&inline_code(&code)),
+            Event::InlineHtml(html) => self.line.push_str(&html),
+            Event::Html(html) => {
+                for line in html.lines() {
+                    self.line.push_str(line);
+                    self.flush_line();
+                }
+            }
+            Event::InlineMath(math) | Event::DisplayMath(math) => self.line.push_str(&math),
+            Event::FootnoteReference(name) => self.line.push_str(&format!("[^{name}]")),
+            Event::TaskListMarker(done) => self.line.push_str(match (self.accessible, done) {
+                (true, true) => "Done: ",
+                (true, false) => "To do: ",
+                (false, true) => "[x] ",
+                (false, false) => "[ ] ",
+            }),
+            Event::SoftBreak | Event::HardBreak => self.flush_line(),
+            Event::Rule if self.accessible => self.flush_line(),
+            Event::Rule => {
+                self.flush_line();
+                self.line.push_str(&"-".repeat(40));
+                self.flush_line();
+                self.blank_line();
+            }
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Heading { .. } | Tag::Paragraph => self.flush_line(),
+            Tag::BlockQuote(_) => {
+                self.flush_line();
+                let mark = if self.accessible { "" } else { "> " };
+                self.indents.push(mark.to_string());
+            }
+            Tag::CodeBlock(kind) => {
+                self.flush_line();
+                let lang = match kind {
+                    CodeBlockKind::Fenced(info) => info.to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                self.code = Some((lang, String::new()));
+            }
+            Tag::List(start) => {
+                self.flush_line();
+                self.lists.push(start);
+            }
+            Tag::Item => {
+                self.flush_line();
+                let bullet = match self.lists.last_mut() {
+                    Some(Some(n)) => {
+                        *n += 1;
+                        format!("{}. ", *n - 1)
+                    }
+                    _ => "- ".to_string(),
+                };
+                self.indents.push(" ".repeat(bullet.chars().count()));
+                self.bullet = Some(bullet);
+            }
+            Tag::Table(_) => {
+                self.flush_line();
+                self.table = Some(Vec::new());
+            }
+            Tag::TableHead | Tag::TableRow => {
+                if let Some(rows) = &mut self.table {
+                    rows.push(Vec::new());
+                }
+            }
+            Tag::Link { dest_url, .. } => self.links.push(dest_url.to_string()),
+            Tag::Image { dest_url, .. } => {
+                self.line.push_str("[image: ");
+                self.links.push(dest_url.to_string());
+            }
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Paragraph => {
+                self.flush_line();
+                self.blank_line();
+            }
+            TagEnd::Heading(level) if self.accessible => {
+                self.line = format!("Heading level {}: {}", level as usize, self.line.trim());
+                self.flush_line();
+                self.blank_line();
+            }
+            TagEnd::Heading(level) => {
+                let title = std::mem::take(&mut self.line);
+                let rule = if level as usize == 1 { "=" } else { "-" };
+                let underline = rule.repeat(title.width());
+                self.line = title;
+                self.flush_line();
+                self.line = underline;
+                self.flush_line();
+                self.blank_line();
+            }
+            TagEnd::BlockQuote(_) => {
+                self.flush_line();
+                // The quote's last blank line is outside it
+                if self.ends_blank() {
+                    self.out.truncate(self.out.len() - self.blank().len());
+                }
+                self.indents.pop();
+                self.blank_line();
+            }
+            TagEnd::CodeBlock => {
+                if let Some((lang, code)) = self.code.take() {
+                    let code = code.strip_suffix('\n').unwrap_or(&code);
+                    match self.accessible {
+                        true => self.block(&announce(&lang, code)),
+                        false => self.block(&fence(&lang, code, "")),
+                    }
+                }
+            }
+            TagEnd::List(_) => {
+                self.flush_line();
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.blank_line();
+                }
+            }
+            TagEnd::Item => {
+                self.flush_line();
+                self.indents.pop();
+                self.bullet = None;
+            }
+            TagEnd::TableCell => {
+                let cell = std::mem::take(&mut self.line);
+                if let Some(row) = self.table.as_mut().and_then(|rows| rows.last_mut()) {
+                    row.push(cell.trim().to_string());
+                }
+            }
+            TagEnd::Table => {
+                if let Some(rows) = self.table.take() {
+                    match self.accessible {
+                        true => self.block(&records(&rows)),
+                        false => self.block(&table(&rows)),
+                    }
+                }
+            }
+            TagEnd::Link => {
+                let dest = self.links.pop().unwrap_or_default();
+                // An autolink already shows where it goes
+                if !dest.is_empty() && !self.line.ends_with(&dest) {
+                    self.line.push_str(&format!(" ({dest})"));
+                }
+            }
+            TagEnd::Image => {
+                let dest = self.links.pop().unwrap_or_default();
+                self.line.push_str(&format!("] ({dest})"));
+            }
+            _ => {}
+        }
+    }
+
+    /// Ends the current line, if anything was written to it.
+    fn flush_line(&mut self) {
+        if self.line.is_empty() {
+            return;
+        }
+        let mut line = std::mem::take(&mut self.line);
+        if self.accessible {
+            line.truncate(line.trim_end().len());
+            // A callout's `[!TIP]` tag, said as a word
+            let callout = line
+                .strip_prefix("[!")
+                .and_then(|kind| kind.strip_suffix(']'))
+                .filter(|kind| !kind.is_empty() && kind.chars().all(|c| c.is_ascii_alphabetic()))
+                .map(|kind| format!("{}{}:", &kind[..1], kind[1..].to_lowercase()));
+            if let Some(word) = callout {
+                line = word;
+            }
+        }
+        let mut prefix: String = self.indents.concat();
+        if let Some(bullet) = self.bullet.take() {
+            let indent = self.indents.last().map_or(0, String::len);
+            prefix.truncate(prefix.len() - indent);
+            prefix.push_str(&bullet);
+        }
+        self.out.push_str(&format!("{prefix}{line}\n"));
+    }
+
+    /// The lines of a laid-out block, blank ones included, then a blank line.
+    fn block(&mut self, block: &str) {
+        for line in block.lines() {
+            match line.is_empty() {
+                true => self.out.push_str(&self.blank()),
+                false => {
+                    self.line.push_str(line);
+                    self.flush_line();
+                }
+            }
+        }
+        self.blank_line();
+    }
+
+    /// An empty line, its quote marks kept.
+    fn blank(&self) -> String {
+        format!("{}\n", self.indents.concat().trim_end())
+    }
+
+    /// Whether the last line written is blank.
+    fn ends_blank(&self) -> bool {
+        self.out.ends_with(&format!("\n{}", self.blank()))
+    }
+
+    /// A blank line, unless the last one already is.
+    fn blank_line(&mut self) {
+        if !self.out.is_empty() && !self.ends_blank() {
+            self.out.push_str(&self.blank());
+        }
+    }
+}
+
+/// Inline code in as many backticks as it needs.
+fn inline_code(code: &str) -> String {
+    let longest = code.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let ticks = "`".repeat(longest + 1);
+    match code.starts_with('`') || code.ends_with('`') {
+        true => format!("{ticks} {code} {ticks}"),
+        false => format!("{ticks}{code}{ticks}"),
+    }
+}
+
+/// `rows` in aligned columns between `|`, the header ruled off with `-`.
+/// A cell with `<br>` tags takes a line for each part.
+fn table(rows: &[Vec<String>]) -> String {
+    let rows: Vec<Vec<Vec<&str>>> = rows
+        .iter()
+        .map(|row| row.iter().map(|cell| tables::cell_lines(cell)).collect())
+        .collect();
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..columns)
+        .map(|i| {
+            rows.iter()
+                .filter_map(|row| row.get(i))
+                .flatten()
+                .map(|line| line.width())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let mut out = String::new();
+    for (n, row) in rows.iter().enumerate() {
+        let height = row.iter().map(Vec::len).max().unwrap_or(1);
+        for line in 0..height {
+            let cells: Vec<String> = widths
+                .iter()
+                .enumerate()
+                .map(|(i, &width)| {
+                    let cell = row.get(i).and_then(|lines| lines.get(line)).unwrap_or(&"");
+                    format!(" {cell}{} ", " ".repeat(width - cell.width()))
+                })
+                .collect();
+            out.push_str(&format!("|{}|\n", cells.join("|")));
+        }
+        if n == 0 {
+            let rule: Vec<String> = widths.iter().map(|width| "-".repeat(width + 2)).collect();
+            out.push_str(&format!("|{}|\n", rule.join("|")));
+        }
+    }
+    out
+}
+
+/// The rows under a table's header, each as `header: value` lines, a blank
+/// line between rows. The parts of a `<br>` cell are read as a list.
+fn records(rows: &[Vec<String>]) -> String {
+    let Some((header, rows)) = rows.split_first() else {
+        return String::new();
+    };
+    let read = |cell: &str| tables::cell_lines(cell).join(", ");
+    let records: Vec<String> = rows
+        .iter()
+        .map(|row| {
+            header
+                .iter()
+                .zip(row)
+                .map(|(name, value)| format!("{}: {}\n", read(name), read(value)))
+                .collect()
+        })
+        .collect();
+    records.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::segment::split_segments;
+
+    fn plain(content: &str) -> String {
+        let mut out = Vec::new();
+        write(&mut out, split_segments(content), false).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_plain_prose() {
+        let content = "# Git\n\nUse **`git status`** often, see [docs](https://git-scm.com).\n\n\
+                       ## Branches\n\n- `git branch`\n- nested\n  1. one\n  2. two\n\n\
+                       > [!TIP]\n> Stash first.\n\n---\n\n| Command | Does |\n|---|---|\n\
+                       | `git log` | history |\n| `add` | stage |\n";
+        assert_eq!(
+            plain(content),
+            "Git\n===\n\nUse `git status` often, see docs (https://git-scm.com).\n\n\
+             Branches\n--------\n\n- `git branch`\n- nested\n  1. one\n  2. two\n\n\
+             > [!TIP]\n> Stash first.\n\n----------------------------------------\n\n\
+             | Command   | Does    |\n|-----------|---------|\n\
+             | `git log` | history |\n| `add`     | stage   |\n"
+        );
+    }
+
+    #[test]
+    fn test_plain_table_aligns_wide_characters() {
+        assert_eq!(
+            plain("# 指令\n\n| 指令 | Does |\n|---|---|\n| `ls` | 列出 |\n"),
+            "指令\n====\n\n| 指令 | Does |\n|------|------|\n| `ls` | 列出 |\n"
+        );
+    }
+
+    #[test]
+    fn test_br_cells() {
+        let content =
+            "| Cmd | Does |\n|---|---|\n| a<br>b | one<br/>two<br/>three |\n| c | four |\n";
+        assert_eq!(
+            plain(content),
+            "| Cmd | Does  |\n|-----|-------|\n| a   | one   |\n| b   | two   |\n\
+             |     | three |\n| c   | four  |\n"
+        );
+        let mut out = Vec::new();
+        write(&mut out, split_segments(content), true).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "Cmd: a, b\nDoes: one, two, three\n\nCmd: c\nDoes: four\n"
+        );
+    }
+
+    #[test]
+    fn test_plain_code_round_trips() {
+        let content = "Intro\n\n```bash\n\tgit log --oneline\n\n  ```nested\n```\n\n\
+                       > ```sh\n> ls\n>\n> pwd\n> ```\n\nDone\n";
+        let out = plain(content);
+        assert!(out.starts_with("Intro\n\n```bash\n\tgit log --oneline\n\n  ```nested\n```\n"));
+        let code = |content| -> Vec<Segment<'static>> {
+            split_segments(content)
+                .into_iter()
+                .filter(|segment| !matches!(segment, Segment::Text(_)))
+                .map(Segment::into_owned)
+                .collect()
+        };
+        assert_eq!(code(&out), code(content));
+        assert_eq!(inline_code("a`b"), "``a`b``");
+    }
+}
@@ -0,0 +1,105 @@
+//! Profiles: separate sheet collections with their own config and state.
+//!
+//! The `default` profile is the plain layout: sheets in the config directory
+//! and settings in `cheetsheet.toml`. Any other profile keeps its sheets in
+//! `profiles/<name>/` and its settings in `cheetsheet.<name>.toml`.
+
+use crate::error::CheetError;
+use anyhow::{Result, bail};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT: &str = "default";
+
+/// Folder in the config directory holding the non-default profiles.
+pub const DIR: &str = "profiles";
+
+/// Environment variable selecting the profile when `--profile` isn't given.
+pub const ENV: &str = "CHEETSHEET_PROFILE";
+
+pub fn check_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        bail!(CheetError::Config(format!(
+            "invalid profile name '{name}'; use letters, digits, '-', '_' and '.'"
+        )));
+    }
+    Ok(())
+}
+
+/// Where the profile's sheets live.
+pub fn sheet_dir(config_dir: &Path, profile: &str) -> PathBuf {
+    match profile {
+        DEFAULT => config_dir.to_path_buf(),
+        _ => config_dir.join(DIR).join(profile),
+    }
+}
+
+/// The profile's settings file.
+pub fn config_file(config_dir: &Path, profile: &str) -> PathBuf {
+    match profile {
+        DEFAULT => config_dir.join(crate::config::FILE_NAME),
+        _ => config_dir.join(format!("cheetsheet.{profile}.toml")),
+    }
+}
+
+/// Where the profile keeps state such as the last viewed sheet.
+pub fn state_dir(data_dir: &Path, profile: &str) -> PathBuf {
+    match profile {
+        DEFAULT => data_dir.to_path_buf(),
+        _ => data_dir.join(DIR).join(profile),
+    }
+}
+
+/// Every profile with a sheet folder or a config file, `default` first.
+pub fn list(config_dir: &Path) -> Vec<String> {
+    let mut names = Vec::new();
+    if let Ok(entries) = fs::read_dir(config_dir.join(DIR)) {
+        names.extend(
+            entries
+                .flatten()
+                .filter(|entry| entry.path().is_dir())
+                .map(|entry| entry.file_name().to_string_lossy().into_owned()),
+        );
+    }
+    if let Ok(entries) = fs::read_dir(config_dir) {
+        names.extend(entries.flatten().filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            let name = file_name
+                .strip_prefix("cheetsheet.")?
+                .strip_suffix(".toml")?;
+            Some(name.to_string())
+        }));
+    }
+    names.retain(|name| name != DEFAULT && check_name(name).is_ok());
+    names.sort();
+    names.dedup();
+    names.insert(0, DEFAULT.to_string());
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_profile_is_plain_layout() {
+        let dir = Path::new("/cfg");
+        assert_eq!(sheet_dir(dir, DEFAULT), dir);
+        assert_eq!(config_file(dir, DEFAULT), dir.join("cheetsheet.toml"));
+        assert_eq!(sheet_dir(dir, "work"), dir.join("profiles/work"));
+        assert_eq!(config_file(dir, "work"), dir.join("cheetsheet.work.toml"));
+    }
+
+    #[test]
+    fn test_check_name() {
+        assert!(check_name("work-2").is_ok());
+        assert!(check_name("").is_err());
+        assert!(check_name("..").is_err());
+        assert!(check_name("a/b").is_err());
+    }
+}
@@ -0,0 +1,872 @@
+//! Backends turning markdown prose into terminal text. Code blocks are split
+//! off and highlighted before prose gets here, so they look the same
+//! whichever backend is used.
+
+use clap::ValueEnum;
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+use serde::Deserialize;
+use std::collections::VecDeque;
+use termimad::{CompoundStyle, MadSkin};
+use unicode_width::UnicodeWidthStr;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Renderer {
+    /// termimad's own markdown layout
+    #[default]
+    Termimad,
+    /// A CommonMark parser (pulldown-cmark), styled with the same skin
+    Cmark,
+}
+
+impl Renderer {
+    /// The backend, joining and wrapping lines of prose unless `reflow` is
+    /// off and numbering ordered lists in `list_style`. termimad keeps every
+    /// line as written either way.
+    pub fn backend(self, reflow: bool, list_style: ListStyle) -> Box<dyn Backend> {
+        match self {
+            Renderer::Termimad => Box::new(Termimad { list_style }),
+            Renderer::Cmark => Box::new(Cmark { reflow, list_style }),
+        }
+    }
+}
+
+/// How the items of ordered lists are numbered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ListStyle {
+    /// 1. 2. 3.
+    #[default]
+    Decimal,
+    /// a. b. c., then aa. after z.
+    Alpha,
+    /// i. ii. iii.
+    Roman,
+}
+
+impl ListStyle {
+    /// Item `number`'s marker without its `.`. Numbers a style can't show,
+    /// such as 0, stay decimal.
+    pub fn marker(self, number: u64) -> String {
+        match self {
+            ListStyle::Alpha if number > 0 => {
+                let mut letters = Vec::new();
+                let mut n = number;
+                while n > 0 {
+                    n -= 1;
+                    letters.push(b'a' + (n % 26) as u8);
+                    n /= 26;
+                }
+                letters.iter().rev().map(|&b| b as char).collect()
+            }
+            ListStyle::Roman if (1..4000).contains(&number) => {
+                const NUMERALS: [(u64, &str); 13] = [
+                    (1000, "m"),
+                    (900, "cm"),
+                    (500, "d"),
+                    (400, "cd"),
+                    (100, "c"),
+                    (90, "xc"),
+                    (50, "l"),
+                    (40, "xl"),
+                    (10, "x"),
+                    (9, "ix"),
+                    (5, "v"),
+                    (4, "iv"),
+                    (1, "i"),
+                ];
+                let mut roman = String::new();
+                let mut n = number;
+                for (value, numeral) in NUMERALS {
+                    while n >= value {
+                        roman.push_str(numeral);
+                        n -= value;
+                    }
+                }
+                roman
+            }
+            _ => number.to_string(),
+        }
+    }
+}
+
+pub trait Backend {
+    /// `text` laid out for a terminal `width` columns wide.
+    fn render(&self, skin: &MadSkin, text: &str, width: usize) -> String;
+}
+
+struct Termimad {
+    list_style: ListStyle,
+}
+
+impl Backend for Termimad {
+    fn render(&self, skin: &MadSkin, text: &str, width: usize) -> String {
+        let mut out = String::new();
+        // Lines termimad lays out together, so code blocks and tables stay whole
+        let mut run = String::new();
+        for (line, hang) in restyle_lists(text, self.list_style) {
+            // termimad only hangs bulleted items, so an ordered one's lines
+            // are laid out on their own in the room right of its marker
+            let Some(hang) = hang.filter(|&hang| width >= hang + MIN_ITEM_WIDTH) else {
+                run.push_str(&line);
+                continue;
+            };
+            out.push_str(
+                &skin
+                    .text(&std::mem::take(&mut run), Some(width))
+                    .to_string(),
+            );
+            let (head, body) = line.split_at(hang);
+            let body = skin.text(body.trim_end_matches('\n'), Some(width - hang));
+            let body = body.to_string();
+            let mut lines = body.lines();
+            out.push_str(head);
+            out.push_str(lines.next().unwrap_or_default());
+            out.push('\n');
+            for line in lines {
+                out.push_str(&" ".repeat(hang));
+                out.push_str(line);
+                out.push('\n');
+            }
+        }
+        out.push_str(&skin.text(&run, Some(width)).to_string());
+        out
+    }
+}
+
+/// Fewer columns than this right of a list marker and an item's lines are
+/// left to wrap to the margin.
+const MIN_ITEM_WIDTH: usize = 10;
+
+/// An item written at `indent` with `delimiter`, whose text started at
+/// `column` and now starts at `new_column`, in the list numbered `list`.
+struct OpenItem {
+    indent: usize,
+    delimiter: char,
+    column: usize,
+    new_column: usize,
+    list: usize,
+}
+
+/// The lines of `text` with the numbers of its ordered list items, which
+/// termimad shows as written, in `style` and right-aligned to the widest of
+/// their list. The lines under an item move with its text when the marker
+/// gets wider or narrower. Each line comes with the column its text hangs
+/// from when it is part of an ordered item, outside code blocks and tables.
+fn restyle_lists(text: &str, style: ListStyle) -> Vec<(String, Option<usize>)> {
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+    let (widths, lists) = list_markers(&lines, style);
+    let mut items: Vec<OpenItem> = Vec::new();
+    let mut fenced = false;
+    let mut out = Vec::with_capacity(lines.len());
+    for (line, list) in lines.iter().zip(lists) {
+        let content = line.trim_start_matches(' ');
+        if content.trim().is_empty() {
+            out.push((line.to_string(), None));
+            continue;
+        }
+        let indent = line.len() - content.len();
+        let fence = is_fence(content);
+        if !fenced {
+            while items.last().is_some_and(|item| indent < item.column) {
+                items.pop();
+            }
+        }
+        let moved = match items.last() {
+            Some(item) => (indent + item.new_column).saturating_sub(item.column),
+            None => indent,
+        };
+        let mut restyled = " ".repeat(moved);
+        let hang = match ordered_item(content).zip(list) {
+            Some(((number, delimiter, rest), list)) => {
+                let marker = format!("{}{delimiter}", style.marker(number));
+                let marker = format!("{marker:>width$}", width = widths[list]);
+                let gap = content.len() - rest.len() - (number.to_string().len() + 1);
+                let new_column = moved + marker.len() + gap;
+                items.push(OpenItem {
+                    indent,
+                    delimiter,
+                    column: indent + content.len() - rest.len(),
+                    new_column,
+                    list,
+                });
+                restyled.push_str(&marker);
+                restyled.push_str(&content[content.len() - rest.len() - gap..]);
+                Some(new_column)
+            }
+            _ => {
+                restyled.push_str(content);
+                let table = content.starts_with('|');
+                items
+                    .last()
+                    .filter(|_| !fenced && !fence && !table)
+                    .map(|item| item.new_column)
+            }
+        };
+        if fence {
+            fenced = !fenced;
+        }
+        out.push((restyled, hang));
+    }
+    out
+}
+
+/// Which list each ordered item of `lines` is in, and the width of each
+/// list's widest marker in `style`, delimiter included.
+fn list_markers(lines: &[&str], style: ListStyle) -> (Vec<usize>, Vec<Option<usize>>) {
+    let mut widths: Vec<usize> = Vec::new();
+    let mut items: Vec<OpenItem> = Vec::new();
+    let mut fenced = false;
+    let mut lists = Vec::with_capacity(lines.len());
+    for line in lines {
+        let content = line.trim_start_matches(' ');
+        let indent = line.len() - content.len();
+        let mut list = None;
+        if is_fence(content) {
+            fenced = !fenced;
+        } else if !fenced && !content.trim().is_empty() {
+            let item = ordered_item(content);
+            while items.last().is_some_and(|open| {
+                indent < open.column
+                    && !item.is_some_and(|(_, delimiter, _)| {
+                        open.indent == indent && open.delimiter == delimiter
+                    })
+            }) {
+                items.pop();
+            }
+            if let Some((number, delimiter, rest)) = item {
+                let sibling = items
+                    .last()
+                    .filter(|open| open.indent == indent && open.delimiter == delimiter)
+                    .map(|open| open.list);
+                let id = sibling.unwrap_or_else(|| {
+                    widths.push(0);
+                    widths.len() - 1
+                });
+                if sibling.is_some() {
+                    items.pop();
+                }
+                let column = indent + content.len() - rest.len();
+                items.push(OpenItem {
+                    indent,
+                    delimiter,
+                    column,
+                    new_column: column,
+                    list: id,
+                });
+                widths[id] = widths[id].max(style.marker(number).width() + 1);
+                list = Some(id);
+            }
+        }
+        lists.push(list);
+    }
+    (widths, lists)
+}
+
+/// Whether `line`, its indentation dropped, opens or closes a code block.
+fn is_fence(line: &str) -> bool {
+    line.starts_with("```") || line.starts_with("~~~")
+}
+
+/// The number, delimiter and text of an ordered list item's line, e.g.
+/// `(2, '.', "text")` for `2. text`.
+fn ordered_item(line: &str) -> Option<(u64, char, &str)> {
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let number = line[..digits].parse().ok().filter(|_| digits <= 9)?;
+    let delimiter = line[digits..]
+        .chars()
+        .next()
+        .filter(|c| matches!(c, '.' | ')'))?;
+    let rest = &line[digits + 1..];
+    let text = rest.trim_start_matches(' ');
+    (rest.len() > text.len()).then_some((number, delimiter, text))
+}
+
+struct Cmark {
+    /// Join a paragraph's lines and wrap them at the width, as CommonMark
+    /// does; off, each line stays one with its spacing kept.
+    reflow: bool,
+    list_style: ListStyle,
+}
+
+impl Backend for Cmark {
+    fn render(&self, skin: &MadSkin, text: &str, width: usize) -> String {
+        let options =
+            Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+        let mut writer = Writer::new(skin, width);
+        writer.reflow = self.reflow;
+        writer.list_style = self.list_style;
+        // Like termimad, keep the blank lines around the text
+        if text.starts_with('\n') {
+            writer.out.push('\n');
+        }
+        let events: Vec<_> = Parser::new_ext(text, options).into_offset_iter().collect();
+        writer.marker_widths =
+            marker_widths(events.iter().map(|(event, _)| event), self.list_style);
+        for (event, range) in events {
+            // The parser drops the indentation of a paragraph's lines
+            if !self.reflow && matches!(event, Event::Text(_)) {
+                let line_start = text[..range.start].rfind('\n').map_or(0, |i| i + 1);
+                let indent = &text[line_start..range.start];
+                if !indent.is_empty() && indent.trim().is_empty() {
+                    writer.indent(indent);
+                }
+            }
+            writer.event(event);
+        }
+        writer.finish(text.ends_with("\n\n"))
+    }
+}
+
+/// The width of each ordered list's widest marker in `style`, delimiter
+/// included, in the order the lists start; 0 for bulleted ones.
+fn marker_widths<'e>(
+    events: impl Iterator<Item = &'e Event<'e>>,
+    style: ListStyle,
+) -> VecDeque<usize> {
+    let mut widths = Vec::new();
+    // Each open list's index in `widths`, first number and items so far
+    let mut open: Vec<(usize, Option<u64>, u64)> = Vec::new();
+    for event in events {
+        match event {
+            Event::Start(Tag::List(start)) => {
+                open.push((widths.len(), *start, 0));
+                widths.push(0);
+            }
+            Event::Start(Tag::Item) => {
+                if let Some((_, _, items)) = open.last_mut() {
+                    *items += 1;
+                }
+            }
+            Event::End(TagEnd::List(_)) => {
+                if let Some((index, Some(start), items)) = open.pop() {
+                    widths[index] = (start..start + items)
+                        .map(|number| style.marker(number).width() + 1)
+                        .max()
+                        .unwrap_or(0);
+                }
+            }
+            _ => {}
+        }
+    }
+    widths.into()
+}
+
+/// A cell or line fragment: styled text and its visible width.
+type Piece = (String, usize);
+
+#[derive(Default)]
+struct Table {
+    rows: Vec<Vec<Piece>>,
+    row: Vec<Piece>,
+    cell: Piece,
+}
+
+/// Lays out pulldown-cmark events, filling lines word by word.
+struct Writer<'s> {
+    skin: &'s MadSkin,
+    width: usize,
+    out: String,
+    /// The line being filled, its prefix included.
+    line: Piece,
+    line_has_words: bool,
+    space_pending: bool,
+    styles: Vec<CompoundStyle>,
+    /// Prefixes of continuation lines: quote marks and list indentation.
+    indents: Vec<Piece>,
+    /// Bullet or number replacing the innermost indent on an item's first line.
+    bullet: Option<Piece>,
+    /// Next number and widest marker of each open list; `None` for bullet
+    /// lists.
+    lists: Vec<Option<(u64, usize)>>,
+    /// From `marker_widths`, taken as lists start.
+    marker_widths: VecDeque<usize>,
+    table: Option<Table>,
+    code: Option<String>,
+    /// Off, soft breaks end lines, spaces are kept and nothing wraps.
+    reflow: bool,
+    list_style: ListStyle,
+}
+
+impl<'s> Writer<'s> {
+    fn new(skin: &'s MadSkin, width: usize) -> Self {
+        Writer {
+            skin,
+            width: width.max(10),
+            out: String::new(),
+            line: Piece::default(),
+            line_has_words: false,
+            space_pending: false,
+            styles: Vec::new(),
+            indents: Vec::new(),
+            bullet: None,
+            lists: Vec::new(),
+            marker_widths: VecDeque::new(),
+            table: None,
+            code: None,
+            reflow: true,
+            list_style: ListStyle::Decimal,
+        }
+    }
+
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) if self.code.is_some() => {
+                self.code.get_or_insert_default().push_str(&text)
+            }
+            Event::Text(text) | Event::InlineHtml(text) | Event::Html(text) => self.text(&text),
+            Event::Code(code) => {
+                let style = self.skin.inline_code.clone();
+                self.word(&code, &style);
+            }
+            Event::InlineMath(math) | Event::DisplayMath(math) => self.text(&math),
+            Event::FootnoteReference(name) => self.text(&format!("[^{name}]")),
+            Event::TaskListMarker(done) => self.text(if done { "[x] " } else { "[ ] " }),
+            Event::SoftBreak if self.reflow => self.space_pending = true,
+            Event::SoftBreak => self.flush_line(),
+            Event::HardBreak => self.flush_line(),
+            Event::Rule => {
+                self.flush_line();
+                let rule = self.skin.horizontal_rule.repeated(self.width);
+                self.out.push_str(&format!("{rule}\n"));
+                self.blank_line();
+            }
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Heading { level, .. } => {
+                self.flush_line();
+                let index = (level as usize).min(self.skin.headers.len()) - 1;
+                self.push_style(self.skin.headers[index].compound_style.clone());
+            }
+            Tag::Emphasis => self.push_style(self.skin.italic.clone()),
+            Tag::Strong => self.push_style(self.skin.bold.clone()),
+            Tag::Strikethrough => self.push_style(self.skin.strikeout.clone()),
+            Tag::BlockQuote(_) => {
+                self.flush_line();
+                let mark = self.skin.quote_mark.to_string();
+                self.indents.push((format!("{mark} "), 2));
+            }
+            Tag::List(start) => {
+                self.flush_line();
+                let widest = self.marker_widths.pop_front().unwrap_or(0);
+                self.lists.push(start.map(|start| (start, widest)));
+            }
+            Tag::Item => {
+                self.flush_line();
+                let (bullet, width) = match self.lists.last_mut() {
+                    Some(Some((number, widest))) => {
+                        let marker = format!("{}.", self.list_style.marker(*number));
+                        *number += 1;
+                        let pad = widest.saturating_sub(marker.width());
+                        (
+                            format!("{}{marker} ", " ".repeat(pad)),
+                            pad + marker.width() + 1,
+                        )
+                    }
+                    _ => (format!("{} ", self.skin.bullet), 2),
+                };
+                self.indents.push((" ".repeat(width), width));
+                self.bullet = Some((bullet, width));
+            }
+            Tag::CodeBlock(_) => {
+                self.flush_line();
+                self.code = Some(String::new());
+            }
+            Tag::Table(_) => {
+                self.flush_line();
+                self.table = Some(Table::default());
+            }
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Paragraph => {
+                self.flush_line();
+                if self.lists.is_empty() {
+                    self.blank_line();
+                }
+            }
+            TagEnd::Heading(_) => {
+                self.styles.pop();
+                self.flush_line();
+                self.blank_line();
+            }
+            TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough => {
+                self.styles.pop();
+            }
+            TagEnd::BlockQuote(_) => {
+                self.flush_line();
+                self.indents.pop();
+                self.blank_line();
+            }
+            TagEnd::List(_) => {
+                self.flush_line();
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.blank_line();
+                }
+            }
+            TagEnd::Item => {
+                self.flush_line();
+                self.indents.pop();
+                self.bullet = None;
+            }
+            TagEnd::CodeBlock => {
+                let code = self.code.take().unwrap_or_default();
+                let style = &self.skin.code_block.compound_style;
+                for line in code.lines() {
+                    self.out.push_str(&format!("  {}\n", style.apply_to(line)));
+                }
+                self.blank_line();
+            }
+            TagEnd::TableCell => {
+                if let Some(table) = &mut self.table {
+                    let cell = std::mem::take(&mut table.cell);
+                    table.row.push(cell);
+                    self.space_pending = false;
+                }
+            }
+            TagEnd::TableHead | TagEnd::TableRow => {
+                if let Some(table) = &mut self.table {
+                    let row = std::mem::take(&mut table.row);
+                    table.rows.push(row);
+                }
+            }
+            TagEnd::Table => {
+                if let Some(table) = self.table.take() {
+                    self.write_table(table);
+                }
+                self.blank_line();
+            }
+            _ => {}
+        }
+    }
+
+    fn push_style(&mut self, style: CompoundStyle) {
+        let mut merged = self.current_style();
+        merged.overwrite_with(&style);
+        self.styles.push(merged);
+    }
+
+    fn current_style(&self) -> CompoundStyle {
+        match self.styles.last() {
+            Some(style) => style.clone(),
+            None => self.skin.paragraph.compound_style.clone(),
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        let style = self.current_style();
+        let mut rest = text;
+        while !rest.is_empty() {
+            let word_len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            if word_len == 0 {
+                let spaces = rest.len() - rest.trim_start().len();
+                match self.reflow || self.table.is_some() {
+                    true => self.space_pending = true,
+                    false => self.spaces(&rest[..spaces]),
+                }
+                rest = &rest[spaces..];
+                continue;
+            }
+            self.word(&rest[..word_len], &style);
+            rest = &rest[word_len..];
+        }
+    }
+
+    /// Adds a word, wrapping first when it doesn't fit.
+    fn word(&mut self, word: &str, style: &CompoundStyle) {
+        let width = word.width();
+        let styled = style.apply_to(word).to_string();
+        if let Some(table) = &mut self.table {
+            if self.space_pending && table.cell.1 > 0 {
+                table.cell.0.push(' ');
+                table.cell.1 += 1;
+            }
+            table.cell.0.push_str(&styled);
+            table.cell.1 += width;
+            self.space_pending = false;
+            return;
+        }
+        if !self.line_has_words {
+            self.start_line();
+        } else if self.reflow && self.line.1 + 1 + width > self.width {
+            self.flush_line();
+            self.start_line();
+        } else if self.space_pending {
+            self.line.0.push(' ');
+            self.line.1 += 1;
+        }
+        self.line.0.push_str(&styled);
+        self.line.1 += width;
+        self.line_has_words = true;
+        self.space_pending = false;
+    }
+
+    /// Whitespace kept as written, with `reflow` off.
+    fn spaces(&mut self, spaces: &str) {
+        if !self.line_has_words {
+            self.start_line();
+            self.line_has_words = true;
+        }
+        self.line.0.push_str(spaces);
+        self.line.1 += spaces.chars().count();
+    }
+
+    /// Indentation the parser dropped from the start of a line, kept when
+    /// `reflow` is off and the line isn't in a list or quote.
+    fn indent(&mut self, indent: &str) {
+        if !self.line_has_words && self.indents.is_empty() && self.table.is_none() {
+            self.spaces(indent);
+        }
+    }
+
+    fn start_line(&mut self) {
+        let mut prefix = Piece::default();
+        let innermost = self.indents.len().saturating_sub(1);
+        for (i, (indent, width)) in self.indents.iter().enumerate() {
+            let (text, width) = match &self.bullet {
+                Some((bullet, width)) if i == innermost => (bullet, width),
+                _ => (indent, width),
+            };
+            prefix.0.push_str(text);
+            prefix.1 += width;
+        }
+        self.bullet = None;
+        self.line = prefix;
+    }
+
+    fn flush_line(&mut self) {
+        if self.line_has_words {
+            self.out.push_str(&self.line.0);
+            self.out.push('\n');
+        }
+        self.line = Piece::default();
+        self.line_has_words = false;
+        self.space_pending = false;
+    }
+
+    fn blank_line(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    fn write_table(&mut self, table: Table) {
+        let columns = table.rows.iter().map(Vec::len).max().unwrap_or(0);
+        let widths: Vec<usize> = (0..columns)
+            .map(|column| {
+                let cells = table.rows.iter().filter_map(|row| row.get(column));
+                cells.map(|(_, width)| *width).max().unwrap_or(0)
+            })
+            .collect();
+        let chars = self.skin.table_border_chars;
+        let border = &self.skin.table.compound_style;
+        let rule = |left: char, junction: char, right: char| {
+            let segments: Vec<String> = widths
+                .iter()
+                .map(|width| chars.horizontal.to_string().repeat(width + 2))
+                .collect();
+            let line = format!("{left}{}{right}", segments.join(&junction.to_string()));
+            format!("{}\n", border.apply_to(line))
+        };
+        let vertical = border.apply_to(chars.vertical).to_string();
+
+        self.out.push_str(&rule(
+            chars.top_left_corner,
+            chars.top_junction,
+            chars.top_right_corner,
+        ));
+        for (i, row) in table.rows.iter().enumerate() {
+            let mut line = vertical.clone();
+            for (column, width) in widths.iter().enumerate() {
+                let (text, used) = row.get(column).map_or(("", 0), |(t, w)| (t.as_str(), *w));
+                line.push_str(&format!(" {text}{} {vertical}", " ".repeat(width - used)));
+            }
+            self.out.push_str(&format!("{line}\n"));
+            if i == 0 && table.rows.len() > 1 {
+                self.out.push_str(&rule(
+                    chars.left_junction,
+                    chars.cross,
+                    chars.right_junction,
+                ));
+            }
+        }
+        self.out.push_str(&rule(
+            chars.bottom_left_corner,
+            chars.bottom_junction,
+            chars.bottom_right_corner,
+        ));
+    }
+
+    fn finish(mut self, trailing_blank: bool) -> String {
+        self.flush_line();
+        let len = self.out.trim_end_matches('\n').len();
+        self.out.truncate(len);
+        if !self.out.trim().is_empty() {
+            self.out
+                .push_str(if trailing_blank { "\n\n" } else { "\n" });
+        }
+        self.out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cmark(text: &str, width: usize) -> String {
+        Cmark {
+            reflow: true,
+            list_style: ListStyle::Decimal,
+        }
+        .render(&MadSkin::no_style(), text, width)
+    }
+
+    #[test]
+    fn test_cmark_wraps_paragraphs() {
+        assert_eq!(cmark("one two three four", 10), "one two\nthree four\n");
+        assert_eq!(
+            cmark("# Title\n\nSome **bold** text.", 80),
+            "Title\n\nSome bold text.\n"
+        );
+    }
+
+    #[test]
+    fn test_cmark_lists_and_quotes() {
+        assert_eq!(
+            cmark("* a\n* b\n\n1. x\n2. y\n", 80),
+            "• a\n• b\n\n1. x\n2. y\n"
+        );
+        assert_eq!(cmark("> quoted line", 80), "▐ quoted line\n");
+    }
+
+    #[test]
+    fn test_list_style_markers() {
+        let markers = |style: ListStyle| {
+            [1, 2, 4, 9, 26, 27, 1994]
+                .map(|n| style.marker(n))
+                .join(" ")
+        };
+        assert_eq!(markers(ListStyle::Decimal), "1 2 4 9 26 27 1994");
+        assert_eq!(markers(ListStyle::Alpha), "a b d i z aa bxr");
+        assert_eq!(markers(ListStyle::Roman), "i ii iv ix xxvi xxvii mcmxciv");
+        assert_eq!(ListStyle::Roman.marker(0), "0");
+    }
+
+    #[test]
+    fn test_cmark_list_style() {
+        let text = "1. one\n2. two\n3. three\n4. four wraps\n";
+        let roman = Cmark {
+            reflow: true,
+            list_style: ListStyle::Roman,
+        }
+        .render(&MadSkin::no_style(), text, 10);
+        assert_eq!(
+            roman,
+            "  i. one\n ii. two\niii. three\n iv. four\n     wraps\n"
+        );
+    }
+
+    #[test]
+    fn test_restyle_lists() {
+        let text =
+            "Steps:\n\n1. one\n   more\n2) two\n   1. nested\n      deeper\n4. four\n\n10 items\n";
+        let (lines, hangs): (Vec<_>, Vec<_>) =
+            restyle_lists(text, ListStyle::Roman).into_iter().unzip();
+        assert_eq!(
+            lines.concat(),
+            "Steps:\n\ni. one\n   more\nii) two\n    i. nested\n       deeper\niv. four\n\n10 items\n"
+        );
+        assert_eq!(
+            hangs,
+            [
+                None,
+                None,
+                Some(3),
+                Some(3),
+                Some(4),
+                Some(7),
+                Some(7),
+                Some(4),
+                None,
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn test_restyle_lists_aligns_markers() {
+        let text: String = (1..=10).map(|n| format!("{n}. item\n")).collect();
+        let text = text + "    ```\n    code\n    ```\n";
+        let restyled = restyle_lists(&text, ListStyle::Roman);
+        let lines: Vec<_> = restyled.iter().map(|(line, _)| line.as_str()).collect();
+        assert_eq!(lines[0], "   i. item\n");
+        assert_eq!(lines[7], "viii. item\n");
+        assert_eq!(lines[9], "   x. item\n");
+        // The code block moves with the item but isn't laid out on its own
+        assert_eq!(restyled[11], ("      code\n".to_string(), None));
+        let decimal = restyle_lists(&text, ListStyle::Decimal);
+        assert_eq!(decimal[0], (" 1. item\n".to_string(), Some(4)));
+        assert_eq!(decimal[9], ("10. item\n".to_string(), Some(4)));
+    }
+
+    #[test]
+    fn test_termimad_hangs_ordered_items() {
+        let text = "8. eight\n9. nine words wrap here\n10. ten\n";
+        let out = Termimad {
+            list_style: ListStyle::Roman,
+        }
+        .render(&MadSkin::no_style(), text, 18);
+        let out: Vec<_> = out.lines().map(str::trim_end).collect();
+        assert_eq!(
+            out,
+            [
+                "viii. eight",
+                "  ix. nine words",
+                "      wrap here",
+                "   x. ten"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cmark_without_reflow_keeps_lines() {
+        let text = "Options:\n  -a      all files\n  -l      long listing, one entry per line\n";
+        let kept = Cmark {
+            reflow: false,
+            list_style: ListStyle::Decimal,
+        }
+        .render(&MadSkin::no_style(), text, 20);
+        assert_eq!(
+            kept,
+            "Options:\n  -a      all files\n  -l      long listing, one entry per line\n"
+        );
+        assert_eq!(
+            cmark(text, 80),
+            "Options: -a all files -l long listing, one entry per line\n"
+        );
+    }
+
+    #[test]
+    fn test_cmark_table() {
+        let mut skin = MadSkin::no_style();
+        skin.limit_to_ascii();
+        let table = Cmark {
+            reflow: true,
+            list_style: ListStyle::Decimal,
+        }
+        .render(&skin, "| a | bb |\n|---|----|\n| ccc | d |\n", 80);
+        assert_eq!(
+            table,
+            "+-----+----+\n| a   | bb |\n+-----+----+\n| ccc | d  |\n+-----+----+\n"
+        );
+    }
+}
@@ -0,0 +1,473 @@
+//! `--after` and `--before`: rendering only the part of a sheet between two
+//! headings, or with `git#stash` the section under one heading. Headings are
+//! found in prose segments only, so a `# comment` in a code block never
+//! counts. A section is named by its title or by its GitHub-style anchor,
+//! which tells repeated titles apart: `git#panes-1` is the second `Panes`.
+
+use crate::error::CheetError;
+use crate::segment::Segment;
+use anyhow::{Result, bail};
+use std::borrow::{Borrow, Cow};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Default)]
+pub struct HeadingRange {
+    /// Start right after this heading.
+    pub after: Option<String>,
+    /// Stop right before this heading, looked for past `after`.
+    pub before: Option<String>,
+    /// Only this heading and what follows up to a heading of its level or
+    /// higher. Used instead of `after` and `before`.
+    pub section: Option<String>,
+}
+
+impl HeadingRange {
+    pub fn is_empty(&self) -> bool {
+        self.after.is_none() && self.before.is_none() && self.section.is_none()
+    }
+
+    /// Fails when a bound names a heading the sheet doesn't have, so nothing
+    /// is rendered for a misspelled heading.
+    pub fn check<'a, S: Borrow<Segment<'a>>>(
+        &self,
+        segments: impl IntoIterator<Item = S>,
+    ) -> Result<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        if let Some(name) = &self.section {
+            let mut anchors = Anchors::default();
+            let found = segments.into_iter().any(|segment| match segment.borrow() {
+                Segment::Text(text) => headings(text)
+                    .any(|(_, _, title)| is_section(title, &anchors.next(title), name)),
+                _ => false,
+            });
+            return match found {
+                true => Ok(()),
+                false => bail!(CheetError::NotFound(format!(
+                    "no section '{name}' in the sheet"
+                ))),
+            };
+        }
+        let mut after = self.after.as_deref();
+        for segment in segments {
+            let Segment::Text(text) = segment.borrow() else {
+                continue;
+            };
+            for (_, _, title) in headings(text) {
+                match (after, self.before.as_deref()) {
+                    (Some(name), _) if matches(title, name) => after = None,
+                    (None, Some(name)) if matches(title, name) => return Ok(()),
+                    _ => {}
+                }
+            }
+        }
+        match (after, &self.before, &self.after) {
+            (Some(name), _, _) => bail!(CheetError::NotFound(format!(
+                "no heading '{name}' in the sheet"
+            ))),
+            (None, None, _) => Ok(()),
+            (None, Some(before), Some(after)) => {
+                bail!(CheetError::NotFound(format!(
+                    "no heading '{before}' after '{after}' in the sheet"
+                )))
+            }
+            (None, Some(before), None) => bail!(CheetError::NotFound(format!(
+                "no heading '{before}' in the sheet"
+            ))),
+        }
+    }
+
+    /// The segments between the bounds, with text split at the headings.
+    pub fn select<'a>(
+        &self,
+        segments: impl IntoIterator<Item = Segment<'a>>,
+    ) -> impl Iterator<Item = Segment<'a>> {
+        let mut inside = self.after.is_none() && self.section.is_none();
+        // The level of the `section` heading, once found
+        let mut level = None;
+        let mut anchors = Anchors::default();
+        let mut done = false;
+        segments.into_iter().filter_map(move |segment| {
+            if done {
+                return None;
+            }
+            let Segment::Text(text) = segment else {
+                return inside.then_some(segment);
+            };
+            let mut start = 0;
+            // Where to look for the end, past the section's own heading
+            let mut from = 0;
+            if !inside {
+                match (&self.section, self.after.as_deref()) {
+                    (Some(section), _) => {
+                        let (offset, found, _) = headings(&text).find(|&(_, _, title)| {
+                            is_section(title, &anchors.next(title), section)
+                        })?;
+                        (start, from, level) = (offset, line_end(&text, offset), Some(found));
+                    }
+                    (None, after) => {
+                        let after = after?;
+                        let (end, _, _) =
+                            headings(&text).find(|&(_, _, title)| matches(title, after))?;
+                        start = line_end(&text, end);
+                        from = start;
+                    }
+                }
+                inside = true;
+            }
+            let stop = match level {
+                Some(level) => headings(&text[from..])
+                    .find(|&(_, found, _)| found <= level)
+                    .map(|(offset, _, _)| from + offset),
+                None => self.before.as_deref().and_then(|before| {
+                    headings(&text[from..])
+                        .find(|&(_, _, title)| matches(title, before))
+                        .map(|(offset, _, _)| from + offset)
+                }),
+            };
+            done = stop.is_some();
+            let end = stop.unwrap_or(text.len());
+            let text = slice(text, start, end);
+            (!text.is_empty()).then_some(Segment::Text(text))
+        })
+    }
+}
+
+/// The headings of a sheet as a markdown list, nested by level, for
+/// `git#`. A repeated title is followed by the anchor that reaches it.
+pub fn toc<'a, S: Borrow<Segment<'a>>>(segments: impl IntoIterator<Item = S>) -> String {
+    let mut anchors = Anchors::default();
+    let mut found = Vec::new();
+    for segment in segments {
+        if let Segment::Text(text) = segment.borrow() {
+            for (_, level, title) in headings(text) {
+                let anchor = anchors.next(title);
+                let entry = match anchor == slug(title) {
+                    true => title.to_string(),
+                    false => format!("{title} (#{anchor})"),
+                };
+                found.push((level, entry));
+            }
+        }
+    }
+    let top = found.iter().map(|(level, _)| *level).min().unwrap_or(1);
+    found
+        .iter()
+        .map(|(level, title)| format!("{}- {title}\n", "  ".repeat(level - top)))
+        .collect()
+}
+
+/// A heading whose anchor an earlier one already has.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Duplicate {
+    pub title: String,
+    /// The anchor that reaches this one, e.g. `panes-1`.
+    pub anchor: String,
+    /// 1-based lines in the sheet's file, front matter included.
+    pub line: usize,
+    /// Where the heading it repeats is.
+    pub first: usize,
+}
+
+/// The headings of markdown `content` repeating an earlier one's title, up
+/// to case and punctuation, which `git#title` can't tell apart.
+pub fn duplicate_headings(content: &str) -> Vec<Duplicate> {
+    let body = crate::front_matter::split(content).1;
+    let skipped = content[..content.len() - body.len()].lines().count();
+    let mut anchors = Anchors::default();
+    let mut first = HashMap::new();
+    let mut duplicates = Vec::new();
+    let mut in_code = false;
+    for (index, line) in body.lines().enumerate() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+        }
+        let Some(title) = heading(line).filter(|_| !in_code) else {
+            continue;
+        };
+        let line = skipped + index + 1;
+        let anchor = anchors.next(title);
+        match first.get(&slug(title)) {
+            Some(&first) => duplicates.push(Duplicate {
+                title: title.to_string(),
+                anchor,
+                line,
+                first,
+            }),
+            None => {
+                first.insert(slug(title), line);
+            }
+        }
+    }
+    duplicates
+}
+
+/// GitHub's anchor for a heading `title`: lowercase, without punctuation,
+/// spaces as dashes.
+pub fn slug(title: &str) -> String {
+    title
+        .trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Anchors of a sheet's headings in order, a repeated one numbered as
+/// GitHub does: `panes`, `panes-1`, `panes-2`. A number is skipped when a
+/// heading already has that anchor, so after two `Panes` a `Panes 1` is
+/// `panes-1-1`.
+#[derive(Default)]
+struct Anchors(HashMap<String, usize>);
+
+impl Anchors {
+    fn next(&mut self, title: &str) -> String {
+        let slug = slug(title);
+        let mut anchor = slug.clone();
+        while self.0.contains_key(&anchor) {
+            let repeats = self.0.entry(slug.clone()).or_default();
+            *repeats += 1;
+            anchor = format!("{slug}-{repeats}");
+        }
+        self.0.insert(anchor.clone(), 0);
+        anchor
+    }
+}
+
+/// The markdown headings of `text` with the byte offset of their line and
+/// their level.
+fn headings(text: &str) -> impl Iterator<Item = (usize, usize, &str)> {
+    text.split_inclusive('\n')
+        .scan(0, |offset, line| {
+            let start = *offset;
+            *offset += line.len();
+            Some((start, line))
+        })
+        .filter_map(|(start, line)| {
+            let title = heading(line)?;
+            let level = line.trim_start().len() - line.trim_start().trim_start_matches('#').len();
+            Some((start, level, title))
+        })
+}
+
+/// The title of an ATX heading line like `## Install ##`.
+pub(crate) fn heading(line: &str) -> Option<&str> {
+    let line = line.trim_end();
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    let line = line.get(indent..).filter(|_| indent <= 3)?;
+    let level = line.len() - line.trim_start_matches('#').len();
+    let rest = &line[level..];
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with([' ', '\t'])) {
+        return None;
+    }
+    let title = rest.trim().trim_end_matches('#');
+    Some(title.trim_end())
+}
+
+fn matches(title: &str, name: &str) -> bool {
+    title.eq_ignore_ascii_case(name.trim())
+}
+
+/// Whether `name` in `git#name` is the heading `title` with `anchor`.
+fn is_section(title: &str, anchor: &str, name: &str) -> bool {
+    matches(title, name) || anchor.eq_ignore_ascii_case(name.trim())
+}
+
+fn line_end(text: &str, start: usize) -> usize {
+    text[start..]
+        .find('\n')
+        .map_or(text.len(), |end| start + end + 1)
+}
+
+/// `text[start..end]`, still borrowed when `text` was.
+fn slice(text: Cow<'_, str>, start: usize, end: usize) -> Cow<'_, str> {
+    match text {
+        Cow::Borrowed(text) => Cow::Borrowed(&text[start..end]),
+        Cow::Owned(text) => Cow::Owned(text[start..end].to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::segment::split_segments;
+
+    const SHEET: &str = "# Tool\n\n## Install\n\n```bash\n# not a heading\nmake\n```\n\nDone\n\n## Usage\n\nRun it\n";
+
+    fn range(after: Option<&str>, before: Option<&str>) -> HeadingRange {
+        HeadingRange {
+            after: after.map(String::from),
+            before: before.map(String::from),
+            section: None,
+        }
+    }
+
+    fn text(segments: Vec<Segment>) -> String {
+        segments
+            .into_iter()
+            .map(|segment| match segment {
+                Segment::Text(text) => text.into_owned(),
+                Segment::Code { code, .. } | Segment::QuotedCode { code, .. } => {
+                    format!("<{code}>")
+                }
+                Segment::Marker(note) => note,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_heading() {
+        assert_eq!(heading("## Install ##\n"), Some("Install"));
+        assert_eq!(heading("   # Title"), Some("Title"));
+        assert_eq!(heading("#hashtag"), None);
+        assert_eq!(heading("    # indented code"), None);
+        assert_eq!(heading("####### seven"), None);
+    }
+
+    #[test]
+    fn test_select_between_headings() {
+        let select = |after, before| {
+            let range = range(after, before);
+            text(range.select(split_segments(SHEET)).collect())
+        };
+        assert_eq!(
+            select(Some("install"), Some("Usage")),
+            "\n<# not a heading\nmake>\n\nDone\n\n"
+        );
+        assert_eq!(select(Some("Usage"), None), "\nRun it\n");
+        assert_eq!(select(None, Some("Install")), "# Tool\n\n");
+    }
+
+    #[test]
+    fn test_check_reports_missing_headings() {
+        let segments = split_segments(SHEET);
+        assert!(
+            range(Some("Install"), Some("Usage"))
+                .check(&segments)
+                .is_ok()
+        );
+        let err = range(Some("Setup"), None).check(&segments).unwrap_err();
+        assert_eq!(err.to_string(), "no heading 'Setup' in the sheet");
+        let err = range(Some("Usage"), Some("Install"))
+            .check(&segments)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "no heading 'Install' after 'Usage' in the sheet"
+        );
+        // Headings inside code blocks don't count
+        assert!(range(Some("not a heading"), None).check(&segments).is_err());
+    }
+
+    #[test]
+    fn test_select_section() {
+        let sheet = "# Git\n\n## Stash\n\nSave work.\n\n```bash\n# not a heading\ngit stash\n```\n\n\
+                     ### Pop\n\nBring it back.\n\n## Log\n\nHistory.\n";
+        let section = |name: &str| HeadingRange {
+            section: Some(name.to_string()),
+            ..HeadingRange::default()
+        };
+        assert_eq!(
+            text(section("stash").select(split_segments(sheet)).collect()),
+            "## Stash\n\nSave work.\n\n<# not a heading\ngit stash>\n\n### Pop\n\nBring it back.\n\n"
+        );
+        assert_eq!(
+            text(section("Log").select(split_segments(sheet)).collect()),
+            "## Log\n\nHistory.\n"
+        );
+        let segments = split_segments(sheet);
+        assert!(section("pop").check(&segments).is_ok());
+        let err = section("Branch").check(&segments).unwrap_err();
+        assert_eq!(err.to_string(), "no section 'Branch' in the sheet");
+        assert_eq!(toc(&segments), "- Git\n  - Stash\n    - Pop\n  - Log\n");
+    }
+
+    #[test]
+    fn test_duplicate_sections_by_anchor() {
+        let sheet = "# Tmux\n\n## Panes\n\nSplit.\n\n## Windows\n\n### Panes\n\nMove.\n\n## panes!\n\nZoom.\n";
+        let section = |name: &str| HeadingRange {
+            section: Some(name.to_string()),
+            ..HeadingRange::default()
+        };
+        let segments = split_segments(sheet);
+        assert_eq!(
+            text(section("panes").select(split_segments(sheet)).collect()),
+            "## Panes\n\nSplit.\n\n"
+        );
+        assert_eq!(
+            text(section("panes-1").select(split_segments(sheet)).collect()),
+            "### Panes\n\nMove.\n\n"
+        );
+        assert_eq!(
+            text(section("Panes-2").select(split_segments(sheet)).collect()),
+            "## panes!\n\nZoom.\n"
+        );
+        assert!(section("panes-3").check(&segments).is_err());
+        assert_eq!(
+            toc(&segments),
+            "- Tmux\n  - Panes\n  - Windows\n    - Panes (#panes-1)\n  - panes! (#panes-2)\n"
+        );
+    }
+
+    #[test]
+    fn test_anchors_skip_taken_numbers() {
+        let mut anchors = Anchors::default();
+        let issued: Vec<_> = ["Panes", "Panes", "Panes 1", "Panes-1", "Panes"]
+            .into_iter()
+            .map(|title| anchors.next(title))
+            .collect();
+        assert_eq!(
+            issued,
+            ["panes", "panes-1", "panes-1-1", "panes-1-2", "panes-2"]
+        );
+
+        let sheet = "# Tmux\n\n## Panes\n\nSplit.\n\n## Panes\n\nMove.\n\n## Panes 1\n\nZoom.\n";
+        let segments = split_segments(sheet);
+        let section = HeadingRange {
+            section: Some("panes-1-1".to_string()),
+            ..HeadingRange::default()
+        };
+        assert_eq!(
+            text(section.select(split_segments(sheet)).collect()),
+            "## Panes 1\n\nZoom.\n"
+        );
+        assert_eq!(
+            toc(&segments),
+            "- Tmux\n  - Panes\n  - Panes (#panes-1)\n  - Panes 1 (#panes-1-1)\n"
+        );
+        let duplicates = duplicate_headings(sheet);
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(
+            (duplicates[0].line, duplicates[0].anchor.as_str()),
+            (7, "panes-1")
+        );
+    }
+
+    #[test]
+    fn test_duplicate_headings() {
+        let sheet =
+            "---\ntitle: Tmux\n---\n# Tmux\n\n## Panes\n\n```bash\n# Panes\n```\n\n### Panes\n";
+        assert_eq!(
+            duplicate_headings(sheet),
+            [Duplicate {
+                title: "Panes".into(),
+                anchor: "panes-1".into(),
+                line: 12,
+                first: 6,
+            }]
+        );
+        assert!(duplicate_headings("# A\n\n## B\n").is_empty());
+    }
+
+    #[test]
+    fn test_slug() {
+        assert_eq!(slug("Copy & Paste!"), "copy--paste");
+        assert_eq!(slug("Session 管理"), "session-管理");
+        assert_eq!(slug("git_log -p"), "git_log--p");
+    }
+}
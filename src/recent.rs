@@ -0,0 +1,277 @@
+//! The sheets viewed by name, for `history`, `top`, `cheetsheet -` and
+//! `default_command = "recent"`.
+//!
+//! Each view appends one `seconds<TAB>name<TAB>path` line to a per-profile
+//! log. A line is written with a single append, which concurrent runs can't
+//! interleave, and once the log holds twice `history_size` views it is
+//! rewritten with the latest ones through a temporary file, so a reader
+//! never sees it half written. Views of sheets deleted since are dropped
+//! then too.
+
+use cheet_sheet::profile;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Views kept when `history_size` is unset.
+pub const DEFAULT_SIZE: usize = 100;
+
+/// One view of a sheet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// Seconds since the Unix epoch.
+    pub time: u64,
+    /// The name it was asked for by.
+    pub name: String,
+    /// The file it resolved to.
+    pub path: PathBuf,
+}
+
+fn state_dir(profile: &str) -> Option<PathBuf> {
+    let data_dir = dirs::data_local_dir()?.join("cheetsheet");
+    Some(profile::state_dir(&data_dir, profile))
+}
+
+fn log_path(profile: &str) -> Option<PathBuf> {
+    Some(state_dir(profile)?.join("history"))
+}
+
+/// Records a view of `name`, resolved to `path`, in `profile`, keeping
+/// about the last `size` views. Failures are ignored; this is a
+/// convenience, not something worth failing a view over.
+pub fn record(profile: &str, name: &str, path: &Path, size: usize) {
+    if let Some(log) = log_path(profile) {
+        let entry = Entry {
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            name: name.to_string(),
+            path: path.to_path_buf(),
+        };
+        let _ = append(&log, &entry, size);
+    }
+}
+
+/// The last `size` views in `profile`, oldest first.
+pub fn entries(profile: &str, size: usize) -> Vec<Entry> {
+    let entries = log_path(profile).map(|log| read(&log)).unwrap_or_default();
+    let skip = entries.len().saturating_sub(size);
+    entries.into_iter().skip(skip).collect()
+}
+
+/// The name of the sheet viewed last in `profile`.
+pub fn last(profile: &str) -> Option<String> {
+    if let Some(entry) = log_path(profile).and_then(|log| read(&log).pop()) {
+        return Some(entry.name);
+    }
+    // Written by versions before the history log
+    let name = fs::read_to_string(state_dir(profile)?.join("recent")).ok()?;
+    let name = name.trim();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Forgets every view in `profile`.
+pub fn clear(profile: &str) -> io::Result<()> {
+    let Some(dir) = state_dir(profile) else {
+        return Ok(());
+    };
+    for file in ["history", "recent"] {
+        match fs::remove_file(dir.join(file)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn append(log: &Path, entry: &Entry, size: usize) -> io::Result<()> {
+    if entry.name.contains(['\t', '\n']) {
+        return Ok(());
+    }
+    if let Some(parent) = log.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log)?
+        .write_all(line(entry).as_bytes())?;
+    let mut entries = read(log);
+    if entries.len() > 2 * size.max(1) {
+        entries.retain(|entry| entry.path.exists());
+        let skip = entries.len().saturating_sub(size.max(1));
+        compact(log, &entries[skip..])?;
+    }
+    Ok(())
+}
+
+/// A sheet in `top` and how often it was viewed.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct Views {
+    /// The name it was last asked for by.
+    pub name: String,
+    pub path: PathBuf,
+    pub views: usize,
+}
+
+/// The sheets in `entries` viewed at `since` or later, most viewed first,
+/// counted by the file they resolved to. Sheets deleted since are left out.
+pub fn top(entries: &[Entry], since: u64) -> Vec<Views> {
+    let mut counts: Vec<Views> = Vec::new();
+    for entry in entries.iter().filter(|entry| entry.time >= since) {
+        match counts.iter_mut().find(|views| views.path == entry.path) {
+            Some(views) => {
+                views.views += 1;
+                views.name.clone_from(&entry.name);
+            }
+            None => counts.push(Views {
+                name: entry.name.clone(),
+                path: entry.path.clone(),
+                views: 1,
+            }),
+        }
+    }
+    counts.retain(|views| views.path.exists());
+    // Ties keep the order they were first viewed in
+    counts.sort_by_key(|views| std::cmp::Reverse(views.views));
+    counts
+}
+
+/// A bar `width` columns long for the most views, `count` of `most` as
+/// long, in eighths of a block or `#`s with `ascii`.
+pub fn bar(count: usize, most: usize, width: usize, ascii: bool) -> String {
+    const EIGHTHS: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+    let eighths = (count * width * 8).checked_div(most).unwrap_or(0);
+    if ascii {
+        return "#".repeat(eighths.div_ceil(8));
+    }
+    let mut bar = "█".repeat(eighths / 8);
+    if !eighths.is_multiple_of(8) {
+        bar.push(EIGHTHS[eighths % 8]);
+    }
+    bar
+}
+
+/// Replaces the log with `entries` in one rename.
+fn compact(log: &Path, entries: &[Entry]) -> io::Result<()> {
+    let content: String = entries.iter().map(line).collect();
+    let temp = log.with_extension(format!("{}.tmp", std::process::id()));
+    fs::write(&temp, content)?;
+    fs::rename(&temp, log)
+}
+
+fn line(entry: &Entry) -> String {
+    let Entry { time, name, path } = entry;
+    format!("{time}\t{name}\t{}\n", path.display())
+}
+
+/// The views in `log`, oldest first. Lines that don't parse, e.g. from a
+/// crash mid-write, are skipped.
+fn read(log: &Path) -> Vec<Entry> {
+    let Ok(content) = fs::read_to_string(log) else {
+        return Vec::new();
+    };
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            let time = fields.next()?.parse().ok()?;
+            let name = fields.next().filter(|name| !name.is_empty())?;
+            let path = fields.next()?;
+            Some(Entry {
+                time,
+                name: name.to_string(),
+                path: PathBuf::from(path),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(time: u64, name: &str) -> Entry {
+        Entry {
+            time,
+            name: name.to_string(),
+            path: PathBuf::from(format!("/sheets/{name}.md")),
+        }
+    }
+
+    #[test]
+    fn test_history_log_round_trips() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let log = dir.path().join("state/history");
+        append(&log, &entry(1, "git"), 10).unwrap();
+        append(&log, &entry(2, "jq"), 10).unwrap();
+        assert_eq!(read(&log), [entry(1, "git"), entry(2, "jq")]);
+
+        // A torn line is skipped, not fatal
+        let mut file = OpenOptions::new().append(true).open(&log).unwrap();
+        file.write_all(b"3\tdoc").unwrap();
+        file.write_all(b"\n").unwrap();
+        assert_eq!(read(&log).len(), 2);
+    }
+
+    #[test]
+    fn test_top_counts_views_since() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let sheet = |name: &str| {
+            let path = dir.path().join(format!("{name}.md"));
+            fs::write(&path, "").unwrap();
+            path
+        };
+        let (git, jq) = (sheet("git"), sheet("jq"));
+        let view = |time, name: &str, path: &Path| Entry {
+            time,
+            name: name.to_string(),
+            path: path.to_path_buf(),
+        };
+        let entries = [
+            view(10, "jq", &jq),
+            view(20, "git", &git),
+            view(30, "g", &git),
+            view(40, "gone", &dir.path().join("gone.md")),
+            view(50, "jq", &jq),
+            view(60, "git", &git),
+        ];
+        let counts: Vec<_> = top(&entries, 0)
+            .into_iter()
+            .map(|views| (views.name, views.views))
+            .collect();
+        assert_eq!(counts, [("git".to_string(), 3), ("jq".to_string(), 2)]);
+        assert_eq!(top(&entries, 45).len(), 2);
+        assert_eq!(top(&entries, 55)[0].views, 1);
+    }
+
+    #[test]
+    fn test_bar() {
+        assert_eq!(bar(4, 4, 5, false), "█████");
+        assert_eq!(bar(1, 4, 5, false), "█▎");
+        assert_eq!(bar(1, 4, 5, true), "##");
+        assert_eq!(bar(0, 4, 5, false), "");
+    }
+
+    #[test]
+    fn test_history_log_is_compacted() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let log = dir.path().join("history");
+        let view = |time| Entry {
+            time,
+            name: format!("s{time}"),
+            path: dir.path().join(format!("s{time}.md")),
+        };
+        for time in 0..7 {
+            // s5's sheet is gone by the time the log is compacted
+            if time != 5 {
+                fs::write(dir.path().join(format!("s{time}.md")), "").unwrap();
+            }
+            append(&log, &view(time), 3).unwrap();
+        }
+        // Seven views went over twice the size, leaving the last three
+        // whose sheets are still there
+        assert_eq!(read(&log), [view(3), view(4), view(6)]);
+    }
+}
@@ -0,0 +1,1398 @@
+//! Rendering segments to a terminal: prose through the chosen backend,
+//! code blocks highlighted with syntect, both into any writer.
+
+use crate::config::{HighlightBg, Spacing, TabWidth, Theme, WideTables};
+use crate::highlight::{self, Highlighter, SyntaxCache};
+use crate::range::HeadingRange;
+use crate::segment::{Segment, SegmentReader, Segments};
+use crate::sheet::{self, Format, Sheet};
+use crate::{
+    admonition, colors, examples, explain, front_matter, hooks, html, images, links,
+    margin::Margin, math, plain, prose, tables, variables,
+};
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::borrow::Cow;
+use std::cell::{LazyCell, OnceCell, RefCell};
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use syntect::easy::HighlightLines;
+use syntect::html::highlighted_html_for_string;
+use syntect::util::{LinesWithEndings, as_24_bit_terminal_escaped};
+use termimad::crossterm::style::{Attribute, Color::Yellow, Stylize};
+use termimad::{MadSkin, ansi, gray};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+fn make_skin(theme: Theme) -> MadSkin {
+    let mut skin = match theme {
+        Theme::Dark => MadSkin::default(),
+        Theme::Light => {
+            let mut skin = MadSkin::default_light();
+            skin.set_headers_fg(ansi(130));
+            skin.bold.set_fg(ansi(94));
+            return skin;
+        }
+    };
+    skin.set_headers_fg(ansi(178)); // 橙黃色標題
+    skin.bold.set_fg(Yellow);
+    skin.italic.set_fg(ansi(147)); // 淡紫色
+    skin.inline_code.set_fgbg(ansi(222), ansi(236)); // 暖黃 on 深灰
+    skin.code_block.set_fgbg(gray(17), gray(3));
+    skin.table.set_fg(ansi(117)); // 淡藍色表格
+    skin.headers[0].add_attr(Attribute::Bold);
+    skin.headers[1].add_attr(Attribute::Bold);
+    skin
+}
+
+pub const DEFAULT_CODE_THEME: &str = "base16-ocean.dark";
+
+fn highlight_code(
+    out: &mut impl Write,
+    highlighter: &Highlighter,
+    theme: &syntect::highlighting::Theme,
+    lang: &str,
+    code: &str,
+    bg: bool,
+) -> io::Result<()> {
+    for escaped in highlight_lines(highlighter, theme, lang, code, bg) {
+        write!(out, "  {escaped}")?;
+    }
+    writeln!(out, "\x1b[0m")
+}
+
+/// The lines of `code` with terminal color escapes, line endings included,
+/// each reset at its end. With `bg` the theme's background is painted too.
+fn highlight_lines(
+    highlighter: &Highlighter,
+    theme: &syntect::highlighting::Theme,
+    lang: &str,
+    code: &str,
+    bg: bool,
+) -> Vec<String> {
+    let ss = &highlighter.syntaxes;
+    let syntax = ss
+        .find_syntax_by_token(lang)
+        .unwrap_or_else(|| ss.find_syntax_plain_text());
+    log::debug!("highlighting '{lang}' as {}", syntax.name);
+
+    let mut hl = HighlightLines::new(syntax, theme);
+    LinesWithEndings::from(code)
+        .map(|line| {
+            let ranges = hl.highlight_line(line, ss).unwrap_or_default();
+            let escaped = as_24_bit_terminal_escaped(&ranges, bg);
+            // Keep colors from bleeding into the next line's indent, or into
+            // the prose after a last line without a newline
+            match escaped.strip_suffix('\n') {
+                Some(line) => format!("{line}\x1b[0m\n"),
+                None => format!("{escaped}\x1b[0m"),
+            }
+        })
+        .collect()
+}
+
+/// `code` as a `<pre>` block colored inline by the code theme.
+fn highlight_html(
+    highlighter: &Highlighter,
+    theme: &syntect::highlighting::Theme,
+    lang: &str,
+    code: &str,
+) -> String {
+    let ss = &highlighter.syntaxes;
+    let syntax = ss
+        .find_syntax_by_token(lang)
+        .unwrap_or_else(|| ss.find_syntax_plain_text());
+    let code = format!("{}\n", code.trim_end_matches('\n'));
+    highlighted_html_for_string(&code, ss, syntax, theme)
+        .unwrap_or_else(|_| format!("<pre>{}</pre>\n", html::escape(&code)))
+}
+
+/// Prints a code block inside a thin box with `lang` in the top border.
+/// `lines` are the rendered lines of `code`, possibly with color escapes.
+fn print_framed_code(
+    out: &mut impl Write,
+    lines: &[String],
+    code: &str,
+    lang: &str,
+    options: &RenderOptions,
+) -> io::Result<()> {
+    let [h, v, top_left, top_right, bottom_left, bottom_right] = match options.ascii {
+        true => ["-", "|", "+", "+", "+", "+"],
+        false => ["─", "│", "╭", "╮", "╰", "╯"],
+    };
+    let border = |text: String| match options.color {
+        true => text.dark_grey().to_string(),
+        false => text,
+    };
+    let widths: Vec<usize> = code.lines().map(UnicodeWidthStr::width).collect();
+    let label = if lang.is_empty() {
+        String::new()
+    } else {
+        format!("{h} {lang} ")
+    };
+    let longest = widths.iter().copied().max().unwrap_or(0);
+    // One space of padding on each side, never narrower than the label
+    let inner = (longest + 2)
+        .max(label.width() + 1)
+        .min(options.width().saturating_sub(4).max(label.width() + 1));
+
+    let fill = inner - label.width();
+    writeln!(
+        out,
+        "  {}",
+        border(format!("{top_left}{label}{}{top_right}", h.repeat(fill)))
+    )?;
+    for (line, width) in lines.iter().zip(&widths) {
+        let line = line.trim_end_matches(['\n', '\r']);
+        let reset = if options.color { "\x1b[0m" } else { "" };
+        let padding = " ".repeat((inner - 2).saturating_sub(*width));
+        writeln!(
+            out,
+            "  {} {line}{reset}{padding} {}",
+            border(v.to_string()),
+            border(v.to_string())
+        )?;
+    }
+    writeln!(
+        out,
+        "  {}",
+        border(format!("{bottom_left}{}{bottom_right}", h.repeat(inner)))
+    )
+}
+
+/// Prints front matter `entries` as a two-column box, keys in the skin's
+/// bold style, with values cut short to fit the width.
+fn print_meta(
+    out: &mut impl Write,
+    entries: &[(String, String)],
+    options: &RenderOptions,
+) -> io::Result<()> {
+    let [h, v, top_left, top_right, bottom_left, bottom_right] = match options.ascii {
+        true => ["-", "|", "+", "+", "+", "+"],
+        false => ["─", "│", "╭", "╮", "╰", "╯"],
+    };
+    let ellipsis = if options.ascii { "..." } else { "…" };
+    let skin = skin(options);
+    let border = |text: String| match options.color {
+        true => text.dark_grey().to_string(),
+        false => text,
+    };
+    let count = |text: &str| text.width();
+    let keys = entries.iter().map(|(key, _)| count(key)).max().unwrap_or(0);
+    let values = entries
+        .iter()
+        .map(|(_, value)| count(value))
+        .max()
+        .unwrap_or(0);
+    // Padding inside the borders and two spaces between the columns
+    let room = values.min(
+        options
+            .width()
+            .saturating_sub(keys + 8)
+            .max(count(ellipsis)),
+    );
+    let inner = keys + room + 4;
+    writeln!(
+        out,
+        "  {}",
+        border(format!("{top_left}{}{top_right}", h.repeat(inner)))
+    )?;
+    for (key, value) in entries {
+        let value = match count(value) > room {
+            true => {
+                let mut left = room - count(ellipsis);
+                let kept: String = value
+                    .chars()
+                    .take_while(|c| {
+                        let width = c.width().unwrap_or(0);
+                        let fits = width <= left;
+                        left = left.saturating_sub(width);
+                        fits
+                    })
+                    .collect();
+                format!("{kept}{ellipsis}")
+            }
+            false => value.clone(),
+        };
+        writeln!(
+            out,
+            "  {} {}{}  {value}{} {}",
+            border(v.to_string()),
+            skin.bold.apply_to(key),
+            " ".repeat(keys - count(key)),
+            " ".repeat(room - count(&value)),
+            border(v.to_string())
+        )?;
+    }
+    writeln!(
+        out,
+        "  {}",
+        border(format!("{bottom_left}{}{bottom_right}", h.repeat(inner)))
+    )
+}
+
+/// Prints the `header` line: the sheet's name, its path with the home
+/// directory shown as `~`, and how long ago it changed.
+fn print_header(
+    out: &mut impl Write,
+    name: &str,
+    path: &Path,
+    options: &RenderOptions,
+) -> io::Result<()> {
+    let dot = if options.ascii { " - " } else { " · " };
+    let mut details = vec![tilde(path)];
+    let age = std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+    if let Some(age) = age {
+        details.push(format!("modified {}", relative_time(age)));
+    }
+    let details = format!("{dot}{}", details.join(dot));
+    let details = match options.color {
+        true => details.dark_grey().to_string(),
+        false => details,
+    };
+    writeln!(out, "{}{details}\n", skin(options).bold.apply_to(name))
+}
+
+/// Prints the `footer` line: how many words of prose and code blocks the
+/// rendered segments hold.
+fn print_footer<'a>(
+    out: &mut impl Write,
+    segments: impl IntoIterator<Item = Segment<'a>>,
+    options: &RenderOptions,
+) -> io::Result<()> {
+    let (mut words, mut blocks) = (0, 0);
+    for segment in segments {
+        match segment {
+            Segment::Text(text) => {
+                words += text
+                    .split_whitespace()
+                    .filter(|word| word.chars().any(char::is_alphanumeric))
+                    .count()
+            }
+            Segment::Code { .. } | Segment::QuotedCode { .. } => blocks += 1,
+            Segment::Marker(_) => {}
+        }
+    }
+    let plural = |n: usize| if n == 1 { "" } else { "s" };
+    let line = format!(
+        "{words} word{}, {blocks} code block{}",
+        plural(words),
+        plural(blocks)
+    );
+    let line = match options.color {
+        true => line.dark_grey().to_string(),
+        false => line,
+    };
+    writeln!(out, "\n{line}")
+}
+
+/// `path` with the home directory abbreviated to `~`.
+fn tilde(path: &Path) -> String {
+    match dirs::home_dir().and_then(|home| Some(path.strip_prefix(home).ok()?.to_path_buf())) {
+        Some(rest) => Path::new("~").join(rest).display().to_string(),
+        None => path.display().to_string(),
+    }
+}
+
+/// `age` in the largest whole unit, as in "3 days ago".
+fn relative_time(age: Duration) -> String {
+    const MINUTE: u64 = 60;
+    const HOUR: u64 = 60 * MINUTE;
+    const DAY: u64 = 24 * HOUR;
+    const MONTH: u64 = 30 * DAY;
+    const YEAR: u64 = 365 * DAY;
+    let secs = age.as_secs();
+    let (n, unit) = match secs {
+        0..MINUTE => return "just now".to_string(),
+        MINUTE..HOUR => (secs / MINUTE, "minute"),
+        HOUR..DAY => (secs / HOUR, "hour"),
+        DAY..MONTH => (secs / DAY, "day"),
+        MONTH..YEAR => (secs / MONTH, "month"),
+        _ => (secs / YEAR, "year"),
+    };
+    format!("{n} {unit}{} ago", if n == 1 { "" } else { "s" })
+}
+
+/// The kind of document a render produces.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Output {
+    /// Styled with terminal escapes
+    #[default]
+    Ansi,
+    /// Copy-friendly text, as with --plain
+    Plain,
+    /// A standalone HTML page
+    Html,
+}
+
+impl Output {
+    /// The output an `--output` file's extension asks for: plain text unless
+    /// it is `.html`/`.htm` or `.ansi`.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("html" | "htm") => Output::Html,
+            Some("ansi") => Output::Ansi,
+            _ => Output::Plain,
+        }
+    }
+}
+
+/// Layout knobs shared by every sheet format.
+#[derive(Debug, Default)]
+pub struct RenderOptions {
+    /// Number of columns for runs of short list items (0 or 1 = off).
+    pub columns: usize,
+    pub syntax_cache: SyntaxCache,
+    /// Language forced onto every code block, whatever its fence says.
+    pub context_lang: Option<String>,
+    /// Draw tables, rules, bullets and icons with ASCII characters only.
+    pub ascii: bool,
+    pub theme: Theme,
+    /// syntect theme name; `DEFAULT_CODE_THEME` when unset.
+    pub code_theme: Option<String>,
+    /// Upper bound on the terminal width.
+    pub width: Option<usize>,
+    pub color: bool,
+    /// Language for code blocks whose fence names none.
+    pub default_lang: Option<String>,
+    /// Show only the summary of `<details>` blocks.
+    pub collapse_details: bool,
+    /// Draw a box around code blocks, labelled with the language.
+    pub code_frame: bool,
+    pub renderer: prose::Renderer,
+    /// Keep each line of prose as written instead of joining paragraphs.
+    pub no_reflow: bool,
+    /// Numbering of ordered lists in prose.
+    pub list_style: prose::ListStyle,
+    pub code_spacing_before: Spacing,
+    pub code_spacing_after: Spacing,
+    /// Columns between tab stops; tabs in code become spaces.
+    pub tab_width: TabWidth,
+    /// Code backgrounds; `None` keeps the skin's and no syntect background.
+    pub highlight_theme_bg: Option<HighlightBg>,
+    /// The headings bounding the part of the sheet to render.
+    pub range: HeadingRange,
+    /// `[colors]` overrides applied over the theme's skin.
+    pub colors: colors::Colors,
+    /// Draw local images on terminals with an inline image protocol.
+    pub images: bool,
+    /// Directory relative image paths are resolved against.
+    pub base_dir: Option<PathBuf>,
+    /// Show `$...$` and `$$...$$` math in prose as inline code.
+    pub math: bool,
+    /// Shell commands drawing code blocks of a language instead of syntect.
+    pub block_renderers: BTreeMap<String, String>,
+    /// Fence tags highlighted as another syntax, over `highlight::LANG_ALIASES`.
+    pub lang_aliases: BTreeMap<String, String>,
+    /// What to write: styled for a terminal, plain text or an HTML page.
+    pub output: Output,
+    /// No blank lines before the first code block or after the last.
+    pub quiet: bool,
+    /// Render only the code blocks, under their headings.
+    pub examples_only: bool,
+    /// With `examples_only`, keep the line right above each block.
+    pub captions: bool,
+    /// Plain text worded for screen readers, whatever `output` is but HTML.
+    pub accessible: bool,
+    /// What becomes of tables wider than `width()`.
+    pub wide_tables: WideTables,
+    /// The sheet's name for a line above it with its path and age; `None`
+    /// for no such line.
+    pub header: Option<String>,
+    /// A line below the sheet counting its words and code blocks.
+    pub footer: bool,
+    /// Show a markdown sheet's front matter in a panel above it.
+    pub show_meta: bool,
+    /// Front matter keys the panel lists, in order; all when empty.
+    pub meta_keys: Vec<String>,
+    /// Blank columns left on each side of the sheet.
+    pub margin: usize,
+    /// Fill in `{{env:VAR}}` and the other `variables` as sheets render.
+    pub expand: bool,
+}
+
+impl RenderOptions {
+    /// The language a code block fenced as `lang` is highlighted as.
+    pub fn code_lang<'a>(&'a self, lang: &'a str) -> &'a str {
+        match (&self.context_lang, &self.default_lang) {
+            (Some(forced), _) => forced,
+            (None, Some(default)) if lang.is_empty() => default,
+            _ => lang,
+        }
+    }
+
+    /// Width to wrap prose at: the terminal's less both margins, at most
+    /// `width`. A margin never leaves fewer than `MIN_WIDTH` columns.
+    pub fn width(&self) -> usize {
+        inner_width(terminal_width(None), self.margin, self.width)
+    }
+
+    /// Columns the sheet is moved in by; HTML pages have none.
+    fn margin(&self) -> usize {
+        match self.output {
+            Output::Html => 0,
+            _ => self.margin,
+        }
+    }
+}
+
+/// Columns assumed when neither the terminal nor `$COLUMNS` says.
+pub const DEFAULT_WIDTH: usize = 80;
+
+/// Columns `margin` leaves for the sheet at the least.
+const MIN_WIDTH: usize = 20;
+
+/// The width to lay a sheet out at, asked of the terminal on every call so
+/// a resized pane or tmux split is picked up. Prose, tables, rules, code
+/// frames and man pages all go through it.
+pub fn terminal_width(max: Option<usize>) -> usize {
+    let terminal = termimad::crossterm::terminal::size().ok();
+    let columns = std::env::var("COLUMNS").ok();
+    layout_width(terminal.map(|(width, _)| width), columns.as_deref(), max)
+}
+
+/// The terminal's width, else `$COLUMNS`, else `DEFAULT_WIDTH`, at most
+/// `max`. A reported width of 0 counts as unknown.
+fn layout_width(terminal: Option<u16>, columns: Option<&str>, max: Option<usize>) -> usize {
+    let width = terminal
+        .map(usize::from)
+        .filter(|&width| width > 0)
+        .or_else(|| columns?.trim().parse().ok().filter(|&width| width > 0))
+        .unwrap_or(DEFAULT_WIDTH);
+    max.map_or(width, |max| width.min(max))
+}
+
+/// `terminal` columns less `margin` on each side, at most `max`.
+fn inner_width(terminal: usize, margin: usize, max: Option<usize>) -> usize {
+    let width = terminal
+        .saturating_sub(2 * margin)
+        .max(terminal.min(MIN_WIDTH));
+    max.map_or(width, |max| width.min(max))
+}
+
+/// Renders sheets into any writer. syntect's syntax set is loaded by the
+/// first code block that needs it and kept for later renders, so `--watch`
+/// pays for it once.
+#[derive(Default)]
+pub struct Renderer {
+    highlighter: OnceCell<Highlighter>,
+    stats: RefCell<RenderStats>,
+}
+
+/// What the renders so far did, for `--explain`.
+#[derive(Debug, Clone, Default)]
+pub struct RenderStats {
+    /// Segments rendered, after `--after`/`--before` picked them.
+    pub segments: usize,
+    /// Languages code blocks were highlighted as, in order of first use.
+    pub languages: Vec<String>,
+    /// The syntect theme code was highlighted with, once any was.
+    pub code_theme: Option<String>,
+}
+
+impl RenderStats {
+    fn record(&mut self, segment: &Segment, options: &RenderOptions) {
+        self.segments += 1;
+        if let Segment::Code { lang, .. } | Segment::QuotedCode { lang, .. } = segment {
+            let lang = options.code_lang(lang);
+            if options.color && !self.languages.iter().any(|seen| seen == lang) {
+                self.languages.push(lang.to_string());
+            }
+        }
+    }
+}
+
+impl Renderer {
+    pub fn new() -> Self {
+        Renderer::default()
+    }
+
+    /// Renders `sheet`, or the part of it `options.range` selects.
+    pub fn render_to(
+        &self,
+        out: &mut impl Write,
+        sheet: &Sheet,
+        options: &RenderOptions,
+    ) -> Result<()> {
+        let out = &mut Margin::new(out, options.margin());
+        let range = &options.range;
+        let expand = options.expand
+            && !front_matter::split(&sheet.content)
+                .0
+                .is_some_and(variables::switched_off);
+        if let Some((name, path)) = options.header.as_deref().zip(sheet.path.as_deref()) {
+            print_header(out, name, path, options)?;
+        }
+        match sheet.format {
+            // Rendered while it is split, without collecting the segments first
+            Format::Markdown => {
+                if options.show_meta {
+                    self.render_meta(out, &sheet.content, options)?;
+                }
+                let body = sheet::markdown_body(&sheet.content, options.collapse_details);
+                range.check(Segments::new(&body))?;
+                let segments = range.select(Segments::new(&body));
+                self.render_sheet(out, filled(segments, expand), options)?;
+                if options.footer {
+                    print_footer(out, range.select(Segments::new(&body)), options)?;
+                }
+            }
+            _ => {
+                let segments = sheet.segments();
+                range.check(&segments)?;
+                let counted = options.footer.then(|| segments.clone());
+                self.render_sheet(out, filled(range.select(segments), expand), options)?;
+                if let Some(segments) = counted {
+                    print_footer(out, range.select(segments), options)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The `show_meta` panel of a markdown sheet with front matter: a box on
+    /// a terminal, `key: value` lines in plain or accessible text. HTML pages
+    /// leave it out.
+    fn render_meta(
+        &self,
+        out: &mut impl Write,
+        content: &str,
+        options: &RenderOptions,
+    ) -> io::Result<()> {
+        let entries = front_matter::entries(content);
+        let entries: Vec<(String, String)> = match options.meta_keys.is_empty() {
+            true => entries,
+            false => options
+                .meta_keys
+                .iter()
+                .filter_map(|key| entries.iter().find(|(name, _)| name == key).cloned())
+                .collect(),
+        };
+        if entries.is_empty() {
+            return Ok(());
+        }
+        match options.output {
+            Output::Html => return Ok(()),
+            Output::Ansi if !options.accessible => print_meta(out, &entries, options)?,
+            _ => {
+                for (key, value) in &entries {
+                    writeln!(out, "{key}: {value}")?;
+                }
+            }
+        }
+        writeln!(out)
+    }
+
+    /// Renders a markdown sheet while it is read from `reader`, so a large
+    /// file starts showing at once and is never held whole. Front matter is
+    /// dropped, but `<details>` and `options.range` need `render_to`.
+    pub fn render_reader(
+        &self,
+        out: &mut impl Write,
+        reader: impl BufRead,
+        options: &RenderOptions,
+    ) -> Result<()> {
+        let out = &mut Margin::new(out, options.margin());
+        let mut error = None;
+        let reader = SegmentReader::new(reader)
+            .skip_front_matter()
+            .context("failed to read the sheet")?;
+        let expand = options.expand && !variables::switched_off(reader.front_matter());
+        let segments = reader.map_while(|segment| segment.map_err(|e| error = Some(e)).ok());
+        self.render_sheet(out, filled(segments, expand), options)?;
+        match error {
+            Some(e) => Err(e).context("failed to read the sheet"),
+            None => Ok(()),
+        }
+    }
+
+    /// Renders a sheet's segments, or with `examples_only` its code blocks.
+    fn render_sheet<'a>(
+        &self,
+        out: &mut impl Write,
+        segments: impl IntoIterator<Item = Segment<'a>>,
+        options: &RenderOptions,
+    ) -> io::Result<()> {
+        match options.examples_only {
+            true => self.write_segments(out, examples::select(segments, options.captions), options),
+            false => self.write_segments(out, segments, options),
+        }
+    }
+
+    /// Renders segments from any source, e.g. a single picked code block.
+    pub fn render_segments<'a>(
+        &self,
+        out: &mut impl Write,
+        segments: impl IntoIterator<Item = Segment<'a>>,
+        options: &RenderOptions,
+    ) -> io::Result<()> {
+        let out = &mut Margin::new(out, options.margin());
+        self.write_segments(out, segments, options)
+    }
+
+    /// `render_segments` into a writer already moved in by the margin.
+    fn write_segments<'a>(
+        &self,
+        out: &mut impl Write,
+        segments: impl IntoIterator<Item = Segment<'a>>,
+        options: &RenderOptions,
+    ) -> io::Result<()> {
+        let stats = &self.stats;
+        let load = || {
+            self.highlighter
+                .get_or_init(|| Highlighter::load(options.syntax_cache))
+        };
+        let record = |segment: &Segment| stats.borrow_mut().record(segment, options);
+        match options.output {
+            Output::Ansi | Output::Plain if options.accessible => {
+                plain::write(out, segments.into_iter().inspect(record), true)
+            }
+            Output::Ansi => render_segments(out, segments, options, load, stats),
+            Output::Plain => plain::write(out, segments.into_iter().inspect(record), false),
+            Output::Html => {
+                let code_theme = options.code_theme.as_deref().unwrap_or(DEFAULT_CODE_THEME);
+                let highlighter =
+                    LazyHighlighter::new(load, code_theme, options.syntax_cache, stats);
+                html::write(out, segments.into_iter().inspect(record), |lang, code| {
+                    let (highlighter, theme) = highlighter.get();
+                    let lang =
+                        highlight::syntax_token(options.code_lang(lang), &options.lang_aliases);
+                    highlight_html(
+                        highlighter,
+                        theme,
+                        lang,
+                        &expand_tabs(code, options.tab_width),
+                    )
+                })
+            }
+        }
+    }
+
+    pub fn stats(&self) -> RenderStats {
+        self.stats.borrow().clone()
+    }
+}
+
+/// syntect's sets and the code theme for one render pass, loaded when the
+/// first code block needs them so sheets without code never pay for it.
+struct LazyHighlighter<'a> {
+    cell: LazyCell<(&'a Highlighter, syntect::highlighting::Theme), LoadHighlighter<'a>>,
+}
+
+/// Loads the highlighter and the theme to use.
+type LoadHighlighter<'a> =
+    Box<dyn FnOnce() -> (&'a Highlighter, syntect::highlighting::Theme) + 'a>;
+
+impl<'a> LazyHighlighter<'a> {
+    fn new(
+        load: impl FnOnce() -> &'a Highlighter + 'a,
+        code_theme: &'a str,
+        cache: SyntaxCache,
+        stats: &'a RefCell<RenderStats>,
+    ) -> Self {
+        let init = move || {
+            let (name, theme) = match highlight::load_theme(code_theme, cache) {
+                Some(theme) => (code_theme, theme),
+                None => {
+                    explain::warn(format!(
+                        "unknown code theme '{code_theme}', using {DEFAULT_CODE_THEME}"
+                    ));
+                    let theme = highlight::load_theme(DEFAULT_CODE_THEME, SyntaxCache::Off)
+                        .expect("the default code theme is embedded");
+                    (DEFAULT_CODE_THEME, theme)
+                }
+            };
+            stats.borrow_mut().code_theme = Some(name.to_string());
+            (load(), theme)
+        };
+        LazyHighlighter {
+            cell: LazyCell::new(Box::new(init)),
+        }
+    }
+
+    fn get(&self) -> (&Highlighter, &syntect::highlighting::Theme) {
+        let (highlighter, theme) = &*self.cell;
+        (highlighter, theme)
+    }
+}
+
+/// The skin prose is styled with: the theme's, with `colors` over it.
+fn skin(options: &RenderOptions) -> MadSkin {
+    let mut skin = match options.color {
+        true => {
+            let mut skin = make_skin(options.theme);
+            if options.highlight_theme_bg == Some(HighlightBg::Off) {
+                skin.code_block.compound_style.object_style.background_color = None;
+                skin.inline_code.object_style.background_color = None;
+            }
+            options.colors.apply(&mut skin);
+            skin
+        }
+        false => MadSkin::no_style(),
+    };
+    if options.ascii {
+        skin.limit_to_ascii();
+    }
+    skin
+}
+
+/// Renders `segments`, calling `load` only if a code block gets highlighted.
+fn render_segments<'a, 'h>(
+    out: &mut impl Write,
+    segments: impl IntoIterator<Item = Segment<'a>>,
+    options: &'h RenderOptions,
+    load: impl FnOnce() -> &'h Highlighter + 'h,
+    stats: &'h RefCell<RenderStats>,
+) -> io::Result<()> {
+    let skin = skin(options);
+    let code_theme = options.code_theme.as_deref().unwrap_or(DEFAULT_CODE_THEME);
+    let highlighter = LazyHighlighter::new(load, code_theme, options.syntax_cache, stats);
+    let theme_bg = options.highlight_theme_bg == Some(HighlightBg::On);
+    let Spacing(before) = options.code_spacing_before;
+    let Spacing(after) = options.code_spacing_after;
+    // Blank lines owed after the last code block, while nothing but
+    // whitespace has followed it; written once something else is
+    let mut after_code: Option<usize> = None;
+    let mut owed = String::new();
+    let mut started = false;
+    for segment in segments {
+        let quoted = matches!(segment, Segment::QuotedCode { .. });
+        let is_code = quoted || matches!(segment, Segment::Code { .. });
+        stats.borrow_mut().record(&segment, options);
+        log::debug!("segment: {}", describe(&segment));
+        let blank_text = matches!(&segment, Segment::Text(text) if text.trim().is_empty());
+        if blank_text && (after_code.is_some() || options.quiet && !started) {
+            continue;
+        }
+        write!(out, "{}", std::mem::take(&mut owed))?;
+        // Spacing around quoted code stays inside the quote
+        let blank = match quoted {
+            true => format!("{}\n", skin.quote_mark),
+            false => "\n".to_string(),
+        };
+        if is_code {
+            // Back-to-back blocks share their spacing instead of stacking it
+            let gap = match after_code {
+                _ if options.quiet && !started => 0,
+                Some(printed) => before.saturating_sub(printed),
+                None => before,
+            };
+            write!(out, "{}", blank.repeat(gap))?;
+        }
+        started = true;
+        match segment {
+            Segment::Text(text) if options.math => {
+                print_text_with_images(out, &skin, &math::convert(&text), options)?
+            }
+            Segment::Text(text) => print_text_with_images(out, &skin, &text, options)?,
+            Segment::Code { lang, code } => {
+                print_code(out, &lang, &code, options, &highlighter, theme_bg)?
+            }
+            Segment::QuotedCode { lang, code } => {
+                // Rendered like any block, then marked as part of the quote
+                let mut block = Vec::new();
+                print_code(&mut block, &lang, &code, options, &highlighter, theme_bg)?;
+                for line in String::from_utf8_lossy(&block).lines() {
+                    writeln!(out, "{} {line}", skin.quote_mark)?;
+                }
+            }
+            Segment::Marker(note) if !options.color => writeln!(out, "{note}")?,
+            Segment::Marker(note) => writeln!(out, "{}", note.dim())?,
+        }
+        after_code = match is_code {
+            true => {
+                owed = blank.repeat(after);
+                Some(after)
+            }
+            false => None,
+        };
+    }
+    // Quiet output ends with the last block
+    if !options.quiet {
+        write!(out, "{owed}")?;
+    }
+    Ok(())
+}
+
+/// `segments` with their variables filled in when `expand`.
+fn filled<'a>(
+    segments: impl IntoIterator<Item = Segment<'a>>,
+    expand: bool,
+) -> impl Iterator<Item = Segment<'a>> {
+    segments.into_iter().map(move |segment| match expand {
+        true => variables::fill(segment),
+        false => segment,
+    })
+}
+
+/// A one-line summary of `segment` for `-vv`.
+fn describe(segment: &Segment) -> String {
+    match segment {
+        Segment::Text(text) => format!("prose, {} lines", text.lines().count()),
+        Segment::Code { lang, code } => {
+            format!("code fenced '{lang}', {} lines", code.lines().count())
+        }
+        Segment::QuotedCode { lang, code } => {
+            format!(
+                "quoted code fenced '{lang}', {} lines",
+                code.lines().count()
+            )
+        }
+        Segment::Marker(note) => format!("marker '{note}'"),
+    }
+}
+
+fn print_code(
+    out: &mut impl Write,
+    lang: &str,
+    code: &str,
+    options: &RenderOptions,
+    highlighter: &LazyHighlighter,
+    theme_bg: bool,
+) -> io::Result<()> {
+    let lang = options.code_lang(lang);
+    if let Some(command) = options.block_renderers.get(lang) {
+        match hooks::run(command, code, hooks::TIMEOUT) {
+            Ok(drawn) if options.color => return write!(out, "{drawn}"),
+            Ok(drawn) => return write!(out, "{}", hooks::strip_escapes(&drawn)),
+            Err(reason) => log::warn!("block renderer for '{lang}' {reason}; highlighting instead"),
+        }
+    }
+    let code = &expand_tabs(code, options.tab_width);
+    let token = highlight::syntax_token(lang, &options.lang_aliases);
+    if options.code_frame {
+        let lines = match options.color {
+            true => {
+                let (highlighter, theme) = highlighter.get();
+                highlight_lines(highlighter, theme, token, code, theme_bg)
+            }
+            false => code.lines().map(String::from).collect(),
+        };
+        return print_framed_code(out, &lines, code, lang, options);
+    }
+    if !options.color {
+        return print_plain_code(out, code);
+    }
+    let (highlighter, theme) = highlighter.get();
+    highlight_code(out, highlighter, theme, token, code, theme_bg)
+}
+
+/// `code` with each tab replaced by the spaces up to the next tab stop, so
+/// columns line up whatever the terminal's own tab width.
+fn expand_tabs(code: &str, TabWidth(width): TabWidth) -> Cow<'_, str> {
+    if !code.contains('\t') {
+        return Cow::Borrowed(code);
+    }
+    let mut expanded = String::with_capacity(code.len());
+    let mut column = 0;
+    for c in code.chars() {
+        match c {
+            '\t' => {
+                let spaces = width - column % width;
+                expanded.extend(std::iter::repeat_n(' ', spaces));
+                column += spaces;
+            }
+            '\n' => {
+                expanded.push(c);
+                column = 0;
+            }
+            _ => {
+                expanded.push(c);
+                column += 1;
+            }
+        }
+    }
+    Cow::Owned(expanded)
+}
+
+/// A code block without escape codes, for `--color never`.
+fn print_plain_code(out: &mut impl Write, code: &str) -> io::Result<()> {
+    for line in code.lines() {
+        writeln!(out, "  {line}")?;
+    }
+    Ok(())
+}
+
+/// Prints prose, drawing standalone images inline with `--images` on
+/// terminals that support it and showing alt text otherwise.
+fn print_text_with_images(
+    out: &mut impl Write,
+    skin: &MadSkin,
+    text: &str,
+    options: &RenderOptions,
+) -> io::Result<()> {
+    let protocol = options.images.then(images::Protocol::detect).flatten();
+    for chunk in images::split(text) {
+        match chunk {
+            images::Chunk::Text(prose) => print_prose(out, skin, &prose, options)?,
+            images::Chunk::Image { alt, src } => {
+                let path = options
+                    .base_dir
+                    .as_deref()
+                    .and_then(|dir| links::resolve(dir, &src));
+                let escape = protocol.zip(path).and_then(|(p, path)| p.escape(&path));
+                match escape {
+                    Some(escape) => write!(out, "{escape}")?,
+                    None => {
+                        let placeholder = format!("{}\n", images::placeholder(&alt));
+                        print_prose(out, skin, &placeholder, options)?
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn print_prose(
+    out: &mut impl Write,
+    skin: &MadSkin,
+    text: &str,
+    options: &RenderOptions,
+) -> io::Result<()> {
+    let width = options.width();
+    for block in admonition::split(text) {
+        match block {
+            admonition::Block::Prose(prose) => {
+                let prose = tables::fit(&prose, width, options.wide_tables, options.ascii);
+                let backend = options
+                    .renderer
+                    .backend(!options.no_reflow, options.list_style);
+                match options.columns > 1 {
+                    true => {
+                        print_text_in_columns(out, skin, &*backend, &prose, options.columns, width)?
+                    }
+                    false => write!(out, "{}", backend.render(skin, &prose, width))?,
+                }
+            }
+            admonition::Block::Admonition { kind, body } => {
+                admonition::print(out, skin, kind, &body, options)?
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Prints `text`, laying out each run of top-level bullet items
+/// newspaper-style across `columns` columns when they fit the terminal.
+fn print_text_in_columns(
+    out: &mut impl Write,
+    skin: &MadSkin,
+    backend: &dyn prose::Backend,
+    text: &str,
+    columns: usize,
+    width: usize,
+) -> io::Result<()> {
+    let mut prose = String::new();
+    let mut lines = text.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        let Some(first) = list_item(line) else {
+            prose.push_str(line);
+            prose.push('\n');
+            continue;
+        };
+        let mut items = vec![first];
+        let mut raw = vec![line];
+        while let Some(item) = lines.peek().and_then(|next| list_item(next)) {
+            items.push(item);
+            raw.push(lines.next().unwrap());
+        }
+        // A run followed by nested or continuation lines isn't a flat list
+        let nested = lines
+            .peek()
+            .is_some_and(|next| next.starts_with(char::is_whitespace));
+        let lengths: Vec<usize> = items
+            .iter()
+            .map(|item| skin.inline(item).composite.visible_length)
+            .collect();
+        let layout = (!nested && !items.iter().any(|item| item.contains('`')))
+            .then(|| column_layout(&lengths, columns, width))
+            .flatten();
+
+        let Some((rows, cell_width)) = layout else {
+            raw.iter().for_each(|line| {
+                prose.push_str(line);
+                prose.push('\n');
+            });
+            continue;
+        };
+        write!(
+            out,
+            "{}",
+            backend.render(skin, &std::mem::take(&mut prose), width)
+        )?;
+        for row in 0..rows {
+            let mut line = String::new();
+            for (index, item) in items.iter().enumerate().skip(row).step_by(rows) {
+                if index != row {
+                    line.push_str(&" ".repeat(COLUMN_GAP));
+                }
+                let padding = cell_width - 2 - lengths[index];
+                line.push_str(&format!(
+                    "{} {}{}",
+                    skin.bullet.repeated(1),
+                    skin.inline(item),
+                    " ".repeat(padding)
+                ));
+            }
+            writeln!(out, "{}", line.trim_end())?;
+        }
+    }
+    write!(out, "{}", backend.render(skin, &prose, width))
+}
+
+const COLUMN_GAP: usize = 2;
+
+/// Text of a top-level `- `/`* `/`+ ` list item line.
+fn list_item(line: &str) -> Option<&str> {
+    ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|bullet| line.strip_prefix(bullet))
+}
+
+/// Balanced column layout for items of the given visible lengths: returns
+/// the number of rows and the width of each cell (bullet included), or
+/// `None` when a single column should be used instead.
+fn column_layout(lengths: &[usize], columns: usize, width: usize) -> Option<(usize, usize)> {
+    if columns < 2 || lengths.len() < 2 {
+        return None;
+    }
+    let cell_width = lengths.iter().max()? + 2;
+    let rows = lengths.len().div_ceil(columns);
+    let used_columns = lengths.len().div_ceil(rows);
+    let total = used_columns * cell_width + (used_columns - 1) * COLUMN_GAP;
+    (total <= width).then_some((rows, cell_width))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sheet::ReadOptions;
+    use std::path::Path;
+
+    #[test]
+    fn test_highlighter_loads_only_for_code() {
+        let options = RenderOptions {
+            color: true,
+            ..RenderOptions::default()
+        };
+        let prose = || vec![Segment::Text("# Keys\n\n* `C-a` start\n".into())];
+        let mut out = Vec::new();
+        let stats = RefCell::default();
+        let load = || -> &Highlighter { panic!("highlighter loaded") };
+        render_segments(&mut out, prose(), &options, load, &stats).unwrap();
+
+        let highlighter = Highlighter::load(SyntaxCache::Off);
+        let loaded = std::cell::Cell::new(false);
+        let mut segments = prose();
+        segments.push(Segment::Code {
+            lang: "bash".to_string(),
+            code: "ls".into(),
+        });
+        let load = || {
+            loaded.set(true);
+            &highlighter
+        };
+        render_segments(&mut out, segments, &options, load, &stats).unwrap();
+        assert!(loaded.get());
+
+        let stats = stats.into_inner();
+        assert_eq!(stats.segments, 3);
+        assert_eq!(stats.languages, ["bash"]);
+        assert_eq!(stats.code_theme.as_deref(), Some(DEFAULT_CODE_THEME));
+    }
+
+    #[test]
+    fn test_render_sheet_into_writer() {
+        let options = RenderOptions {
+            width: Some(40),
+            ..RenderOptions::default()
+        };
+        let mut out = Vec::new();
+        let sheet = "Intro\n\n```bash\necho hi\n```\n\n![diagram](x.png)\n";
+        let sheet = Sheet::new(sheet, Format::Markdown);
+        Renderer::new()
+            .render_to(&mut out, &sheet, &options)
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out, "Intro\n\n\n  echo hi\n\n\n\n[diagram]\n");
+    }
+
+    #[test]
+    fn test_code_frame_fits_wide_characters() {
+        let options = RenderOptions {
+            width: Some(40),
+            code_frame: true,
+            ascii: true,
+            ..RenderOptions::default()
+        };
+        let mut out = Vec::new();
+        let sheet = Sheet::new("```text\n列出分支\ngit branch\n```\n", Format::Markdown);
+        Renderer::new()
+            .render_to(&mut out, &sheet, &options)
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let frame: Vec<&str> = out.lines().filter(|line| !line.is_empty()).collect();
+        assert_eq!(
+            frame,
+            [
+                "  +- text -----+",
+                "  | 列出分支   |",
+                "  | git branch |",
+                "  +------------+",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_quiet_drops_edge_blank_lines() {
+        let render = |quiet| {
+            let options = RenderOptions {
+                width: Some(40),
+                quiet,
+                ..RenderOptions::default()
+            };
+            let sheet = Sheet::new(
+                "```bash\nls\n```\n\nThen\n\n```sh\npwd\n```\n",
+                Format::Markdown,
+            );
+            let mut out = Vec::new();
+            Renderer::new()
+                .render_to(&mut out, &sheet, &options)
+                .unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(render(false), "\n  ls\n\n\n\nThen\n\n\n  pwd\n\n");
+        assert_eq!(render(true), "  ls\n\n\n\nThen\n\n\n  pwd\n");
+    }
+
+    /// Renders `tests/fixtures/{name}` and compares the bytes with
+    /// `render/{name}.{kind}.txt` there, `kind` being `color`, `plain`,
+    /// `accessible` or `copy` (as with --plain). Run with `UPDATE_SNAPSHOTS=1`
+    /// to rewrite them after an intended change.
+    fn assert_snapshot(name: &str, kind: &str) {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+        let sheet = Sheet::read(&dir.join(name), &ReadOptions::default()).unwrap();
+        // Whatever NO_COLOR says where the tests run
+        termimad::crossterm::style::force_color_output(true);
+        let options = RenderOptions {
+            width: Some(60),
+            color: kind == "color",
+            accessible: kind == "accessible",
+            output: match kind {
+                "copy" => Output::Plain,
+                _ => Output::Ansi,
+            },
+            ..RenderOptions::default()
+        };
+        let mut out = Vec::new();
+        Renderer::new()
+            .render_to(&mut out, &sheet, &options)
+            .unwrap();
+        let snapshot = dir.join(format!("render/{name}.{kind}.txt"));
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            std::fs::write(&snapshot, &out).unwrap();
+        }
+        let expected = std::fs::read(&snapshot).unwrap();
+        assert!(
+            out == expected,
+            "{} differs:\n{}",
+            snapshot.display(),
+            String::from_utf8_lossy(&out).escape_debug()
+        );
+    }
+
+    #[test]
+    fn test_render_snapshots() {
+        for name in ["tmux.md", "quoted-code.md", "runbook.adoc"] {
+            assert_snapshot(name, "plain");
+            assert_snapshot(name, "color");
+            assert_snapshot(name, "copy");
+        }
+    }
+
+    #[test]
+    fn test_accessible_snapshots() {
+        for name in ["tmux.md", "release.md"] {
+            assert_snapshot(name, "accessible");
+        }
+    }
+
+    #[test]
+    fn test_relative_time() {
+        let time = |secs| relative_time(Duration::from_secs(secs));
+        assert_eq!(time(59), "just now");
+        assert_eq!(time(60), "1 minute ago");
+        assert_eq!(time(3 * 3600 + 5), "3 hours ago");
+        assert_eq!(time(45 * 86400), "1 month ago");
+        assert_eq!(time(800 * 86400), "2 years ago");
+    }
+
+    #[test]
+    fn test_highlight_lines_theme_bg() {
+        let highlighter = Highlighter::load(SyntaxCache::Off);
+        let theme = &highlight::load_theme(DEFAULT_CODE_THEME, SyntaxCache::Off).unwrap();
+        let plain = highlight_lines(&highlighter, theme, "bash", "ls -l\n", false);
+        assert!(!plain[0].contains("\x1b[48;"));
+        let painted = highlight_lines(&highlighter, theme, "bash", "ls -l\n", true);
+        assert!(painted[0].contains("\x1b[48;"));
+        assert!(painted[0].ends_with("\x1b[0m\n"));
+    }
+
+    #[test]
+    fn test_code_colors_reset_at_each_line_end() {
+        termimad::crossterm::style::force_color_output(true);
+        let options = RenderOptions {
+            color: true,
+            ..RenderOptions::default()
+        };
+        let sheet = Sheet::new(
+            "```bash\necho \"$HOME\"\nls -l\n```\n\nAfter the block\n",
+            Format::Markdown,
+        );
+        let mut out = Vec::new();
+        Renderer::new()
+            .render_to(&mut out, &sheet, &options)
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        for line in out.lines().filter(|line| line.contains('\x1b')) {
+            assert!(line.ends_with("\x1b[0m"), "dangling escape in {line:?}");
+        }
+
+        // A last line without a newline is reset too
+        let highlighter = Highlighter::load(SyntaxCache::Off);
+        let theme = &highlight::load_theme(DEFAULT_CODE_THEME, SyntaxCache::Off).unwrap();
+        let lines = highlight_lines(&highlighter, theme, "bash", "ls\necho \"$HOME", false);
+        assert!(lines[0].ends_with("\x1b[0m\n"));
+        assert!(lines[1].ends_with("\x1b[0m"));
+    }
+
+    #[test]
+    fn test_context_lang_overrides_fence_tag() {
+        let mut options = RenderOptions::default();
+        assert_eq!(options.code_lang("sh"), "sh");
+        options.context_lang = Some("python".to_string());
+        assert_eq!(options.code_lang("sh"), "python");
+        assert_eq!(options.code_lang(""), "python");
+    }
+
+    #[test]
+    fn test_layout_width() {
+        // A terminal, clamped to the configured width
+        assert_eq!(layout_width(Some(120), Some("90"), None), 120);
+        assert_eq!(layout_width(Some(400), None, Some(100)), 100);
+        assert_eq!(layout_width(Some(60), None, Some(100)), 60);
+        // Not a terminal: `$COLUMNS`, then the default
+        assert_eq!(layout_width(None, Some("132"), None), 132);
+        assert_eq!(layout_width(Some(0), Some(" 72 "), None), 72);
+        assert_eq!(layout_width(None, Some("wide"), None), DEFAULT_WIDTH);
+        assert_eq!(layout_width(None, None, Some(60)), 60);
+        assert_eq!(layout_width(None, None, Some(200)), DEFAULT_WIDTH);
+    }
+
+    #[test]
+    fn test_sheet_without_code_never_loads_syntect() {
+        let options = RenderOptions {
+            color: true,
+            code_theme: Some("InspiredGitHub".to_string()),
+            ..RenderOptions::default()
+        };
+        let sheet = Sheet::new("# Git\n\nUse `git add -p`.\n", Format::Markdown);
+        let renderer = Renderer::new();
+        renderer
+            .render_to(&mut Vec::new(), &sheet, &options)
+            .unwrap();
+        assert!(renderer.highlighter.get().is_none());
+        assert_eq!(renderer.stats().code_theme, None);
+    }
+
+    #[test]
+    fn test_no_reflow_keeps_wrapped_lines() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+        let sheet = Sheet::read(&dir.join("wrapped.md"), &ReadOptions::default()).unwrap();
+        for renderer in [prose::Renderer::Termimad, prose::Renderer::Cmark] {
+            let options = RenderOptions {
+                width: Some(100),
+                renderer,
+                no_reflow: true,
+                ..RenderOptions::default()
+            };
+            let mut out = Vec::new();
+            Renderer::new()
+                .render_to(&mut out, &sheet, &options)
+                .unwrap();
+            let out = String::from_utf8(out).unwrap();
+            for line in sheet.content.lines().skip(2) {
+                assert!(
+                    out.lines().any(|shown| shown == line),
+                    "{renderer:?} lost {line:?} in\n{out}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_inner_width() {
+        assert_eq!(inner_width(100, 0, None), 100);
+        assert_eq!(inner_width(100, 4, None), 92);
+        assert_eq!(inner_width(100, 4, Some(80)), 80);
+        // Never squeezed below MIN_WIDTH, or wider than the terminal
+        assert_eq!(inner_width(30, 10, None), MIN_WIDTH);
+        assert_eq!(inner_width(12, 4, None), 12);
+    }
+
+    #[test]
+    fn test_margin_moves_every_line_in() {
+        let options = RenderOptions {
+            margin: 3,
+            ..RenderOptions::default()
+        };
+        let sheet = Sheet::new(
+            "# Git\n\nStage files.\n\n```bash\ngit add -p\n```\n\n| A | B |\n|---|---|\n| 1 | 2 |\n",
+            Format::Markdown,
+        );
+        let mut out = Vec::new();
+        Renderer::new()
+            .render_to(&mut out, &sheet, &options)
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("git add -p"));
+        for line in out.lines().filter(|line| !line.trim().is_empty()) {
+            assert!(line.starts_with("   "), "{line:?} isn't moved in");
+        }
+    }
+
+    #[test]
+    fn test_expand_tabs_to_tab_stops() {
+        assert_eq!(expand_tabs("a\tb\n\tc", TabWidth(4)), "a   b\n    c");
+        assert_eq!(expand_tabs("abcd\te", TabWidth(4)), "abcd    e");
+        assert_eq!(
+            expand_tabs("if x:\n\t\tpass", TabWidth(2)),
+            "if x:\n    pass"
+        );
+        assert!(matches!(
+            expand_tabs("no tabs", TabWidth(4)),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn test_column_layout_balances_rows() {
+        assert_eq!(column_layout(&[3, 5, 4, 2, 6], 2, 80), Some((3, 8)));
+        assert_eq!(column_layout(&[3, 5, 4, 2, 6], 3, 80), Some((2, 8)));
+        // 4 items over 3 columns only needs 2 columns of 2 rows
+        assert_eq!(column_layout(&[1, 1, 1, 1], 3, 8), Some((2, 3)));
+    }
+
+    #[test]
+    fn test_column_layout_falls_back_when_too_wide() {
+        assert_eq!(column_layout(&[30, 30, 30], 3, 80), None);
+        assert_eq!(column_layout(&[3, 3], 1, 80), None);
+        assert_eq!(column_layout(&[3], 4, 80), None);
+    }
+}
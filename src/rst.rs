@@ -0,0 +1,256 @@
+//! Minimal reStructuredText support. Like the Org parser this converts the
+//! document into markdown-flavoured segments rather than rendering directly.
+
+use crate::segment::{Segment, SegmentBuilder};
+
+const CODE_DIRECTIVES: &[&str] = &["code-block", "code", "sourcecode"];
+
+pub fn split_segments(content: &str) -> Vec<Segment<'static>> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut builder = SegmentBuilder::default();
+    // Heading levels follow the order in which adornment styles first appear.
+    let mut styles: Vec<(char, bool)> = Vec::new();
+    let mut ordinal = 0;
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+
+        if let Some((title, style, consumed)) = section_title(&lines[i..]) {
+            if !styles.contains(&style) {
+                styles.push(style);
+            }
+            let level = styles.iter().position(|s| *s == style).unwrap() + 1;
+            builder.line(&format!("{} {}", "#".repeat(level), convert_inline(title)));
+            i += consumed;
+            continue;
+        }
+
+        if adornment_char(line).is_some() {
+            builder.line("---");
+            i += 1;
+            continue;
+        }
+
+        if let Some(rest) = line.trim_start().strip_prefix(".. ") {
+            let indent = indent_of(line);
+            let (body, next) = indented_block(&lines, i + 1, indent);
+            i = next;
+            if let Some((name, args)) = rest.split_once("::") {
+                let (name, args) = (name.trim(), args.trim());
+                if CODE_DIRECTIVES.contains(&name) {
+                    let code = body.into_iter().skip_while(is_option).collect();
+                    builder.push(Segment::Code {
+                        lang: args.to_string(),
+                        code: dedent(code).into(),
+                    });
+                } else {
+                    let marker =
+                        format!("[{name}{}{args}]", if args.is_empty() { "" } else { ": " });
+                    builder.push(Segment::Marker(marker));
+                }
+            }
+            // Anything else after `..` is a comment or link target
+            continue;
+        }
+
+        if let Some(paragraph) = line.trim_end().strip_suffix("::") {
+            let indent = indent_of(line);
+            let (body, next) = indented_block(&lines, i + 1, indent);
+            if !body.iter().all(|l| l.trim().is_empty()) {
+                let paragraph = paragraph.trim_end();
+                if !paragraph.trim().is_empty() {
+                    builder.line(&format!("{}:", convert_line(paragraph, &mut ordinal)));
+                }
+                builder.push(Segment::Code {
+                    lang: String::new(),
+                    code: dedent(body).into(),
+                });
+                i = next;
+                continue;
+            }
+        }
+
+        builder.line(&convert_line(line, &mut ordinal));
+        i += 1;
+    }
+
+    builder.finish()
+}
+
+/// Detects a section title at the start of `lines`, with an optional
+/// overline. Returns the title, its adornment style and lines consumed.
+fn section_title<'a>(lines: &[&'a str]) -> Option<(&'a str, (char, bool), usize)> {
+    let first = *lines.first()?;
+    if let Some(over) = adornment_char(first) {
+        let title = lines.get(1)?.trim();
+        if !title.is_empty() && lines.get(2).and_then(|l| adornment_char(l)) == Some(over) {
+            return Some((title, (over, true), 3));
+        }
+        return None;
+    }
+    let under = adornment_char(lines.get(1)?)?;
+    if first.trim().is_empty() || first.starts_with(char::is_whitespace) {
+        return None;
+    }
+    Some((first.trim(), (under, false), 2))
+}
+
+/// The repeated punctuation character of an adornment line like `=====`.
+fn adornment_char(line: &str) -> Option<char> {
+    let line = line.trim_end();
+    let c = line.chars().next()?;
+    let is_adornment = c.is_ascii_punctuation()
+        && line.chars().count() >= 3
+        && line.chars().all(|other| other == c);
+    is_adornment.then_some(c)
+}
+
+fn indent_of(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// Collects the block of lines indented deeper than `indent`, starting at
+/// `start`. Blank lines inside the block are kept; trailing ones are not.
+fn indented_block<'a>(lines: &[&'a str], start: usize, indent: usize) -> (Vec<&'a str>, usize) {
+    let mut end = start;
+    let mut last_content = start;
+    while end < lines.len() {
+        let line = lines[end];
+        if !line.trim().is_empty() {
+            if indent_of(line) <= indent {
+                break;
+            }
+            last_content = end + 1;
+        }
+        end += 1;
+    }
+    let body = lines[start..last_content.max(start)].to_vec();
+    (body, last_content.max(start))
+}
+
+fn is_option(line: &&str) -> bool {
+    let line = line.trim();
+    line.is_empty() || (line.starts_with(':') && line[1..].contains(':'))
+}
+
+fn dedent(lines: Vec<&str>) -> String {
+    let lines: Vec<&str> = lines
+        .into_iter()
+        .skip_while(|l| l.trim().is_empty())
+        .collect();
+    let common = lines
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| indent_of(l))
+        .min()
+        .unwrap_or(0);
+    lines
+        .iter()
+        .map(|l| l.get(common..).unwrap_or(""))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn convert_line(line: &str, ordinal: &mut usize) -> String {
+    let indent = &line[..indent_of(line)];
+    let body = line.trim_start();
+
+    for bullet in ["* ", "- ", "+ "] {
+        if let Some(item) = body.strip_prefix(bullet) {
+            return format!("{indent}- {}", convert_inline(item));
+        }
+    }
+    if let Some(item) = body.strip_prefix("#. ") {
+        *ordinal += 1;
+        return format!("{indent}{ordinal}. {}", convert_inline(item));
+    }
+    if let Some((number, item)) = enumerated_item(body) {
+        *ordinal = number.parse().unwrap_or(*ordinal);
+        return format!("{indent}{number}. {}", convert_inline(item));
+    }
+    if body.is_empty() {
+        *ordinal = 0;
+    }
+    convert_inline(line)
+}
+
+/// Splits `1. item`, `1) item` and `(1) item` into number and item text.
+fn enumerated_item(body: &str) -> Option<(&str, &str)> {
+    let inner = body.strip_prefix('(').unwrap_or(body);
+    let digits = inner.chars().take_while(char::is_ascii_digit).count();
+    if digits == 0 {
+        return None;
+    }
+    let rest = &inner[digits..];
+    let item = if body.starts_with('(') {
+        rest.strip_prefix(") ")?
+    } else {
+        rest.strip_prefix(". ").or(rest.strip_prefix(") "))?
+    };
+    Some((&inner[..digits], item))
+}
+
+/// Turns ``double-backtick`` literals into markdown inline code.
+fn convert_inline(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("``") {
+        let Some(len) = rest[start + 2..].find("``") else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        out.push('`');
+        out.push_str(&rest[start + 2..start + 2 + len]);
+        out.push('`');
+        rest = &rest[start + 2 + len + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_section_levels_follow_adornment_order() {
+        let content = "=====\nGit\n=====\n\nBranches\n--------\n\nTags\n----\n";
+        let segments = split_segments(content);
+        assert_eq!(segments.len(), 1);
+        assert!(matches!(&segments[0], Segment::Text(t)
+            if t == "# Git\n\n## Branches\n\n## Tags\n"));
+    }
+
+    #[test]
+    fn test_code_block_directive() {
+        let content =
+            "Intro\n\n.. code-block:: bash\n   :linenos:\n\n   git status\n     -s\n\nAfter\n";
+        let segments = split_segments(content);
+        assert_eq!(segments.len(), 3);
+        assert!(matches!(&segments[1], Segment::Code { lang, code }
+            if lang == "bash" && code == "git status\n  -s"));
+    }
+
+    #[test]
+    fn test_literal_block() {
+        let segments = split_segments("Run this::\n\n    make all\n\nDone\n");
+        assert!(matches!(&segments[0], Segment::Text(t) if t == "Run this:\n"));
+        assert!(matches!(&segments[1], Segment::Code { lang, code }
+            if lang.is_empty() && code == "make all"));
+    }
+
+    #[test]
+    fn test_unknown_directive_is_marker() {
+        let segments = split_segments(".. image:: diagram.png\n   :alt: x\n\nText ``ls``\n");
+        assert!(matches!(&segments[0], Segment::Marker(m) if m == "[image: diagram.png]"));
+        assert!(matches!(&segments[1], Segment::Text(t) if t.contains("Text `ls`")));
+    }
+
+    #[test]
+    fn test_lists() {
+        let segments = split_segments("* one\n#. first\n#. second\n(3) third\n");
+        assert!(matches!(&segments[0], Segment::Text(t)
+            if t == "- one\n1. first\n2. second\n3. third\n"));
+    }
+}
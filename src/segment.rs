@@ -0,0 +1,482 @@
+//! Sheets split into prose and code blocks, the unit every renderer and
+//! format converter works on.
+
+use crate::front_matter;
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::io::{self, BufRead};
+
+/// A run of prose or a code block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment<'a> {
+    Text(Cow<'a, str>),
+    Code {
+        lang: String,
+        code: Cow<'a, str>,
+    },
+    /// A fenced block inside a blockquote, its `> ` prefixes stripped.
+    QuotedCode {
+        lang: String,
+        code: Cow<'a, str>,
+    },
+    /// A dimmed one-line note standing in for content we don't render.
+    Marker(String),
+}
+
+impl Segment<'_> {
+    /// The same segment, no longer borrowing from the sheet.
+    pub fn into_owned(self) -> Segment<'static> {
+        match self {
+            Segment::Text(text) => Segment::Text(text.into_owned().into()),
+            Segment::Code { lang, code } => Segment::Code {
+                lang,
+                code: code.into_owned().into(),
+            },
+            Segment::QuotedCode { lang, code } => Segment::QuotedCode {
+                lang,
+                code: code.into_owned().into(),
+            },
+            Segment::Marker(note) => Segment::Marker(note),
+        }
+    }
+}
+
+/// Accumulates prose lines and flushes them as `Segment::Text` whenever a
+/// non-text segment is pushed. Used by the line-oriented format parsers.
+#[derive(Default)]
+pub(crate) struct SegmentBuilder {
+    segments: Vec<Segment<'static>>,
+    text: String,
+}
+
+impl SegmentBuilder {
+    pub(crate) fn line(&mut self, line: &str) {
+        self.text.push_str(line);
+        self.text.push('\n');
+    }
+
+    pub(crate) fn push(&mut self, segment: Segment<'static>) {
+        if !self.text.is_empty() {
+            self.segments
+                .push(Segment::Text(std::mem::take(&mut self.text).into()));
+        }
+        self.segments.push(segment);
+    }
+
+    pub(crate) fn finish(mut self) -> Vec<Segment<'static>> {
+        if !self.text.is_empty() {
+            self.segments.push(Segment::Text(self.text.into()));
+        }
+        self.segments
+    }
+}
+
+/// Splits markdown into text and fenced code segments lazily, borrowing from
+/// the content, so rendering can start before the whole sheet is scanned.
+pub struct Segments<'a> {
+    rest: &'a str,
+    /// Found together with the text before it, yielded right after.
+    pending: Option<Segment<'a>>,
+}
+
+impl<'a> Segments<'a> {
+    pub fn new(content: &'a str) -> Self {
+        Segments {
+            rest: content,
+            pending: None,
+        }
+    }
+}
+
+impl<'a> Iterator for Segments<'a> {
+    type Item = Segment<'a>;
+
+    fn next(&mut self) -> Option<Segment<'a>> {
+        if let Some(segment) = self.pending.take() {
+            return Some(segment);
+        }
+        let rest = self.rest;
+        if rest.is_empty() {
+            return None;
+        }
+        let Some(fence_start) = rest.find("```") else {
+            self.rest = "";
+            return Some(Segment::Text(rest.into()));
+        };
+
+        // A fence opened after a `> ` prefix runs to the matching quoted fence
+        let line_start = rest[..fence_start].rfind('\n').map_or(0, |i| i + 1);
+        let prefix = &rest[line_start..fence_start];
+        let quoted = prefix.contains('>') && prefix.chars().all(|c| c == '>' || c == ' ');
+        let (before, segment) = match quoted.then(|| quoted_fence(&rest[line_start..], prefix)) {
+            Some(Some((segment, len))) => {
+                self.rest = &rest[line_start + len..];
+                (&rest[..line_start], segment)
+            }
+            Some(None) => {
+                // Unclosed within the quote — the fence line stays text
+                let end = rest[fence_start..]
+                    .find('\n')
+                    .map_or(rest.len(), |i| fence_start + i + 1);
+                self.rest = &rest[end..];
+                return Some(Segment::Text(rest[..end].into()));
+            }
+            None => (&rest[..fence_start], self.fence(&rest[fence_start..])),
+        };
+
+        // Text before the fence
+        if before.is_empty() {
+            return Some(segment);
+        }
+        self.pending = Some(segment);
+        Some(Segment::Text(before.into()))
+    }
+}
+
+impl<'a> Segments<'a> {
+    /// The code block at the start of `rest`, setting `self.rest` past it.
+    fn fence(&mut self, rest: &'a str) -> Segment<'a> {
+        let after_fence = &rest[3..];
+
+        // Find the end of the opening fence line to extract language
+        let lang_end = after_fence.find('\n').unwrap_or(after_fence.len());
+        let lang = after_fence[..lang_end].trim().to_string();
+        let code_start = after_fence[lang_end..].trim_start_matches('\n');
+
+        // Find the closing fence
+        if let Some(close) = code_start.find("\n```") {
+            self.rest = &code_start[close + 4..]; // skip "\n```"
+            Segment::Code {
+                lang,
+                code: code_start[..close].into(),
+            }
+        } else {
+            // Unclosed fence — treat remainder as text
+            self.rest = "";
+            Segment::Text(rest.into())
+        }
+    }
+}
+
+/// The blockquoted code block starting `text`, whose lines all begin with
+/// `prefix`, and the length of its lines through the closing fence. `None`
+/// when the quote or the text ends before the block is closed.
+fn quoted_fence<'a>(text: &str, prefix: &str) -> Option<(Segment<'a>, usize)> {
+    let mut lines = text.split_inclusive('\n');
+    let opening = lines.next()?;
+    let lang = opening[prefix.len() + 3..].trim().to_string();
+    let mut len = opening.len();
+    let mut code = String::new();
+    for line in lines {
+        len += line.len();
+        // A bare `>` is a blank line of the quote
+        let inner = match line.strip_prefix(prefix) {
+            Some(inner) => inner,
+            None if line.trim_end() == prefix.trim_end() => "\n",
+            None => return None,
+        };
+        if inner.trim_start().starts_with("```") {
+            if code.ends_with('\n') {
+                code.pop();
+            }
+            let code = code.into();
+            return Some((Segment::QuotedCode { lang, code }, len));
+        }
+        code.push_str(inner);
+    }
+    None
+}
+
+/// Prose buffered past this many bytes is flushed at the next blank line.
+const CHUNK: usize = 64 * 1024;
+
+/// `Segments` over a reader, for sheets too large to read up front: lines
+/// are read as segments are asked for, so only the one in progress is held
+/// in memory. Long prose comes as several texts, split at blank lines.
+pub struct SegmentReader<R> {
+    reader: R,
+    /// Lines read ahead, e.g. by a quoted fence that never closed, to scan again.
+    replay: VecDeque<String>,
+    text: String,
+    ready: VecDeque<Segment<'static>>,
+    done: bool,
+    /// What `skip_front_matter` skipped, without its delimiters.
+    front_matter: String,
+}
+
+impl<R: BufRead> SegmentReader<R> {
+    pub fn new(reader: R) -> Self {
+        SegmentReader {
+            reader,
+            replay: VecDeque::new(),
+            text: String::new(),
+            ready: VecDeque::new(),
+            done: false,
+            front_matter: String::new(),
+        }
+    }
+
+    /// The front matter `skip_front_matter` read past; empty without one.
+    pub fn front_matter(&self) -> &str {
+        &self.front_matter
+    }
+
+    /// Reads past the front matter, as `Sheet::segments` drops it.
+    pub fn skip_front_matter(mut self) -> io::Result<Self> {
+        let mut read = Vec::new();
+        if let Some(line) = self.line()? {
+            let opened = line.ends_with('\n') && front_matter::is_delimiter(&line);
+            read.push(line);
+            while opened && let Some(line) = self.line()? {
+                // Like `front_matter::split`, the closing line can't be the first
+                if front_matter::is_delimiter(&line) && read.len() > 1 {
+                    self.front_matter = read[1..].concat();
+                    read.clear();
+                    while let Some(line) = self.line()? {
+                        if line != "\n" && line != "\r\n" {
+                            read.push(line);
+                            break;
+                        }
+                    }
+                    break;
+                }
+                read.push(line);
+            }
+        }
+        self.replay.extend(read);
+        Ok(self)
+    }
+
+    fn line(&mut self) -> io::Result<Option<String>> {
+        if let Some(line) = self.replay.pop_front() {
+            return Ok(Some(line));
+        }
+        let mut line = String::new();
+        match self.reader.read_line(&mut line)? {
+            0 => Ok(None),
+            _ => Ok(Some(line)),
+        }
+    }
+
+    fn flush(&mut self) {
+        if !self.text.is_empty() {
+            let text = std::mem::take(&mut self.text);
+            self.ready.push_back(Segment::Text(text.into()));
+        }
+    }
+
+    /// Reads until a segment is ready or the input ends.
+    fn fill(&mut self) -> io::Result<()> {
+        while self.ready.is_empty() {
+            let Some(line) = self.line()? else {
+                self.flush();
+                self.done = true;
+                return Ok(());
+            };
+            let Some(fence_start) = line.find("```") else {
+                let blank = line.trim().is_empty();
+                self.text.push_str(&line);
+                if blank && self.text.len() >= CHUNK {
+                    self.flush();
+                }
+                continue;
+            };
+            let prefix = &line[..fence_start];
+            if prefix.contains('>') && prefix.chars().all(|c| c == '>' || c == ' ') {
+                self.quoted_fence(&line, fence_start)?;
+            } else {
+                self.text.push_str(prefix);
+                self.flush();
+                self.fence(&line[fence_start..])?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The code block opened by `opening`, read through its closing fence.
+    fn fence(&mut self, opening: &str) -> io::Result<()> {
+        let lang = opening[3..].trim().to_string();
+        let mut raw = opening.to_string();
+        let mut code = String::new();
+        while let Some(line) = self.line()? {
+            raw.push_str(&line);
+            // Blank lines right after the opening fence aren't code, and the
+            // first code line can't close the block
+            if code.is_empty() && line == "\n" {
+                continue;
+            }
+            if let Some(rest) = line.strip_prefix("```").filter(|_| !code.is_empty()) {
+                code.pop();
+                if !rest.is_empty() {
+                    self.replay.push_front(rest.to_string());
+                }
+                let code = code.into();
+                self.ready.push_back(Segment::Code { lang, code });
+                return Ok(());
+            }
+            code.push_str(&line);
+        }
+        // Unclosed fence — treat remainder as text
+        self.ready.push_back(Segment::Text(raw.into()));
+        Ok(())
+    }
+
+    /// The blockquoted code block opened by `opening`, whose lines all begin
+    /// with its first `prefix_len` bytes.
+    fn quoted_fence(&mut self, opening: &str, prefix_len: usize) -> io::Result<()> {
+        let prefix = &opening[..prefix_len];
+        let lang = opening[prefix_len + 3..].trim().to_string();
+        let mut read = Vec::new();
+        let mut code = String::new();
+        while let Some(line) = self.line()? {
+            // A bare `>` is a blank line of the quote
+            let inner = match line.strip_prefix(prefix) {
+                Some(inner) => inner,
+                None if line.trim_end() == prefix.trim_end() => "\n",
+                None => {
+                    read.push(line);
+                    break;
+                }
+            };
+            if inner.trim_start().starts_with("```") {
+                if code.ends_with('\n') {
+                    code.pop();
+                }
+                self.flush();
+                let code = code.into();
+                self.ready.push_back(Segment::QuotedCode { lang, code });
+                return Ok(());
+            }
+            code.push_str(inner);
+            read.push(line);
+        }
+        // Unclosed within the quote — the fence line stays text
+        self.text.push_str(opening);
+        self.flush();
+        for line in read.into_iter().rev() {
+            self.replay.push_front(line);
+        }
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Iterator for SegmentReader<R> {
+    type Item = io::Result<Segment<'static>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.ready.is_empty()
+            && !self.done
+            && let Err(e) = self.fill()
+        {
+            self.done = true;
+            return Some(Err(e));
+        }
+        self.ready.pop_front().map(Ok)
+    }
+}
+
+/// All segments of markdown `content` at once.
+pub fn split_segments(content: &str) -> Vec<Segment<'_>> {
+    Segments::new(content).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_segments_no_code() {
+        let content = "# Title\n\nSome text\n";
+        let segments = split_segments(content);
+        assert_eq!(segments.len(), 1);
+        assert!(matches!(&segments[0], Segment::Text(t) if t == content));
+    }
+
+    #[test]
+    fn test_split_segments_with_code() {
+        let content = "# Title\n\n```bash\necho hello\n```\n\nAfter\n";
+        let segments = split_segments(content);
+        assert_eq!(segments.len(), 3);
+        assert!(matches!(&segments[0], Segment::Text(_)));
+        assert!(matches!(&segments[1], Segment::Code { lang, code }
+            if lang == "bash" && code == "echo hello"));
+        assert!(matches!(&segments[2], Segment::Text(_)));
+    }
+
+    #[test]
+    fn test_segments_borrow_and_stream() {
+        let content = "Intro\n```sh\nls\n```\n```\nunclosed\n";
+        let mut segments = Segments::new(content);
+        assert!(matches!(
+            segments.next(),
+            Some(Segment::Text(Cow::Borrowed("Intro\n")))
+        ));
+        assert!(
+            matches!(segments.next(), Some(Segment::Code { lang, code: Cow::Borrowed("ls") })
+            if lang == "sh")
+        );
+        assert!(matches!(
+            segments.next(),
+            Some(Segment::Text(Cow::Borrowed("\n")))
+        ));
+        assert!(matches!(segments.next(), Some(Segment::Text(t)) if t == "```\nunclosed\n"));
+        assert!(segments.next().is_none());
+    }
+
+    #[test]
+    fn test_segment_reader_matches_segments() {
+        let read = |content: &str| -> Vec<Segment<'static>> {
+            let reader = SegmentReader::new(content.as_bytes());
+            reader.map(Result::unwrap).collect()
+        };
+        for content in [
+            "# Title\n\nSome text\n",
+            "Intro\n```sh\n\nls\n```\n```\nunclosed\n",
+            "Run ```inline``` here\n```\n```\ncode\n```trailing\nafter",
+            "```\nno newline",
+            include_str!("../tests/fixtures/quoted-code.md"),
+        ] {
+            assert_eq!(read(content), split_segments(content), "{content:?}");
+        }
+
+        // Prose is cut at blank lines once a chunk is full
+        let long = "word ".repeat(CHUNK) + "\n\nnext\n";
+        let segments = read(&long);
+        assert_eq!(segments.len(), 2);
+        assert!(matches!(&segments[1], Segment::Text(t) if t == "next\n"));
+    }
+
+    #[test]
+    fn test_segment_reader_skips_front_matter() {
+        let read = |content: &str| -> Vec<Segment<'static>> {
+            let reader = SegmentReader::new(content.as_bytes());
+            let reader = reader.skip_front_matter().unwrap();
+            reader.map(Result::unwrap).collect()
+        };
+        let text = |t: &str| vec![Segment::Text(t.to_string().into())];
+        assert_eq!(read("---\ntags: [a]\n---\n\n# Git\n"), text("# Git\n"));
+        assert_eq!(read("---\nunclosed\n"), text("---\nunclosed\n"));
+        assert_eq!(
+            read("---\r\ntags: [a]\r\n---\r\n\r\n# Git\r\n"),
+            text("# Git\r\n")
+        );
+        assert_eq!(read("---\ntags: [a]\n---"), vec![]);
+        assert_eq!(read("# Git\n"), text("# Git\n"));
+    }
+
+    #[test]
+    fn test_split_segments_quoted_code() {
+        let segments = split_segments(include_str!("../tests/fixtures/quoted-code.md"));
+        assert!(
+            matches!(&segments[0], Segment::Text(t) if t.ends_with("> Drain the node first:\n>\n"))
+        );
+        assert!(matches!(&segments[1], Segment::QuotedCode { lang, code }
+            if lang == "bash" && code == "kubectl drain node-1\n\nkubectl get pods -o wide"));
+        // The unclosed quoted fence stays text and later fences still count
+        assert!(matches!(&segments[2], Segment::Text(t)
+            if t == ">\n> Then continue.\n\n> ```yaml\n"));
+        assert!(matches!(&segments[3], Segment::Text(t) if t == "> unclosed: true\n\n"));
+        assert!(matches!(&segments[4], Segment::Code { lang, code }
+            if lang == "bash" && code == "make deploy"));
+    }
+}
@@ -0,0 +1,851 @@
+//! Sheets on disk: their formats, how names resolve to files in the config
+//! directory and search paths, and the local overlays of remote sheets.
+
+use crate::error::CheetError;
+use crate::fuzzy::Matcher;
+use crate::segment::{Segment, Segments};
+use crate::{adoc, age, details, front_matter, org, profile, range, rst, walk};
+use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
+use encoding_rs::Encoding;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    #[value(name = "md", alias = "markdown")]
+    Markdown,
+    Org,
+    Rst,
+    Adoc,
+}
+
+/// Markdown sheets larger than this many bytes are rendered as they are read.
+pub const STREAM_SIZE: u64 = 4 << 20;
+
+/// Extensions `find_sheet` accepts, in lookup priority order.
+pub const SHEET_EXTENSIONS: &[&str] = &["md", "markdown", "org", "rst", "adoc"];
+
+impl Format {
+    /// The format a file's extension implies, markdown when in doubt. An
+    /// encrypted sheet's is the one before `.age`.
+    pub fn from_path(path: &Path) -> Self {
+        match age::plain_path(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+        {
+            Some("org") => Format::Org,
+            Some("rst") => Format::Rst,
+            Some("adoc") => Format::Adoc,
+            _ => Format::Markdown,
+        }
+    }
+}
+
+/// A sheet's text and the format to parse it in.
+#[derive(Debug, Clone)]
+pub struct Sheet {
+    pub content: String,
+    pub format: Format,
+    /// The file it was read from; `None` for a sheet piped on stdin.
+    pub path: Option<PathBuf>,
+}
+
+impl Sheet {
+    pub fn new(content: impl Into<String>, format: Format) -> Sheet {
+        Sheet {
+            content: content.into(),
+            format,
+            path: None,
+        }
+    }
+
+    /// Reads the file at `path` as is, in the format of its extension, or
+    /// all parts of the sheet split into numbered files in directory `path`.
+    pub fn read(path: &Path, options: &ReadOptions) -> Result<Sheet> {
+        if path.is_dir() {
+            return Sheet::read_parts(path, None, options);
+        }
+        let content = match age::is_encrypted(path) {
+            true => age::decrypt(path, options.age_identities)?,
+            false => read_text(path, options.encoding)?,
+        };
+        Ok(Sheet {
+            content,
+            format: Format::from_path(path),
+            path: Some(path.to_path_buf()),
+        })
+    }
+
+    /// The numbered markdown files in `dir` joined by horizontal rules, or
+    /// only its `part`th one, counting from 1.
+    pub fn read_parts(dir: &Path, part: Option<usize>, options: &ReadOptions) -> Result<Sheet> {
+        let parts = sheet_parts(dir);
+        let parts = match part {
+            None => &parts[..],
+            Some(n) => match parts.get(n.wrapping_sub(1)) {
+                Some(path) => std::slice::from_ref(path),
+                None => bail!(CheetError::NotFound(format!(
+                    "{} has {} parts; there is no part {n}",
+                    dir.display(),
+                    parts.len()
+                ))),
+            },
+        };
+        if parts.is_empty() {
+            bail!(CheetError::NotFound(format!(
+                "{} has no numbered parts like 01-intro.md",
+                dir.display()
+            )));
+        }
+        log::info!("reading {} parts of {}", parts.len(), dir.display());
+        let mut content = String::new();
+        for path in parts {
+            let text = read_text(path, options.encoding)?;
+            match content.is_empty() {
+                true => content = text,
+                // Only the first part's front matter is the sheet's
+                false => append_section(&mut content, front_matter::split(&text).1),
+            }
+        }
+        Ok(Sheet {
+            content,
+            format: Format::Markdown,
+            path: Some(dir.to_path_buf()),
+        })
+    }
+
+    /// The `title` in its front matter, else its first heading.
+    pub fn title(&self) -> Option<String> {
+        if let Some(title) = front_matter::value(&self.content, "title") {
+            return Some(title.to_string());
+        }
+        self.segments().iter().find_map(|segment| match segment {
+            Segment::Text(text) => text.lines().find_map(range::heading).map(String::from),
+            _ => None,
+        })
+    }
+
+    /// How many sections it has: its headings below the top level.
+    pub fn sections(&self) -> usize {
+        let subheading = |line: &&str| {
+            let line = line.trim_start();
+            range::heading(line).is_some() && line.starts_with("##")
+        };
+        self.segments()
+            .iter()
+            .map(|segment| match segment {
+                Segment::Text(text) => text.lines().filter(subheading).count(),
+                _ => 0,
+            })
+            .sum()
+    }
+
+    /// The sheet split into prose and code, front matter dropped and
+    /// `<details>` bodies shown. Markdown segments borrow from the sheet.
+    pub fn segments(&self) -> Vec<Segment<'_>> {
+        match self.format {
+            Format::Markdown => match markdown_body(&self.content, false) {
+                Cow::Borrowed(body) => Segments::new(body).collect(),
+                Cow::Owned(body) => Segments::new(&body).map(Segment::into_owned).collect(),
+            },
+            Format::Org => org::split_segments(&self.content),
+            Format::Rst => rst::split_segments(&self.content),
+            Format::Adoc => adoc::split_segments(&self.content),
+        }
+    }
+}
+
+/// The markdown to split: front matter dropped and `<details>` rewritten.
+pub(crate) fn markdown_body(content: &str, collapse_details: bool) -> Cow<'_, str> {
+    details::convert(strip_front_matter(content), collapse_details)
+}
+
+/// Drops a leading `---` delimited front matter block.
+fn strip_front_matter(content: &str) -> &str {
+    front_matter::split(content).1
+}
+
+/// How sheet files become text.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReadOptions<'a> {
+    /// The encoding sheets are written in, from `--encoding`; UTF-8 when
+    /// `None`.
+    pub encoding: Option<&'static Encoding>,
+    /// Identity files `age` decrypts with, from the `age_identities` key;
+    /// without any it asks for a passphrase.
+    pub age_identities: &'a [PathBuf],
+}
+
+/// Where and how sheet names are resolved.
+#[derive(Debug, Clone)]
+pub struct SheetLocator<'a> {
+    pub config_dir: &'a Path,
+    /// Searched in order after `config_dir`.
+    pub search_paths: &'a [PathBuf],
+    /// Also look in subfolders, so `aws` finds `cloud/aws.md`.
+    pub recursive: bool,
+    /// Languages whose variants (`git.zh.md`) win over the sheet, most
+    /// specific first; see `locale::chain`.
+    pub langs: &'a [String],
+    pub matcher: Matcher,
+    /// How the sheets found are read.
+    pub read: ReadOptions<'a>,
+}
+
+impl SheetLocator<'_> {
+    /// The sheet `name` resolves to, with its local overlay appended.
+    pub fn find(&self, name: &str) -> Result<Sheet> {
+        self.load(&self.find_path(name)?, true)
+    }
+
+    /// `find_sheet` over the config directory, then each search path. A
+    /// miss everywhere reports the config directory's error.
+    pub fn find_path(&self, name: &str) -> Result<PathBuf> {
+        let roots = walk::roots(self.config_dir, self.search_paths);
+        let find = |dir: &Path| {
+            log::info!("looking for '{name}' in {}", dir.display());
+            let found = find_sheet(dir, name, self.recursive, &self.matcher);
+            localize(dir, name, found, self.langs)
+        };
+        let path = find(self.config_dir)
+            .or_else(|err| roots[1..].iter().find_map(|dir| find(dir).ok()).ok_or(err))?;
+        log::info!("'{name}' is {}", path.display());
+        Ok(path)
+    }
+
+    /// Every sheet in the config directory and the search paths, by name,
+    /// each root's in order. As with `find_path`, a name in an earlier root
+    /// hides the same name further on.
+    pub fn sheets(&self) -> Vec<(String, PathBuf)> {
+        let mut names = HashSet::new();
+        walk::roots(self.config_dir, self.search_paths)
+            .iter()
+            .flat_map(|root| {
+                collect_sheets(root, self.recursive)
+                    .into_iter()
+                    .map(|path| (sheet_name(root, &path), path))
+            })
+            .filter(|(name, _)| names.insert(name.clone()))
+            .collect()
+    }
+
+    /// The steps from `name` to the sheet `path` it found: the name, the
+    /// sheet name it matched when that differs (`aws` to `cloud/aws`), the
+    /// file and, when `with_overlay`, its overlay.
+    pub fn resolution(&self, name: &str, path: &Path, with_overlay: bool) -> Vec<String> {
+        let roots = walk::roots(self.config_dir, self.search_paths);
+        let root = roots
+            .iter()
+            .find(|root| path.starts_with(root))
+            .map_or(self.config_dir, PathBuf::as_path);
+        let mut chain = vec![name.to_string()];
+        let matched = sheet_name(root, path);
+        if matched != name {
+            chain.push(matched);
+        }
+        chain.push(path.display().to_string());
+        if let Some(overlay) = with_overlay
+            .then(|| find_overlay(self.config_dir, path))
+            .flatten()
+        {
+            chain.push(format!("+ {}", overlay.display()));
+        }
+        chain
+    }
+
+    /// Reads the sheet at `path`, with its local overlay appended when
+    /// `with_overlay`.
+    pub fn load(&self, path: &Path, with_overlay: bool) -> Result<Sheet> {
+        let mut sheet = Sheet::read(path, &self.read)?;
+        if let Some(overlay) = with_overlay
+            .then(|| find_overlay(self.config_dir, path))
+            .flatten()
+        {
+            log::info!("appending overlay {}", overlay.display());
+            let additions = read_text(&overlay, self.read.encoding)?;
+            append_section(&mut sheet.content, &additions);
+        }
+        Ok(sheet)
+    }
+
+    /// Whether the file at `path` is over `STREAM_SIZE` and can be rendered
+    /// while it is read: markdown with no overlay to append.
+    pub fn streams(&self, path: &Path, format: Format, with_overlay: bool) -> bool {
+        format == Format::Markdown
+            && self.read.encoding.is_none()
+            && !age::is_encrypted(path)
+            && fs::metadata(path).is_ok_and(|meta| meta.is_file() && meta.len() > STREAM_SIZE)
+            && !(with_overlay && find_overlay(self.config_dir, path).is_some())
+    }
+}
+
+/// The text of the file at `path`, written in `encoding`.
+fn read_text(path: &Path, encoding: Option<&'static Encoding>) -> Result<String> {
+    let bytes = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    decode(bytes, encoding).with_context(|| {
+        format!(
+            "failed to read {}\nTip: name its encoding, e.g. `--encoding latin1`.",
+            path.display()
+        )
+    })
+}
+
+/// `bytes` as text: UTF-8, or in `encoding`, where bytes it has no
+/// character for become U+FFFD.
+pub fn decode(bytes: Vec<u8>, encoding: Option<&'static Encoding>) -> io::Result<String> {
+    let Some(encoding) = encoding else {
+        return String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e));
+    };
+    let (text, _, replaced) = encoding.decode(&bytes);
+    if replaced {
+        log::info!("some bytes aren't {}; shown as U+FFFD", encoding.name());
+    }
+    Ok(text.into_owned())
+}
+
+/// Resolves `command` to a sheet file. `cloud/aws` names a sheet relative to
+/// `config_dir`; a bare name matches a top-level sheet first and then, when
+/// `recursive`, a unique sheet of that basename in any subfolder.
+fn find_sheet(
+    config_dir: &Path,
+    command: &str,
+    recursive: bool,
+    matcher: &Matcher,
+) -> Result<PathBuf> {
+    if let Some(path) = sheet_with_extension(&config_dir.join(command)) {
+        return Ok(path);
+    }
+    // `git/01-basics.md`, `git/02-branching.md`, ... make up `git`
+    let parts_dir = config_dir.join(command);
+    if !sheet_parts(&parts_dir).is_empty() {
+        return Ok(parts_dir);
+    }
+    if recursive && !command.contains('/') {
+        let matches: Vec<PathBuf> = collect_sheets(config_dir, true)
+            .into_iter()
+            .filter(|path| path.file_stem().is_some_and(|stem| stem == command))
+            .collect();
+        match matches.as_slice() {
+            [] => {}
+            [path] => return Ok(path.clone()),
+            _ => {
+                let names: Vec<String> = matches
+                    .iter()
+                    .map(|path| format!("  {}", sheet_name(config_dir, path)))
+                    .collect();
+                bail!(CheetError::NotFound(format!(
+                    "'{command}' is ambiguous; it matches:\n{}\nTip: use the full name, e.g. '{}'.",
+                    names.join("\n"),
+                    sheet_name(config_dir, &matches[0])
+                )));
+            }
+        }
+    }
+    // `My Tool` finds `my-tool.md` and the other way round
+    let wanted = normalize_name(command);
+    let sheets = collect_sheets(config_dir, recursive);
+    let normalized_match = sheets.iter().find(|path| {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        normalize_name(&sheet_name(config_dir, path)) == wanted
+            || (!command.contains('/') && normalize_name(&stem) == wanted)
+    });
+    if let Some(path) = normalized_match {
+        return Ok(path.clone());
+    }
+    let names: Vec<String> = sheets
+        .iter()
+        .map(|path| sheet_name(config_dir, path))
+        .collect();
+    let suggestion = match matcher.suggest(command, &names).as_slice() {
+        [] => String::new(),
+        [name] => format!("\nDid you mean '{name}'?"),
+        several => format!("\nDid you mean one of: {}?", several.join(", ")),
+    };
+    bail!(CheetError::NotFound(format!(
+        "No cheatsheet found for '{command}'.{suggestion}\nExpected: {}\nTip: create a markdown file at that path to get started.",
+        config_dir.join(format!("{command}.md")).display()
+    )))
+}
+
+/// The variant of the sheet `find_sheet` `found` for `name` in the first
+/// of `langs` that has one, or that sheet. A variant without a sheet of its
+/// own, `git.zh.md` alone, is found too.
+fn localize(dir: &Path, name: &str, found: Result<PathBuf>, langs: &[String]) -> Result<PathBuf> {
+    let variant = |base: &Path| {
+        let file_name = base.file_name()?.to_str()?;
+        langs.iter().find_map(|lang| {
+            sheet_with_extension(&base.with_file_name(format!("{file_name}.{lang}")))
+        })
+    };
+    let path = match found {
+        Ok(path) if path.is_dir() => return Ok(path),
+        Ok(path) => path,
+        Err(err) => return variant(&dir.join(name)).ok_or(err),
+    };
+    let plain = age::plain_path(&path);
+    match variant(&plain.with_extension("")) {
+        Some(variant) => {
+            log::info!("preferring {} for the language", variant.display());
+            Ok(variant)
+        }
+        None => Ok(path),
+    }
+}
+
+/// The conventional form of a sheet name: lowercase, spaces as dashes.
+pub fn normalize_name(name: &str) -> String {
+    name.trim()
+        .split(' ')
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_lowercase()
+}
+
+/// The existing file for `base` with the highest-priority sheet extension,
+/// else its encrypted counterpart.
+pub fn sheet_with_extension(base: &Path) -> Option<PathBuf> {
+    let file_name = base.file_name()?.to_str()?;
+    let plain = SHEET_EXTENSIONS
+        .iter()
+        .map(|ext| base.with_file_name(format!("{file_name}.{ext}")));
+    let encrypted = SHEET_EXTENSIONS
+        .iter()
+        .map(|ext| base.with_file_name(format!("{file_name}.{ext}.{}", age::EXTENSION)));
+    plain.chain(encrypted).find(|path| path.is_file())
+}
+
+/// All sheet files under `dir`, sorted; see `walk::sheets`. Only the top
+/// level is scanned unless `recursive`.
+pub fn collect_sheets(dir: &Path, recursive: bool) -> Vec<PathBuf> {
+    let mut sheets: Vec<PathBuf> = walk::sheets(dir, recursive).collect();
+    sheets.sort();
+    sheets
+}
+
+/// The name a sheet is addressed by: its path relative to `config_dir`,
+/// without extension and with `/` separators (e.g. `cloud/aws`).
+pub fn sheet_name(config_dir: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(config_dir).unwrap_or(path);
+    let mut parts: Vec<String> = relative
+        .parent()
+        .into_iter()
+        .flat_map(|parent| parent.components())
+        .map(|part| part.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    parts.push(
+        relative
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned(),
+    );
+    parts.join("/")
+}
+
+/// Folder holding sheets managed by `sync --remotes`; they get overwritten,
+/// so local additions live in an overlay next to them instead.
+pub const REMOTES_DIR: &str = "remotes";
+/// Alternative home for overlays: `overrides/docker.md`.
+pub const OVERRIDES_DIR: &str = "overrides";
+/// Suffix of overlay files kept beside the sheets: `docker.local.md`.
+pub const OVERLAY_SUFFIX: &str = ".local";
+
+pub fn is_remote_managed(config_dir: &Path, path: &Path) -> bool {
+    path.starts_with(config_dir.join(REMOTES_DIR))
+}
+
+pub fn is_overlay_file(path: &Path) -> bool {
+    path.file_stem()
+        .is_some_and(|stem| stem.to_string_lossy().ends_with(OVERLAY_SUFFIX))
+}
+
+/// The local overlay of a remote-managed sheet, if one exists.
+pub fn find_overlay(config_dir: &Path, sheet_path: &Path) -> Option<PathBuf> {
+    if !is_remote_managed(config_dir, sheet_path) {
+        return None;
+    }
+    let stem = sheet_path.file_stem()?.to_str()?;
+    [
+        config_dir.join(format!("{stem}{OVERLAY_SUFFIX}")),
+        config_dir.join(OVERRIDES_DIR).join(stem),
+    ]
+    .iter()
+    .find_map(|base| sheet_with_extension(base))
+}
+
+/// Appends `addition` to `content` after a horizontal rule, as overlays
+/// and sheet parts are.
+fn append_section(content: &mut String, addition: &str) {
+    content.truncate(content.trim_end().len());
+    content.push_str("\n\n---\n\n");
+    content.push_str(addition);
+}
+
+/// The markdown files of a sheet split into parts, like `git/01-basics.md`,
+/// in the order of their numbers. Empty unless `dir` has some and every
+/// markdown file in it is numbered, so plain folders of sheets don't count.
+pub fn sheet_parts(dir: &Path) -> Vec<PathBuf> {
+    if !dir.is_dir() {
+        return Vec::new();
+    }
+    let mut parts = Vec::new();
+    for path in walk::sheets(dir, false).filter(|path| Format::from_path(path) == Format::Markdown)
+    {
+        match part_number(&path) {
+            Some(number) => parts.push((number, path)),
+            None => return Vec::new(),
+        }
+    }
+    parts.sort();
+    parts.into_iter().map(|(_, path)| path).collect()
+}
+
+/// The leading number of a part's file name: 2 for `02-branching.md`.
+fn part_number(path: &Path) -> Option<u64> {
+    let stem = path.file_stem()?.to_str()?;
+    let digits = stem.len() - stem.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    stem[..digits].parse().ok()
+}
+
+/// Folder of named templates for `new --template NAME`.
+pub const TEMPLATES_DIR: &str = "templates";
+
+/// Folders in the config directory that never hold sheets.
+pub const RESERVED_DIRS: &[&str] = &[OVERRIDES_DIR, TEMPLATES_DIR, profile::DIR];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_find_sheet_missing() {
+        let tmp = TempDir::new().unwrap();
+        let result = find_sheet(tmp.path(), "nonexistent", true, &Matcher::default());
+        assert!(result.is_err());
+        let msg = result.unwrap_err().to_string();
+        assert!(msg.contains("No cheatsheet found for 'nonexistent'"));
+    }
+
+    #[test]
+    fn test_find_sheet_found() {
+        let tmp = TempDir::new().unwrap();
+        let sheet = tmp.path().join("tmux.md");
+        fs::write(&sheet, "# tmux\n").unwrap();
+        let result = find_sheet(tmp.path(), "tmux", true, &Matcher::default());
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), sheet);
+    }
+
+    #[test]
+    fn test_find_sheet_prefers_markdown_over_org() {
+        let tmp = TempDir::new().unwrap();
+        fs::write(tmp.path().join("git.org"), "* git\n").unwrap();
+        assert_eq!(
+            find_sheet(tmp.path(), "git", true, &Matcher::default()).unwrap(),
+            tmp.path().join("git.org")
+        );
+        fs::write(tmp.path().join("git.md"), "# git\n").unwrap();
+        assert_eq!(
+            find_sheet(tmp.path(), "git", true, &Matcher::default()).unwrap(),
+            tmp.path().join("git.md")
+        );
+    }
+
+    #[test]
+    fn test_find_encrypted_sheet() {
+        let tmp = TempDir::new().unwrap();
+        let encrypted = tmp.path().join("vpn.org.age");
+        fs::write(&encrypted, "").unwrap();
+        let found = find_sheet(tmp.path(), "vpn", false, &Matcher::default()).unwrap();
+        assert_eq!(found, encrypted);
+        assert_eq!(Format::from_path(&found), Format::Org);
+        // Kept out of listings
+        assert!(collect_sheets(tmp.path(), true).is_empty());
+        fs::write(tmp.path().join("vpn.md"), "# vpn\n").unwrap();
+        assert_eq!(
+            find_sheet(tmp.path(), "vpn", false, &Matcher::default()).unwrap(),
+            tmp.path().join("vpn.md")
+        );
+    }
+
+    #[test]
+    fn test_find_sheet_in_subfolders() {
+        let tmp = TempDir::new().unwrap();
+        fs::create_dir_all(tmp.path().join("cloud")).unwrap();
+        fs::write(tmp.path().join("cloud/aws.md"), "# aws\n").unwrap();
+        let aws = tmp.path().join("cloud/aws.md");
+        assert_eq!(
+            find_sheet(tmp.path(), "cloud/aws", false, &Matcher::default()).unwrap(),
+            aws
+        );
+        assert_eq!(
+            find_sheet(tmp.path(), "aws", true, &Matcher::default()).unwrap(),
+            aws
+        );
+        assert!(find_sheet(tmp.path(), "aws", false, &Matcher::default()).is_err());
+    }
+
+    #[test]
+    fn test_find_sheet_ambiguous_basename() {
+        let tmp = TempDir::new().unwrap();
+        for dir in ["work", "home"] {
+            fs::create_dir_all(tmp.path().join(dir)).unwrap();
+            fs::write(tmp.path().join(dir).join("vpn.md"), "# vpn\n").unwrap();
+        }
+        let err = find_sheet(tmp.path(), "vpn", true, &Matcher::default()).unwrap_err();
+        assert_eq!(crate::error::classify(&err), (3, "not_found"));
+        let msg = err.to_string();
+        assert!(msg.contains("ambiguous"));
+        assert!(msg.contains("home/vpn") && msg.contains("work/vpn"));
+
+        // A top-level sheet wins over nested ones
+        fs::write(tmp.path().join("vpn.md"), "# vpn\n").unwrap();
+        assert_eq!(
+            find_sheet(tmp.path(), "vpn", true, &Matcher::default()).unwrap(),
+            tmp.path().join("vpn.md")
+        );
+    }
+
+    #[test]
+    fn test_find_overlay() {
+        let tmp = TempDir::new().unwrap();
+        let remote = tmp.path().join("remotes/community");
+        fs::create_dir_all(&remote).unwrap();
+        fs::write(remote.join("docker.md"), "# docker\n").unwrap();
+        fs::write(tmp.path().join("git.md"), "# git\n").unwrap();
+        fs::write(tmp.path().join("git.local.md"), "mine\n").unwrap();
+
+        let docker = remote.join("docker.md");
+        assert_eq!(find_overlay(tmp.path(), &docker), None);
+        fs::create_dir_all(tmp.path().join("overrides")).unwrap();
+        fs::write(tmp.path().join("overrides/docker.md"), "mine\n").unwrap();
+        assert_eq!(
+            find_overlay(tmp.path(), &docker),
+            Some(tmp.path().join("overrides/docker.md"))
+        );
+        // Only remote-managed sheets get overlays
+        assert_eq!(find_overlay(tmp.path(), &tmp.path().join("git.md")), None);
+        // Overlays never resolve as sheets of their own
+        assert_eq!(
+            find_sheet(tmp.path(), "docker", true, &Matcher::default()).unwrap(),
+            docker
+        );
+    }
+
+    #[test]
+    fn test_find_sheet_suggests_close_names() {
+        let tmp = TempDir::new().unwrap();
+        fs::write(tmp.path().join("git.md"), "# git\n").unwrap();
+        let msg = find_sheet(tmp.path(), "gti", true, &Matcher::default())
+            .unwrap_err()
+            .to_string();
+        assert!(msg.contains("Did you mean 'git'?"));
+
+        let strict = Matcher {
+            max_distance: 1,
+            ..Matcher::default()
+        };
+        let msg = find_sheet(tmp.path(), "gti", true, &strict)
+            .unwrap_err()
+            .to_string();
+        assert!(!msg.contains("Did you mean"));
+    }
+
+    #[test]
+    fn test_strip_front_matter() {
+        assert_eq!(
+            strip_front_matter("---\ndescription: x\n---\n\n# ssh\n"),
+            "# ssh\n"
+        );
+        assert_eq!(strip_front_matter("---\n\nText\n"), "---\n\nText\n");
+    }
+
+    #[test]
+    fn test_normalize_name() {
+        assert_eq!(normalize_name("My  Tool "), "my-tool");
+        assert_eq!(normalize_name("cloud/AWS CLI"), "cloud/aws-cli");
+    }
+
+    #[test]
+    fn test_locator_find_appends_overlay() {
+        let tmp = TempDir::new().unwrap();
+        fs::create_dir_all(tmp.path().join("remotes")).unwrap();
+        fs::write(tmp.path().join("remotes/docker.md"), "# docker\n").unwrap();
+        fs::write(tmp.path().join("docker.local.md"), "mine\n").unwrap();
+        let locator = SheetLocator {
+            config_dir: tmp.path(),
+            search_paths: &[],
+            recursive: true,
+            langs: &[],
+            matcher: Matcher::default(),
+            read: ReadOptions::default(),
+        };
+        let sheet = locator.find("docker").unwrap();
+        assert_eq!(sheet.content, "# docker\n\n---\n\nmine\n");
+        assert_eq!(sheet.format, Format::Markdown);
+        assert_eq!(sheet.path, Some(tmp.path().join("remotes/docker.md")));
+    }
+
+    #[test]
+    fn test_locator_sheets_span_search_paths() {
+        let tmp = TempDir::new().unwrap();
+        let team = TempDir::new().unwrap();
+        fs::write(tmp.path().join("git.md"), "# mine\n").unwrap();
+        fs::write(team.path().join("git.md"), "# team\n").unwrap();
+        fs::write(team.path().join("jq.md"), "# jq\n").unwrap();
+        let search_paths = [team.path().to_path_buf()];
+        let locator = SheetLocator {
+            config_dir: tmp.path(),
+            search_paths: &search_paths,
+            recursive: true,
+            langs: &[],
+            matcher: Matcher::default(),
+            read: ReadOptions::default(),
+        };
+        assert_eq!(
+            locator.sheets(),
+            [
+                ("git".to_string(), tmp.path().join("git.md")),
+                ("jq".to_string(), team.path().join("jq.md")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_locator_prefers_language_variants() {
+        let tmp = TempDir::new().unwrap();
+        fs::write(tmp.path().join("git.md"), "# git\n").unwrap();
+        fs::write(tmp.path().join("git.zh.md"), "# git zh\n").unwrap();
+        fs::write(tmp.path().join("git.zh-TW.md"), "# git zh-TW\n").unwrap();
+        fs::write(tmp.path().join("tmux.ja.md"), "# tmux ja\n").unwrap();
+        fs::create_dir_all(tmp.path().join("cloud")).unwrap();
+        fs::write(tmp.path().join("cloud/aws.md"), "# aws\n").unwrap();
+        fs::write(tmp.path().join("cloud/aws.zh.md"), "# aws zh\n").unwrap();
+        let find = |name: &str, code: &str| {
+            let langs = crate::locale::chain(code);
+            let locator = SheetLocator {
+                config_dir: tmp.path(),
+                search_paths: &[],
+                recursive: true,
+                langs: &langs,
+                matcher: Matcher::default(),
+                read: ReadOptions::default(),
+            };
+            let path = locator.find_path(name).unwrap();
+            sheet_name(tmp.path(), &path)
+        };
+        // Exact locale, then its language, then the sheet itself
+        assert_eq!(find("git", "zh-TW"), "git.zh-TW");
+        assert_eq!(find("git", "zh-CN"), "git.zh");
+        assert_eq!(find("git", "zh"), "git.zh");
+        assert_eq!(find("git", "fr-FR"), "git");
+        assert_eq!(find("aws", "zh-TW"), "cloud/aws.zh");
+        // A variant with no default sheet still answers for its language
+        assert_eq!(find("tmux", "ja-JP"), "tmux.ja");
+        assert_eq!(find("git.zh", "fr"), "git.zh");
+    }
+
+    #[test]
+    fn test_locator_resolution() {
+        let tmp = TempDir::new().unwrap();
+        fs::create_dir_all(tmp.path().join("remotes")).unwrap();
+        fs::write(tmp.path().join("remotes/docker.md"), "# docker\n").unwrap();
+        fs::write(tmp.path().join("docker.local.md"), "mine\n").unwrap();
+        fs::write(tmp.path().join("my-tool.md"), "# tool\n").unwrap();
+        let locator = SheetLocator {
+            config_dir: tmp.path(),
+            search_paths: &[],
+            recursive: true,
+            langs: &[],
+            matcher: Matcher::default(),
+            read: ReadOptions::default(),
+        };
+        let docker = tmp.path().join("remotes/docker.md");
+        assert_eq!(
+            locator.resolution("docker", &docker, true),
+            [
+                "docker".to_string(),
+                "remotes/docker".to_string(),
+                docker.display().to_string(),
+                format!("+ {}", tmp.path().join("docker.local.md").display()),
+            ]
+        );
+        let tool = locator.find_path("My Tool").unwrap();
+        assert_eq!(
+            locator.resolution("my-tool", &tool, true),
+            ["my-tool".to_string(), tool.display().to_string()]
+        );
+    }
+
+    #[test]
+    fn test_sheet_title() {
+        let titled = Sheet::new("---\ntitle: Git Basics\n---\n# git\n", Format::Markdown);
+        assert_eq!(titled.title().as_deref(), Some("Git Basics"));
+        let headed = Sheet::new("#tag\n\n## Tmux ##\n", Format::Markdown);
+        assert_eq!(headed.title().as_deref(), Some("Tmux"));
+        let org = Sheet::new("* Emacs\n", Format::Org);
+        assert_eq!(org.title().as_deref(), Some("Emacs"));
+        assert_eq!(Sheet::new("no heading\n", Format::Markdown).title(), None);
+    }
+
+    #[test]
+    fn test_sheet_sections() {
+        let sheet = Sheet::new(
+            "# Git\n\n## Basics\n\n```bash\n## not a heading\n```\n\n### Staging\n",
+            Format::Markdown,
+        );
+        assert_eq!(sheet.sections(), 2);
+        assert_eq!(Sheet::new("# Git\n", Format::Markdown).sections(), 0);
+    }
+
+    #[test]
+    fn test_sheet_segments_skip_front_matter() {
+        let sheet = Sheet::new("---\ntags: [git]\n---\n\n# git\n", Format::Markdown);
+        assert!(matches!(
+            sheet.segments().as_slice(),
+            [Segment::Text(Cow::Borrowed("# git\n"))]
+        ));
+    }
+
+    #[test]
+    fn test_sheet_parts() {
+        let tmp = TempDir::new().unwrap();
+        let git = tmp.path().join("git");
+        fs::create_dir_all(&git).unwrap();
+        for name in ["10-hooks.md", "02-branching.md", "01-basics.md"] {
+            fs::write(git.join(name), format!("# {name}\n")).unwrap();
+        }
+        fs::write(git.join("logo.png"), "").unwrap();
+        let names: Vec<String> = sheet_parts(&git)
+            .iter()
+            .map(|path| sheet_name(&git, path))
+            .collect();
+        assert_eq!(names, ["01-basics", "02-branching", "10-hooks"]);
+        assert_eq!(
+            find_sheet(tmp.path(), "git", true, &Matcher::default()).unwrap(),
+            git
+        );
+
+        let sheet = Sheet::read(&git, &ReadOptions::default()).unwrap();
+        assert_eq!(
+            sheet.content,
+            "# 01-basics.md\n\n---\n\n# 02-branching.md\n\n---\n\n# 10-hooks.md\n"
+        );
+        let part = Sheet::read_parts(&git, Some(2), &ReadOptions::default()).unwrap();
+        assert_eq!(part.content, "# 02-branching.md\n");
+        let err = Sheet::read_parts(&git, Some(4), &ReadOptions::default())
+            .unwrap_err()
+            .to_string();
+        assert!(err.ends_with("has 3 parts; there is no part 4"));
+
+        // A folder with an unnumbered sheet is just a folder
+        fs::write(git.join("notes.md"), "# notes\n").unwrap();
+        assert!(sheet_parts(&git).is_empty());
+    }
+}
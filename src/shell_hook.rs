@@ -0,0 +1,70 @@
+//! `shell-hook`: a shell function run when a command isn't found, offering
+//! its sheet when there is one. `eval "$(cheetsheet shell-hook bash)"` in
+//! `~/.bashrc` (or `zsh` in `~/.zshrc`) installs it, replacing any handler
+//! defined before.
+
+use clap::ValueEnum;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+}
+
+impl Shell {
+    /// The function the shell calls for a command it can't find.
+    fn handler(self) -> &'static str {
+        match self {
+            Shell::Bash => "command_not_found_handle",
+            Shell::Zsh => "command_not_found_handler",
+        }
+    }
+}
+
+/// The hook for `shell`. With `ask`, the sheet is only shown once the user
+/// says yes, and never when stdin isn't a terminal.
+pub fn script(shell: Shell, ask: bool) -> String {
+    let show = match ask {
+        true => {
+            "if [ -t 0 ]; then
+            printf '%s: command not found; show its cheatsheet? [y/N] ' \"$1\" >&2
+            read -r answer
+            case $answer in [yY]*) cheetsheet -- \"$1\" ;; esac
+        fi"
+        }
+        false => {
+            "printf '%s: command not found; its cheatsheet:\\n' \"$1\" >&2
+        cheetsheet -- \"$1\""
+        }
+    };
+    format!(
+        "# cheetsheet: offer the sheet for a command that isn't installed
+{handler}() {{
+    if command -v cheetsheet >/dev/null 2>&1 &&
+        cheetsheet --print-path-only -- \"$1\" >/dev/null 2>&1; then
+        {show}
+    else
+        printf '%s: command not found\\n' \"$1\" >&2
+    fi
+    return 127
+}}
+",
+        handler = shell.handler()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script() {
+        let bash = script(Shell::Bash, true);
+        assert!(bash.contains("\ncommand_not_found_handle() {\n"));
+        assert!(bash.contains("read -r answer"));
+        let zsh = script(Shell::Zsh, false);
+        assert!(zsh.contains("\ncommand_not_found_handler() {\n"));
+        assert!(!zsh.contains("read"));
+        assert!(zsh.ends_with("    return 127\n}\n"));
+    }
+}
@@ -0,0 +1,295 @@
+//! Markdown tables too wide for the terminal. Both prose backends squeeze
+//! them into the width by wrapping every cell; `wide_tables` can instead
+//! keep the columns that fit, or turn each row into a record of `header:
+//! value` lines. Tables that fit are left alone, but for cells split with
+//! `<br>`, whose lines are stacked on rows of their own.
+
+use crate::config::WideTables;
+use std::borrow::Cow;
+
+/// `text` with each table wider than `width` rewritten as `mode` asks, and
+/// the lines of `<br>` cells stacked.
+pub fn fit(text: &str, width: usize, mode: WideTables, ascii: bool) -> Cow<'_, str> {
+    if (mode == WideTables::Wrap && !has_break(text)) || !text.contains('|') {
+        return Cow::Borrowed(text);
+    }
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+    let mut out = String::with_capacity(text.len());
+    let mut changed = false;
+    let mut i = 0;
+    while i < lines.len() {
+        let is_table = lines[i].contains('|') && lines.get(i + 1).is_some_and(|l| delimiter(l));
+        if !is_table {
+            out.push_str(lines[i]);
+            i += 1;
+            continue;
+        }
+        let end = (i + 2..lines.len())
+            .find(|&n| lines[n].trim().is_empty() || !lines[n].contains('|'))
+            .unwrap_or(lines.len());
+        let header = cells(lines[i]);
+        let rows: Vec<Vec<String>> = lines[i + 2..end].iter().map(|line| cells(line)).collect();
+        let widths = column_widths(&header, &rows);
+        let fits = mode == WideTables::Wrap || table_width(&widths) <= width;
+        if fits && !lines[i..end].iter().any(|line| has_break(line)) {
+            lines[i..end].iter().for_each(|line| out.push_str(line));
+        } else if fits {
+            changed = true;
+            out.push_str(&row(&joined(&header), header.len()));
+            out.push_str(lines[i + 1]);
+            rows.iter()
+                .for_each(|cells| out.push_str(&row(cells, header.len())));
+        } else {
+            changed = true;
+            out.push_str(&match mode {
+                WideTables::Records => records(&header, &rows),
+                _ => clip(&header, &rows, &widths, width, ascii),
+            });
+        }
+        i = end;
+    }
+    match changed {
+        true => Cow::Owned(out),
+        false => Cow::Borrowed(text),
+    }
+}
+
+/// A table's `|---|:--:|` line.
+fn delimiter(line: &str) -> bool {
+    let line = line.trim();
+    line.contains('-')
+        && line.contains(['|', ':'])
+        && line
+            .chars()
+            .all(|c| matches!(c, '|' | '-' | ':' | ' ' | '\t'))
+}
+
+/// The cells of a table row, split at `|` outside code spans.
+fn cells(line: &str) -> Vec<String> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = match line.strip_suffix('|') {
+        Some(rest) if !rest.ends_with('\\') => rest,
+        _ => line,
+    };
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut code = false;
+    let mut escaped = false;
+    for c in line.chars() {
+        match c {
+            '|' if !code && !escaped => cells.push(std::mem::take(&mut cell).trim().to_string()),
+            _ => {
+                if c == '`' && !escaped {
+                    code = !code;
+                }
+                escaped = c == '\\' && !escaped;
+                cell.push(c);
+            }
+        }
+    }
+    cells.push(cell.trim().to_string());
+    cells
+}
+
+/// Whether `text` has a `<br>`, `<br/>` or `<br />` tag.
+fn has_break(text: &str) -> bool {
+    text.to_ascii_lowercase().contains("<br")
+}
+
+/// The lines of a cell split at `<br>` tags.
+pub(crate) fn cell_lines(cell: &str) -> Vec<&str> {
+    let mut lines = Vec::new();
+    let mut rest = cell;
+    // Where to look for the next tag: past `<branch>` and the like
+    let mut from = 0;
+    while let Some(start) = rest[from..].to_ascii_lowercase().find("<br") {
+        let start = from + start;
+        let Some(len) = rest[start..].find('>') else {
+            break;
+        };
+        let tag = rest[start + 3..start + len].trim();
+        if !matches!(tag, "" | "/") {
+            from = start + len + 1;
+            continue;
+        }
+        lines.push(rest[..start].trim());
+        rest = &rest[start + len + 1..];
+        from = 0;
+    }
+    lines.push(rest.trim());
+    // A trailing `<br>` adds no line
+    while lines.len() > 1 && lines.last() == Some(&"") {
+        lines.pop();
+    }
+    lines
+}
+
+/// The first `columns` cells as table rows, a row per line of the `<br>`
+/// cells, other cells left blank below their one line.
+fn row(cells: &[String], columns: usize) -> String {
+    let lines: Vec<Vec<&str>> = (0..columns)
+        .map(|i| cells.get(i).map_or(vec![""], |cell| cell_lines(cell)))
+        .collect();
+    let height = lines.iter().map(Vec::len).max().unwrap_or(1);
+    (0..height)
+        .map(|n| {
+            let cells: Vec<&str> = lines
+                .iter()
+                .map(|lines| lines.get(n).copied().unwrap_or(""))
+                .collect();
+            format!("| {} |\n", cells.join(" | "))
+        })
+        .collect()
+}
+
+/// Header cells with their `<br>` lines joined by spaces, as a header
+/// takes a single row.
+fn joined(header: &[String]) -> Vec<String> {
+    header
+        .iter()
+        .map(|cell| cell_lines(cell).join(" "))
+        .collect()
+}
+
+/// Roughly how wide a cell shows: its widest line's characters without
+/// emphasis and code span markers.
+fn visible_width(cell: &str) -> usize {
+    cell_lines(cell)
+        .iter()
+        .map(|line| line.chars().filter(|c| !matches!(c, '*' | '`')).count())
+        .max()
+        .unwrap_or(0)
+}
+
+fn column_widths(header: &[String], rows: &[Vec<String>]) -> Vec<usize> {
+    (0..header.len())
+        .map(|i| {
+            std::iter::once(header)
+                .chain(rows.iter().map(Vec::as_slice))
+                .filter_map(|row| row.get(i))
+                .map(|cell| visible_width(cell))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect()
+}
+
+/// The width of a drawn table: cells padded by a space each side, between
+/// borders.
+fn table_width(widths: &[usize]) -> usize {
+    widths.iter().map(|width| width + 3).sum::<usize>() + 1
+}
+
+/// Each row as `**header**: value` lines, ending in two spaces so CommonMark
+/// keeps them apart too, with a blank line between rows.
+fn records(header: &[String], rows: &[Vec<String>]) -> String {
+    let mut out = String::new();
+    for (n, row) in rows.iter().enumerate() {
+        if n > 0 {
+            out.push('\n');
+        }
+        for (i, name) in joined(header).iter().enumerate() {
+            let value = cell_lines(row.get(i).map_or("", String::as_str)).join("  \n");
+            let name = match name.is_empty() {
+                true => format!("{}", i + 1),
+                false => name.replace('*', ""),
+            };
+            out.push_str(&format!("**{name}**: {value}  \n"));
+        }
+    }
+    out
+}
+
+/// The leading columns that fit `width`, and a note naming the rest.
+fn clip(
+    header: &[String],
+    rows: &[Vec<String>],
+    widths: &[usize],
+    width: usize,
+    ascii: bool,
+) -> String {
+    let kept = (1..=widths.len())
+        .take_while(|&n| table_width(&widths[..n]) <= width)
+        .last()
+        .unwrap_or(1);
+    let header = joined(header);
+    let mut out = row(&header, kept);
+    out.push_str(&format!("|{}\n", "---|".repeat(kept)));
+    rows.iter()
+        .for_each(|cells| out.push_str(&row(cells, kept)));
+    let hidden: Vec<&str> = header[kept..].iter().map(String::as_str).collect();
+    let arrow = if ascii { ">" } else { "▶" };
+    let columns = if hidden.len() == 1 {
+        "column"
+    } else {
+        "columns"
+    };
+    out.push_str(&format!(
+        "\n*{arrow} {} more {columns}: {}*\n",
+        hidden.len(),
+        hidden.join(", ")
+    ));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TABLE: &str = "Intro\n\n| Command | Does | Since |\n|---|:---:|---|\n\
+                         | `git log` | history | 1.0 |\n| add | stage \\| index | |\n\nAfter\n";
+
+    #[test]
+    fn test_fitting_table_is_kept() {
+        for mode in [WideTables::Wrap, WideTables::Clip, WideTables::Records] {
+            assert!(matches!(fit(TABLE, 80, mode, false), Cow::Borrowed(_)));
+        }
+    }
+
+    #[test]
+    fn test_wide_table_as_records() {
+        assert_eq!(
+            fit(TABLE, 20, WideTables::Records, false),
+            "Intro\n\n**Command**: `git log`  \n**Does**: history  \n**Since**: 1.0  \n\n\
+             **Command**: add  \n**Does**: stage \\| index  \n**Since**:   \n\nAfter\n"
+        );
+    }
+
+    #[test]
+    fn test_wide_table_clipped() {
+        assert_eq!(
+            fit(TABLE, 30, WideTables::Clip, false),
+            "Intro\n\n| Command | Does |\n|---|---|\n| `git log` | history |\n\
+             | add | stage \\| index |\n\n*▶ 1 more column: Since*\n\nAfter\n"
+        );
+    }
+
+    #[test]
+    fn test_br_cells_are_stacked() {
+        let table = "| Key | Does |\n|:--|---|\n| `C-b d` | detach<br>keep running<BR /> |\n| `C-b c` | new |\n";
+        assert_eq!(
+            fit(table, 80, WideTables::Wrap, false),
+            "| Key | Does |\n|:--|---|\n| `C-b d` | detach |\n|  | keep running |\n\
+             | `C-b c` | new |\n"
+        );
+        // The widest line counts, not the whole cell
+        assert_eq!(visible_width("detach<br/>keep running"), 12);
+        assert!(matches!(
+            fit(table, 20, WideTables::Records, false),
+            Cow::Owned(records) if records.contains("**Does**: detach  \nkeep running  \n")
+        ));
+        assert_eq!(cell_lines("a <bread> b"), ["a <bread> b"]);
+        assert_eq!(
+            cell_lines("git switch <branch><br>git checkout <branch>"),
+            ["git switch <branch>", "git checkout <branch>"]
+        );
+    }
+
+    #[test]
+    fn test_cells() {
+        assert_eq!(cells("| `a|b` | c \\| d |\n"), ["`a|b`", "c \\| d"]);
+        assert!(delimiter("|---|:--:|\n"));
+        assert!(!delimiter("| a | b |\n"));
+    }
+}
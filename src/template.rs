@@ -0,0 +1,140 @@
+//! Templates for `new`: `{{variable}}` placeholders filled in when a sheet
+//! is created.
+
+use anyhow::{Result, bail};
+use cheet_sheet::{CheetError, variables};
+use std::process::Command;
+use std::time::SystemTime;
+
+/// Used when neither `--template` nor the `template` config key is set.
+pub const BUILTIN: &str = "# {{name}}\n";
+
+pub struct Vars {
+    pub name: String,
+    pub date: String,
+    pub author: String,
+    pub tags: Vec<String>,
+}
+
+/// Replaces every `{{variable}}` in `template`. `origin` names the template
+/// in errors.
+pub fn expand(template: &str, vars: &Vars, origin: &str) -> Result<String> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let variable = rest[start + 2..start + 2 + len].trim();
+        let value = match variable {
+            "name" => vars.name.clone(),
+            "date" => vars.date.clone(),
+            "author" => vars.author.clone(),
+            "tags" => vars.tags.join(", "),
+            _ => bail!(CheetError::Parse(format!(
+                "unknown variable '{{{{{variable}}}}}' in template {origin}"
+            ))),
+        };
+        out.push_str(&rest[..start]);
+        out.push_str(&value);
+        rest = &rest[start + 2 + len + 2..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Records `tags` in the sheet's front matter, unless it already has a
+/// `tags:` line.
+pub fn add_tags(content: String, tags: &[String]) -> String {
+    if tags.is_empty() {
+        return content;
+    }
+    let line = format!("tags: [{}]\n", tags.join(", "));
+    match content.strip_prefix("---\n") {
+        Some(rest) => {
+            let front_matter = rest.split("\n---\n").next().unwrap_or_default();
+            if front_matter.lines().any(|l| l.starts_with("tags:")) {
+                content
+            } else {
+                format!("---\n{line}{rest}")
+            }
+        }
+        None => format!("---\n{line}---\n\n{content}"),
+    }
+}
+
+/// `user.name` from git's config, if git is installed and has one.
+pub fn git_author() -> Option<String> {
+    let output = Command::new("git")
+        .args(["config", "user.name"])
+        .output()
+        .ok()?;
+    let name = String::from_utf8(output.stdout).ok()?;
+    let name = name.trim();
+    (output.status.success() && !name.is_empty()).then(|| name.to_string())
+}
+
+/// Today's date (UTC) as `YYYY-MM-DD`.
+pub fn today() -> String {
+    rfc3339(SystemTime::now())[..10].to_string()
+}
+
+/// `time` in UTC as RFC 3339, e.g. `2024-05-01T09:30:00Z`.
+pub fn rfc3339(time: SystemTime) -> String {
+    variables::format_date(time, "%FT%TZ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    fn vars() -> Vars {
+        Vars {
+            name: "ssh".into(),
+            date: "2024-05-01".into(),
+            author: "Ada".into(),
+            tags: vec!["net".into(), "remote".into()],
+        }
+    }
+
+    #[test]
+    fn test_expand() {
+        let out = expand(
+            "# {{name}}\nby {{ author }} on {{date}}: {{tags}}\n",
+            &vars(),
+            "t.md",
+        );
+        assert_eq!(out.unwrap(), "# ssh\nby Ada on 2024-05-01: net, remote\n");
+    }
+
+    #[test]
+    fn test_expand_unknown_variable() {
+        let err = expand("# {{title}}\n", &vars(), "templates/tool.md").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unknown variable '{{title}}' in template templates/tool.md"
+        );
+    }
+
+    #[test]
+    fn test_add_tags() {
+        let tags = vars().tags;
+        assert_eq!(
+            add_tags("# ssh\n".into(), &tags),
+            "---\ntags: [net, remote]\n---\n\n# ssh\n"
+        );
+        assert_eq!(
+            add_tags("---\nauthor: Ada\n---\n# ssh\n".into(), &tags),
+            "---\ntags: [net, remote]\nauthor: Ada\n---\n# ssh\n"
+        );
+        let tagged = "---\ntags: [x]\n---\n".to_string();
+        assert_eq!(add_tags(tagged.clone(), &tags), tagged);
+    }
+
+    #[test]
+    fn test_rfc3339() {
+        let time = UNIX_EPOCH + std::time::Duration::from_secs(19_844 * 86_400 + 34_205);
+        assert_eq!(rfc3339(time), "2024-05-01T09:30:05Z");
+    }
+}
@@ -0,0 +1,76 @@
+//! What the terminal on stdout can show, decided from the environment in one
+//! place: whether `--color auto` colors, and whether escapes and box drawing
+//! are safe at all, which they aren't under `TERM=dumb` (Emacs shell-mode,
+//! some IDE consoles) or on a Windows console that won't take VT mode.
+
+use std::io::IsTerminal;
+
+/// Whether `ColorMode::Auto` colors: stdout is a terminal that isn't dumb
+/// and `NO_COLOR` is unset.
+pub fn auto_color() -> bool {
+    let tty = std::io::stdout().is_terminal();
+    tty && std::env::var_os("NO_COLOR").is_none() && !dumb(term().as_deref(), tty, vt())
+}
+
+/// Whether output should be plain ASCII without escapes, as with `--color
+/// never --ascii`.
+pub fn is_dumb() -> bool {
+    dumb(term().as_deref(), std::io::stdout().is_terminal(), vt())
+}
+
+/// Switches a Windows console to virtual terminal processing, so it reads
+/// escape codes instead of printing them, and says whether that worked.
+/// Other terminals always read them. Asked once; later calls are free.
+pub fn enable_ansi() -> bool {
+    #[cfg(windows)]
+    let enabled = termimad::crossterm::ansi_support::supports_ansi();
+    #[cfg(not(windows))]
+    let enabled = true;
+    enabled
+}
+
+fn term() -> Option<String> {
+    std::env::var("TERM").ok()
+}
+
+/// Whether the Windows console took VT mode; `None` off Windows.
+fn vt() -> Option<bool> {
+    cfg!(windows).then(enable_ansi)
+}
+
+/// `TERM=dumb`, or a terminal with no `TERM` at all. Windows consoles go
+/// without one, so there it is whether VT mode could be switched on. Piped
+/// output is left alone; it may well be read by something capable.
+fn dumb(term: Option<&str>, tty: bool, vt: Option<bool>) -> bool {
+    match (term.map(str::trim).filter(|term| !term.is_empty()), vt) {
+        (Some(term), _) => term == "dumb",
+        (None, Some(vt)) => tty && !vt,
+        (None, None) => tty,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dumb() {
+        assert!(dumb(Some("dumb"), true, None));
+        assert!(dumb(Some("dumb"), false, None));
+        assert!(!dumb(Some("xterm-256color"), true, None));
+        assert!(dumb(None, true, None));
+        assert!(dumb(Some(" "), true, None));
+        assert!(!dumb(None, false, None));
+    }
+
+    #[test]
+    fn test_dumb_windows_console() {
+        // conhost and Windows Terminal set no TERM
+        assert!(!dumb(None, true, Some(true)));
+        assert!(dumb(None, true, Some(false)));
+        assert!(!dumb(None, false, Some(false)));
+        // Git Bash's mintty sets one
+        assert!(!dumb(Some("xterm"), true, Some(false)));
+        assert!(dumb(Some("dumb"), true, Some(true)));
+    }
+}
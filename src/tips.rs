@@ -0,0 +1,200 @@
+//! `tip`: one short code block and its caption, for a shell's startup file.
+//! Tips are read from a small cache of every sheet's short blocks, built
+//! when it is missing, a day old, made for another config directory or
+//! asked for with `--refresh`, so a tip costs no directory walk and no
+//! syntax highlighting.
+
+use cheet_sheet::segment::Segment;
+use cheet_sheet::sheet::{self, ReadOptions, Sheet, collect_sheets};
+use cheet_sheet::{age, profile};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Code blocks longer than this many lines make poor tips.
+const MAX_LINES: usize = 3;
+
+/// How long the cache is trusted before it is rebuilt.
+const MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tip {
+    /// The sheet's name.
+    pub sheet: String,
+    /// The line of prose right above the block.
+    pub caption: Option<String>,
+    pub code: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Cache {
+    config_dir: PathBuf,
+    tips: Vec<Tip>,
+}
+
+fn cache_path(profile: &str) -> Option<PathBuf> {
+    let data_dir = dirs::data_local_dir()?.join("cheetsheet");
+    Some(profile::state_dir(&data_dir, profile).join("tips.json"))
+}
+
+/// The tips of the sheets in `config_dir`, read with `read`, from the cache
+/// unless it is stale or `refresh`. A cache that can't be written is rebuilt
+/// next time.
+pub fn load(
+    config_dir: &Path,
+    profile: &str,
+    recursive: bool,
+    read: &ReadOptions,
+    refresh: bool,
+) -> Vec<Tip> {
+    let path = cache_path(profile);
+    let fresh = |path: &Path| {
+        let modified = fs::metadata(path).and_then(|meta| meta.modified()).ok()?;
+        let age = SystemTime::now().duration_since(modified).ok()?;
+        (age < MAX_AGE).then_some(())
+    };
+    let cached = path
+        .as_deref()
+        .filter(|_| !refresh)
+        .and_then(|path| fresh(path).and_then(|()| fs::read(path).ok()))
+        .and_then(|json| serde_json::from_slice::<Cache>(&json).ok())
+        .filter(|cache| cache.config_dir == config_dir);
+    if let Some(cache) = cached {
+        return cache.tips;
+    }
+    let cache = Cache {
+        config_dir: config_dir.to_path_buf(),
+        tips: extract(config_dir, recursive, read),
+    };
+    if let Some(path) = path {
+        log::info!("writing {} tips to {}", cache.tips.len(), path.display());
+        let _ = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| fs::write(&path, serde_json::to_vec(&cache).unwrap_or_default()));
+    }
+    cache.tips
+}
+
+/// The short code blocks of every sheet in `config_dir`. Encrypted sheets
+/// are left out, as their plain text mustn't reach the cache.
+fn extract(config_dir: &Path, recursive: bool, read: &ReadOptions) -> Vec<Tip> {
+    let mut tips = Vec::new();
+    let sheets = collect_sheets(config_dir, recursive);
+    for path in sheets.iter().filter(|path| !age::is_encrypted(path)) {
+        let Ok(sheet) = Sheet::read(path, read) else {
+            continue;
+        };
+        let name = sheet::sheet_name(config_dir, path);
+        let mut caption = None;
+        for segment in sheet.segments() {
+            match segment {
+                Segment::Text(text) => {
+                    caption = text
+                        .lines()
+                        .rev()
+                        .map(str::trim)
+                        .find(|line| !line.is_empty())
+                        .filter(|line| !line.starts_with('#'))
+                        .map(|line| line.trim_end_matches(':').to_string());
+                }
+                Segment::Code { code, .. } => {
+                    let caption = caption.take();
+                    let code = code.trim_end();
+                    if !code.trim().is_empty() && code.lines().count() <= MAX_LINES {
+                        tips.push(Tip {
+                            sheet: name.clone(),
+                            caption,
+                            code: code.to_string(),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    tips
+}
+
+/// `tip` as lines at most `width` columns wide: the sheet and caption, then
+/// the code.
+pub fn format(tip: &Tip, width: usize, ascii: bool) -> String {
+    let title = match &tip.caption {
+        Some(caption) => format!("{}: {caption}", tip.sheet),
+        None => tip.sheet.clone(),
+    };
+    std::iter::once(title.as_str())
+        .chain(tip.code.lines())
+        .map(|line| truncate(line, width, ascii) + "\n")
+        .collect()
+}
+
+/// `line` cut to `width` columns, ending in an ellipsis when cut.
+fn truncate(line: &str, width: usize, ascii: bool) -> String {
+    if line.width() <= width {
+        return line.to_string();
+    }
+    let ellipsis = if ascii { "..." } else { "…" };
+    let mut left = width.saturating_sub(ellipsis.width());
+    let kept: String = line
+        .chars()
+        .take_while(|c| {
+            let width = c.width().unwrap_or(0);
+            let fits = width <= left;
+            left = left.saturating_sub(width);
+            fits
+        })
+        .collect();
+    kept + ellipsis
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_short_blocks() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("git.md"),
+            "# Git\n\nStage parts of files:\n\n```bash\ngit add -p\n```\n\n\
+             ## Long\n\n```bash\na\nb\nc\nd\n```\n\n```bash\ngit log\n```\n",
+        )
+        .unwrap();
+        assert_eq!(
+            extract(dir.path(), true, &ReadOptions::default()),
+            [
+                Tip {
+                    sheet: "git".into(),
+                    caption: Some("Stage parts of files".into()),
+                    code: "git add -p".into(),
+                },
+                Tip {
+                    sheet: "git".into(),
+                    caption: None,
+                    code: "git log".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_truncates() {
+        let tip = Tip {
+            sheet: "git".into(),
+            caption: Some("Stage parts of files".into()),
+            code: "git add --patch --verbose".into(),
+        };
+        assert_eq!(
+            format(&tip, 16, false),
+            "git: Stage part…\ngit add --patch…\n"
+        );
+        assert_eq!(
+            format(&tip, 16, true),
+            "git: Stage pa...\ngit add --pat...\n"
+        );
+        assert_eq!(truncate("列出所有分支", 7, false), "列出所…");
+    }
+}
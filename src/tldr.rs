@@ -0,0 +1,179 @@
+//! Conversion between tldr pages and cheetsheet markdown.
+//!
+//! A tldr page is a title, a `> ` description and a list of examples, each a
+//! `- caption:` line followed by a single backticked command with
+//! `{{placeholders}}`. In cheetsheet markdown every example becomes a caption
+//! paragraph plus a fenced bash block with `<placeholders>`.
+
+use crate::segment::{Segment, split_segments};
+use anyhow::{Result, bail};
+
+pub fn to_markdown(page: &str) -> Result<String> {
+    let mut out = String::new();
+    let mut caption: Option<&str> = None;
+
+    for (number, line) in page.lines().enumerate() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            continue;
+        }
+        if line.starts_with("# ") {
+            out.push_str(line);
+            out.push_str("\n\n");
+        } else if line.starts_with('>') {
+            out.push_str(line);
+            out.push('\n');
+        } else if let Some(text) = line.strip_prefix("- ") {
+            if !out.ends_with("\n\n") {
+                out.push('\n');
+            }
+            caption = Some(text);
+        } else if let Some(command) = line.strip_prefix('`').and_then(|l| l.strip_suffix('`')) {
+            let Some(text) = caption.take() else {
+                bail!(
+                    "line {}: example command without a `- ` caption",
+                    number + 1
+                );
+            };
+            let command = command.replace("{{", "<").replace("}}", ">");
+            out.push_str(&format!("{text}\n\n```bash\n{command}\n```\n\n"));
+        } else {
+            bail!("line {}: not valid tldr syntax: {line}", number + 1);
+        }
+    }
+
+    if let Some(text) = caption {
+        bail!("example '{text}' has no command");
+    }
+    Ok(format!("{}\n", out.trim_end()))
+}
+
+pub fn from_markdown(sheet: &str) -> Result<String> {
+    let mut out = String::new();
+    let mut caption: Option<String> = None;
+
+    for segment in split_segments(sheet) {
+        match segment {
+            Segment::Text(text) => {
+                for line in text.lines().map(str::trim_end).filter(|l| !l.is_empty()) {
+                    if let Some(previous) = caption.replace(line.to_string()) {
+                        bail!("too complex for tldr: '{previous}' is not followed by a code block");
+                    }
+                    if line.starts_with("# ") && out.is_empty() {
+                        out.push_str(line);
+                        out.push('\n');
+                        caption = None;
+                    } else if line.starts_with('>') {
+                        if !out.contains('>') {
+                            out.push('\n');
+                        }
+                        out.push_str(line);
+                        out.push('\n');
+                        caption = None;
+                    } else if line.starts_with('#') {
+                        bail!("too complex for tldr: headings other than the title ('{line}')");
+                    } else if line.starts_with('|') {
+                        bail!("too complex for tldr: tables are not supported");
+                    }
+                }
+            }
+            Segment::Code { code, .. } => {
+                let Some(text) = caption.take() else {
+                    bail!("too complex for tldr: code block without a caption:\n{code}");
+                };
+                if code.trim().lines().count() != 1 {
+                    bail!("too complex for tldr: the example for '{text}' spans several lines");
+                }
+                let text = text.trim_end_matches(':');
+                let command = to_placeholders(code.trim());
+                out.push_str(&format!("\n- {text}:\n\n`{command}`\n"));
+            }
+            Segment::QuotedCode { .. } => {
+                bail!("too complex for tldr: code blocks inside blockquotes are not supported")
+            }
+            Segment::Marker(_) => {}
+        }
+    }
+
+    if let Some(text) = caption {
+        bail!("too complex for tldr: '{text}' is not followed by a code block");
+    }
+    if !out.starts_with("# ") {
+        bail!("too complex for tldr: the sheet must start with a `# title`");
+    }
+    Ok(out)
+}
+
+/// Rewrites `<placeholder>` as tldr's `{{placeholder}}`, leaving shell
+/// redirections like `< in > out` and heredocs alone.
+fn to_placeholders(command: &str) -> String {
+    let mut out = String::with_capacity(command.len());
+    let mut rest = command;
+    while let Some(start) = rest.find('<') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let end = after.find(['<', '>']);
+        let is_placeholder = end.is_some_and(|end| {
+            end > 0
+                && after.as_bytes()[end] == b'>'
+                && !after.starts_with(char::is_whitespace)
+                && !after[..end].ends_with(char::is_whitespace)
+        });
+        match end {
+            Some(end) if is_placeholder => {
+                out.push_str(&format!("{{{{{}}}}}", &after[..end]));
+                rest = &after[end + 1..];
+            }
+            _ => {
+                out.push('<');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURES: &[&str] = &[
+        include_str!("../tests/fixtures/tldr/tar.md"),
+        include_str!("../tests/fixtures/tldr/jq.md"),
+        include_str!("../tests/fixtures/tldr/whoami.md"),
+    ];
+
+    #[test]
+    fn test_to_markdown_fixture() {
+        let expected = include_str!("../tests/fixtures/tldr/tar.expected.md");
+        assert_eq!(to_markdown(FIXTURES[0]).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for page in FIXTURES {
+            let markdown = to_markdown(page).unwrap();
+            assert_eq!(&from_markdown(&markdown).unwrap(), page);
+        }
+    }
+
+    #[test]
+    fn test_from_markdown_rejects_complex_sheets() {
+        let err = from_markdown("# git\n\n## Branches\n").unwrap_err();
+        assert!(err.to_string().contains("headings other than the title"));
+
+        let err =
+            from_markdown("# git\n\nStatus:\n\n```bash\ngit status\ngit diff\n```\n").unwrap_err();
+        assert!(err.to_string().contains("spans several lines"));
+
+        let err = from_markdown("# git\n\n```bash\ngit status\n```\n").unwrap_err();
+        assert!(err.to_string().contains("without a caption"));
+    }
+
+    #[test]
+    fn test_to_placeholders_skips_redirections() {
+        assert_eq!(to_placeholders("sort < <in> > out"), "sort < {{in}} > out");
+        assert_eq!(to_placeholders("cat <<EOF"), "cat <<EOF");
+    }
+}
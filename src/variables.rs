@@ -0,0 +1,229 @@
+//! Runtime variables: `{{env:VAR}}`, `{{hostname}}`, `{{user}}` and
+//! `{{date:%Y-%m-%d}}` in a sheet are filled in as it is rendered, in prose
+//! and code alike, so examples show this machine's values. `--no-expand` or
+//! `expand: false` in the front matter leave them as written.
+
+use crate::segment::Segment;
+use std::borrow::Cow;
+use std::fs;
+use std::process::Command;
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// `segment` with its variables filled in. Code is filled in before it is
+/// highlighted, so values are colored like the code around them.
+pub fn fill(segment: Segment<'_>) -> Segment<'_> {
+    let now = SystemTime::now();
+    let fill = |text| fill_text(text, now);
+    match segment {
+        Segment::Text(text) => Segment::Text(fill(text)),
+        Segment::Code { lang, code } => Segment::Code {
+            lang,
+            code: fill(code),
+        },
+        Segment::QuotedCode { lang, code } => Segment::QuotedCode {
+            lang,
+            code: fill(code),
+        },
+        Segment::Marker(note) => Segment::Marker(note),
+    }
+}
+
+fn fill_text(text: Cow<'_, str>, now: SystemTime) -> Cow<'_, str> {
+    let filled = match expand(&text, |name| value(name, now)) {
+        Cow::Owned(filled) => Some(filled),
+        Cow::Borrowed(_) => None,
+    };
+    filled.map_or(text, Cow::Owned)
+}
+
+/// Whether a sheet's front matter, without its delimiters, turns variables
+/// off with `expand: false`.
+pub fn switched_off(front_matter: &str) -> bool {
+    front_matter.lines().any(|line| {
+        line.strip_prefix("expand:")
+            .is_some_and(|value| value.trim().trim_matches(['"', '\'']) == "false")
+    })
+}
+
+/// `text` with each one-line `{{variable}}` replaced by what `value` gives
+/// for it. Ones it gives a reason for instead stay as written, noted at
+/// `-v`, so sheets about other templating syntax still read right.
+fn expand<'t>(text: &'t str, value: impl Fn(&str) -> Result<String, String>) -> Cow<'t, str> {
+    if !text.contains("{{") {
+        return Cow::Borrowed(text);
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let token = &rest[start..start + len + 4];
+        if token.contains('\n') {
+            out.push_str(&rest[..start + 2]);
+            rest = &rest[start + 2..];
+            continue;
+        }
+        out.push_str(&rest[..start]);
+        match value(token[2..token.len() - 2].trim()) {
+            Ok(value) => out.push_str(&value),
+            Err(reason) => {
+                log::info!("leaving {token} as written: {reason}");
+                out.push_str(token);
+            }
+        }
+        rest = &rest[start + len + 4..];
+    }
+    out.push_str(rest);
+    Cow::Owned(out)
+}
+
+/// The value of variable `name` at `now`, or why there is none.
+fn value(name: &str, now: SystemTime) -> Result<String, String> {
+    if let Some(var) = name.strip_prefix("env:") {
+        let var = var.trim();
+        return std::env::var(var).map_err(|_| format!("${var} is not set"));
+    }
+    if let Some(format) = name.strip_prefix("date:") {
+        return Ok(format_date(now, format.trim()));
+    }
+    match name {
+        "hostname" => hostname().ok_or_else(|| "the hostname is unknown".to_string()),
+        "user" => std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .map_err(|_| "$USER is not set".to_string()),
+        "date" => Ok(format_date(now, "%F")),
+        _ => Err("not a variable".to_string()),
+    }
+}
+
+/// This machine's name, looked up once.
+fn hostname() -> Option<String> {
+    static HOSTNAME: OnceLock<Option<String>> = OnceLock::new();
+    HOSTNAME
+        .get_or_init(|| {
+            let name = std::env::var("HOSTNAME")
+                .ok()
+                .or_else(|| fs::read_to_string("/etc/hostname").ok())
+                .or_else(|| {
+                    let output = Command::new("hostname").output().ok()?;
+                    String::from_utf8(output.stdout).ok()
+                })?;
+            let name = name.trim();
+            (!name.is_empty()).then(|| name.to_string())
+        })
+        .clone()
+}
+
+/// `time` in UTC as `format` says: `%Y`, `%m`, `%d`, `%H`, `%M`, `%S`, `%F`
+/// for `%Y-%m-%d`, `%T` for `%H:%M:%S` and `%%`. Anything else is kept.
+pub fn format_date(time: SystemTime, format: &str) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let (hour, minute, second) = (secs / 3600 % 24, secs / 60 % 60, secs % 60);
+    let mut out = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => out.push_str(&format!("{year:04}")),
+            Some('m') => out.push_str(&format!("{month:02}")),
+            Some('d') => out.push_str(&format!("{day:02}")),
+            Some('H') => out.push_str(&format!("{hour:02}")),
+            Some('M') => out.push_str(&format!("{minute:02}")),
+            Some('S') => out.push_str(&format!("{second:02}")),
+            Some('F') => out.push_str(&format!("{year:04}-{month:02}-{day:02}")),
+            Some('T') => out.push_str(&format!("{hour:02}:{minute:02}:{second:02}")),
+            Some('%') => out.push('%'),
+            Some(other) => {
+                out.push('%');
+                out.push(other);
+            }
+            None => out.push('%'),
+        }
+    }
+    out
+}
+
+/// Converts days since 1970-01-01 to a (year, month, day) date, after
+/// Howard Hinnant's `civil_from_days`.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// 2024-05-01 09:30:05 UTC.
+    fn time() -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(19_844 * 86_400 + 34_205)
+    }
+
+    #[test]
+    fn test_expand() {
+        let value = |name: &str| match name {
+            "user" => Ok("ada".to_string()),
+            _ => value(name, time()),
+        };
+        assert_eq!(
+            expand("ssh {{user}}@host # {{ date:%F %T }}", value),
+            "ssh ada@host # 2024-05-01 09:30:05"
+        );
+        // Not variables, or not set: left as written
+        assert_eq!(
+            expand("{{ .Values.image }} {{env:CHEETSHEET_TEST_UNSET}}", value),
+            "{{ .Values.image }} {{env:CHEETSHEET_TEST_UNSET}}"
+        );
+        assert_eq!(expand("{{\nuser}} {{user", value), "{{\nuser}} {{user");
+        assert!(matches!(expand("no variables", value), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_fill_code() {
+        let code = Segment::Code {
+            lang: "bash".into(),
+            code: "echo {{env:PATH}}\n".into(),
+        };
+        let Segment::Code { code, .. } = fill(code) else {
+            unreachable!()
+        };
+        assert_eq!(code, format!("echo {}\n", std::env::var("PATH").unwrap()));
+    }
+
+    #[test]
+    fn test_switched_off() {
+        assert!(switched_off("title: Helm\nexpand: false"));
+        assert!(!switched_off("expand: true"));
+        assert!(!switched_off("title: Helm"));
+    }
+
+    #[test]
+    fn test_format_date() {
+        assert_eq!(
+            format_date(time(), "%Y/%m/%d %H:%M:%S"),
+            "2024/05/01 09:30:05"
+        );
+        assert_eq!(format_date(time(), "100%% %q"), "100% %q");
+    }
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19_844), (2024, 5, 1));
+    }
+}
@@ -0,0 +1,139 @@
+//! Walking sheet directories. Hidden entries (`.git`, editor swap files),
+//! `RESERVED_DIRS`, local overlays and files without a sheet extension are
+//! skipped, and a config directory that is a git checkout has its
+//! `.gitignore` honored, so assets next to the sheets cost next to nothing.
+
+use crate::sheet::{RESERVED_DIRS, SHEET_EXTENSIONS, is_overlay_file};
+use ignore::{DirEntry, WalkBuilder};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The sheet files under `dir`, unsorted. Only the top level is read unless
+/// `recursive`.
+pub fn sheets(dir: &Path, recursive: bool) -> impl Iterator<Item = PathBuf> {
+    WalkBuilder::new(dir)
+        .max_depth((!recursive).then_some(1))
+        .follow_links(true)
+        // Rules from above the directory or the user's global git config
+        // have no say over which files are sheets
+        .parents(false)
+        .git_global(false)
+        .filter_entry(|entry| !is_reserved_dir(entry))
+        .build()
+        .flatten()
+        .filter(|entry| entry.file_type().is_some_and(|kind| kind.is_file()))
+        .map(DirEntry::into_path)
+        .filter(|path| is_sheet_file(path))
+}
+
+/// `dir` followed by the `search_paths`, dropping any that resolve to a
+/// directory already listed.
+pub fn roots(dir: &Path, search_paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut seen = HashSet::new();
+    std::iter::once(dir)
+        .chain(search_paths.iter().map(PathBuf::as_path))
+        .filter(|root| seen.insert(fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf())))
+        .map(Path::to_path_buf)
+        .collect()
+}
+
+fn is_reserved_dir(entry: &DirEntry) -> bool {
+    entry.depth() > 0
+        && entry.file_type().is_some_and(|kind| kind.is_dir())
+        && RESERVED_DIRS.iter().any(|dir| entry.file_name() == *dir)
+}
+
+fn is_sheet_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| SHEET_EXTENSIONS.contains(&ext))
+        && !is_overlay_file(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+    use tempfile::TempDir;
+
+    fn names(dir: &Path, recursive: bool) -> Vec<String> {
+        let mut names: Vec<String> = sheets(dir, recursive)
+            .map(|path| {
+                let relative = path.strip_prefix(dir).unwrap();
+                relative.to_string_lossy().replace('\\', "/")
+            })
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_sheets_skips_noise() {
+        let tmp = TempDir::new().unwrap();
+        let dir = tmp.path();
+        for file in [
+            "git.md",
+            ".git.md.swp",
+            "logo.png",
+            "cloud/aws.org",
+            "cloud/.hidden.md",
+            ".cache/notes.md",
+            "templates/t.md",
+            "remotes/docker.local.md",
+            "build/out.md",
+        ] {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        assert_eq!(
+            names(dir, true),
+            ["build/out.md", "cloud/aws.org", "git.md"]
+        );
+        assert_eq!(names(dir, false), ["git.md"]);
+
+        // `.gitignore` only counts in a git checkout
+        fs::write(dir.join(".gitignore"), "build/\n").unwrap();
+        assert_eq!(names(dir, true).len(), 3);
+        fs::create_dir_all(dir.join(".git")).unwrap();
+        assert_eq!(names(dir, true), ["cloud/aws.org", "git.md"]);
+    }
+
+    #[test]
+    fn test_roots_dedups_by_canonical_path() {
+        let tmp = TempDir::new().unwrap();
+        let dir = tmp.path().join("sheets");
+        let other = tmp.path().join("other");
+        fs::create_dir_all(&dir).unwrap();
+        fs::create_dir_all(&other).unwrap();
+        let search_paths = [
+            tmp.path().join("sheets/../sheets"),
+            other.clone(),
+            other.clone(),
+        ];
+        assert_eq!(roots(&dir, &search_paths), [dir, other]);
+    }
+
+    #[test]
+    fn test_sheets_is_fast_on_large_trees() {
+        let tmp = TempDir::new().unwrap();
+        let dir = tmp.path();
+        fs::create_dir_all(dir.join(".git/objects")).unwrap();
+        fs::create_dir_all(dir.join("assets")).unwrap();
+        for i in 0..5_000 {
+            fs::write(dir.join(format!(".git/objects/{i}")), "").unwrap();
+            fs::write(dir.join(format!("assets/{i}.png")), "").unwrap();
+        }
+        fs::write(dir.join("git.md"), "").unwrap();
+
+        let start = Instant::now();
+        assert_eq!(names(dir, true), ["git.md"]);
+        // `.git` isn't entered at all, so only the assets are listed
+        assert!(
+            start.elapsed() < Duration::from_secs(1),
+            "{:?}",
+            start.elapsed()
+        );
+    }
+}
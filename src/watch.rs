@@ -0,0 +1,317 @@
+//! `--watch`: re-render a sheet whenever its content changes.
+//!
+//! Editors often touch a file several times per save (truncate, write,
+//! rename, chmod). The file is polled and a change only counts once its
+//! content hash differs from what was last rendered and has stayed the
+//! same for `DEBOUNCE`, so a save renders once and no-op writes not at all.
+//!
+//! A sheet that is deleted (or renamed away) for longer than `DEBOUNCE` is
+//! reported once as `Update::Missing`, and whatever content shows up at the
+//! path again renders, even if it's what was rendered before.
+//!
+//! A re-render is repainted by `Screen`, which only redraws the lines that
+//! differ from the last frame so an edit doesn't flash the whole sheet.
+
+use crate::template;
+use anyhow::Result;
+use cheet_sheet::sheet;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use termimad::crossterm::cursor::MoveTo;
+use termimad::crossterm::queue;
+use termimad::crossterm::terminal::{Clear, ClearType};
+use unicode_width::UnicodeWidthChar;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+const DEBOUNCE: Duration = Duration::from_millis(150);
+
+fn hash(content: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Decides when a file's content has settled on something new.
+pub struct ChangeDetector {
+    /// `None` once forgotten, so any content is new.
+    rendered: Option<u64>,
+    /// A new hash and when it was first seen.
+    pending: Option<(u64, Instant)>,
+    debounce: Duration,
+}
+
+impl ChangeDetector {
+    pub fn new(content: &[u8], debounce: Duration) -> Self {
+        ChangeDetector {
+            rendered: Some(hash(content)),
+            pending: None,
+            debounce,
+        }
+    }
+
+    /// Feeds the content read at `now`; true when it should be rendered.
+    /// `None` (the file is missing, e.g. mid-save) is not a change.
+    pub fn observe(&mut self, content: Option<&[u8]>, now: Instant) -> bool {
+        let Some(content) = content else {
+            return false;
+        };
+        let current = hash(content);
+        if Some(current) == self.rendered {
+            self.pending = None;
+            return false;
+        }
+        match self.pending {
+            Some((pending, since)) if pending == current => {
+                if now.duration_since(since) < self.debounce {
+                    return false;
+                }
+                self.rendered = Some(current);
+                self.pending = None;
+                true
+            }
+            _ => {
+                self.pending = Some((current, now));
+                false
+            }
+        }
+    }
+
+    /// Makes the next settled content render, whatever it is.
+    pub fn forget(&mut self) {
+        self.rendered = None;
+        self.pending = None;
+    }
+}
+
+/// What `run` asks to be shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Update {
+    /// The sheet changed and should be rendered again.
+    Changed,
+    /// The sheet has gone missing; it renders again once it's back.
+    Missing,
+}
+
+/// The `updated 09:30:00 UTC` line under a re-rendered sheet, dimmed when
+/// `color` is on.
+pub fn stamp(time: SystemTime, color: bool) -> String {
+    let line = format!("updated {} UTC", &template::rfc3339(time)[11..19]);
+    match color {
+        true => format!("\x1b[2m{line}\x1b[0m"),
+        false => line,
+    }
+}
+
+/// The frame last painted at the top of the terminal.
+#[derive(Default)]
+pub struct Screen {
+    /// `None` until the screen has been cleared once.
+    lines: Option<Vec<String>>,
+}
+
+impl Screen {
+    /// Turns the screen into `frame`, a `columns` x `rows` terminal, moving
+    /// the cursor only to lines that changed. When either frame doesn't fit,
+    /// so rows no longer map to lines, the screen is cleared and redrawn.
+    pub fn repaint(
+        &mut self,
+        out: &mut impl Write,
+        frame: &str,
+        size: Option<(u16, u16)>,
+    ) -> io::Result<()> {
+        let lines: Vec<String> = frame.lines().map(String::from).collect();
+        let fits = |lines: &[String]| {
+            size.is_some_and(|(columns, rows)| {
+                lines.len() < usize::from(rows)
+                    && lines
+                        .iter()
+                        .all(|line| visible_width(line) <= usize::from(columns))
+            })
+        };
+        match self
+            .lines
+            .as_deref()
+            .filter(|last| fits(last) && fits(&lines))
+        {
+            Some(last) => {
+                for (row, line) in lines.iter().enumerate() {
+                    if last.get(row) != Some(line) {
+                        queue!(out, MoveTo(0, row as u16), Clear(ClearType::UntilNewLine))?;
+                        write!(out, "{line}")?;
+                    }
+                }
+                queue!(out, MoveTo(0, lines.len() as u16))?;
+                if last.len() > lines.len() {
+                    queue!(out, Clear(ClearType::FromCursorDown))?;
+                }
+            }
+            None => {
+                queue!(out, Clear(ClearType::All), MoveTo(0, 0))?;
+                write!(out, "{frame}")?;
+            }
+        }
+        self.lines = Some(lines);
+        Ok(())
+    }
+}
+
+/// Columns `line` takes, not counting color escapes. Other escapes, like an
+/// inline image, take space no one can tell, so they count as too wide.
+fn visible_width(line: &str) -> usize {
+    let mut width = 0;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            width += c.width().unwrap_or(0);
+            continue;
+        }
+        if chars.next() != Some('[') {
+            return usize::MAX;
+        }
+        // Parameters up to the final byte, e.g. `38;5;117m`
+        chars.by_ref().find(|c| ('@'..='~').contains(c));
+    }
+    width
+}
+
+/// The content of the file at `path`, or of all parts of a sheet split
+/// into numbered files in directory `path`.
+fn read(path: &Path) -> Option<Vec<u8>> {
+    if !path.is_dir() {
+        return fs::read(path).ok();
+    }
+    let mut content = Vec::new();
+    for part in sheet::sheet_parts(path) {
+        content.extend(fs::read(part).ok()?);
+    }
+    Some(content)
+}
+
+/// Calls `render` each time the file at `path` changes or goes missing,
+/// until interrupted.
+pub fn run(path: &Path, mut render: impl FnMut(Update) -> Result<()>) -> Result<()> {
+    let initial = read(path).unwrap_or_default();
+    let mut detector = ChangeDetector::new(&initial, DEBOUNCE);
+    // When the file was first seen missing, and whether that was reported
+    let mut missing: Option<(Instant, bool)> = None;
+    loop {
+        thread::sleep(POLL_INTERVAL);
+        let content = read(path);
+        let now = Instant::now();
+        match (&content, &mut missing) {
+            (None, Some((since, reported))) => {
+                if !*reported && now.duration_since(*since) >= DEBOUNCE {
+                    *reported = true;
+                    detector.forget();
+                    render(Update::Missing)?;
+                }
+            }
+            (None, None) => missing = Some((now, false)),
+            (Some(_), _) => missing = None,
+        }
+        if detector.observe(content.as_deref(), now) {
+            render(Update::Changed)?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    fn repaint(screen: &mut Screen, frame: &str, size: (u16, u16)) -> String {
+        let mut out = Vec::new();
+        screen.repaint(&mut out, frame, Some(size)).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_repaint_only_changed_lines() {
+        let mut screen = Screen::default();
+        let first = "# Git\n\x1b[1mls\x1b[0m\nold\ngone\n";
+        assert_eq!(
+            repaint(&mut screen, first, (80, 24)),
+            format!("\x1b[2J\x1b[1;1H{first}")
+        );
+        assert_eq!(
+            repaint(&mut screen, "# Git\n\x1b[1mls\x1b[0m\nnew\n", (80, 24)),
+            "\x1b[3;1H\x1b[Knew\x1b[4;1H\x1b[J"
+        );
+        assert_eq!(
+            repaint(&mut screen, "# Git\n\x1b[1mls\x1b[0m\nnew\n", (80, 24)),
+            "\x1b[4;1H"
+        );
+
+        // Taller than the terminal, or wrapping, means a full redraw
+        let tall = "line\n".repeat(30);
+        assert!(repaint(&mut screen, &tall, (80, 24)).starts_with("\x1b[2J"));
+        let wide = "x".repeat(100);
+        assert!(repaint(&mut screen, "short\n", (80, 24)).starts_with("\x1b[2J"));
+        assert!(repaint(&mut screen, &wide, (80, 24)).starts_with("\x1b[2J"));
+    }
+
+    #[test]
+    fn test_visible_width() {
+        assert_eq!(visible_width("\x1b[38;5;117mls -l\x1b[0m"), 5);
+        assert_eq!(visible_width("│ λ"), 3);
+        assert_eq!(visible_width("\x1b[1m列出\x1b[0m ls"), 7);
+        assert_eq!(visible_width("\x1b_Gf=100;data\x1b\\"), usize::MAX);
+    }
+
+    #[test]
+    fn test_change_renders_once_settled() {
+        let start = Instant::now();
+        let mut detector = ChangeDetector::new(b"old", 100 * MS);
+        assert!(!detector.observe(Some(b"new"), start));
+        assert!(!detector.observe(Some(b"new"), start + 50 * MS));
+        assert!(detector.observe(Some(b"new"), start + 120 * MS));
+        // Already rendered
+        assert!(!detector.observe(Some(b"new"), start + 300 * MS));
+    }
+
+    #[test]
+    fn test_no_op_and_transient_writes_are_ignored() {
+        let start = Instant::now();
+        let mut detector = ChangeDetector::new(b"same", 100 * MS);
+        assert!(!detector.observe(Some(b"same"), start));
+        assert!(!detector.observe(None, start + 10 * MS));
+        // Truncated mid-save, then restored before the debounce ran out
+        assert!(!detector.observe(Some(b""), start + 20 * MS));
+        assert!(!detector.observe(Some(b"same"), start + 200 * MS));
+        assert!(!detector.observe(Some(b"same"), start + 400 * MS));
+    }
+
+    #[test]
+    fn test_forgotten_content_renders_again() {
+        let start = Instant::now();
+        let mut detector = ChangeDetector::new(b"same", 100 * MS);
+        detector.forget();
+        assert!(!detector.observe(Some(b"same"), start));
+        assert!(detector.observe(Some(b"same"), start + 120 * MS));
+        assert!(!detector.observe(Some(b"same"), start + 300 * MS));
+    }
+
+    #[test]
+    fn test_stamp() {
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(34_200 + 7);
+        assert_eq!(stamp(time, false), "updated 09:30:07 UTC");
+        assert_eq!(stamp(time, true), "\x1b[2mupdated 09:30:07 UTC\x1b[0m");
+    }
+
+    #[test]
+    fn test_content_still_changing_restarts_debounce() {
+        let start = Instant::now();
+        let mut detector = ChangeDetector::new(b"a", 100 * MS);
+        assert!(!detector.observe(Some(b"ab"), start));
+        assert!(!detector.observe(Some(b"abc"), start + 90 * MS));
+        assert!(!detector.observe(Some(b"abc"), start + 150 * MS));
+        assert!(detector.observe(Some(b"abc"), start + 200 * MS));
+    }
+}
@@ -0,0 +1,94 @@
+//! `new --wizard`: asks a few questions and writes a skeleton sheet.
+
+use anyhow::{Context, Result};
+use std::io::{BufRead, Write};
+
+const DEFAULT_SECTIONS: &str = "Basics, Examples";
+
+pub struct Answers {
+    pub title: String,
+    pub description: String,
+    pub sections: Vec<String>,
+}
+
+/// Prompts on `output` and reads one answer per line from `input`. Blank
+/// answers and end of input take the defaults, so piped input needs only
+/// the lines it wants to set. Pass `interactive: false` to skip the prompts.
+pub fn ask(
+    command: &str,
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+    interactive: bool,
+) -> Result<Answers> {
+    let mut prompt = |question: &str, default: &str| -> Result<String> {
+        if interactive {
+            write!(output, "{question} [{default}]: ")?;
+            output.flush()?;
+        }
+        let mut line = String::new();
+        input
+            .read_line(&mut line)
+            .context("failed to read answer")?;
+        let answer = line.trim();
+        Ok(if answer.is_empty() { default } else { answer }.to_string())
+    };
+    let title = prompt("Title", &format!("{command} Cheat Sheet"))?;
+    let description = prompt("Short description", "")?;
+    let sections = prompt("Sections (comma-separated)", DEFAULT_SECTIONS)?
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+        .collect();
+    Ok(Answers {
+        title,
+        description,
+        sections,
+    })
+}
+
+/// A markdown sheet with front matter, one heading per section and an
+/// example block under each.
+pub fn skeleton(command: &str, answers: &Answers) -> String {
+    let mut out = String::new();
+    if !answers.description.is_empty() {
+        out.push_str(&format!(
+            "---\ndescription: {}\n---\n\n",
+            answers.description
+        ));
+    }
+    out.push_str(&format!("# {}\n", answers.title));
+    for section in &answers.sections {
+        out.push_str(&format!(
+            "\n## {section}\n\nWhat this does:\n\n```bash\n{command} <args>\n```\n"
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ask_uses_defaults_for_blank_answers() {
+        let mut input = "\nRemote sessions\n".as_bytes();
+        let answers = ask("ssh", &mut input, &mut Vec::new(), false).unwrap();
+        assert_eq!(answers.title, "ssh Cheat Sheet");
+        assert_eq!(answers.description, "Remote sessions");
+        assert_eq!(answers.sections, ["Basics", "Examples"]);
+    }
+
+    #[test]
+    fn test_skeleton() {
+        let answers = Answers {
+            title: "SSH".into(),
+            description: "Remote sessions".into(),
+            sections: vec!["Keys".into()],
+        };
+        assert_eq!(
+            skeleton("ssh", &answers),
+            "---\ndescription: Remote sessions\n---\n\n# SSH\n\n## Keys\n\nWhat this does:\n\n```bash\nssh <args>\n```\n"
+        );
+    }
+}
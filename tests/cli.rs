@@ -1,10 +1,45 @@
 use assert_cmd::Command;
+use assert_cmd::assert::OutputAssertExt;
 use predicates::prelude::*;
 use std::fs;
 use tempfile::TempDir;
 
 fn cmd() -> Command {
-    assert_cmd::cargo_bin_cmd!("cheetsheet")
+    Command::from_std(bin())
+}
+
+/// The binary, for tests that spawn it themselves.
+fn bin() -> std::process::Command {
+    let mut command = std::process::Command::new(env!("CARGO_BIN_EXE_cheetsheet"));
+    command.envs(home());
+    command
+}
+
+thread_local! {
+    /// Each test runs on a thread of its own, so this is a home per test,
+    /// removed when the test ends.
+    static HOME: TempDir = TempDir::new().unwrap();
+}
+
+/// Variables pointing the home, data, cache and config directories into the
+/// test's own home, so state like history and caches never reaches the
+/// real ones. A test can still set one to a directory it looks into.
+fn home() -> Vec<(&'static str, std::path::PathBuf)> {
+    let home = HOME.with(|home| home.path().to_path_buf());
+    vec![
+        ("XDG_DATA_HOME", home.join(".local/share")),
+        ("XDG_CACHE_HOME", home.join(".cache")),
+        ("XDG_CONFIG_HOME", home.join(".config")),
+        ("HOME", home),
+    ]
+}
+
+/// The binary with stdin redirected from `file`, whose contents are there
+/// before the process starts, unlike `write_stdin`'s pipe.
+fn cmd_with_stdin(file: &std::path::Path) -> std::process::Command {
+    let mut command = bin();
+    command.stdin(fs::File::open(file).unwrap());
+    command
 }
 
 #[test]
@@ -28,7 +63,7 @@ fn test_missing_sheet() {
             tmp.path().to_str().unwrap(),
         ])
         .assert()
-        .failure()
+        .code(3)
         .stderr(predicate::str::contains(
             "No cheatsheet found for 'nonexistent-cmd-xyz'",
         ));
@@ -45,3 +80,2865 @@ fn test_found_sheet() {
         .assert()
         .success();
 }
+
+#[test]
+fn test_open_missing_sheet() {
+    let tmp = TempDir::new().unwrap();
+    cmd()
+        .args(["open", "nonexistent-cmd-xyz"])
+        .args(["--config-dir", tmp.path().to_str().unwrap()])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains(
+            "No cheatsheet found for 'nonexistent-cmd-xyz'",
+        ));
+}
+
+#[test]
+fn test_missing_command_is_usage_error() {
+    cmd()
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("Usage"));
+}
+
+#[test]
+fn test_json_errors() {
+    let tmp = TempDir::new().unwrap();
+    let output = cmd()
+        .args(["kubectl", "--json-errors", "--config-dir"])
+        .arg(tmp.path())
+        .assert()
+        .code(3)
+        .get_output()
+        .stderr
+        .clone();
+    let error: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(error["kind"], "not_found");
+    assert_eq!(error["code"], 3);
+    assert!(
+        error["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("No cheatsheet found for 'kubectl'")
+    );
+}
+
+#[test]
+fn test_found_org_sheet() {
+    let tmp = TempDir::new().unwrap();
+    let sheet = tmp.path().join("emacs.org");
+    fs::write(
+        &sheet,
+        "* Emacs\n#+BEGIN_SRC elisp\n(save-buffer)\n#+END_SRC\n",
+    )
+    .unwrap();
+
+    cmd()
+        .args(["emacs", "--config-dir", tmp.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Emacs"));
+}
+
+#[test]
+fn test_format_flag_overrides_extension() {
+    let tmp = TempDir::new().unwrap();
+    let sheet = tmp.path().join("notes.md");
+    fs::write(&sheet, "Git\n=====\n\n.. todo:: later\n").unwrap();
+
+    cmd()
+        .args(["notes", "--format", "rst"])
+        .args(["--config-dir", tmp.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("=====").not())
+        .stdout(predicate::str::contains("[todo: later]"));
+}
+
+#[test]
+fn test_encoding() {
+    let tmp = TempDir::new().unwrap();
+    let dir = tmp.path().to_str().unwrap();
+    fs::copy("tests/fixtures/latin1.md", tmp.path().join("cafe.md")).unwrap();
+
+    cmd()
+        .args(["cafe", "--config-dir", dir])
+        .assert()
+        .code(4)
+        .stderr(predicate::str::contains("--encoding latin1"));
+    cmd()
+        .args(["cafe", "--encoding", "latin1", "--config-dir", dir])
+        .args(["--color", "never"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Café"))
+        .stdout(predicate::str::contains("Résumé of the naïve commands"))
+        .stdout(predicate::str::contains("echo \"© 2024\""));
+    cmd()
+        .args(["-", "--encoding", "iso-8859-1", "--color", "never"])
+        .write_stdin(fs::read("tests/fixtures/latin1.md").unwrap())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Café"));
+    cmd()
+        .args(["cafe", "--encoding", "klingon", "--config-dir", dir])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("unknown encoding 'klingon'"));
+    // Listings read the sheets the same way
+    let mut tagged = b"---\ntags: [caf\xe9]\n---\n".to_vec();
+    tagged.extend(fs::read("tests/fixtures/latin1.md").unwrap());
+    fs::write(tmp.path().join("tagged.md"), tagged).unwrap();
+    cmd()
+        .args(["tags", "--encoding", "latin1", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout("   1  café\n");
+}
+
+#[test]
+fn test_found_adoc_sheet() {
+    let tmp = TempDir::new().unwrap();
+    fs::copy(
+        "tests/fixtures/runbook.adoc",
+        tmp.path().join("deploy.adoc"),
+    )
+    .unwrap();
+
+    cmd()
+        .args(["deploy", "--config-dir", tmp.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Deploy Runbook"))
+        .stdout(predicate::str::contains("Run from the bastion host."))
+        .stdout(predicate::str::contains("----").not())
+        .stdout(predicate::str::contains("[source,bash]").not());
+}
+
+#[test]
+fn test_syntax_cache_is_created() {
+    let tmp = TempDir::new().unwrap();
+    let cache = TempDir::new().unwrap();
+    fs::write(tmp.path().join("sh.md"), "```bash\nls\n```\n").unwrap();
+
+    cmd()
+        .args([
+            "sh",
+            "--syntax-cache",
+            // Plain output never loads syntect
+            "--color",
+            "always",
+            "--config-dir",
+            tmp.path().to_str().unwrap(),
+        ])
+        .env("XDG_CACHE_HOME", cache.path())
+        .assert()
+        .success();
+    let dumps = cache.path().join("cheetsheet");
+    let version_dir = fs::read_dir(&dumps)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    assert!(version_dir.join("syntaxes.bin").exists());
+}
+
+#[test]
+fn test_convert_tldr_to_md() {
+    let tmp = TempDir::new().unwrap();
+    let output = tmp.path().join("tar.md");
+    cmd()
+        .args(["convert", "--from", "tldr", "--to", "md"])
+        .arg("tests/fixtures/tldr/tar.md")
+        .arg("-o")
+        .arg(&output)
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(output).unwrap(),
+        fs::read_to_string("tests/fixtures/tldr/tar.expected.md").unwrap()
+    );
+}
+
+#[test]
+fn test_convert_complex_sheet_to_tldr_fails() {
+    let tmp = TempDir::new().unwrap();
+    let sheet = tmp.path().join("git.md");
+    fs::write(&sheet, "# git\n\n## Branches\n").unwrap();
+    cmd()
+        .args(["convert", "--from", "md", "--to", "tldr"])
+        .arg(&sheet)
+        .assert()
+        .code(6)
+        .stderr(predicate::str::contains("too complex for tldr"));
+}
+
+#[test]
+fn test_stdin_sheet() {
+    cmd()
+        .arg("-")
+        .write_stdin("# Piped Notes\n\n```bash\necho hi\n```\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Piped Notes"));
+}
+
+#[test]
+fn test_stdin_large_sheet() {
+    let line = "* a fairly ordinary list item with `code` in it\n";
+    let content = format!("# Big\n\n{}", line.repeat(50_000));
+    cmd()
+        .arg("-")
+        .write_stdin(content)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Big"));
+}
+
+#[test]
+fn test_stdin_with_format() {
+    cmd()
+        .args(["-", "--format", "org"])
+        .write_stdin("* Org Title\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Org Title"))
+        .stdout(predicate::str::contains("* Org").not());
+}
+
+#[test]
+fn test_file_flag_bypasses_lookup() {
+    let tmp = TempDir::new().unwrap();
+    let readme = tmp.path().join("README.md");
+    fs::write(&readme, "# Project Readme\n").unwrap();
+
+    cmd()
+        .arg("--file")
+        .arg(&readme)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Project Readme"));
+    cmd()
+        .arg(&readme)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Project Readme"));
+}
+
+#[test]
+fn test_file_flag_missing_file() {
+    cmd()
+        .args(["--file", "does/not/exist.md"])
+        .assert()
+        .code(4)
+        .stderr(predicate::str::contains("failed to read does/not/exist.md"))
+        .stderr(predicate::str::contains("Tip:").not());
+}
+
+#[test]
+fn test_list_and_nested_lookup() {
+    let tmp = TempDir::new().unwrap();
+    fs::create_dir_all(tmp.path().join("cloud")).unwrap();
+    fs::write(tmp.path().join("cloud/aws.md"), "# AWS Notes\n").unwrap();
+    fs::write(tmp.path().join("git.md"), "# Git\n").unwrap();
+    let dir = tmp.path().to_str().unwrap();
+
+    cmd()
+        .args(["list", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout("cloud/\n  aws\ngit\n");
+    cmd()
+        .args(["list", "--flat", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout("git\n");
+    for name in ["cloud/aws", "aws"] {
+        cmd()
+            .args([name, "--config-dir", dir])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("AWS Notes"));
+    }
+    cmd()
+        .args(["aws", "--flat", "--config-dir", dir])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_global_flags_before_subcommand() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(tmp.path().join("git.md"), "# Git\n").unwrap();
+    let dir = tmp.path().to_str().unwrap();
+
+    cmd()
+        .args(["--config-dir", dir, "--flat", "list"])
+        .assert()
+        .success()
+        .stdout("git\n");
+    // After a sheet name, a subcommand's name is another sheet
+    cmd()
+        .args(["--config-dir", dir, "git", "list"])
+        .assert()
+        .code(3)
+        .stdout(predicate::str::contains("Git"))
+        .stderr(predicate::str::contains("No cheatsheet found for 'list'"));
+}
+
+#[test]
+fn test_list_json() {
+    let output = cmd()
+        .args(["list", "--json", "--color", "always"])
+        .args(["--config-dir", "tests/fixtures/list"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let mut sheets: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    let fixtures = std::path::absolute("tests/fixtures/list").unwrap();
+    for sheet in sheets.as_array_mut().unwrap() {
+        let modified = sheet.as_object_mut().unwrap().remove("modified").unwrap();
+        // RFC 3339 in UTC, e.g. 2024-05-01T09:30:05Z
+        let modified = modified.as_str().unwrap();
+        assert!(modified.len() == 20 && modified.ends_with('Z') && modified.contains('T'));
+    }
+    let path = |name: &str| fixtures.join(name).to_str().unwrap().to_string();
+    assert_eq!(
+        sheets,
+        serde_json::json!([
+            {
+                "name": "cloud/aws",
+                "path": path("cloud/aws.md"),
+                "source": "user",
+                "title": "AWS CLI",
+                "tags": [],
+                "size": 33,
+                "draft": false,
+            },
+            {
+                "name": "git",
+                "path": path("git.md"),
+                "source": "user",
+                "title": "Git Basics",
+                "tags": ["vcs", "git"],
+                "size": 86,
+                "draft": true,
+            },
+            {
+                "name": "remotes/docker",
+                "path": path("remotes/docker.md"),
+                "source": "remote",
+                "title": null,
+                "tags": [],
+                "size": 35,
+                "draft": false,
+            },
+        ])
+    );
+}
+
+#[test]
+fn test_follow_aliases() {
+    let tmp = TempDir::new().unwrap();
+    fs::create_dir_all(tmp.path().join("cloud")).unwrap();
+    fs::write(tmp.path().join("cloud/aws.md"), "# AWS Notes\n").unwrap();
+    let dir = tmp.path().to_str().unwrap();
+    let path = tmp.path().join("cloud/aws.md");
+    let chain = format!("aws → cloud/aws → {}", path.display());
+
+    cmd()
+        .args(["aws", "--print-path-only", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout(format!("{chain}\n"));
+    cmd()
+        .args(["aws", "--follow-aliases", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("AWS Notes"))
+        .stderr(format!("{chain}\n"));
+    cmd()
+        .args(["-", "--print-path-only"])
+        .write_stdin("# Piped\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("stdin has no path"));
+}
+
+#[test]
+fn test_ascii_output() {
+    let sheet = "# Keys\n\n| Key | Action |\n|-----|--------|\n| a | b |\n\n---\n\n* item\n\n> [!TIP]\n> Use it\n";
+    let output = cmd()
+        .args(["-", "--ascii"])
+        .write_stdin(sheet)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(output.stdout.is_ascii());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("(+) Tip"));
+}
+
+/// Puts a fake `man` first on PATH that knows a single page, `frob`, asked
+/// for after `--`.
+#[cfg(unix)]
+fn fake_man_path(dir: &std::path::Path) -> String {
+    use std::os::unix::fs::PermissionsExt;
+    let script = dir.join("man");
+    fs::write(
+        &script,
+        "#!/bin/sh\n[ \"$1\" = -w ] && { [ \"$2\" = -- ] && [ \"$3\" = frob ]; exit $?; }\n[ \"$1\" = -- ] && [ \"$2\" = frob ] || exit 16\nprintf 'N\\bNA\\bAM\\bME\\bE\\n    frob - width %s\\n' \"$MANWIDTH\"\n",
+    )
+    .unwrap();
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+    format!("{}:{}", dir.display(), std::env::var("PATH").unwrap())
+}
+
+/// Puts a fake `age` first on PATH that "encrypts" with a header line
+/// naming the key, and only decrypts with the identity file holding it.
+#[cfg(unix)]
+fn fake_age_path(dir: &std::path::Path) -> String {
+    use std::os::unix::fs::PermissionsExt;
+    let bin = dir.join("bin");
+    fs::create_dir(&bin).unwrap();
+    let script = bin.join("age");
+    fs::write(
+        &script,
+        r#"#!/bin/sh
+mode=$1; shift; key=pass
+while [ $# -gt 0 ]; do
+  case $1 in
+    --recipient) key=$2; shift 2 ;;
+    --identity) key=$(cat "$2"); shift 2 ;;
+    --output) file=$2; shift 2 ;;
+    *) file=$1; shift ;;
+  esac
+done
+case $mode in
+  --encrypt)
+    [ "$key" = broken ] && { echo "age: error: malformed recipient" >&2; exit 1; }
+    { echo "AGE $key"; cat; } > "$file" ;;
+  --decrypt)
+    [ "$(head -n 1 "$file")" = "AGE $key" ] || { echo "age: error: no identity matched any of the recipients" >&2; exit 1; }
+    tail -n +2 "$file" ;;
+esac
+"#,
+    )
+    .unwrap();
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+    format!("{}:{}", bin.display(), std::env::var("PATH").unwrap())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_encrypted_sheet() {
+    let tmp = TempDir::new().unwrap();
+    let path = fake_age_path(tmp.path());
+    let sheets = tmp.path().join("sheets");
+    let temp = tmp.path().join("temp");
+    fs::create_dir(&sheets).unwrap();
+    fs::create_dir(&temp).unwrap();
+    fs::write(sheets.join("secret.md"), "# Secret\n\nhttps://internal\n").unwrap();
+    let key = tmp.path().join("key.txt");
+    fs::write(&key, "k1").unwrap();
+    let config = tmp.path().join("cheetsheet.toml");
+    fs::write(
+        &config,
+        format!(
+            "age_recipients = [\"k1\"]\nage_identities = [\"{}\"]\n",
+            key.display()
+        ),
+    )
+    .unwrap();
+    let run = |args: &[&str]| {
+        let mut cmd = cmd();
+        cmd.args(args)
+            .arg("--config-dir")
+            .arg(&sheets)
+            .arg("--config")
+            .arg(&config)
+            .env("PATH", &path)
+            .env("TMPDIR", &temp);
+        cmd
+    };
+
+    run(&["encrypt", "secret"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("secret.md.age"));
+    assert!(!sheets.join("secret.md").exists());
+    let encrypted = fs::read_to_string(sheets.join("secret.md.age")).unwrap();
+    assert!(encrypted.starts_with("AGE k1\n"));
+    run(&["secret", "--plain"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("https://internal"));
+
+    // Edited through a private file that is gone afterwards
+    run(&["edit", "secret"])
+        .env("EDITOR", "sed -i s/internal/changed/")
+        .assert()
+        .success();
+    assert_eq!(fs::read_dir(&temp).unwrap().count(), 0);
+    run(&["secret", "--plain"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("https://changed"));
+
+    // Changes that can't be encrypted stay in the private file
+    let settings = fs::read_to_string(&config).unwrap();
+    fs::write(&config, settings.replace("[\"k1\"]", "[\"broken\"]")).unwrap();
+    run(&["edit", "secret"])
+        .env("EDITOR", "sed -i s/changed/lost/")
+        .assert()
+        .code(7)
+        .stderr(predicate::str::contains(
+            "your changes are kept unencrypted in",
+        ));
+    let kept: Vec<_> = fs::read_dir(&temp).unwrap().collect();
+    assert_eq!(kept.len(), 1);
+    let kept = kept[0].as_ref().unwrap().path();
+    assert!(fs::read_to_string(kept).unwrap().contains("https://lost"));
+    assert_eq!(
+        fs::read_dir(&sheets).unwrap().count(),
+        1,
+        "no staged file is left beside the sheet"
+    );
+    fs::write(&config, settings).unwrap();
+    run(&["secret", "--plain"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("https://changed"));
+
+    fs::write(&key, "k2").unwrap();
+    run(&["secret"])
+        .assert()
+        .code(7)
+        .stderr(
+            predicate::str::contains("failed to decrypt").and(predicate::str::contains(
+                "no identity matched any of the recipients",
+            )),
+        );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_shell_hook() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(tmp.path().join("frob.md"), "# Frob\n\nfrob --all\n").unwrap();
+    let hook = cmd()
+        .args(["shell-hook", "bash", "--yes"])
+        .output()
+        .unwrap();
+    let hook = String::from_utf8(hook.stdout).unwrap();
+    let bin = std::path::Path::new(env!("CARGO_BIN_EXE_cheetsheet"));
+    let path = format!(
+        "{}:{}",
+        bin.parent().unwrap().display(),
+        std::env::var("PATH").unwrap()
+    );
+    let bash = |command: &str| {
+        let mut bash = Command::new("bash");
+        bash.args(["-c", &format!("{hook}\n{command}")])
+            .envs(home())
+            .env("PATH", &path)
+            .env("CHEETSHEET_CONFIG_DIR", tmp.path())
+            .env("CHEETSHEET_FALLBACKS", "man");
+        bash
+    };
+
+    bash("frob")
+        .assert()
+        .code(127)
+        .stdout(predicate::str::contains("frob --all"))
+        .stderr(predicate::str::contains(
+            "frob: command not found; its cheatsheet:",
+        ));
+    bash("nosuch")
+        .assert()
+        .code(127)
+        .stdout("")
+        .stderr("nosuch: command not found\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_man_fallback() {
+    let tmp = TempDir::new().unwrap();
+    let path = fake_man_path(tmp.path());
+    let dir = tmp.path().to_str().unwrap();
+
+    cmd()
+        .args(["frob", "--fallback", "man", "--config-dir", dir])
+        .env("PATH", &path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("showing its man page"))
+        .stdout(predicate::str::contains("NAME\n    frob - width"));
+    // Not configured, or no such page: the usual error
+    cmd()
+        .args(["frob", "--config-dir", dir])
+        .env("PATH", &path)
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("No cheatsheet found for 'frob'"));
+    cmd()
+        .args(["nope", "--fallback", "man", "--config-dir", dir])
+        .env("PATH", &path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("No cheatsheet found for 'nope'"));
+}
+
+#[test]
+fn test_remote_sheet_overlay() {
+    let tmp = TempDir::new().unwrap();
+    let remote = tmp.path().join("remotes/community");
+    fs::create_dir_all(&remote).unwrap();
+    fs::write(remote.join("docker.md"), "# Docker Upstream\n").unwrap();
+    fs::write(tmp.path().join("docker.local.md"), "My own notes\n").unwrap();
+    let dir = tmp.path().to_str().unwrap();
+
+    cmd()
+        .args(["docker", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Docker Upstream"))
+        .stdout(predicate::str::contains("My own notes"));
+    cmd()
+        .args(["docker", "--no-overlay", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("My own notes").not());
+    cmd()
+        .args(["list", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout("remotes/\n  community/\n    docker (+local)\n");
+}
+
+#[test]
+fn test_edit_opens_overlay_for_remote_sheet() {
+    let tmp = TempDir::new().unwrap();
+    let remote = tmp.path().join("remotes/community");
+    fs::create_dir_all(&remote).unwrap();
+    fs::write(remote.join("docker.md"), "# Docker\n").unwrap();
+    let dir = tmp.path().to_str().unwrap();
+
+    cmd()
+        .args(["edit", "docker", "--config-dir", dir])
+        .env_remove("VISUAL")
+        .env("EDITOR", "echo")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("docker.local.md"));
+    cmd()
+        .args(["edit", "docker", "--base", "--config-dir", dir])
+        .env_remove("VISUAL")
+        .env("EDITOR", "echo")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("remotes/community/docker.md"));
+}
+
+#[test]
+fn test_missing_sheet_suggestions() {
+    let tmp = TempDir::new().unwrap();
+    fs::create_dir_all(tmp.path().join("cloud")).unwrap();
+    fs::write(tmp.path().join("cloud/kubectl.md"), "# kubectl\n").unwrap();
+    fs::write(tmp.path().join("kafka.md"), "# kafka\n").unwrap();
+    let dir = tmp.path().to_str().unwrap();
+
+    cmd()
+        .args(["kafak", "--config-dir", dir])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("Did you mean 'kafka'?"));
+    cmd()
+        .args(["kctl", "--config-dir", dir])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Did you mean").not());
+    cmd()
+        .args(["kctl", "--match", "subsequence", "--config-dir", dir])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Did you mean 'cloud/kubectl'?"));
+}
+
+#[test]
+fn test_new_wizard_from_piped_answers() {
+    let tmp = TempDir::new().unwrap();
+    let dir = tmp.path().to_str().unwrap();
+
+    cmd()
+        .args(["new", "ssh", "--wizard", "--config-dir", dir])
+        .write_stdin("SSH\nRemote sessions\nKeys, Tunnels\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Created"));
+    let sheet = fs::read_to_string(tmp.path().join("ssh.md")).unwrap();
+    assert!(sheet.starts_with("---\ndescription: Remote sessions\n---\n"));
+    assert!(sheet.contains("## Tunnels"));
+
+    cmd()
+        .args(["ssh", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("description").not());
+    cmd()
+        .args(["new", "ssh", "--config-dir", dir])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("already exists"));
+}
+
+#[test]
+fn test_config_file_seeds_defaults() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(tmp.path().join("git.md"), "# Git\n").unwrap();
+    fs::write(
+        tmp.path().join("cheetsheet.toml"),
+        "editor = \"echo\"\nbogus = 1\n",
+    )
+    .unwrap();
+    let dir = tmp.path().to_str().unwrap();
+
+    cmd()
+        .args(["edit", "git", "--config-dir", dir])
+        .env_remove("VISUAL")
+        .env_remove("EDITOR")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("git.md"))
+        .stderr(predicate::str::contains("unknown config key 'bogus'"));
+}
+
+#[test]
+fn test_config_flag_and_search_paths() {
+    let tmp = TempDir::new().unwrap();
+    let team = TempDir::new().unwrap();
+    let extra = team.path();
+    fs::write(extra.join("deploy.md"), "# Deploy Steps\n").unwrap();
+    let config = tmp.path().join("alt.toml");
+    fs::write(
+        &config,
+        format!("search_paths = [{:?}]\ncolor = \"always\"\n", extra),
+    )
+    .unwrap();
+    let dir = tmp.path().to_str().unwrap();
+    let config = config.to_str().unwrap();
+
+    cmd()
+        .args(["deploy", "--config-dir", dir, "--config", config])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Deploy Steps"))
+        .stdout(predicate::str::contains("\x1b["));
+    cmd()
+        .args(["deploy", "--color", "never", "--config-dir", dir])
+        .args(["--config", config])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b[").not());
+    cmd()
+        .args(["deploy", "--config-dir", dir])
+        .assert()
+        .failure();
+    cmd()
+        .args([
+            "deploy",
+            "--config",
+            "/nonexistent.toml",
+            "--config-dir",
+            dir,
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("failed to read /nonexistent.toml"));
+}
+
+#[test]
+fn test_env_overrides_sit_between_file_and_flags() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(tmp.path().join("git.md"), "# Git\n").unwrap();
+    fs::write(
+        tmp.path().join("cheetsheet.toml"),
+        "color = \"never\"\neditor = \"false\"\n",
+    )
+    .unwrap();
+    let dir = tmp.path().to_str().unwrap();
+
+    cmd()
+        .args(["git", "--config-dir", dir])
+        .env("CHEETSHEET_COLOR", "always")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b["));
+    cmd()
+        .args(["git", "--color", "never", "--config-dir", dir])
+        .env("CHEETSHEET_COLOR", "always")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b[").not());
+    cmd()
+        .args(["edit", "git", "--config-dir", dir])
+        .env("CHEETSHEET_EDITOR", "echo")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("git.md"));
+    cmd()
+        .args(["git", "--config-dir", dir])
+        .env("CHEETSHEET_WIDTH", "wide")
+        .assert()
+        .code(5)
+        .stderr(predicate::str::contains(
+            "CHEETSHEET_WIDTH: invalid value 'wide'",
+        ));
+}
+
+#[test]
+fn test_details_blocks() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(
+        tmp.path().join("git.md"),
+        "# Git\n\n<details>\n<summary>Rare flags</summary>\n\nUse --porcelain\n</details>\n",
+    )
+    .unwrap();
+    let dir = tmp.path().to_str().unwrap();
+
+    cmd()
+        .args(["git", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Rare flags"))
+        .stdout(predicate::str::contains("--porcelain"))
+        .stdout(predicate::str::contains("<summary>").not());
+    cmd()
+        .args(["git", "--collapse-details", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Rare flags"))
+        .stdout(predicate::str::contains("--porcelain").not());
+}
+
+#[test]
+fn test_stdin_precedence() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(tmp.path().join("git.md"), "# Git Sheet\n").unwrap();
+    let dir = tmp.path().to_str().unwrap();
+
+    // `-` reads stdin
+    cmd()
+        .args(["-", "--config-dir", dir])
+        .write_stdin("# Piped\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Piped"))
+        .stderr(predicate::str::is_empty());
+    // A name wins over piped input, with a warning
+    let piped = tmp.path().join("piped.md");
+    fs::write(&piped, "# Piped\n").unwrap();
+    cmd_with_stdin(&piped)
+        .args(["git", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Git Sheet"))
+        .stdout(predicate::str::contains("Piped").not())
+        .stderr(predicate::str::contains("ignoring input piped to stdin"));
+    // Nothing piped, nothing to warn about
+    cmd()
+        .args(["git", "--config-dir", dir])
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty());
+    // The guard skips the check
+    cmd_with_stdin(&piped)
+        .args(["git", "--no-args-stdin", "--config-dir", dir])
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty());
+    // An explicit file ignores it the same way
+    cmd_with_stdin(&piped)
+        .args(["--file", tmp.path().join("git.md").to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Git Sheet"))
+        .stderr(predicate::str::contains("ignoring input piped to stdin"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_stdin_check_leaves_input() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(tmp.path().join("git.md"), "# Git Sheet\n").unwrap();
+    let piped = tmp.path().join("piped.txt");
+    fs::write(&piped, "line one\nline two\n").unwrap();
+    // The next command of the script still reads all of stdin
+    let script = format!(
+        "'{}' git --config-dir '{}' >/dev/null; cat",
+        env!("CARGO_BIN_EXE_cheetsheet"),
+        tmp.path().display()
+    );
+    let mut command = std::process::Command::new("sh");
+    command
+        .args(["-c", &script])
+        .envs(home())
+        .stdin(fs::File::open(&piped).unwrap());
+    command
+        .assert()
+        .success()
+        .stdout("line one\nline two\n")
+        .stderr(predicate::str::contains("ignoring input piped to stdin"));
+}
+
+#[test]
+fn test_config_get_set_list() {
+    let tmp = TempDir::new().unwrap();
+    let dir = tmp.path().to_str().unwrap();
+    let file = tmp.path().join("cheetsheet.toml");
+
+    cmd()
+        .args(["config", "set", "width", "100", "--config-dir", dir])
+        .assert()
+        .success();
+    assert_eq!(fs::read_to_string(&file).unwrap(), "width = 100\n");
+
+    cmd()
+        .args(["config", "get", "width", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout(format!("100 (file {})\n", file.display()));
+    cmd()
+        .args(["config", "get", "width", "--config-dir", dir])
+        .env("CHEETSHEET_WIDTH", "90")
+        .assert()
+        .success()
+        .stdout("90 (env CHEETSHEET_WIDTH)\n");
+    cmd()
+        .args([
+            "config",
+            "get",
+            "width",
+            "--width",
+            "70",
+            "--config-dir",
+            dir,
+        ])
+        .env("CHEETSHEET_WIDTH", "90")
+        .assert()
+        .success()
+        .stdout("70 (flag --width)\n");
+    cmd()
+        .args(["config", "list", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("theme = (default)  # default\n"))
+        .stdout(predicate::str::contains("width = 100  # file"));
+
+    cmd()
+        .args([
+            "config",
+            "set",
+            "theme",
+            "gruvbox-dark",
+            "--config-dir",
+            dir,
+        ])
+        .assert()
+        .code(5)
+        .stderr(predicate::str::contains(
+            "invalid value 'gruvbox-dark' for 'theme'",
+        ));
+    cmd()
+        .args(["config", "get", "colour", "--config-dir", dir])
+        .assert()
+        .code(5)
+        .stderr(predicate::str::contains("valid keys: theme"));
+}
+
+/// Runs cheetsheet under a pseudo-terminal through util-linux `script`, for
+/// behavior that only kicks in when stdout is a terminal.
+#[cfg(target_os = "linux")]
+fn tty_cmd(args: &[&str]) -> Command {
+    let line: Vec<String> = std::iter::once(env!("CARGO_BIN_EXE_cheetsheet"))
+        .chain(args.iter().copied())
+        .map(|arg| format!("'{arg}'"))
+        .collect();
+    let mut cmd = Command::new("script");
+    cmd.args(["-qec", &line.join(" "), "/dev/null"])
+        .envs(home());
+    cmd
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_default_command_variants() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(tmp.path().join("git.md"), "# Git Sheet\n").unwrap();
+    fs::write(tmp.path().join("tmux.md"), "# Tmux Sheet\n").unwrap();
+    let dir = tmp.path().to_str().unwrap();
+    let config = tmp.path().join("default.toml");
+    let config_str = config.to_str().unwrap();
+    let run = |value: &str| {
+        fs::write(&config, format!("default_command = \"{value}\"\n")).unwrap();
+        let mut cmd = tty_cmd(&["--config-dir", dir, "--config", config_str]);
+        cmd.env("XDG_DATA_HOME", tmp.path());
+        cmd
+    };
+
+    run("recent")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("no recently viewed sheet"));
+    run("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("tmux"));
+    run("view:tmux")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Tmux Sheet"));
+    tty_cmd(&["git", "--config-dir", dir])
+        .env("XDG_DATA_HOME", tmp.path())
+        .assert()
+        .success();
+    run("recent")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Git Sheet"));
+    run("picker")
+        .write_stdin("2\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Tmux Sheet"));
+
+    // Without a terminal a bare invocation stays a usage error
+    cmd()
+        .args(["--config-dir", dir, "--config", config_str])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Usage"));
+    fs::write(&config, "default_command = \"tui\"\n").unwrap();
+    cmd()
+        .args(["git", "--config-dir", dir, "--config", config_str])
+        .assert()
+        .code(5)
+        .stderr(predicate::str::contains(
+            "list, picker, recent, view:<name>",
+        ));
+}
+
+#[test]
+fn test_code_frame() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(
+        tmp.path().join("git.md"),
+        "# Git\n\n```bash\ngit status\n```\n",
+    )
+    .unwrap();
+    let dir = tmp.path().to_str().unwrap();
+
+    cmd()
+        .args(["git", "--code-frame", "--ascii", "--color", "never"])
+        .args(["--config-dir", dir])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "  +- bash -----+\n  | git status |\n  +------------+\n",
+        ));
+    cmd()
+        .args(["git", "--code-frame", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("╭─ bash"));
+}
+
+#[test]
+fn test_random() {
+    let tmp = TempDir::new().unwrap();
+    for name in ["git", "tmux", "vim", "jq"] {
+        fs::write(
+            tmp.path().join(format!("{name}.md")),
+            format!("# {name} sheet\n\n```bash\n{name} --help\n```\n"),
+        )
+        .unwrap();
+    }
+    let dir = tmp.path().to_str().unwrap();
+    let output = |args: &[&str]| {
+        let out = cmd()
+            .args(["random", "--config-dir", dir])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(out.status.success());
+        String::from_utf8(out.stdout).unwrap()
+    };
+
+    let first = output(&["--seed", "7"]);
+    assert!(first.contains(" sheet"));
+    assert_eq!(output(&["--seed", "7"]), first);
+
+    let block = output(&["--seed", "7", "--block"]);
+    assert!(!block.contains(" sheet"));
+    let name = ["git", "tmux", "vim", "jq"]
+        .into_iter()
+        .find(|name| block.contains(&format!("{name} --help")))
+        .unwrap();
+    assert!(block.lines().any(|line| line.trim() == name));
+
+    let empty = TempDir::new().unwrap();
+    cmd()
+        .args(["random", "--config-dir", empty.path().to_str().unwrap()])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("no sheets"));
+
+    // Sheets in the search paths are picked from too
+    let team = TempDir::new().unwrap();
+    fs::write(
+        team.path().join("deploy.md"),
+        "# Deploy\n\n```bash\nmake deploy\n```\n",
+    )
+    .unwrap();
+    let config = empty.path().join("team.toml");
+    fs::write(&config, format!("search_paths = [{:?}]\n", team.path())).unwrap();
+    cmd()
+        .args(["random", "--block", "--color", "never"])
+        .args(["--config-dir", empty.path().to_str().unwrap()])
+        .args(["--config", config.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("deploy"))
+        .stdout(predicate::str::contains("make deploy"));
+}
+
+#[test]
+fn test_tip() {
+    let tmp = TempDir::new().unwrap();
+    let data = TempDir::new().unwrap();
+    for name in ["git", "tmux"] {
+        fs::write(
+            tmp.path().join(format!("{name}.md")),
+            format!("# {name}\n\nShow the help:\n\n```bash\n{name} --help\n```\n"),
+        )
+        .unwrap();
+    }
+    let output = |dir: &std::path::Path, args: &[&str]| {
+        let out = cmd()
+            .args(["tip", "--config-dir", dir.to_str().unwrap()])
+            .args(args)
+            .env("XDG_DATA_HOME", data.path())
+            .env("COLUMNS", "80")
+            .output()
+            .unwrap();
+        assert!(out.status.success());
+        assert!(out.stderr.is_empty());
+        String::from_utf8(out.stdout).unwrap()
+    };
+
+    let first = output(tmp.path(), &["--seed", "7"]);
+    assert_eq!(output(tmp.path(), &["--seed", "7"]), first);
+    assert_eq!(
+        output(tmp.path(), &["--sheet", "tmux"]),
+        "tmux: Show the help\ntmux --help\n"
+    );
+
+    // The cache misses new sheets until it is refreshed
+    fs::write(tmp.path().join("jq.md"), "```bash\njq .\n```\n").unwrap();
+    assert_eq!(output(tmp.path(), &["--sheet", "jq"]), "");
+    assert_eq!(
+        output(tmp.path(), &["--sheet", "jq", "--refresh"]),
+        "jq\njq .\n"
+    );
+
+    let empty = TempDir::new().unwrap();
+    assert_eq!(output(empty.path(), &[]), "");
+}
+
+#[test]
+fn test_language_variants() {
+    let tmp = TempDir::new().unwrap();
+    let dir = tmp.path().to_str().unwrap();
+    fs::write(tmp.path().join("git.md"), "# Git\n").unwrap();
+    fs::write(tmp.path().join("git.zh.md"), "# Git 中文\n").unwrap();
+    fs::write(tmp.path().join("node.js.md"), "# Node\n").unwrap();
+    let view = |args: &[&str], locale: &str| {
+        let out = cmd()
+            .args(args)
+            .args(["--config-dir", dir, "--color", "never"])
+            .env_remove("LC_ALL")
+            .env_remove("LC_MESSAGES")
+            .env("LANG", locale)
+            .output()
+            .unwrap();
+        assert!(out.status.success());
+        String::from_utf8(out.stdout).unwrap()
+    };
+
+    assert!(view(&["git"], "zh_TW.UTF-8").contains("Git 中文"));
+    assert!(!view(&["git"], "en_US.UTF-8").contains("中文"));
+    assert!(!view(&["git", "--lang", "fr"], "zh_TW.UTF-8").contains("中文"));
+    assert!(view(&["git", "--lang", "zh-CN"], "C").contains("中文"));
+    assert_eq!(view(&["list"], "C"), "git [zh]\nnode.js\n");
+
+    cmd()
+        .args(["new", "git", "--lang", "ja_JP", "--config-dir", dir])
+        .assert()
+        .success();
+    assert!(tmp.path().join("git.ja-JP.md").is_file());
+    cmd()
+        .args(["git", "--lang", "chinese", "--config-dir", dir])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("not a language code"));
+}
+
+#[test]
+fn test_new_from_templates() {
+    let tmp = TempDir::new().unwrap();
+    let dir = tmp.path().to_str().unwrap();
+    fs::create_dir_all(tmp.path().join("templates")).unwrap();
+    fs::write(
+        tmp.path().join("templates/tool.md"),
+        "# {{name}}\n\nMaintained by {{author}} since {{date}}.\n",
+    )
+    .unwrap();
+    fs::write(tmp.path().join("templates/bad.md"), "# {{title}}\n").unwrap();
+    fs::write(tmp.path().join("cheetsheet.toml"), "author = \"Ada\"\n").unwrap();
+
+    cmd()
+        .args(["new", "ssh", "--config-dir", dir])
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(tmp.path().join("ssh.md")).unwrap(),
+        "# ssh\n"
+    );
+
+    cmd()
+        .args(["new", "rg", "--template", "tool", "--tags", "search,cli"])
+        .args(["--config-dir", dir])
+        .assert()
+        .success();
+    let sheet = fs::read_to_string(tmp.path().join("rg.md")).unwrap();
+    assert!(sheet.starts_with("---\ntags: [search, cli]\n---\n\n# rg\n"));
+    assert!(sheet.contains("Maintained by Ada since 2"));
+
+    cmd()
+        .args(["new", "fd", "--template", "bad", "--config-dir", dir])
+        .assert()
+        .code(6)
+        .stderr(predicate::str::contains("unknown variable '{{title}}'"))
+        .stderr(predicate::str::contains("templates/bad.md"));
+    cmd()
+        .args(["new", "--list-templates", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout("default: built-in\nbad\ntool\n");
+    // Templates are not sheets
+    cmd()
+        .args(["list", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout("rg\nssh\n");
+}
+
+#[test]
+fn test_extension_and_name_conventions() {
+    let tmp = TempDir::new().unwrap();
+    let dir = tmp.path().to_str().unwrap();
+    fs::write(
+        tmp.path().join("cheetsheet.toml"),
+        "extension = \"markdown\"\nnormalize_names = true\n",
+    )
+    .unwrap();
+
+    cmd()
+        .args(["new", "My Tool", "--config-dir", dir])
+        .assert()
+        .success();
+    assert!(tmp.path().join("my-tool.markdown").is_file());
+    cmd()
+        .args(["My Tool", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("My Tool"));
+
+    fs::write(tmp.path().join("Git Tricks.md"), "# Git Tricks\n").unwrap();
+    cmd()
+        .args(["git-tricks", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Git Tricks"));
+
+    cmd()
+        .args(["doctor", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout("Git Tricks.md: should be named git-tricks.markdown\nRun `cheetsheet doctor --fix` to rename them.\n");
+    cmd()
+        .args(["doctor", "--fix", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout("Git Tricks.md: renamed to git-tricks.markdown\n");
+    assert!(tmp.path().join("git-tricks.markdown").is_file());
+    cmd()
+        .args(["doctor", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout("No problems found.\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_doctor_fix_changes_only_case() {
+    let tmp = TempDir::new().unwrap();
+    let dir = tmp.path().to_str().unwrap();
+    fs::write(tmp.path().join("git.md"), "# Git\n").unwrap();
+    // Both names are one file, as they would be on a case-insensitive
+    // file system
+    fs::hard_link(tmp.path().join("git.md"), tmp.path().join("Git.md")).unwrap();
+    cmd()
+        .args(["doctor", "--fix", "--config-dir", dir])
+        .env("CHEETSHEET_NORMALIZE_NAMES", "true")
+        .assert()
+        .success()
+        .stdout("Git.md: renamed to git.md\n");
+    let names: Vec<_> = fs::read_dir(tmp.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    assert_eq!(names, ["git.md"]);
+    assert_eq!(
+        fs::read_to_string(tmp.path().join("git.md")).unwrap(),
+        "# Git\n"
+    );
+}
+
+#[test]
+fn test_duplicate_headings() {
+    let tmp = TempDir::new().unwrap();
+    let dir = tmp.path().to_str().unwrap();
+    fs::write(
+        tmp.path().join("tmux.md"),
+        "# Tmux\n\n## Panes\n\nSplit.\n\n## Windows\n\n### Panes\n\nMove.\n",
+    )
+    .unwrap();
+    cmd()
+        .args(["doctor", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout("tmux.md:9: heading 'Panes' repeats line 3; `tmux#panes-1` shows it\n");
+    cmd()
+        .args(["tmux#panes-1", "--config-dir", dir, "--color", "never"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Move."))
+        .stdout(predicate::str::contains("Split.").not())
+        .stderr("");
+    cmd()
+        .args(["tmux", "--warn-duplicate-headings", "--config-dir", dir])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "tmux.md:9: heading 'Panes' repeats line 3; #panes-1 reaches it",
+        ));
+}
+
+#[test]
+fn test_doctor_dead_links() {
+    let tmp = TempDir::new().unwrap();
+    let dir = tmp.path().to_str().unwrap();
+    fs::create_dir_all(tmp.path().join("img")).unwrap();
+    fs::write(tmp.path().join("img/map.png"), b"\x89PNG").unwrap();
+    fs::write(tmp.path().join("docker.md"), "# Docker\n").unwrap();
+    fs::write(
+        tmp.path().join("net.md"),
+        "# Net\n\nSee [docker](./docker.md), [compose](./dockr.md) and \
+         [web](https://example.com).\n\n![map](img/map.png)\n![topology](img/topology.png)\n\n\
+         ```bash\necho [not](a-link.md)\n```\n\n[back](nets.md)\n",
+    )
+    .unwrap();
+    fs::write(tmp.path().join("latin.md"), b"# Caf\xe9\n").unwrap();
+    cmd()
+        .args(["doctor", "--config-dir", dir])
+        .assert()
+        .code(1)
+        .stdout(
+            "latin.md: error: can't be read: invalid utf-8 sequence of 1 bytes from index 5\n\
+             net.md:3: error: link to ./dockr.md doesn't exist; did you mean ./docker.md?\n\
+             net.md:6: warning: image img/topology.png doesn't exist\n\
+             net.md:12: error: link to nets.md doesn't exist\n",
+        )
+        .stderr(predicate::str::contains("3 errors in the sheets"));
+}
+
+#[test]
+fn test_images_show_alt_text() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(
+        tmp.path().join("net.md"),
+        "# Net\n\n![Topology diagram](topology.png)\n\nSee ![icon](i.png) above\n",
+    )
+    .unwrap();
+    fs::write(tmp.path().join("topology.png"), b"\x89PNG").unwrap();
+    let dir = tmp.path().to_str().unwrap();
+
+    // Not a terminal, so --images falls back to the alt text as well
+    cmd()
+        .args(["net", "--images", "--config-dir", dir])
+        .env("TERM", "xterm-kitty")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[Topology diagram]"))
+        .stdout(predicate::str::contains("See [icon] above"))
+        .stdout(predicate::str::contains("](").not())
+        .stdout(predicate::str::contains("\x1b_G").not());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_block_renderers() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(
+        tmp.path().join("flow.md"),
+        "# Flow\n\n```shout\nbuild then ship\n```\n\n```broken\nstays put\n```\n",
+    )
+    .unwrap();
+    fs::write(
+        tmp.path().join("cheetsheet.toml"),
+        "block_renderers = { shout = \"tr a-z A-Z\", broken = \"exit 1\" }\n",
+    )
+    .unwrap();
+    let dir = tmp.path().to_str().unwrap();
+
+    cmd()
+        .args(["flow", "-v", "--color", "never", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("BUILD THEN SHIP"))
+        .stdout(predicate::str::contains("  stays put"))
+        .stderr(predicate::str::contains(
+            "block renderer for 'broken' exited with exit status: 1; highlighting instead",
+        ));
+    cmd()
+        .args([
+            "flow",
+            "--no-hooks",
+            "--color",
+            "never",
+            "--config-dir",
+            dir,
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("build then ship"))
+        .stdout(predicate::str::contains("BUILD").not());
+}
+
+#[test]
+fn test_math() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(
+        tmp.path().join("stats.md"),
+        "# Stats\n\nMean: $\\bar{x}$\n\n```bash\necho \"$HOME and $PATH\"\n```\n",
+    )
+    .unwrap();
+    let dir = tmp.path().to_str().unwrap();
+
+    cmd()
+        .args(["stats", "--math", "--color", "never", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("$\\bar").not())
+        .stdout(predicate::str::contains("\\bar{x}"))
+        .stdout(predicate::str::contains("$HOME"))
+        .stdout(predicate::str::contains("$PATH"));
+    cmd()
+        .args(["stats", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("$\\bar{x}$"));
+}
+
+#[test]
+fn test_tags() {
+    let tmp = TempDir::new().unwrap();
+    fs::create_dir_all(tmp.path().join("cloud")).unwrap();
+    fs::write(
+        tmp.path().join("git.md"),
+        "---\ntags: [vcs, cli]\n---\n# Git\n",
+    )
+    .unwrap();
+    fs::write(
+        tmp.path().join("cloud/aws.md"),
+        "---\ntags: [cli, cloud]\n---\n",
+    )
+    .unwrap();
+    fs::write(tmp.path().join("jq.md"), "---\ntags: cli\n---\n").unwrap();
+    fs::write(tmp.path().join("plain.md"), "# No tags\n").unwrap();
+    let dir = tmp.path().to_str().unwrap();
+
+    cmd()
+        .args(["tags", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout("   3  cli\n   1  cloud\n   1  vcs\n");
+}
+
+#[test]
+fn test_profiles() {
+    let tmp = TempDir::new().unwrap();
+    let work = tmp.path().join("profiles/work");
+    fs::create_dir_all(&work).unwrap();
+    fs::write(tmp.path().join("git.md"), "# Personal Git\n").unwrap();
+    fs::write(work.join("kubectl.md"), "# Work Kubectl\n").unwrap();
+    fs::write(tmp.path().join("cheetsheet.home.toml"), "width = 60\n").unwrap();
+    let dir = tmp.path().to_str().unwrap();
+
+    cmd()
+        .args(["git", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Personal Git"));
+    // Profiles never see each other's sheets
+    cmd()
+        .args(["kubectl", "--config-dir", dir])
+        .assert()
+        .failure();
+    cmd()
+        .args(["git", "--profile", "work", "--config-dir", dir])
+        .assert()
+        .failure();
+    cmd()
+        .args(["kubectl", "--config-dir", dir])
+        .env("CHEETSHEET_PROFILE", "work")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Work Kubectl"));
+
+    cmd()
+        .args(["profile", "list", "--profile", "work", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout("  default\n  home\n* work\n");
+    cmd()
+        .args(["config", "list", "--config-dir", dir])
+        .env("CHEETSHEET_PROFILE", "home")
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "profile = \"home\"  # env CHEETSHEET_PROFILE\n",
+        ))
+        .stdout(predicate::str::contains("width = 60"));
+    cmd()
+        .args(["list", "--profile", "../x", "--config-dir", dir])
+        .assert()
+        .code(5)
+        .stderr(predicate::str::contains("invalid profile name"));
+}
+
+#[test]
+fn test_code_spacing() {
+    let tmp = TempDir::new().unwrap();
+    let sheet = tmp.path().join("s.md");
+    fs::write(&sheet, "```\none\n```\n\n```\ntwo\n```\n").unwrap();
+    let run = |extra: &[&str]| {
+        let mut cmd = cmd();
+        cmd.args(["--file", sheet.to_str().unwrap(), "--color", "never"])
+            .args(extra);
+        String::from_utf8(cmd.assert().success().get_output().stdout.clone()).unwrap()
+    };
+
+    // Consecutive blocks share one gap rather than stacking both
+    assert_eq!(run(&[]), "\n  one\n\n  two\n\n");
+    assert_eq!(
+        run(&["--code-spacing-before", "2", "--code-spacing-after", "0"]),
+        "\n\n  one\n\n\n  two\n"
+    );
+    fs::write(
+        tmp.path().join("cheetsheet.toml"),
+        "code_spacing_before = 0\n",
+    )
+    .unwrap();
+    assert_eq!(
+        run(&["--config-dir", tmp.path().to_str().unwrap()]),
+        "  one\n\n  two\n\n"
+    );
+    cmd()
+        .args([
+            "--file",
+            sheet.to_str().unwrap(),
+            "--code-spacing-after",
+            "3",
+        ])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_tab_width() {
+    let tmp = TempDir::new().unwrap();
+    let sheet = tmp.path().join("make.md");
+    fs::write(
+        &sheet,
+        "```make
+all:
+	cc -o app	main.c
+```
+",
+    )
+    .unwrap();
+    let run = |extra: &[&str]| {
+        let mut cmd = cmd();
+        cmd.args(["--file", sheet.to_str().unwrap(), "--color", "never"])
+            .args(extra);
+        String::from_utf8(cmd.assert().success().get_output().stdout.clone()).unwrap()
+    };
+
+    assert_eq!(run(&[]), "\n  all:\n      cc -o app   main.c\n\n");
+    assert_eq!(
+        run(&["--tab-width", "2"]),
+        "\n  all:\n    cc -o app main.c\n\n"
+    );
+    cmd()
+        .args(["--file", sheet.to_str().unwrap(), "--tab-width", "0"])
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn test_migrate_misspelled_config_dir() {
+    let tmp = TempDir::new().unwrap();
+    let typo = tmp.path().join("cheatsheet");
+    fs::create_dir_all(&typo).unwrap();
+    fs::write(typo.join("git.md"), "# Git\n").unwrap();
+    let dir = tmp.path().join("cheetsheet");
+    let dir_arg = dir.to_str().unwrap();
+
+    cmd()
+        .args(["git", "--config-dir", dir_arg])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("warning: sheets are read from"))
+        .stderr(predicate::str::contains("run `cheetsheet migrate`"));
+    for _ in 0..2 {
+        cmd()
+            .args(["migrate", "--config-dir", dir_arg])
+            .assert()
+            .success();
+    }
+    assert!(!typo.exists());
+    cmd()
+        .args(["git", "--config-dir", dir_arg])
+        .assert()
+        .success()
+        .stderr("");
+}
+
+#[test]
+fn test_init() {
+    let tmp = TempDir::new().unwrap();
+    let dir = tmp.path().join("cheetsheet");
+    let dir_arg = dir.to_str().unwrap();
+
+    cmd()
+        .args(["init", "--config-dir", dir_arg])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("created  ").count(3))
+        .stdout(predicate::str::contains("Next steps:"));
+    let settings = fs::read_to_string(dir.join("cheetsheet.toml")).unwrap();
+    assert!(settings.contains("# width = 100"));
+    cmd()
+        .args(["example", "--config-dir", dir_arg])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("du -ah"));
+
+    // A second run keeps what is there and fills in only what's missing
+    fs::write(dir.join("example.md"), "# Mine\n").unwrap();
+    fs::remove_file(dir.join("cheetsheet.toml")).unwrap();
+    cmd()
+        .args(["init", "--config-dir", dir_arg])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("exists   ").count(2))
+        .stdout(predicate::str::contains("created  ").count(1));
+    assert_eq!(
+        fs::read_to_string(dir.join("example.md")).unwrap(),
+        "# Mine\n"
+    );
+    assert!(dir.join("cheetsheet.toml").is_file());
+}
+
+#[test]
+fn test_closed_stdout_is_not_an_error() {
+    use std::io::Read;
+    use std::process::Stdio;
+
+    let tmp = TempDir::new().unwrap();
+    let lines: String = (0..20_000).map(|i| format!("line {i}\n\n")).collect();
+    fs::write(tmp.path().join("long.md"), lines).unwrap();
+
+    let mut child = bin()
+        .args(["long", "--config-dir", tmp.path().to_str().unwrap()])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // Read a little, then hang up like `head` does
+    let mut start = [0; 16];
+    child.stdout.take().unwrap().read_exact(&mut start).unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+}
+
+#[test]
+fn test_closed_stdout_while_listing() {
+    use std::process::Stdio;
+
+    let tmp = TempDir::new().unwrap();
+    // Well past a pipe buffer of names
+    for i in 0..3000 {
+        let name = format!("a-sheet-with-a-fairly-long-name-{i:05}.md");
+        fs::write(tmp.path().join(name), "# Sheet\n").unwrap();
+    }
+    let dir = tmp.path().to_str().unwrap();
+    for args in [vec!["list"], vec!["list", "--json"]] {
+        let mut child = bin()
+            .args(&args)
+            .args(["--config-dir", dir])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        // Hang up without reading, like `| head -0`
+        drop(child.stdout.take());
+        let output = child.wait_with_output().unwrap();
+
+        assert!(output.status.success(), "{args:?}");
+        assert_eq!(String::from_utf8_lossy(&output.stderr), "", "{args:?}");
+    }
+}
+
+#[test]
+fn test_color_overrides() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(tmp.path().join("s.md"), "# Title\n").unwrap();
+    let dir = tmp.path().to_str().unwrap();
+
+    fs::write(
+        tmp.path().join("cheetsheet.toml"),
+        "[colors]\nheaders = \"cyan\"\n",
+    )
+    .unwrap();
+    cmd()
+        .args(["s", "--config-dir", dir, "--color", "always"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b[38;5;14m"));
+
+    fs::write(
+        tmp.path().join("cheetsheet.toml"),
+        "[colors]\nheaders = \"teal\"\n",
+    )
+    .unwrap();
+    cmd()
+        .args(["s", "--config-dir", dir])
+        .assert()
+        .code(5)
+        .stderr(predicate::str::contains("unknown color 'teal'"))
+        .stderr(predicate::str::contains("colors.headers"));
+}
+
+#[test]
+fn test_color_mode_overrides_tty_detection() {
+    let tmp = TempDir::new().unwrap();
+    let sheet = tmp.path().join("s.rst");
+    fs::write(
+        &sheet,
+        "Intro\n\n.. image:: diagram.png\n\n.. code:: bash\n\n   ls -l\n",
+    )
+    .unwrap();
+    let file = sheet.to_str().unwrap();
+
+    // Piped output: `always` wins even over NO_COLOR, like git
+    let output = cmd()
+        .args(["--file", file, "--color", "always"])
+        .env("NO_COLOR", "1")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("\x1b[38;2;"), "code is highlighted");
+    assert!(
+        stdout.contains("\x1b[2m[image: diagram.png]"),
+        "marker is dimmed"
+    );
+
+    for mode in ["never", "auto"] {
+        cmd()
+            .args(["--file", file, "--color", mode])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("[image: diagram.png]"))
+            .stdout(predicate::str::contains("\x1b").not());
+    }
+}
+
+#[test]
+fn test_quoted_code_block() {
+    cmd()
+        .args([
+            "--file",
+            "tests/fixtures/quoted-code.md",
+            "--color",
+            "never",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "▐   kubectl drain node-1\n▐   \n▐   kubectl get pods -o wide\n",
+        ))
+        .stdout(predicate::str::contains("```").not())
+        .stdout(predicate::str::contains("\n  make deploy\n"));
+}
+
+#[test]
+fn test_highlight_theme_bg() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(
+        tmp.path().join("s.md"),
+        "Run `ls`:\n\n```bash\nls -l\n```\n",
+    )
+    .unwrap();
+    let dir = tmp.path().to_str().unwrap();
+    let view = |bg: &str| {
+        let output = cmd()
+            .args(["s", "--config-dir", dir, "--color", "always"])
+            .args(["--highlight-theme-bg", bg])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    assert!(view("on").contains("\x1b[48;2;"));
+    let off = view("off");
+    assert!(!off.contains("\x1b[48;"));
+    assert!(off.contains("\x1b[38;2;"));
+}
+
+#[test]
+fn test_heading_range() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(
+        tmp.path().join("docker.md"),
+        "# Docker\n\n## Install\n\nInstall step\n\n## Usage\n\nUsage step\n",
+    )
+    .unwrap();
+    let dir = tmp.path().to_str().unwrap();
+
+    cmd()
+        .args(["docker", "--config-dir", dir, "--after", "install"])
+        .args(["--before", "Usage"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Install step"))
+        .stdout(predicate::str::contains("Install\n").not())
+        .stdout(predicate::str::contains("Usage").not());
+    cmd()
+        .args(["docker", "--config-dir", dir, "--before", "Install"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Docker"))
+        .stdout(predicate::str::contains("step").not());
+    cmd()
+        .args(["docker", "--config-dir", dir, "--after", "Setup"])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("no heading 'Setup' in the sheet"));
+}
+
+#[test]
+fn test_section_in_command() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(
+        tmp.path().join("docker.md"),
+        "# Docker\n\n## Install\n\nInstall step\n\n### Linux\n\napt step\n\n## Usage\n\nUsage step\n",
+    )
+    .unwrap();
+    let dir = tmp.path().to_str().unwrap();
+
+    cmd()
+        .args(["docker#install", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Install step"))
+        .stdout(predicate::str::contains("apt step"))
+        .stdout(predicate::str::contains("Usage").not());
+    cmd()
+        .args(["docker#", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Linux"))
+        .stdout(predicate::str::contains("step").not());
+    cmd()
+        .args(["docker#Setup", "--config-dir", dir])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("no section 'Setup' in the sheet"));
+    cmd()
+        .args(["docker#Usage", "--config-dir", dir, "--after", "Install"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("drop --after and --before"));
+}
+
+#[test]
+fn test_config_check() {
+    let tmp = TempDir::new().unwrap();
+    let dir = tmp.path().to_str().unwrap();
+    let file = tmp.path().join("cheetsheet.toml");
+
+    fs::write(&file, "width = 100\ncode_theme = \"InspiredGitHub\"\n").unwrap();
+    cmd()
+        .args(["config", "check", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(": ok"));
+
+    fs::write(
+        &file,
+        "theme = \"drak\"\ncode_theme = \"InspiredGithub\"\nwidht = 80\n",
+    )
+    .unwrap();
+    cmd()
+        .args(["config", "check", "--config-dir", dir])
+        .assert()
+        .code(5)
+        .stdout(predicate::str::contains(format!(
+            "{}:1:9: invalid theme",
+            file.display()
+        )))
+        .stdout(predicate::str::contains(
+            ":2:14: unknown code theme \"InspiredGithub\", did you mean \"InspiredGitHub\"?",
+        ))
+        .stdout(predicate::str::contains(
+            ":3:1: unknown config key 'widht', did you mean 'width'?",
+        ))
+        .stderr(predicate::str::contains("3 problems"));
+
+    // Loading stops on the bad value but only warns about the unknown key
+    cmd()
+        .args(["list", "--config-dir", dir])
+        .assert()
+        .code(5)
+        .stderr(predicate::str::contains("warning: ").and(predicate::str::contains("widht")))
+        .stderr(predicate::str::contains(":1:9: invalid theme"));
+}
+
+#[test]
+fn test_cmark_renderer() {
+    let tmp = TempDir::new().unwrap();
+    let sheet = tmp.path().join("s.md");
+    fs::write(
+        &sheet,
+        "# Title\n\nJoined\nlines\n\n| a | b |\n|---|---|\n| 1 | 2 |\n\n```bash\nls\n```\n",
+    )
+    .unwrap();
+    let file = sheet.to_str().unwrap();
+
+    cmd()
+        .args(["--file", file, "--renderer", "cmark", "--color", "never"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Joined lines\n"))
+        .stdout(predicate::str::contains("│ a │ b │\n"))
+        .stdout(predicate::str::contains("  ls\n"));
+    fs::write(tmp.path().join("cheetsheet.toml"), "renderer = \"cmark\"\n").unwrap();
+    cmd()
+        .args(["--file", file, "--color", "never"])
+        .args(["--config-dir", tmp.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Joined lines\n"));
+}
+
+#[test]
+fn test_sheet_in_numbered_parts() {
+    let tmp = TempDir::new().unwrap();
+    let git = tmp.path().join("git");
+    fs::create_dir_all(&git).unwrap();
+    fs::write(git.join("01-basics.md"), "Basics\n").unwrap();
+    fs::write(git.join("02-branching.md"), "Branching\n").unwrap();
+    let dir = tmp.path().to_str().unwrap();
+
+    let output = cmd().args(["git", "--config-dir", dir]).output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let (basics, branching) = (stdout.find("Basics"), stdout.find("Branching"));
+    assert!(basics.unwrap() < branching.unwrap());
+
+    cmd()
+        .args(["git", "--config-dir", dir, "--part", "2"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Branching"))
+        .stdout(predicate::str::contains("Basics").not());
+    cmd()
+        .args(["git", "--config-dir", dir, "--part", "3"])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("has 2 parts; there is no part 3"));
+}
+
+#[test]
+fn test_explain_trace() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(
+        tmp.path().join("git.md"),
+        "# Git\n\n```bash\ngit log\n```\n\n```\ngit st\n```\n",
+    )
+    .unwrap();
+    let dir = tmp.path().to_str().unwrap();
+    let output = cmd()
+        .args(["git", "--config-dir", dir, "--explain", "--color", "always"])
+        .args(["--code-theme", "nope"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let json = &stderr[stderr.find('{').unwrap()..];
+    let trace: serde_json::Value = serde_json::from_str(json).unwrap();
+    assert_eq!(trace["query"], "git");
+    assert_eq!(trace["path"], tmp.path().join("git.md").to_str().unwrap());
+    assert_eq!(trace["format"], "md");
+    assert_eq!(trace["config"], serde_json::Value::Null);
+    assert_eq!(trace["code_theme"], "base16-ocean.dark");
+    assert_eq!(trace["segments"], 5);
+    assert_eq!(trace["languages"], serde_json::json!(["bash", ""]));
+    assert_eq!(
+        trace["warnings"],
+        serde_json::json!(["unknown code theme 'nope', using base16-ocean.dark"])
+    );
+
+    // Without --explain, stderr only has the warning
+    cmd()
+        .args(["git", "--config-dir", dir, "--color", "always"])
+        .args(["--code-theme", "nope"])
+        .assert()
+        .success()
+        .stderr("warning: unknown code theme 'nope', using base16-ocean.dark\n");
+}
+
+#[test]
+fn test_verbose_logs_to_stderr() {
+    let tmp = TempDir::new().unwrap();
+    let search = TempDir::new().unwrap();
+    fs::write(search.path().join("git.md"), "# Git\n\n```bash\nls\n```\n").unwrap();
+    let dir = tmp.path().to_str().unwrap();
+    let path = search.path().join("git.md");
+
+    cmd()
+        .args(["git", "-v", "--color", "never", "--config-dir", dir])
+        .env("CHEETSHEET_SEARCH_PATHS", search.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("info:").not())
+        .stderr(predicate::str::contains(format!(
+            "looking for 'git' in {dir}"
+        )))
+        .stderr(predicate::str::contains(format!(
+            "'git' is {}",
+            path.display()
+        )))
+        .stderr(predicate::str::contains("debug:").not());
+    cmd()
+        .args(["git", "--color", "always", "--config-dir", dir])
+        .env("CHEETSHEET_SEARCH_PATHS", search.path())
+        .env("CHEETSHEET_LOG", "debug")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "debug: segment: code fenced 'bash'",
+        ))
+        .stderr(predicate::str::contains("debug: highlighting 'bash' as"));
+    // Quiet by default
+    cmd()
+        .args(["git", "--config-dir", dir])
+        .env("CHEETSHEET_SEARCH_PATHS", search.path())
+        .assert()
+        .success()
+        .stderr("");
+}
+
+#[test]
+fn test_watch_rerenders_on_change() {
+    use std::io::Read;
+    use std::process::Stdio;
+    use std::time::{Duration, Instant};
+
+    let tmp = TempDir::new().unwrap();
+    let sheet = tmp.path().join("w.md");
+    fs::write(&sheet, "# First\n").unwrap();
+    let mut child = bin()
+        .args([
+            "--file",
+            sheet.to_str().unwrap(),
+            "--watch",
+            "--color",
+            "never",
+        ])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdout = child.stdout.take().unwrap();
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let mut buf = [0; 256];
+        while let Ok(n @ 1..) = stdout.read(&mut buf) {
+            tx.send(String::from_utf8_lossy(&buf[..n]).into_owned())
+                .unwrap();
+        }
+    });
+    let mut output = String::new();
+    let mut wait_for = |text: &str| {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !output.contains(text) && Instant::now() < deadline {
+            if let Ok(chunk) = rx.recv_timeout(Duration::from_millis(50)) {
+                output.push_str(&chunk);
+            }
+        }
+        output.clone()
+    };
+
+    assert!(wait_for("First").contains("First"));
+    // Rewriting the same content is not a change
+    fs::write(&sheet, "# First\n").unwrap();
+    std::thread::sleep(Duration::from_millis(500));
+    fs::write(&sheet, "# Second\n").unwrap();
+    let output = wait_for("Second");
+    child.kill().unwrap();
+    child.wait().unwrap();
+
+    assert!(output.contains("Second"));
+    assert_eq!(output.matches("First").count(), 1);
+}
+
+/// Cold start of viewing a one-block sheet, with an embedded and a bundled
+/// code theme, and of a sheet without code, which never loads syntect.
+/// Slow, so run on demand: `cargo test --release -- --ignored --nocapture`.
+#[test]
+#[ignore]
+fn test_cold_start_latency() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(tmp.path().join("s.md"), "# S\n\n```bash\nls -l\n```\n").unwrap();
+    fs::write(tmp.path().join("prose.md"), "# P\n\n- `ls -l` lists\n").unwrap();
+    let dir = tmp.path().to_str().unwrap();
+    let median = |sheet: &str, theme: &str| {
+        let mut runs: Vec<_> = (0..21)
+            .map(|_| {
+                let start = std::time::Instant::now();
+                cmd()
+                    .args([sheet, "--config-dir", dir, "--color", "always"])
+                    .args(["--code-theme", theme])
+                    .assert()
+                    .success();
+                start.elapsed()
+            })
+            .collect();
+        runs.sort();
+        runs[runs.len() / 2]
+    };
+
+    let embedded = median("s", "base16-ocean.dark");
+    let bundled = median("s", "InspiredGitHub");
+    let without_code = median("prose", "InspiredGitHub");
+    println!("embedded theme: {embedded:?}, bundled theme: {bundled:?}, no code: {without_code:?}");
+    let budget = match cfg!(debug_assertions) {
+        true => std::time::Duration::from_millis(400),
+        false => std::time::Duration::from_millis(40),
+    };
+    assert!(embedded < budget, "{embedded:?} over the {budget:?} budget");
+    assert!(
+        without_code < embedded,
+        "{without_code:?} without code, {embedded:?} with"
+    );
+}
+
+#[test]
+fn test_large_sheet_is_streamed() {
+    let tmp = TempDir::new().unwrap();
+    let mut content = String::from("---\ntags: [big]\n---\n\n# Big\n\n```bash\nls -la\n```\n\n");
+    while content.len() <= 5 << 20 {
+        content.push_str("A line of notes that goes on for a while.\n\n");
+    }
+    content.push_str("## The end\n");
+    let path = tmp.path().join("big.md");
+    fs::write(&path, &content).unwrap();
+    let dir = tmp.path().to_str().unwrap();
+
+    cmd()
+        .args(["--file", path.to_str().unwrap(), "-v", "--color", "never"])
+        .args(["--config-dir", dir])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("ls -la"))
+        .stdout(predicate::str::contains("The end"))
+        .stdout(predicate::str::contains("tags:").not())
+        .stderr(predicate::str::contains("while reading it"));
+    // A range needs the whole sheet
+    cmd()
+        .args(["--file", path.to_str().unwrap(), "-v", "--color", "never"])
+        .args(["--after", "The end", "--config-dir", dir])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("while reading it").not());
+}
+
+#[test]
+fn test_plain() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(
+        tmp.path().join("git.md"),
+        "# Git\n\nSee **`git status`**.\n\n```bash\n\tgit log  --oneline\n\n```\n\n| Key | Does |\n|---|---|\n| `a` | add |\n",
+    )
+    .unwrap();
+
+    cmd()
+        .args(["git", "--plain", "--color", "always"])
+        .args(["--config-dir", tmp.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(
+            "Git\n===\n\nSee `git status`.\n\n```bash\n\tgit log  --oneline\n\n```\n\n\
+             | Key | Does |\n|-----|------|\n| `a` | add  |\n",
+        );
+}
+
+#[test]
+fn test_examples_only() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(
+        tmp.path().join("git.md"),
+        "# Git\n\nA long introduction.\n\n## Branches\n\nList them:\n\n```bash\ngit branch\n```\n",
+    )
+    .unwrap();
+    let dir = tmp.path().to_str().unwrap();
+
+    cmd()
+        .args(["git", "--examples-only", "--plain", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout("Branches\n--------\n\n```bash\ngit branch\n```\n");
+    cmd()
+        .args(["git", "--examples-only", "--with-captions", "--plain"])
+        .args(["--config-dir", dir])
+        .assert()
+        .success()
+        .stdout("Branches\n--------\n\nList them:\n\n```bash\ngit branch\n```\n");
+    cmd()
+        .args(["git", "--with-captions", "--config-dir", dir])
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn test_quiet() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(tmp.path().join("ls.md"), "```bash\nls -la\n```\n").unwrap();
+    let dir = tmp.path().to_str().unwrap();
+
+    cmd()
+        .args(["ls", "--color", "always", "--code-theme", "nope"])
+        .args(["--config-dir", dir])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("\n"))
+        .stderr(predicate::str::contains("unknown code theme"));
+    cmd()
+        .args(["ls", "-q", "--color", "always", "--code-theme", "nope"])
+        .args(["--config-dir", dir])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("\n").not())
+        .stdout(predicate::str::ends_with("\n\n").not())
+        .stderr("");
+    // Also as a config default
+    fs::write(tmp.path().join("cheetsheet.toml"), "quiet = true\n").unwrap();
+    cmd()
+        .args(["ls", "--color", "always", "--code-theme", "nope"])
+        .args(["--config-dir", dir])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("\n").not())
+        .stderr("");
+}
+
+#[test]
+fn test_lang_aliases() {
+    let tmp = TempDir::new().unwrap();
+    let dir = tmp.path().to_str().unwrap();
+    for lang in ["bash", "console", "pwsh", "nope"] {
+        let sheet = format!("```{lang}\nexport PATH=\"$HOME/bin\" # add\n```\n");
+        fs::write(tmp.path().join(format!("{lang}.md")), sheet).unwrap();
+    }
+    fs::write(
+        tmp.path().join("cheetsheet.toml"),
+        "[lang_aliases]\npwsh = \"bash\"\n",
+    )
+    .unwrap();
+    let render = |name: &str| {
+        let output = cmd()
+            .args([name, "--color", "always", "--config-dir", dir])
+            .output()
+            .unwrap();
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let bash = render("bash");
+    assert_eq!(render("console"), bash);
+    assert_eq!(render("pwsh"), bash);
+    assert_ne!(render("nope"), bash);
+}
+
+#[test]
+fn test_dumb_terminal() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(tmp.path().join("ls.md"), "- list\n\n```bash\nls\n```\n").unwrap();
+    let dir = tmp.path().to_str().unwrap();
+
+    cmd()
+        .args(["ls", "--code-frame", "--config-dir", dir])
+        .env("TERM", "dumb")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("+- bash"))
+        .stdout(predicate::str::contains("╭").not())
+        .stdout(predicate::str::contains("\x1b[").not());
+    // Piped without a TERM could be anything, so box drawing stays
+    cmd()
+        .args(["ls", "--code-frame", "--config-dir", dir])
+        .env_remove("TERM")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("╭"));
+    // Forcing color says the terminal is capable
+    cmd()
+        .args([
+            "ls",
+            "--code-frame",
+            "--color",
+            "always",
+            "--config-dir",
+            dir,
+        ])
+        .env("TERM", "dumb")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("╭"))
+        .stdout(predicate::str::contains("\x1b["));
+}
+
+#[test]
+fn test_output_file() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(
+        tmp.path().join("git.md"),
+        "# Git\n\nSee `git status`.\n\n```bash\ngit log\n```\n",
+    )
+    .unwrap();
+    let dir = tmp.path().to_str().unwrap();
+    let notes = tmp.path().join("notes.txt");
+    let page = tmp.path().join("git.html");
+
+    cmd()
+        .args(["git", "--config-dir", dir, "-o"])
+        .arg(&notes)
+        .assert()
+        .success()
+        .stdout("");
+    assert_eq!(
+        fs::read_to_string(&notes).unwrap(),
+        "Git\n===\n\nSee `git status`.\n\n```bash\ngit log\n```\n"
+    );
+    cmd()
+        .args(["git", "--config-dir", dir, "-o"])
+        .arg(&page)
+        .assert()
+        .success()
+        .stdout("");
+    let html = fs::read_to_string(&page).unwrap();
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<title>Git</title>"));
+    assert!(html.contains("<code>git status</code>"));
+    assert!(html.contains("<pre style="));
+
+    // Writing the same rendering again needs no --force
+    cmd()
+        .args(["git", "--config-dir", dir, "-o"])
+        .arg(&page)
+        .assert()
+        .success();
+    assert_eq!(fs::read_to_string(&page).unwrap(), html);
+
+    // An existing file is only replaced with --force
+    cmd()
+        .args(["git", "--config-dir", dir, "--output-format", "html", "-o"])
+        .arg(&notes)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("pass --force"));
+    assert!(fs::read_to_string(&notes).unwrap().starts_with("Git\n==="));
+    cmd()
+        .args([
+            "git",
+            "--config-dir",
+            dir,
+            "--output-format",
+            "html",
+            "--force",
+        ])
+        .arg("-o")
+        .arg(&notes)
+        .assert()
+        .success();
+    assert!(
+        fs::read_to_string(&notes)
+            .unwrap()
+            .starts_with("<!DOCTYPE html>")
+    );
+
+    // A missing sheet leaves no file behind
+    cmd()
+        .args(["nope", "--config-dir", dir, "-o"])
+        .arg(tmp.path().join("nope.txt"))
+        .assert()
+        .failure();
+    assert!(!tmp.path().join("nope.txt").exists());
+}
+
+#[test]
+fn test_output_ansi_file_is_colored() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(
+        tmp.path().join("git.md"),
+        "# Git\n\n```bash\ngit log\n```\n",
+    )
+    .unwrap();
+    let out = tmp.path().join("git.ansi");
+
+    cmd()
+        .args(["git", "--config-dir", tmp.path().to_str().unwrap(), "-o"])
+        .arg(&out)
+        .assert()
+        .success()
+        .stdout("");
+    assert!(fs::read_to_string(&out).unwrap().contains("\x1b["));
+    let entries = fs::read_dir(tmp.path()).unwrap().count();
+    assert_eq!(entries, 2, "no temporary file is left behind");
+}
+
+#[test]
+fn test_show_meta() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(
+        tmp.path().join("git.md"),
+        "---\ntitle: Git\ntags: [git, vcs]\ndraft: true\n---\n# Git\n",
+    )
+    .unwrap();
+    let dir = tmp.path().to_str().unwrap();
+
+    cmd()
+        .args(["git", "--show-meta", "--color", "never", "--ascii"])
+        .args(["--config-dir", dir])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "  +-----------------+\n  | title  Git      |\n  | tags   git, vcs |\n  \
+             | draft  true     |\n  +-----------------+\n\nGit\n",
+        ));
+    fs::write(
+        tmp.path().join("cheetsheet.toml"),
+        "show_meta = true\nmeta_keys = [\"tags\", \"title\"]\n",
+    )
+    .unwrap();
+    cmd()
+        .args(["git", "--plain", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout("tags: git, vcs\ntitle: Git\n\nGit\n===\n");
+}
+
+#[test]
+fn test_wide_tables() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(
+        tmp.path().join("git.md"),
+        "| Command | Description |\n|---|---|\n| `git log --oneline` | show history compactly |\n",
+    )
+    .unwrap();
+    let dir = tmp.path().to_str().unwrap();
+
+    cmd()
+        .args(["git", "--color", "never", "--width", "40"])
+        .args(["--config-dir", dir])
+        .args(["--wide-tables", "records", "--renderer", "cmark"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Command: git log --oneline\nDescription: show history compactly\n",
+        ));
+    cmd()
+        .args(["git", "--color", "never", "--width", "40"])
+        .args(["--config-dir", dir])
+        .args(["--wide-tables", "clip", "--ascii"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("> 1 more column: Description"))
+        .stdout(predicate::str::contains("show history").not());
+    // Tables that fit are drawn as ever
+    cmd()
+        .args(["git", "--color", "never", "--width", "80"])
+        .args(["--config-dir", dir])
+        .args(["--wide-tables", "records"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Command: ").not());
+}
+
+#[test]
+fn test_header_and_footer() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(
+        tmp.path().join("git.md"),
+        "# Git\n\nSee the log.\n\n```bash\ngit log\n```\n",
+    )
+    .unwrap();
+    let dir = tmp.path().to_str().unwrap();
+
+    cmd()
+        .args([
+            "git", "--header", "--footer", "--color", "always", "--ascii",
+        ])
+        .args(["--config-dir", dir])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("git"))
+        .stdout(predicate::str::contains(" - modified just now"))
+        .stdout(predicate::str::contains("4 words, 1 code block"));
+    // Plain text piped elsewhere stays as it was
+    cmd()
+        .args(["git", "--header", "--footer", "--color", "never"])
+        .args(["--config-dir", dir])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("modified").not())
+        .stdout(predicate::str::contains("code block").not());
+}
+
+#[test]
+fn test_margin() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(
+        tmp.path().join("git.md"),
+        "# Git\n\nStage files.\n\n```bash\ngit add -p\n```\n",
+    )
+    .unwrap();
+    let dir = tmp.path().to_str().unwrap();
+
+    cmd()
+        .args(["git", "--color", "never", "--margin", "4"])
+        .args(["--config-dir", dir])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\n    Stage files."))
+        .stdout(predicate::str::contains("\n      git add -p\n"));
+}
+
+#[test]
+fn test_variables() {
+    let tmp = TempDir::new().unwrap();
+    let sheet = "# Deploy\n\nOn {{env:DEPLOY_HOST}}:\n\n```bash\nssh {{user}}@{{env:DEPLOY_HOST}} {{ .Args }}\n```\n";
+    fs::write(tmp.path().join("deploy.md"), sheet).unwrap();
+    fs::write(
+        tmp.path().join("helm.md"),
+        format!("---\nexpand: false\n---\n{sheet}"),
+    )
+    .unwrap();
+    let dir = tmp.path().to_str().unwrap();
+
+    cmd()
+        .args(["deploy", "--plain", "-v", "--config-dir", dir])
+        .env("DEPLOY_HOST", "web1")
+        .env("USER", "ada")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("On web1:"))
+        .stdout(predicate::str::contains("ssh ada@web1 {{ .Args }}"))
+        .stderr(predicate::str::contains(
+            "leaving {{ .Args }} as written: not a variable",
+        ));
+    for args in [&["deploy", "--no-expand"][..], &["helm"]] {
+        cmd()
+            .args(args)
+            .args(["--plain", "--config-dir", dir])
+            .env("DEPLOY_HOST", "web1")
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("ssh {{user}}@{{env:DEPLOY_HOST}}"));
+    }
+}
+
+#[test]
+fn test_diff() {
+    let tmp = TempDir::new().unwrap();
+    let dir = tmp.path().to_str().unwrap();
+    fs::write(tmp.path().join("git.md"), "# Git\n\ngit add -p\n").unwrap();
+
+    cmd()
+        .args(["diff", "git", "--config-dir", dir])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("is not in a git checkout"));
+
+    let git = |args: &[&str]| {
+        let status = std::process::Command::new("git")
+            .args(["-c", "user.name=t", "-c", "user.email=t@example.com"])
+            .args(args)
+            .current_dir(tmp.path())
+            .status()
+            .unwrap();
+        assert!(status.success());
+    };
+    git(&["init", "--quiet"]);
+    git(&["add", "git.md"]);
+    git(&["commit", "--quiet", "-m", "Add git"]);
+    cmd()
+        .args(["diff", "git", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout("")
+        .stderr(predicate::str::contains("No changes to git"));
+
+    fs::write(tmp.path().join("git.md"), "# Git\n\ngit add -N\n").unwrap();
+    git(&["commit", "--quiet", "-am", "Change git"]);
+    fs::write(tmp.path().join("git.md"), "# Git\n\ngit add -u\n").unwrap();
+    cmd()
+        .args(["diff", "git", "--color", "never", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("-git add -N"))
+        .stdout(predicate::str::contains("+git add -u"));
+    cmd()
+        .args(["diff", "git", "--rev", "HEAD~1", "--color", "never"])
+        .args(["--config-dir", dir])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("-git add -p"))
+        .stdout(predicate::str::contains("+git add -u"));
+    cmd()
+        .args(["diff", "git", "--color", "always", "--config-dir", dir])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b[38;2;"));
+    // The diff is git's own, whatever diff tool is configured
+    cmd()
+        .args(["diff", "git", "--color", "never", "--config-dir", dir])
+        .env("GIT_EXTERNAL_DIFF", "false")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("+git add -u"));
+    // A revision is never read as an option
+    cmd()
+        .args(["diff", "git", "--rev=--output=out.txt", "--config-dir", dir])
+        .assert()
+        .code(7);
+    assert!(!tmp.path().join("out.txt").exists());
+
+    fs::write(tmp.path().join("jq.md"), "# jq\n").unwrap();
+    cmd()
+        .args(["diff", "jq", "--config-dir", dir])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("jq is untracked"));
+}
+
+#[test]
+fn test_several_sheets() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(tmp.path().join("git.md"), "# Git\n\nStage files.\n").unwrap();
+    fs::write(tmp.path().join("jq.md"), "# jq\n\nFilter JSON.\n").unwrap();
+    let dir = tmp.path().to_str().unwrap();
+
+    cmd()
+        .args(["git", "nosuch", "jq", "--color", "never", "--width", "20"])
+        .args(["--config-dir", dir])
+        .assert()
+        .code(3)
+        .stdout(
+            predicate::str::is_match(
+                "(?s)^git · .*Stage files\\..*\n\n─{20}\n\njq · .*Filter JSON\\.",
+            )
+            .unwrap(),
+        )
+        .stderr(predicate::str::contains("nosuch"));
+    cmd()
+        .args(["git", "jq", "--after", "Git"])
+        .args(["--config-dir", dir])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "--after works on a single sheet; name only one",
+        ));
+    let separated = |style: &str| {
+        let output = cmd()
+            .args(["git", "jq", "--color", "never", "--width", "20"])
+            .args(["--separator", style, "--config-dir", dir])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let rule = separated("rule");
+    assert!(rule.contains(&"─".repeat(20)));
+    assert!(!rule.contains("git · "));
+    let heading = separated("heading");
+    assert!(heading.contains("jq · "));
+    assert!(!heading.contains('─'));
+    assert!(separated("formfeed").contains("\n\x0c\n"));
+    assert!(separated("* * *").contains("\n* * *\n\njq · "));
+
+    // A section is refused before any sheet is shown
+    cmd()
+        .args(["git", "jq#Filter"])
+        .args(["--config-dir", dir])
+        .assert()
+        .code(2)
+        .stdout("")
+        .stderr(predicate::str::contains(
+            "'jq#Filter' names a section, which works on a single sheet",
+        ));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_several_sheets_report_before_paging() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(tmp.path().join("git.md"), "# Git\n\nStage files.\n").unwrap();
+    let dir = tmp.path().to_str().unwrap();
+
+    tty_cmd(&["git", "nosuch", "--config-dir", dir])
+        .env("PAGER", "sed s/^/paged:/")
+        .assert()
+        .code(3)
+        .stdout(predicate::str::is_match("(?s)nosuch.*paged:.*Stage files").unwrap());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_history() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(tmp.path().join("git.md"), "# Git Sheet\n").unwrap();
+    fs::write(tmp.path().join("jq.md"), "# Jq Sheet\n").unwrap();
+    let dir = tmp.path().to_str().unwrap();
+    let run = |args: &[&str]| {
+        let mut cmd = cmd();
+        cmd.args(args)
+            .args(["--config-dir", dir])
+            .env("XDG_DATA_HOME", tmp.path());
+        cmd
+    };
+
+    run(&["git"]).assert().success();
+    run(&["jq"]).assert().success();
+    run(&["history"]).assert().success().stdout(
+        predicate::str::is_match(
+            r"^1  \d{4}-\d\d-\d\dT[\d:]{8}Z  git  .*git\.md\n2  .*  jq  .*jq\.md\n$",
+        )
+        .unwrap(),
+    );
+    // `-` on a terminal is the last sheet viewed, like `cd -`
+    tty_cmd(&["-", "--config-dir", dir])
+        .env("XDG_DATA_HOME", tmp.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Jq Sheet"));
+
+    run(&["history", "--clear"]).assert().success();
+    run(&["history"]).assert().success().stdout("");
+
+    fs::write(tmp.path().join("cheetsheet.toml"), "history = false\n").unwrap();
+    run(&["git"]).assert().success();
+    run(&["history"]).assert().success().stdout("");
+}
+
+#[test]
+fn test_list_long() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(
+        tmp.path().join("git.md"),
+        "---\ndescription: Everyday commands\n---\n# Git\n\n## Stage\n\n## Commit\n",
+    )
+    .unwrap();
+    fs::create_dir(tmp.path().join("cloud")).unwrap();
+    fs::write(tmp.path().join("cloud/aws.md"), "# AWS CLI\n\n## S3\n").unwrap();
+
+    cmd()
+        .args(["list", "--long", "--color", "never"])
+        .args(["--config-dir", tmp.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::is_match(
+                r"^cloud/aws   1 section  \d{4}-\d\d-\d\d  AWS CLI\ngit        2 sections  \d{4}-\d\d-\d\d  Everyday commands\n$",
+            )
+            .unwrap(),
+        );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_top() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(tmp.path().join("git.md"), "# Git\n").unwrap();
+    fs::write(tmp.path().join("jq.md"), "# Jq\n").unwrap();
+    let dir = tmp.path().to_str().unwrap();
+    let run = |args: &[&str]| {
+        let mut cmd = cmd();
+        cmd.args(args)
+            .args(["--config-dir", dir])
+            .env("XDG_DATA_HOME", tmp.path());
+        cmd
+    };
+    for name in ["jq", "git", "git", "git", "jq"] {
+        run(&[name]).assert().success();
+    }
+
+    run(&["top", "--days", "30"])
+        .assert()
+        .success()
+        .stdout(format!(
+            "git  3  {}\njq   2  {}\n",
+            "█".repeat(20),
+            "█".repeat(13) + "▎"
+        ));
+    let output = run(&["top", "--json", "--limit", "1"]).output().unwrap();
+    let top: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(top[0]["name"], "git");
+    assert_eq!(top[0]["views"], 3);
+    assert_eq!(top.as_array().unwrap().len(), 1);
+
+    // Views of a deleted sheet don't count
+    fs::remove_file(tmp.path().join("git.md")).unwrap();
+    run(&["top", "--ascii"])
+        .assert()
+        .success()
+        .stdout(format!("jq  2  {}\n", "#".repeat(20)));
+}